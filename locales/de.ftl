@@ -0,0 +1,113 @@
+# Die Meldungen von up2date auf Deutsch. Was hier fehlt, wird auf Englisch gezeigt.
+
+## Prompts
+prompt-yes-no = [j/N]
+answer-yes = j, ja
+prompt-first-run = Keine Konfigurationsdatei gefunden, ein paar Fragen beantworten, um eine anzulegen?
+prompt-apply = Diese Änderungen anwenden?
+prompt-run = `{ $command }` ausführen?
+prompt-remove = Diese entfernen?
+prompt-explain = Enter führt ihn aus, Strg-C bricht ab
+prompt-close = Enter schließt das Fenster
+explain-unknown = Dieser Befehl gehört zu keinem Schritt von up2date selbst, er kommt von einem Plugin, einem Skript oder der Konfiguration.
+error-explain-stopped = abgebrochen, --explain bekam keine Antwort
+prompt-remove-lock = { $lock } ist von einem pacman übrig, der nicht fertig wurde, und kein pacman läuft. Entfernen?
+prompt-restore-which = Welchen Snapshot wiederherstellen? [1-{ $count }]
+prompt-restore = { $snapshot } wiederherstellen?
+
+## Running
+group-skipped = Gruppe { $group } wird übersprungen
+step-skipped = `{ $command }` wird übersprungen, { $reason }
+reason-needs-network = es braucht das Netzwerk
+reason-may-need-network = es braucht vielleicht das Netzwerk
+reason-restart-services-off = restart_services ist ausgeschaltet
+reason-not-due = es läuft { $frequency } und lief zuletzt vor { $ago }
+reason-lists-fresh = die Paketlisten wurden vor weniger als einer Stunde aktualisiert
+reason-lists-refreshed = die Paketlisten wurden gerade für die Vorschau aktualisiert
+reason-read-only-root = das Wurzeldateisystem ist schreibgeschützt und wird mit jedem Update ersetzt
+reason-up-to-date = es gibt kein neueres Abbild
+removal-nothing = `{ $command }` hat nichts zu entfernen
+removal-list = `{ $command }` würde entfernen:
+removal-off = Es wird nichts entfernt, setze [cleanup] remove auf true oder "ask", um es zu entfernen
+lock-waiting = Warte bis zu { $timeout }s darauf, dass { $holder } die Paketdatenbank freigibt
+lock-still-waiting = Warte noch auf { $holder }, { $left }s verbleiben
+error-stale-lock = { $lock } ist von einem pacman übrig, der nicht fertig wurde, entfernen oder [locks] remove_stale = true setzen
+error-locked = { $holder } hält die Paketdatenbank, erneut ausführen, sobald es fertig ist, oder [locks] timeout erhöhen
+policy-denied = { $package } { $version } ist durch [policy] deny verboten
+policy-not-allowed = { $package } { $version } steht nicht in [policy] allow
+policy-script-blocked = { $script } hat die Aktualisierungen blockiert: { $reason }
+error-policy = die anstehenden Aktualisierungen sind durch [policy] nicht erlaubt, nichts wurde aktualisiert
+schedule-outside = außerhalb des Wartungsfensters ({ $windows }), es wird nicht aktualisiert, --force erzwingt den Lauf
+schedule-waiting = { $seconds } Sekunden Wartezeit vor dem Aktualisieren
+error-required-failed = `{ $command }` ist fehlgeschlagen und wird gebraucht, Abbruch
+error-offline-failed = `{ $command }` ist offline fehlgeschlagen und musste vielleicht etwas herunterladen. Führe zuerst online `up2date fetch` aus.
+error-snapshot-failed = der Snapshot ist fehlgeschlagen, es wird nichts aktualisiert
+restore-nothing = Nichts wiederhergestellt
+restore-reboot = Neu starten, um die Wiederherstellung von { $snapshot } abzuschließen
+snapshots-none = up2date hat noch keine Snapshots angelegt
+
+## Pending changes
+preview-title = Ausstehende Änderungen
+plan-title = Dieser Lauf führt aus:
+plan-removals = und entfernt:
+preview-nothing = Nichts zu aktualisieren
+preview-download = { $manager } wird { $size } herunterladen
+preview-changelog = Neu in { $package } { $to }:
+package-new = (neu)
+package-removed = (entfernt)
+kubernetes-available = { $distribution } { $available } ist verfügbar (installiert ist { $running })
+selfhosted-gitlab = GitLab { $version } wird mit den Systempaketen aktualisiert, vor einer neuen Hauptversion den Upgrade-Pfad prüfen
+selfhosted-nextcloud-upgrade = Nextcloud ist gesperrt, bis occ upgrade die Datenbank aktualisiert
+
+## Summary
+status-up-to-date = aktuell
+status-pending-one = 1 Update ausstehend
+status-pending = { $count } Updates ausstehend
+status-security = { $count } Sicherheit
+status-last-run = letzter Lauf vor { $ago }
+status-last-run-failed = letzter Lauf vor { $ago }, { $failed } fehlgeschlagen
+status-reboot = Neustart nötig
+summary-title = Zusammenfassung
+summary-commands = { $run } Befehle ausgeführt, { $failed } fehlgeschlagen
+summary-group = { $group }: { $run } Befehle ausgeführt, { $failed } fehlgeschlagen
+summary-failed-code = fehlgeschlagen: `{ $command }` (Exit-Code { $code })
+summary-failed-signal = fehlgeschlagen: `{ $command }` (durch ein Signal beendet)
+summary-notes = Hinweise der Paketmanager:
+summary-highlighted = `{ $command }` meldete:
+summary-failed-pipeline = Befehl { $position } von { $count } in der Pipeline endete mit { $code }
+summary-changed = `{ $command }` hat { $count } Pakete geändert
+summary-vulnerabilities = { $count } bekannte Sicherheitslücken bleiben
+summary-vulnerability = { $package } { $version } { $advisory } (gefunden von { $tool })
+summary-baseline = Zeitzonen, Zertifikate und GeoIP aktualisiert: { $packages }
+summary-health = { $check }: { $status }
+summary-restarted = neu gestartete Dienste: { $services }
+summary-needs-restart = Dienste mit veralteten Bibliotheken: { $services }
+summary-stale-processes = Prozesse mit ersetzten Bibliotheken: { $processes }
+summary-recreated = Mit neuen Images neu erstellte Container: { $containers }
+summary-pinned = durch Pins zurückgehalten: { $packages }
+summary-kept-back = von apt zurückgehalten: { $packages }
+summary-phased = noch ausstehende gestaffelte Updates: { $packages } (mit [apt] phased = true jetzt installieren)
+summary-flatpak = flatpak ({ $installation }): { $apps } Apps und { $runtimes } Laufzeiten aktualisiert
+summary-not-due = noch nicht fällig: { $commands }
+summary-automatic = { $service } lief zuletzt vor { $ago }
+summary-automatic-upgraded = { $service } lief zuletzt vor { $ago } und aktualisierte { $packages }
+summary-pro = Ubuntu Pro: { $services }
+summary-pro-nothing = verbunden, nichts eingeschaltet
+summary-pro-detached = Ubuntu Pro ist nicht verbunden, ESM-Sicherheitsupdates werden nicht installiert
+summary-livepatch = Livepatch: { $state }
+summary-automatic-never = { $service } ist eingeschaltet, lief aber noch nicht
+summary-warning = Warnung: { $warning }
+summary-uptime = läuft seit { $duration }
+summary-reboot-pending = Neustart steht seit { $duration } aus
+summary-downloaded = { $size } heruntergeladen
+summary-disk = Festplatte { $delta }
+
+## Report documents
+report-meta = { $host }, { $started } UTC, { $outcome }
+report-package = Paket
+report-from = Von
+report-to = Nach
+outcome-completed = abgeschlossen
+outcome-aborted = nach einem Fehler abgebrochen
+outcome-panicked = abgestürzt
+outcome-interrupted = unterbrochen
@@ -0,0 +1,115 @@
+# The messages up2date shows, in English. Every other catalog falls back to these.
+# Each message is one line: `id = text`, with `{ $name }` where an argument goes.
+
+## Prompts
+prompt-yes-no = [y/N]
+# what counts as a yes besides y and yes, separated by commas
+answer-yes = y, yes
+prompt-first-run = No config file found, answer a few questions to set one up?
+prompt-apply = Apply these changes?
+prompt-run = Run `{ $command }`?
+prompt-remove = Remove these?
+prompt-explain = Press Enter to run it, Ctrl-C to stop
+prompt-close = Press Enter to close
+explain-unknown = This command isn't one of up2date's own steps, it comes from a plugin, a script or the config.
+error-explain-stopped = stopped, there was no answer to --explain
+prompt-remove-lock = { $lock } is left over from a pacman that didn't finish and no pacman is running. Remove it?
+prompt-restore-which = Restore which snapshot? [1-{ $count }]
+prompt-restore = Restore { $snapshot }?
+
+## Running
+group-skipped = Skipping the { $group } group
+step-skipped = Skipping `{ $command }`, { $reason }
+reason-needs-network = it needs the network
+reason-may-need-network = it may need the network
+reason-restart-services-off = restart_services is turned off
+reason-not-due = it runs { $frequency } and last ran { $ago } ago
+reason-lists-fresh = the package lists were refreshed less than an hour ago
+reason-lists-refreshed = the package lists were just refreshed for the preview
+reason-read-only-root = the root is read-only and replaced with each update
+reason-up-to-date = there's no newer image
+removal-nothing = Nothing for `{ $command }` to remove
+removal-list = `{ $command }` would remove:
+removal-off = Not removing anything, set [cleanup] remove to true or "ask" to
+lock-waiting = Waiting for { $holder } to finish with the package database, for up to { $timeout }s
+lock-still-waiting = Still waiting for { $holder }, { $left }s left
+error-stale-lock = { $lock } is left over from a pacman that didn't finish, remove it or set [locks] remove_stale = true
+error-locked = { $holder } is holding the package database, run again once it's finished or raise [locks] timeout
+policy-denied = { $package } { $version } is denied by [policy] deny
+policy-not-allowed = { $package } { $version } isn't in [policy] allow
+policy-script-blocked = { $script } blocked the updates: { $reason }
+error-policy = the pending updates aren't allowed by [policy], nothing was updated
+schedule-outside = outside maintenance window ({ $windows }), not updating, --force runs anyway
+schedule-waiting = waiting { $seconds } seconds before updating
+error-required-failed = `{ $command }` failed and is required, stopping
+error-offline-failed = `{ $command }` failed while offline, it may have needed to download something. Run `up2date fetch` while online first.
+error-snapshot-failed = the snapshot failed, not updating anything
+restore-nothing = Nothing restored
+restore-reboot = Reboot to finish restoring { $snapshot }
+snapshots-none = up2date hasn't taken any snapshots yet
+
+## Pending changes
+preview-title = Pending changes
+plan-title = This run will run:
+plan-removals = and remove:
+preview-nothing = Nothing to update
+preview-download = { $manager } will download { $size }
+preview-changelog = What's new in { $package } { $to }:
+package-new = (new)
+package-removed = (removed)
+kubernetes-available = { $distribution } { $available } is available (running { $running })
+selfhosted-gitlab = GitLab { $version } is upgraded with the system packages, check its upgrade path before a new major version
+selfhosted-nextcloud-upgrade = Nextcloud is locked until occ upgrade brings its database up to date
+
+## Summary
+status-up-to-date = up to date
+status-pending-one = 1 update pending
+status-pending = { $count } updates pending
+status-security = { $count } security
+status-last-run = last run { $ago } ago
+status-last-run-failed = last run { $ago } ago, { $failed } failed
+status-reboot = reboot needed
+summary-title = Summary
+summary-commands = { $run } commands run, { $failed } failed
+summary-group = { $group }: { $run } commands run, { $failed } failed
+summary-failed-code = failed: `{ $command }` (exit code { $code })
+summary-failed-signal = failed: `{ $command }` (killed by a signal)
+summary-notes = Notes from the package managers:
+summary-highlighted = `{ $command }` said:
+summary-failed-pipeline = command { $position } of { $count } in the pipeline exited with { $code }
+summary-changed = `{ $command }` changed { $count } packages
+summary-vulnerabilities = { $count } known vulnerabilities remain
+summary-vulnerability = { $package } { $version } { $advisory } (found by { $tool })
+summary-baseline = time zones, certificates and GeoIP updated: { $packages }
+summary-health = { $check }: { $status }
+summary-restarted = restarted services: { $services }
+summary-needs-restart = services running outdated libraries: { $services }
+summary-stale-processes = processes using replaced libraries: { $processes }
+summary-recreated = containers recreated with new images: { $containers }
+summary-pinned = held back by pins: { $packages }
+summary-kept-back = kept back by apt: { $packages }
+summary-phased = phased updates still to come: { $packages } (set [apt] phased = true to install them now)
+summary-flatpak = flatpak ({ $installation }): { $apps } apps and { $runtimes } runtimes updated
+summary-not-due = not due yet: { $commands }
+summary-automatic = { $service } last ran { $ago } ago
+summary-automatic-upgraded = { $service } last ran { $ago } ago and upgraded { $packages }
+summary-pro = Ubuntu Pro: { $services }
+summary-pro-nothing = attached, nothing turned on
+summary-pro-detached = Ubuntu Pro isn't attached, ESM security updates aren't installed
+summary-livepatch = livepatch: { $state }
+summary-automatic-never = { $service } is turned on but hasn't run yet
+summary-warning = warning: { $warning }
+summary-uptime = up { $duration }
+summary-reboot-pending = reboot pending for { $duration }
+summary-downloaded = downloaded { $size }
+summary-disk = disk { $delta }
+
+## Report documents
+report-meta = { $host }, { $started } UTC, { $outcome }
+report-package = Package
+report-from = From
+report-to = To
+outcome-completed = completed
+outcome-aborted = stopped after a failure
+outcome-panicked = crashed
+outcome-interrupted = interrupted
@@ -0,0 +1,184 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use scuttle::App;
+
+use crate::detect::{self, has_binary};
+
+/// How deep to look for changed directories, fonts are usually kept two levels down,
+/// e.g. `/usr/share/fonts/truetype/dejavu`
+const DEPTH: usize = 3;
+
+/// What a directory holds, each kind has its own cache to rebuild
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Fonts,
+    Applications,
+    Icons,
+}
+
+/// A directory of fonts, desktop entries or icons, with whether it belongs to the system
+struct Location {
+    kind: Kind,
+    dir: PathBuf,
+    system: bool,
+}
+
+/// When each location last had something added, replaced or removed
+pub struct Stamp(Vec<Option<SystemTime>>);
+
+/// Every location that's looked at, the system's and the user's
+fn locations() -> Vec<Location> {
+    let mut locations: Vec<Location> = [
+        (Kind::Fonts, "/usr/share/fonts"),
+        (Kind::Fonts, "/usr/local/share/fonts"),
+        (Kind::Applications, "/usr/share/applications"),
+        (Kind::Icons, "/usr/share/icons/hicolor"),
+    ]
+    .into_iter()
+    .map(|(kind, dir)| Location {
+        kind,
+        dir: PathBuf::from(dir),
+        system: true,
+    })
+    .collect();
+
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")),
+    };
+
+    if let Some(data_home) = data_home {
+        locations.extend(
+            [
+                (Kind::Fonts, "fonts"),
+                (Kind::Applications, "applications"),
+                (Kind::Icons, "icons/hicolor"),
+            ]
+            .into_iter()
+            .map(|(kind, dir)| Location {
+                kind,
+                dir: data_home.join(dir),
+                system: false,
+            }),
+        );
+    }
+
+    locations
+}
+
+/// The newest modification time of a directory and the directories in it
+///
+/// Packages replace files by renaming new ones into place, which changes the time of the
+/// directory they're in, so the files themselves don't need to be looked at.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to look at
+/// * `depth` - How many levels of directories below it to look at as well
+fn newest(dir: &Path, depth: usize) -> Option<SystemTime> {
+    let modified = fs::metadata(dir).ok()?.modified().ok()?;
+
+    if depth == 0 {
+        return Some(modified);
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Some(modified),
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|entry| newest(&entry.path(), depth - 1))
+        .max()
+        .max(Some(modified))
+}
+
+/// Look at every location before the packages change, only on Linux and never with
+/// shims since only what the shims do matters
+pub fn stamp() -> Stamp {
+    if detect::shims().is_some() || detect::os() != "linux" {
+        return Stamp(Vec::new());
+    }
+
+    Stamp(
+        locations()
+            .iter()
+            .map(|location| newest(&location.dir, DEPTH))
+            .collect(),
+    )
+}
+
+/// Rebuild the caches of the locations that changed since `before`, so new fonts,
+/// desktop entries and icons show up without logging out
+///
+/// fontconfig's cache is rebuilt once for every font directory, the desktop database
+/// and icon cache for each directory that changed. The system's need root.
+///
+/// # Arguments
+///
+/// * `before` - What `stamp()` found before the packages changed
+pub fn refresh_apps(before: &Stamp) -> Vec<App> {
+    let after = stamp();
+    let mut apps: Vec<App> = Vec::new();
+    let mut fonts = false;
+
+    let changed = locations()
+        .into_iter()
+        .zip(before.0.iter().zip(after.0.iter()))
+        .filter(|(_, (before, after))| after.is_some() && before != after)
+        .map(|(location, _)| location);
+
+    for location in changed {
+        let (command, args): (&str, Vec<String>) = match location.kind {
+            Kind::Fonts => {
+                fonts = true;
+                continue;
+            }
+            Kind::Applications => (
+                "update-desktop-database",
+                vec![location.dir.display().to_string()],
+            ),
+            Kind::Icons => (
+                "gtk-update-icon-cache",
+                vec![
+                    "--force".to_string(),
+                    "--ignore-theme-index".to_string(),
+                    location.dir.display().to_string(),
+                ],
+            ),
+        };
+
+        if !has_binary(command) {
+            continue;
+        }
+
+        apps.push(if location.system {
+            App {
+                command: String::from("sudo"),
+                args: std::iter::once(command.to_string()).chain(args).collect(),
+            }
+        } else {
+            App {
+                command: command.to_string(),
+                args,
+            }
+        });
+    }
+
+    if fonts && has_binary("fc-cache") {
+        apps.insert(
+            0,
+            App {
+                command: String::from("fc-cache"),
+                args: vec!["-f".to_string()],
+            },
+        );
+    }
+
+    apps
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use scuttle::App;
+
+use crate::config::Config;
+use crate::detect::{self, has_binary};
+use crate::parsers;
+use crate::report::ChangedPackage;
+
+/// The packages every program trusts without knowing it: time zones, certificate
+/// authorities and GeoIP, by the names each distribution gives them
+const PACKAGES: [&str; 5] = [
+    "tzdata",
+    "ca-certificates",
+    "ca-certificates-mozilla",
+    "geoip-database",
+    "geoip",
+];
+
+/// The installed version of each of `PACKAGES` that's installed, asked of the package
+/// database so it works whichever manager upgraded them
+///
+/// # Arguments
+///
+/// * `config` - The config, for the binaries to ask with
+/// * `release` - The Linux distribution id, `None` on other platforms
+pub fn installed(config: &Config, release: Option<&str>) -> HashMap<String, String> {
+    let pacman = config.pacman.executable.binary("pacman");
+    let brew = config.brew.executable.binary("brew");
+    let mut command_line: Vec<&str> = match release {
+        Some("ubuntu") | Some("pop") | Some("debian") => {
+            vec![
+                "dpkg-query",
+                "--show",
+                "--showformat",
+                "${Package} ${Version}\n",
+            ]
+        }
+        Some("arch") | Some("endeavouros") => vec![&pacman, "-Q"],
+        _ if detect::os() == "macos" => vec![&brew, "list", "--versions"],
+        _ => return HashMap::new(),
+    };
+
+    if !has_binary(command_line[0]) {
+        return HashMap::new();
+    }
+
+    command_line.extend(PACKAGES);
+
+    // the ones that aren't installed are complained about and left out
+    let output = match scuttle::run_output(&App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    }) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(_) => return HashMap::new(),
+    };
+
+    parsers::parse_rpm(&output)
+        .into_iter()
+        .filter(|(package, version)| PACKAGES.contains(&package.as_str()) && !version.is_empty())
+        .collect()
+}
+
+/// Which of the packages changed between two looks at what's installed
+///
+/// # Arguments
+///
+/// * `before` - What `installed()` found before upgrading
+/// * `after` - What it found afterwards
+pub fn changed(
+    before: &HashMap<String, String>,
+    after: &HashMap<String, String>,
+) -> Vec<ChangedPackage> {
+    let mut changed: Vec<ChangedPackage> = after
+        .iter()
+        .filter(|(package, version)| before.get(*package) != Some(*version))
+        .map(|(package, version)| ChangedPackage {
+            package: package.clone(),
+            from: before.get(package).cloned(),
+            to: Some(version.clone()),
+        })
+        .collect();
+
+    changed.sort_by(|a, b| a.package.cmp(&b.package));
+    changed
+}
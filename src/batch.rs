@@ -0,0 +1,105 @@
+use std::fmt;
+use std::io::{self, Read};
+
+use scuttle::App;
+use serde::Deserialize;
+
+use crate::runner::Step;
+use crate::template;
+
+/// One app in a list given on stdin
+#[derive(Debug, Deserialize)]
+struct AppDefinition {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    /// Stop the whole run when this app fails
+    #[serde(default)]
+    required: bool,
+    /// Carry on when this app fails, even if it's required
+    #[serde(default)]
+    continue_on_error: bool,
+}
+
+/// A TOML list of apps, written as `[[apps]]` tables
+#[derive(Debug, Deserialize)]
+struct AppList {
+    apps: Vec<AppDefinition>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Read(error) => write!(f, "could not read stdin: {}", error),
+            Error::Parse(error) => write!(f, "could not parse the apps on stdin: {}", error),
+        }
+    }
+}
+
+/// Parse a list of apps, either a JSON array or TOML `[[apps]]` tables, with the
+/// placeholders in their args filled in
+///
+/// ```json
+/// [{ "command": "flatpak", "args": ["update", "-y"], "required": true }]
+/// ```
+///
+/// ```toml
+/// [[apps]]
+/// command = "flatpak"
+/// args = ["update", "-y"]
+///
+/// [[apps]]
+/// command = "restic"
+/// args = ["backup", "--tag", "{hostname}-{date}", "/home"]
+/// ```
+///
+/// # Arguments
+///
+/// * `input` - The JSON or TOML to parse
+pub fn parse(input: &str) -> Result<Vec<Step>, Error> {
+    let trimmed = input.trim_start();
+    let definitions = if trimmed.starts_with('[') && !trimmed.starts_with("[[") {
+        serde_json::from_str::<Vec<AppDefinition>>(input)
+            .map_err(|error| Error::Parse(error.to_string()))?
+    } else {
+        toml::from_str::<AppList>(input)
+            .map_err(|error| Error::Parse(error.to_string()))?
+            .apps
+    };
+
+    let variables = template::variables();
+
+    Ok(definitions
+        .into_iter()
+        .map(|definition| Step {
+            app: App {
+                command: definition.command,
+                args: definition
+                    .args
+                    .iter()
+                    .map(|arg| template::expand(arg, &variables))
+                    .collect(),
+            },
+            // stdin is taken up by the list so there's nobody to ask
+            ask: false,
+            required: definition.required && !definition.continue_on_error,
+        })
+        .collect())
+}
+
+/// Read a list of apps from stdin
+pub fn read() -> Result<Vec<Step>, Error> {
+    let mut input = String::new();
+
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(Error::Read)?;
+
+    parse(&input)
+}
@@ -0,0 +1,273 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config;
+use crate::detect;
+
+/// An installed kernel and the files booting it depends on
+struct Kernel {
+    /// The kernel image the bootloader loads
+    image: PathBuf,
+    /// The initramfs generated for the image
+    initramfs: PathBuf,
+    /// The image the package installed, when it's copied to `/boot` separately
+    packaged: Option<PathBuf>,
+    /// What a bootloader entry for this kernel has to mention
+    entry: String,
+}
+
+/// Find the installed kernels for the distributions up2date knows about
+///
+/// Ubuntu keeps `/boot/vmlinuz-<version>` and `/boot/initrd.img-<version>` for every
+/// version in `/lib/modules`, Arch copies `/usr/lib/modules/<version>/vmlinuz` to
+/// `/boot/vmlinuz-<pkgbase>` next to `/boot/initramfs-<pkgbase>.img`.
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+fn kernels(release: Option<&str>) -> Vec<Kernel> {
+    let modules = match release {
+        Some("ubuntu") | Some("pop") | Some("debian") => "/lib/modules",
+        Some("arch") | Some("endeavouros") => "/usr/lib/modules",
+        _ => return Vec::new(),
+    };
+    let entries = match fs::read_dir(modules) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut kernels: Vec<Kernel> = Vec::new();
+
+    for entry in entries.flatten() {
+        let version = entry.file_name().to_string_lossy().into_owned();
+
+        match release {
+            Some("ubuntu") | Some("pop") | Some("debian") => {
+                let image = PathBuf::from(format!("/boot/vmlinuz-{}", version));
+
+                // modules of removed kernels are sometimes left behind
+                if !image.exists() {
+                    continue;
+                }
+
+                kernels.push(Kernel {
+                    image,
+                    initramfs: PathBuf::from(format!("/boot/initrd.img-{}", version)),
+                    packaged: None,
+                    entry: version,
+                });
+            }
+            _ => {
+                let pkgbase = match fs::read_to_string(entry.path().join("pkgbase")) {
+                    Ok(pkgbase) => pkgbase.trim().to_string(),
+                    Err(_) => continue,
+                };
+
+                kernels.push(Kernel {
+                    image: PathBuf::from(format!("/boot/vmlinuz-{}", pkgbase)),
+                    initramfs: PathBuf::from(format!("/boot/initramfs-{}.img", pkgbase)),
+                    packaged: Some(entry.path().join("vmlinuz")),
+                    entry: format!("vmlinuz-{}", pkgbase),
+                });
+            }
+        }
+    }
+
+    kernels
+}
+
+/// Read every grub config and systemd-boot entry that's readable
+///
+/// grub.cfg is often only readable by root, an empty list means the entries couldn't be
+/// checked rather than that there aren't any.
+fn bootloader_entries() -> Vec<String> {
+    let mut entries: Vec<String> = ["/boot/grub/grub.cfg", "/boot/grub2/grub.cfg"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .collect();
+
+    for dir in ["/boot", "/efi", "/boot/efi"].iter() {
+        if let Ok(files) = fs::read_dir(Path::new(dir).join("loader/entries")) {
+            entries.extend(
+                files
+                    .flatten()
+                    .filter_map(|file| fs::read_to_string(file.path()).ok()),
+            );
+        }
+    }
+
+    entries
+}
+
+/// Whether `path` was last modified before `other`, `false` when either can't be read
+fn older(path: &Path, other: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+    match (modified(path), modified(other)) {
+        (Ok(path), Ok(other)) => path < other,
+        _ => false,
+    }
+}
+
+/// Check that every installed kernel has an image, an initramfs that was regenerated
+/// for it and a bootloader entry, returning a message for each problem found
+///
+/// An upgrade that fails to regenerate the initramfs usually only shows up at the next
+/// boot so this runs after the system packages were upgraded.
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+pub fn check(release: Option<&str>) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+    let kernels = kernels(release);
+
+    for kernel in kernels.iter() {
+        if let Some(packaged) = kernel.packaged.as_ref() {
+            let size = |path: &Path| fs::metadata(path).map(|metadata| metadata.len()).ok();
+
+            if !kernel.image.exists() {
+                problems.push(format!(
+                    "{} is missing, was /boot mounted during the upgrade?",
+                    kernel.image.display()
+                ));
+                continue;
+            }
+
+            if size(packaged) != size(&kernel.image) {
+                problems.push(format!(
+                    "{} doesn't match {}, was /boot mounted during the upgrade?",
+                    kernel.image.display(),
+                    packaged.display()
+                ));
+            }
+        }
+
+        match fs::metadata(&kernel.initramfs) {
+            Err(_) => problems.push(format!(
+                "no initramfs at {} for {}",
+                kernel.initramfs.display(),
+                kernel.image.display()
+            )),
+            Ok(metadata) if metadata.len() == 0 => {
+                problems.push(format!("{} is empty", kernel.initramfs.display()))
+            }
+            Ok(_) if older(&kernel.initramfs, &kernel.image) => problems.push(format!(
+                "{} is older than {}, it may not have been regenerated",
+                kernel.initramfs.display(),
+                kernel.image.display()
+            )),
+            Ok(_) => {}
+        }
+    }
+
+    let entries = bootloader_entries();
+
+    if !entries.is_empty() {
+        for kernel in kernels.iter() {
+            if !entries.iter().any(|entry| entry.contains(&kernel.entry)) {
+                problems.push(format!(
+                    "no grub or systemd-boot entry mentions {}",
+                    kernel.image.display()
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// How long the system has been up, in seconds
+pub fn uptime() -> Option<u64> {
+    if detect::os() == "macos" {
+        // `{ sec = 1700000000, usec = 0 } Tue Nov 14 22:13:20 2023`
+        let output = scuttle::run_output(&scuttle::App {
+            command: String::from("sysctl"),
+            args: vec!["-n".to_string(), "kern.boottime".to_string()],
+        })
+        .ok()?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        let booted: u64 = output
+            .split_once("sec = ")?
+            .1
+            .split(',')
+            .next()?
+            .parse()
+            .ok()?;
+
+        return Some(now().saturating_sub(booted));
+    }
+
+    // `350735.47 234388.90`, the first number is the uptime
+    let uptime = fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+
+    Some(seconds as u64)
+}
+
+/// Whether the updates that were applied need a reboot to take effect
+///
+/// Ubuntu flags this itself, on Arch the running kernel's modules disappear once a new
+/// kernel is installed.
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+fn reboot_required(release: Option<&str>) -> bool {
+    match release {
+        Some("ubuntu") | Some("pop") | Some("debian") => {
+            Path::new("/var/run/reboot-required").exists()
+        }
+        Some("arch") | Some("endeavouros") => {
+            match fs::read_to_string("/proc/sys/kernel/osrelease") {
+                Ok(running) => !Path::new("/usr/lib/modules").join(running.trim()).exists(),
+                Err(_) => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// When a reboot was first found to be pending, in seconds since the Unix epoch
+///
+/// The first run that finds one pending remembers when in the state directory and the
+/// time is forgotten again once there's no reboot pending.
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+pub fn reboot_pending_since(release: Option<&str>) -> Option<u64> {
+    let path = config::state_dir()?.join("reboot-pending");
+
+    if !reboot_required(release) {
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+
+    if let Some(since) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|since| since.trim().parse().ok())
+    {
+        return Some(since);
+    }
+
+    let since = now();
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    if let Err(error) = fs::write(&path, since.to_string()) {
+        eprintln!("WARNING: could not write {}: {}", path.display(), error);
+    }
+
+    Some(since)
+}
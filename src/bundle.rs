@@ -0,0 +1,136 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use scuttle::App;
+use serde_json::Value;
+
+use crate::boot;
+use crate::config;
+use crate::detect;
+use crate::history;
+use crate::report;
+
+/// Describe the machine: its hostname, OS, kernel, uptime and the up2date version
+fn system_info() -> String {
+    let mut info = format!("up2date {}\n", env!("CARGO_PKG_VERSION"));
+    let mut line = |name: &str, value: Option<String>| {
+        info.push_str(&format!(
+            "{}: {}\n",
+            name,
+            value.unwrap_or(String::from("unknown"))
+        ));
+    };
+
+    line("hostname", sys_info::hostname().ok());
+    line("os", sys_info::os_type().ok());
+    line("kernel", sys_info::os_release().ok());
+
+    if detect::os() == "linux" {
+        line(
+            "distribution",
+            sys_info::linux_os_release()
+                .ok()
+                .and_then(|release| release.pretty_name),
+        );
+    }
+
+    line("uptime", boot::uptime().map(report::format_duration));
+    info
+}
+
+/// List every package a run changed, one per line under the step that changed it
+///
+/// # Arguments
+///
+/// * `run` - The run as it's kept in the history
+fn changes(run: &Value) -> String {
+    let mut changes = String::new();
+
+    for step in run["steps"].as_array().map_or(&[][..], Vec::as_slice) {
+        let packages = step["changes"].as_array().map_or(&[][..], Vec::as_slice);
+
+        if packages.is_empty() {
+            continue;
+        }
+
+        changes.push_str(&format!("{}\n", step["command"].as_str().unwrap_or("")));
+
+        for package in packages.iter() {
+            changes.push_str(&format!(
+                "    {} {} -> {}\n",
+                package["package"].as_str().unwrap_or(""),
+                package["from"].as_str().unwrap_or("(new)"),
+                package["to"].as_str().unwrap_or("(removed)")
+            ));
+        }
+    }
+
+    changes
+}
+
+/// Write a `.tar.gz` with what's needed to look into the last run somewhere else: its
+/// JSON report, the packages it changed, the whole run history and a description of the
+/// machine
+///
+/// Nothing is sent anywhere. The reports are already redacted when they're recorded
+/// and the config file is left out since it can name where the secrets are kept.
+///
+/// # Arguments
+///
+/// * `output` - Where to write the bundle, `up2date-<hostname>-<date>.tar.gz` in the
+///   current directory when `None`
+pub fn write(output: Option<&Path>) -> io::Result<PathBuf> {
+    let runs = history::read()?;
+    let last = runs
+        .last()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no runs recorded yet"))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => PathBuf::from(format!(
+            "up2date-{}-{}.tar.gz",
+            sys_info::hostname().unwrap_or(String::from("unknown")),
+            &history::format_date(now)[..10]
+        )),
+    };
+    let dir = config::state_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?
+        .join(format!("bundle-{}", now));
+
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join("report.json"),
+        serde_json::to_string_pretty(last)? + "\n",
+    )?;
+    fs::write(dir.join("changes.txt"), changes(last))?;
+    fs::write(dir.join("system.txt"), system_info())?;
+
+    if let Some(path) = history::path() {
+        fs::copy(path, dir.join("history.jsonl"))?;
+    }
+
+    let tar = App {
+        command: String::from("tar"),
+        args: vec![
+            "-czf".to_string(),
+            output.display().to_string(),
+            "-C".to_string(),
+            dir.display().to_string(),
+            ".".to_string(),
+        ],
+    };
+    let archived = scuttle::run_status(&tar);
+
+    fs::remove_dir_all(&dir)?;
+
+    if archived?.success() {
+        Ok(output)
+    } else {
+        Err(io::Error::other("tar could not write the bundle"))
+    }
+}
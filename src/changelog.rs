@@ -0,0 +1,122 @@
+use scuttle::App;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::detect::has_binary;
+use crate::parsers;
+use crate::preview::PendingUpdate;
+
+/// Where Arch keeps each package's packaging, whose commits are the closest it has to a
+/// changelog
+const ARCH_PACKAGING: &str =
+    "https://gitlab.archlinux.org/api/v4/projects/archlinux%2Fpackaging%2Fpackages%2F";
+
+/// What's in a pending update of one of the packages `[changelogs]` asks about
+#[derive(Debug, Serialize)]
+pub struct Changelog {
+    pub manager: &'static str,
+    pub package: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// The changes since the installed version, the newest first
+    pub changes: Vec<String>,
+}
+
+/// Run a command and return what it printed, nothing when it fails
+///
+/// # Arguments
+///
+/// * `command_line` - The command and its arguments
+fn output(command_line: &[&str]) -> String {
+    match scuttle::run_output(&App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    }) {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => String::new(),
+    }
+}
+
+/// The changes in a pending update, as far as its manager can say before it's installed
+///
+/// apt and dnf have the changelogs of the packages in their repositories. Arch has none,
+/// the commit messages of the package's packaging repository stand in for it.
+///
+/// # Arguments
+///
+/// * `pending` - The pending update
+/// * `config` - The config, for the binaries to ask with
+fn changes(pending: &PendingUpdate, config: &Config) -> Vec<String> {
+    let package = pending.package.as_str();
+
+    match pending.manager {
+        "apt" => parsers::parse_debian_changelog(
+            &output(&[
+                &config.apt.executable.binary("apt-get"),
+                "changelog",
+                package,
+            ]),
+            pending.from.as_deref(),
+        ),
+        "dnf" => output(&["dnf", "changelog", "--upgrades", package])
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with('-'))
+            .map(|line| line.trim_start_matches(['-', ' ']).to_string())
+            .collect(),
+        "pacman" if has_binary("curl") => {
+            let url = format!(
+                "{}{}/repository/commits?per_page=20",
+                ARCH_PACKAGING, package
+            );
+            let commits: Value =
+                serde_json::from_str(&output(&["curl", "--silent", "--fail", &url]))
+                    .unwrap_or(Value::Null);
+
+            commits
+                .as_array()
+                .map(|commits| {
+                    commits
+                        .iter()
+                        .filter_map(|commit| commit["title"].as_str())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The changelogs of the pending updates `[changelogs]` asks about, nothing when it
+/// doesn't list any packages
+///
+/// # Arguments
+///
+/// * `pending` - Every pending update
+/// * `config` - The config, for the `[changelogs]` settings and the binaries to ask with
+pub fn gather(pending: &[PendingUpdate], config: &Config) -> Vec<Changelog> {
+    let settings = &config.changelogs;
+
+    pending
+        .iter()
+        .filter(|pending| settings.includes(&pending.package))
+        .map(|pending| Changelog {
+            manager: pending.manager,
+            package: pending.package.clone(),
+            from: pending.from.clone(),
+            to: pending.to.clone(),
+            changes: changes(pending, config)
+                .into_iter()
+                .take(settings.lines)
+                .collect(),
+        })
+        .filter(|changelog| !changelog.changes.is_empty())
+        .collect()
+}
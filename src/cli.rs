@@ -0,0 +1,1107 @@
+use std::path::PathBuf;
+
+use crate::config::Group;
+use crate::history;
+use crate::report;
+
+/// What up2date has been asked to do
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum Command {
+    /// Update everything
+    #[default]
+    Run,
+    /// Only download updates so they can be applied later
+    Fetch,
+    /// List what every manager would change without changing anything
+    Check,
+    /// Print the commands a run would run without running them
+    Plan,
+    /// Write a sudoers snippet for the commands a run would run with sudo, to `path` when
+    /// it's given and stdout otherwise
+    Sudoers(Option<PathBuf>),
+    /// Restore a snapshot taken before an update
+    Rollback,
+    /// Show how long each step takes and how often it fails over time
+    Stats,
+    /// Print what the last check and run found, from what they left behind
+    Status(StatusFormat),
+    /// Look through the history of past runs
+    History(History),
+    /// Answer HTTP requests for the status and start runs when asked to
+    Serve,
+    /// Write the last run's report, its changes and the system info to an archive, to
+    /// `path` when it's given
+    ReportBundle(Option<PathBuf>),
+    /// Turn a step on in the config file
+    Enable(String),
+    /// Turn a step off in the config file
+    Disable(String),
+    /// Print the long help or what the steps do
+    Help(Help),
+    /// Print the man page
+    Man,
+    /// Set up or start the desktop launcher
+    Desktop(Desktop),
+}
+
+/// What `up2date desktop` does
+#[derive(Debug, PartialEq, Eq)]
+pub enum Desktop {
+    /// Create the launcher, and the autostart entry when `autostart` is set
+    Install { autostart: bool },
+    /// Start a run in a terminal when the last one is too long ago, or right away when
+    /// `now` is set
+    Launch { now: bool },
+}
+
+/// What `up2date help` is about
+#[derive(Debug, PartialEq, Eq)]
+pub enum Help {
+    /// The usage and every step
+    Usage,
+    /// What one step runs, or every step when no step is given
+    Steps(Option<String>),
+}
+
+/// How `up2date status` prints the status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFormat {
+    /// One part on each line
+    Lines,
+    /// Every part on one line, for a shell prompt or a status bar
+    Short,
+    /// The JSON a waybar custom module reads
+    Waybar,
+}
+
+/// What to do with the history of past runs
+#[derive(Debug, PartialEq, Eq)]
+pub enum History {
+    /// List the recorded runs
+    List,
+    /// Compare two runs, each given by number or date
+    Diff(String, String),
+    /// Print the recorded runs, only those started on or after `since` when it's set
+    Export { format: Format, since: Option<u64> },
+}
+
+/// The formats the history can be exported in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One row per step, or per package a step changed
+    Csv,
+    /// The runs as they were recorded
+    Json,
+}
+
+/// Options given on the command line
+#[derive(Debug, Default)]
+pub struct Options {
+    pub command: Command,
+    /// Print the usage and exit
+    pub help: bool,
+    /// Show what every manager intends to change and ask once before applying anything
+    pub preview: bool,
+    /// Apply previously fetched updates without refreshing the package databases
+    pub offline_ok: bool,
+    /// Skip everything that needs the network and stop if anything else fails
+    pub offline: bool,
+    /// Where to write a report of the run
+    pub report_file: Option<PathBuf>,
+    /// What the report is written as
+    pub report_format: report::Format,
+    /// Run a list of apps read from stdin instead of the usual updates
+    pub stdin: bool,
+    /// Only run the steps in these groups, every enabled group runs when empty
+    pub only_groups: Vec<Group>,
+    /// Print the pending updates as JSON
+    pub json: bool,
+    /// Send a notification when the pending updates are over the configured threshold
+    pub notify: bool,
+    /// Only show the steps that changed something or failed
+    pub changed_only: bool,
+    /// Plan the whole run and ask once before any of it runs
+    pub confirm: bool,
+    /// Run even outside the `[schedule]` windows
+    pub force: bool,
+    /// Started by a timer or cron, so `[schedule]` applies
+    pub scheduled: bool,
+    /// Say what each step does and wait for Enter before running it
+    pub explain: bool,
+    /// Write the summary as an HTML page and open it in the browser once the run is done
+    pub open_report: bool,
+}
+
+/// A command as it's parsed and as the usage and the man page describe it
+struct CommandSpec {
+    /// The words it's given as, e.g. `history export`
+    name: &'static str,
+    /// What it takes after its words besides the options, `<...>` when it's needed and
+    /// `[<...>]` when it isn't
+    args: &'static str,
+    about: &'static str,
+}
+
+/// An option as it's parsed and as the usage and the man page describe it
+struct OptionSpec {
+    flag: &'static str,
+    /// What it takes after it, a placeholder like `<path>` or the values it can be like
+    /// `json|md|html`, nothing for a switch
+    value: Option<&'static str>,
+    /// The commands it's for, by name
+    commands: &'static [&'static str],
+    /// Whether the commands can't do without it
+    required: bool,
+    about: &'static str,
+}
+
+/// The commands runs, plans and sudoers snippets share the options of
+const RUNS: &[&str] = &["run", "plan", "sudoers generate"];
+
+/// Every command, in the order the usage lists them, `run` when none is given
+const COMMANDS: [CommandSpec; 20] = [
+    CommandSpec {
+        name: "run",
+        args: "",
+        about: "update everything (the default)",
+    },
+    CommandSpec {
+        name: "fetch",
+        args: "",
+        about: "download updates without installing them",
+    },
+    CommandSpec {
+        name: "check",
+        args: "",
+        about: "list what every package manager would change, without changing anything",
+    },
+    CommandSpec {
+        name: "plan",
+        args: "",
+        about: "print every command a run would run, by group, without running anything that changes something. The commands that list outdated or orphaned packages are run to fill in the rest",
+    },
+    CommandSpec {
+        name: "sudoers generate",
+        args: "",
+        about: "print a sudoers snippet that lets you run exactly the commands the plan runs with sudo, with their full paths and args, without a password, e.g. for a timer. Generate it again when the plan changes",
+    },
+    CommandSpec {
+        name: "rollback",
+        args: "",
+        about: "restore one of the snapshots taken before updating",
+    },
+    CommandSpec {
+        name: "stats",
+        args: "",
+        about: "show how long each step takes and how often it fails, from the history of past runs",
+    },
+    CommandSpec {
+        name: "status",
+        args: "",
+        about: "print how many updates the last check found, when the last run was and whether a reboot is needed. Nothing is checked again",
+    },
+    CommandSpec {
+        name: "history",
+        args: "",
+        about: "list the recorded runs with their numbers",
+    },
+    CommandSpec {
+        name: "history diff",
+        args: "<run> <run>",
+        about: "show the packages that changed between two runs, each given by its number or a YYYY-MM-DD date (UTC, the day's last run)",
+    },
+    CommandSpec {
+        name: "history export",
+        args: "",
+        about: "print the recorded runs and the packages they changed",
+    },
+    CommandSpec {
+        name: "serve",
+        args: "",
+        about: "answer HTTP requests on [serve] address: GET /status for the last run, GET /pending for the pending updates found by the scheduled checks and POST /run, with the token as a bearer token, to start a run",
+    },
+    CommandSpec {
+        name: "report bundle",
+        args: "",
+        about: "write a .tar.gz with the last run's JSON report and package changes, the run history and a description of this machine",
+    },
+    CommandSpec {
+        name: "enable",
+        args: "<step>",
+        about: "turn a step or group on in the config file, e.g. julia or cloud",
+    },
+    CommandSpec {
+        name: "disable",
+        args: "<step>",
+        about: "turn a step or group off in the config file, e.g. nvim",
+    },
+    CommandSpec {
+        name: "help",
+        args: "",
+        about: "this message followed by every step",
+    },
+    CommandSpec {
+        name: "help steps",
+        args: "[<step>]",
+        about: "every step, or what one step runs, what it needs and where it's set up",
+    },
+    CommandSpec {
+        name: "man",
+        args: "",
+        about: "print the man page, e.g. up2date man > up2date.1",
+    },
+    CommandSpec {
+        name: "desktop install",
+        args: "",
+        about: "add up2date to the desktop's applications, opening a terminal with a run only when the last run without failures is older than [desktop] stale_after. The launcher's Update now action always opens one",
+    },
+    CommandSpec {
+        name: "desktop launch",
+        args: "",
+        about: "what the launcher runs: open a terminal with a run when the last run without failures is older than [desktop] stale_after",
+    },
+];
+
+/// Every option besides `-h` and `--help`, which every command takes
+const OPTIONS: [OptionSpec; 23] = [
+    OptionSpec {
+        flag: "--preview",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "show all pending changes and ask before applying them",
+    },
+    OptionSpec {
+        flag: "--confirm",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "plan the whole run first, show every command and everything it would remove, and ask once before anything runs",
+    },
+    OptionSpec {
+        flag: "--explain",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "say what each command does and why before it runs, and wait for Enter",
+    },
+    OptionSpec {
+        flag: "--force",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "run even outside the [schedule] windows when started by a timer or cron",
+    },
+    OptionSpec {
+        flag: "--scheduled",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "this run was started by cron, so [schedule] applies. Runs in a systemd unit are found out on their own",
+    },
+    OptionSpec {
+        flag: "--offline-ok",
+        value: None,
+        commands: RUNS,
+        required: false,
+        about: "apply fetched updates without refreshing package databases",
+    },
+    OptionSpec {
+        flag: "--offline",
+        value: None,
+        commands: RUNS,
+        required: false,
+        about: "only do what works without a network connection and stop on the first failure",
+    },
+    OptionSpec {
+        flag: "--stdin",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "run a JSON or TOML list of apps read from stdin instead of the usual updates",
+    },
+    OptionSpec {
+        flag: "--changed-only",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "only show the steps that changed something or failed, and the summary. Not with a manager whose assume_yes is false, its prompts would be held back too",
+    },
+    OptionSpec {
+        flag: "--only-group",
+        value: Some("<group>"),
+        commands: RUNS,
+        required: false,
+        about: "only run the steps in <group>, can be given more than once. The groups are system, cleanup, rust, editors, languages, containers, cloud, gaming, selfhosted, security, maintenance and audit",
+    },
+    OptionSpec {
+        flag: "--report-file",
+        value: Some("<path>"),
+        commands: &["run", "fetch"],
+        required: false,
+        about: "write a JSON report of the run to <path>, even if it's interrupted",
+    },
+    OptionSpec {
+        flag: "--report-format",
+        value: Some("json|md|html"),
+        commands: &["run", "fetch"],
+        required: false,
+        about: "write the --report-file as the summary in Markdown, e.g. for a wiki or a ticket, or as an HTML page instead of JSON",
+    },
+    OptionSpec {
+        flag: "--open-report",
+        value: None,
+        commands: &["run"],
+        required: false,
+        about: "write the summary, with every changed package and the notes, to report.html in the state directory and open it in the browser once the run is done",
+    },
+    OptionSpec {
+        flag: "--json",
+        value: None,
+        commands: &["check"],
+        required: false,
+        about: "print what would change as JSON",
+    },
+    OptionSpec {
+        flag: "--notify",
+        value: None,
+        commands: &["check"],
+        required: false,
+        about: "send a desktop or webhook notification when enough updates, or any security update, are pending, e.g. from an hourly timer",
+    },
+    OptionSpec {
+        flag: "--output",
+        value: Some("<path>"),
+        commands: &["sudoers generate", "report bundle"],
+        required: false,
+        about: "write the snippet or the bundle to <path> instead of stdout or up2date-<hostname>-<date>.tar.gz",
+    },
+    OptionSpec {
+        flag: "--short",
+        value: None,
+        commands: &["status"],
+        required: false,
+        about: "print the status on one line, e.g. for a shell prompt",
+    },
+    OptionSpec {
+        flag: "--format",
+        value: Some("waybar"),
+        commands: &["status"],
+        required: false,
+        about: "print the status as a waybar module's JSON",
+    },
+    OptionSpec {
+        flag: "--format",
+        value: Some("csv|json"),
+        commands: &["history export"],
+        required: true,
+        about: "what to export the runs as",
+    },
+    OptionSpec {
+        flag: "--since",
+        value: Some("<date>"),
+        commands: &["history export"],
+        required: false,
+        about: "only export the runs since a YYYY-MM-DD date",
+    },
+    OptionSpec {
+        flag: "--autostart",
+        value: None,
+        commands: &["desktop install"],
+        required: false,
+        about: "also start the launcher at login",
+    },
+    OptionSpec {
+        flag: "--now",
+        value: None,
+        commands: &["desktop launch"],
+        required: false,
+        about: "start a run however recent the last one is",
+    },
+    OptionSpec {
+        flag: "--help",
+        value: None,
+        commands: &[],
+        required: false,
+        about: "show this message",
+    },
+];
+
+/// How far in the usage the descriptions of the commands and options start
+const INDENT: usize = 17;
+
+/// How wide the usage is at most
+const WIDTH: usize = 78;
+
+impl OptionSpec {
+    /// The option as it's given, e.g. `--only-group <group>`, `-h, --help` for the help
+    fn term(&self) -> String {
+        match self.value {
+            Some(value) => format!("{} {}", self.flag, value),
+            None if self.flag == "--help" => String::from("-h, --help"),
+            None => self.flag.to_string(),
+        }
+    }
+}
+
+/// Each command with what it takes, e.g. `history export --format csv|json [--since
+/// <date>]`, for the usage and the man page's synopsis
+pub fn synopsis() -> Vec<String> {
+    COMMANDS
+        .iter()
+        .map(|command| {
+            if command.name == "run" {
+                return String::from("[run] [options]");
+            }
+
+            let options = OPTIONS
+                .iter()
+                .filter(|option| option.commands.contains(&command.name))
+                .map(|option| match option.required {
+                    true => option.term(),
+                    false => format!("[{}]", option.term()),
+                });
+
+            std::iter::once(command.name.to_string())
+                .chain(Some(command.args.to_string()).filter(|args| !args.is_empty()))
+                .chain(options)
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+/// The commands as what they're given as and what they do, for the usage and the man page
+pub fn commands() -> Vec<(String, String)> {
+    COMMANDS
+        .iter()
+        .map(|command| {
+            let term = match command.args {
+                "" => command.name.to_string(),
+                args => format!("{} {}", command.name, args),
+            };
+
+            (term, command.about.to_string())
+        })
+        .collect()
+}
+
+/// The options as they're given and what they do, with the commands they're for when
+/// that's not only `run`, for the usage and the man page
+pub fn options() -> Vec<(String, String)> {
+    OPTIONS
+        .iter()
+        .map(|option| {
+            let about = match option.commands {
+                [] | ["run"] => option.about.to_string(),
+                commands => format!("{} ({})", option.about, commands.join(", ")),
+            };
+
+            (option.term(), about)
+        })
+        .collect()
+}
+
+/// A command or an option in the usage, its description wrapped to the usage's width and
+/// under it when the term is too long to have it next to it
+///
+/// # Arguments
+///
+/// * `term` - The command or the option
+/// * `about` - What it does
+fn entry(term: &str, about: &str) -> String {
+    let mut entry = format!("  {}", term);
+    let mut width = entry.len();
+
+    if width + 2 > INDENT {
+        entry.push('\n');
+        width = 0;
+    }
+
+    for word in about.split_whitespace() {
+        if width < INDENT {
+            entry.push_str(&" ".repeat(INDENT - width));
+            width = INDENT;
+        } else if width + 1 + word.len() > WIDTH {
+            entry.push('\n');
+            entry.push_str(&" ".repeat(INDENT));
+            width = INDENT;
+        } else {
+            entry.push(' ');
+            width += 1;
+        }
+
+        entry.push_str(word);
+        width += word.len();
+    }
+
+    entry.push('\n');
+    entry
+}
+
+/// The usage, built from the commands and options the parser takes so the two can't
+/// drift apart
+pub fn usage() -> String {
+    let mut usage = String::new();
+
+    for (index, line) in synopsis().iter().enumerate() {
+        let mut synopsis = format!("{} up2date", if index == 0 { "usage:" } else { "      " });
+
+        // the options that don't fit go on the next line, under the command
+        for (index, part) in line.split(" [").enumerate() {
+            let part = if index == 0 {
+                part.to_string()
+            } else {
+                format!("[{}", part)
+            };
+
+            if synopsis.rsplit('\n').next().unwrap_or_default().len() + 1 + part.len() > WIDTH {
+                synopsis.push('\n');
+                synopsis.push_str(&" ".repeat(INDENT));
+            } else {
+                synopsis.push(' ');
+            }
+
+            synopsis.push_str(&part);
+        }
+
+        usage.push_str(&synopsis);
+        usage.push('\n');
+    }
+
+    usage.push_str("\ncommands:\n");
+
+    for (term, about) in commands() {
+        usage.push_str(&entry(&term, &about));
+    }
+
+    usage.push_str("\noptions:\n");
+
+    for (term, about) in options() {
+        usage.push_str(&entry(&term, &about));
+    }
+
+    usage.trim_end().to_string()
+}
+
+/// Parse the command line arguments, not including the program name
+///
+/// The command is the longest one in `COMMANDS` whose words the arguments start with,
+/// `run` when they don't start with any. Its args follow and then the options `OPTIONS`
+/// has for it, in any order.
+///
+/// # Arguments
+///
+/// * `args` - The arguments to parse
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let args: Vec<String> = args.collect();
+    let starts = |command: &&CommandSpec| {
+        let words: Vec<&str> = command.name.split(' ').collect();
+
+        args.len() >= words.len() && words.iter().zip(args.iter()).all(|(word, arg)| word == arg)
+    };
+    let (command, words) = match COMMANDS
+        .iter()
+        .filter(starts)
+        .max_by_key(|command| command.name.len())
+    {
+        Some(command) => (command, command.name.split(' ').count()),
+        None => {
+            let first = args.first().map(String::as_str).unwrap_or_default();
+            let subcommands: Vec<&str> = COMMANDS
+                .iter()
+                .filter_map(|command| command.name.strip_prefix(first)?.strip_prefix(' '))
+                .collect();
+
+            if !first.is_empty() && !subcommands.is_empty() {
+                return Err(format!(
+                    "{} needs a subcommand: {}",
+                    first,
+                    subcommands.join(", ")
+                ));
+            }
+
+            (&COMMANDS[0], 0)
+        }
+    };
+    let mut args = args.into_iter().skip(words).peekable();
+    let mut positional: Vec<String> = Vec::new();
+
+    for arg in command.args.split_whitespace() {
+        match args.next_if(|next| !next.starts_with('-')) {
+            Some(value) => positional.push(value),
+            None if arg.starts_with('[') => {}
+            None => return Err(format!("{} needs {}", command.name, command.args)),
+        }
+    }
+
+    let mut output: Option<PathBuf> = None;
+    let mut status = StatusFormat::Lines;
+    let mut export: Option<Format> = None;
+    let mut since: Option<u64> = None;
+    let mut autostart = false;
+    let mut now = false;
+    let mut given: Vec<&str> = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            options.help = true;
+            continue;
+        }
+
+        let option = OPTIONS
+            .iter()
+            .find(|option| option.flag == arg && option.commands.contains(&command.name))
+            .ok_or_else(|| format!("unknown argument: {}", arg))?;
+        let value = match option.value {
+            Some(expected) => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs {}", arg, expected))?;
+
+                // a value that isn't a placeholder is one of the values it lists
+                if !expected.starts_with('<') && !expected.split('|').any(|choice| choice == value)
+                {
+                    return Err(format!("{} needs {}, not {}", arg, expected, value));
+                }
+
+                value
+            }
+            None => String::new(),
+        };
+
+        given.push(option.flag);
+
+        match option.flag {
+            "--preview" => options.preview = true,
+            "--confirm" => options.confirm = true,
+            "--explain" => options.explain = true,
+            "--force" => options.force = true,
+            "--scheduled" => options.scheduled = true,
+            "--offline-ok" => options.offline_ok = true,
+            "--offline" => options.offline = true,
+            "--stdin" => options.stdin = true,
+            "--changed-only" => options.changed_only = true,
+            "--open-report" => options.open_report = true,
+            "--json" => options.json = true,
+            "--notify" => options.notify = true,
+            "--only-group" => match Group::parse(&value) {
+                Some(group) => options.only_groups.push(group),
+                None => return Err(format!("unknown group: {}", value)),
+            },
+            "--report-file" => options.report_file = Some(PathBuf::from(value)),
+            "--report-format" => {
+                options.report_format = match value.as_str() {
+                    "md" => report::Format::Markdown,
+                    "html" => report::Format::Html,
+                    _ => report::Format::Json,
+                }
+            }
+            "--output" => output = Some(PathBuf::from(value)),
+            "--short" => status = StatusFormat::Short,
+            "--format" => match value.as_str() {
+                "waybar" => status = StatusFormat::Waybar,
+                "csv" => export = Some(Format::Csv),
+                _ => export = Some(Format::Json),
+            },
+            "--since" => match history::parse_date(&value) {
+                Some(date) => since = Some(date),
+                None => return Err(format!("--since needs a YYYY-MM-DD date, not {}", value)),
+            },
+            "--autostart" => autostart = true,
+            "--now" => now = true,
+            _ => {}
+        }
+    }
+
+    if let Some(option) = OPTIONS.iter().find(|option| {
+        option.required && option.commands.contains(&command.name) && !given.contains(&option.flag)
+    }) {
+        return Err(format!("{} needs {}", command.name, option.term()));
+    }
+
+    let mut positional = positional.into_iter();
+
+    options.command = match command.name {
+        "fetch" => Command::Fetch,
+        "check" => Command::Check,
+        "plan" => Command::Plan,
+        "sudoers generate" => Command::Sudoers(output),
+        "rollback" => Command::Rollback,
+        "stats" => Command::Stats,
+        "status" => Command::Status(status),
+        "history" => Command::History(History::List),
+        "history diff" => match (positional.next(), positional.next()) {
+            (Some(from), Some(to)) => Command::History(History::Diff(from, to)),
+            _ => return Err(format!("{} needs {}", command.name, command.args)),
+        },
+        "history export" => match export {
+            Some(format) => Command::History(History::Export { format, since }),
+            None => return Err(format!("{} needs --format", command.name)),
+        },
+        "serve" => Command::Serve,
+        "report bundle" => Command::ReportBundle(output),
+        "enable" => Command::Enable(positional.next().unwrap_or_default()),
+        "disable" => Command::Disable(positional.next().unwrap_or_default()),
+        "help" => Command::Help(Help::Usage),
+        "help steps" => Command::Help(Help::Steps(positional.next())),
+        "man" => Command::Man,
+        "desktop install" => Command::Desktop(Desktop::Install { autostart }),
+        "desktop launch" => Command::Desktop(Desktop::Launch { now }),
+        _ => Command::Run,
+    };
+
+    if options.preview && options.stdin {
+        return Err(String::from("--preview can't be used with --stdin"));
+    }
+
+    if options.confirm && options.stdin {
+        // stdin can only be read once, by the plan or by the run
+        return Err(String::from("--confirm can't be used with --stdin"));
+    }
+
+    if options.explain && options.stdin {
+        // Enter is read from stdin too
+        return Err(String::from("--explain can't be used with --stdin"));
+    }
+
+    if options.report_format != report::Format::Json && options.report_file.is_none() {
+        return Err(String::from("--report-format needs --report-file"));
+    }
+
+    if options.preview && options.offline {
+        // the check commands refresh the package databases first
+        return Err(String::from(
+            "--preview needs the network so it can't be used with --offline",
+        ));
+    }
+
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Options, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn command(args: &[&str]) -> Command {
+        parse_args(args).unwrap().command
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse_args(args).unwrap_err()
+    }
+
+    #[test]
+    fn run_is_the_default() {
+        assert_eq!(command(&[]), Command::Run);
+        assert_eq!(command(&["run"]), Command::Run);
+    }
+
+    #[test]
+    fn run_options() {
+        let options = parse_args(&[
+            "--preview",
+            "--explain",
+            "--force",
+            "--scheduled",
+            "--changed-only",
+            "--only-group",
+            "rust",
+            "--only-group",
+            "cloud",
+            "--report-file",
+            "report.md",
+            "--report-format",
+            "md",
+            "--open-report",
+        ])
+        .unwrap();
+
+        assert!(options.preview && options.explain && options.force && options.scheduled);
+        assert!(options.changed_only && options.open_report);
+        assert_eq!(options.only_groups, vec![Group::Rust, Group::Cloud]);
+        assert_eq!(options.report_file, Some(PathBuf::from("report.md")));
+        assert_eq!(options.report_format, report::Format::Markdown);
+
+        let options = parse_args(&["run", "--confirm", "--offline", "--stdin"]);
+
+        assert_eq!(options.unwrap_err(), "--confirm can't be used with --stdin");
+        assert!(
+            parse_args(&["run", "--offline-ok", "--stdin"])
+                .unwrap()
+                .stdin
+        );
+    }
+
+    #[test]
+    fn commands_without_arguments() {
+        assert_eq!(command(&["fetch"]), Command::Fetch);
+        assert_eq!(command(&["check"]), Command::Check);
+        assert_eq!(command(&["plan"]), Command::Plan);
+        assert_eq!(command(&["rollback"]), Command::Rollback);
+        assert_eq!(command(&["stats"]), Command::Stats);
+        assert_eq!(command(&["serve"]), Command::Serve);
+        assert_eq!(command(&["man"]), Command::Man);
+    }
+
+    #[test]
+    fn check_and_fetch_options() {
+        let options = parse_args(&["check", "--json", "--notify"]).unwrap();
+
+        assert!(options.json && options.notify);
+
+        let options = parse_args(&[
+            "fetch",
+            "--report-file",
+            "r.html",
+            "--report-format",
+            "html",
+        ]);
+
+        assert_eq!(options.unwrap().report_format, report::Format::Html);
+    }
+
+    #[test]
+    fn plan_and_sudoers() {
+        let options = parse_args(&["plan", "--offline-ok", "--only-group", "system"]).unwrap();
+
+        assert_eq!(options.command, Command::Plan);
+        assert!(options.offline_ok);
+        assert_eq!(options.only_groups, vec![Group::System]);
+        assert_eq!(command(&["sudoers", "generate"]), Command::Sudoers(None));
+        assert_eq!(
+            command(&["sudoers", "generate", "--output", "up2date"]),
+            Command::Sudoers(Some(PathBuf::from("up2date")))
+        );
+        assert!(
+            parse_args(&["sudoers", "generate", "--offline"])
+                .unwrap()
+                .offline
+        );
+    }
+
+    #[test]
+    fn status_formats() {
+        assert_eq!(command(&["status"]), Command::Status(StatusFormat::Lines));
+        assert_eq!(
+            command(&["status", "--short"]),
+            Command::Status(StatusFormat::Short)
+        );
+        assert_eq!(
+            command(&["status", "--format", "waybar"]),
+            Command::Status(StatusFormat::Waybar)
+        );
+        assert_eq!(
+            error(&["status", "--format", "i3"]),
+            "--format needs waybar, not i3"
+        );
+    }
+
+    #[test]
+    fn history_commands() {
+        assert_eq!(command(&["history"]), Command::History(History::List));
+        assert_eq!(
+            command(&["history", "diff", "3", "2024-03-05"]),
+            Command::History(History::Diff(String::from("3"), String::from("2024-03-05")))
+        );
+        assert_eq!(
+            command(&["history", "export", "--format", "csv"]),
+            Command::History(History::Export {
+                format: Format::Csv,
+                since: None
+            })
+        );
+        assert_eq!(
+            command(&[
+                "history",
+                "export",
+                "--format",
+                "json",
+                "--since",
+                "2024-03-05"
+            ]),
+            Command::History(History::Export {
+                format: Format::Json,
+                since: history::parse_date("2024-03-05")
+            })
+        );
+        assert_eq!(
+            error(&["history", "diff", "3"]),
+            "history diff needs <run> <run>"
+        );
+        assert_eq!(
+            error(&["history", "export"]),
+            "history export needs --format csv|json"
+        );
+        assert_eq!(
+            error(&["history", "export", "--format", "waybar"]),
+            "--format needs csv|json, not waybar"
+        );
+        assert_eq!(
+            error(&["history", "export", "--format", "csv", "--since", "March"]),
+            "--since needs a YYYY-MM-DD date, not March"
+        );
+    }
+
+    #[test]
+    fn report_bundle() {
+        assert_eq!(command(&["report", "bundle"]), Command::ReportBundle(None));
+        assert_eq!(
+            command(&["report", "bundle", "--output", "b.tar.gz"]),
+            Command::ReportBundle(Some(PathBuf::from("b.tar.gz")))
+        );
+        assert_eq!(error(&["report"]), "report needs a subcommand: bundle");
+    }
+
+    #[test]
+    fn enable_and_disable() {
+        assert_eq!(
+            command(&["enable", "julia"]),
+            Command::Enable(String::from("julia"))
+        );
+        assert_eq!(
+            command(&["disable", "nvim"]),
+            Command::Disable(String::from("nvim"))
+        );
+        assert_eq!(error(&["enable"]), "enable needs <step>");
+    }
+
+    #[test]
+    fn help_and_help_steps() {
+        assert_eq!(command(&["help"]), Command::Help(Help::Usage));
+        assert_eq!(
+            command(&["help", "steps"]),
+            Command::Help(Help::Steps(None))
+        );
+        assert_eq!(
+            command(&["help", "steps", "apt"]),
+            Command::Help(Help::Steps(Some(String::from("apt"))))
+        );
+        assert!(parse_args(&["--help"]).unwrap().help);
+        assert!(parse_args(&["status", "-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn desktop_commands() {
+        assert_eq!(
+            command(&["desktop", "install"]),
+            Command::Desktop(Desktop::Install { autostart: false })
+        );
+        assert_eq!(
+            command(&["desktop", "install", "--autostart"]),
+            Command::Desktop(Desktop::Install { autostart: true })
+        );
+        assert_eq!(
+            command(&["desktop", "launch", "--now"]),
+            Command::Desktop(Desktop::Launch { now: true })
+        );
+        assert_eq!(
+            error(&["desktop", "install", "--now"]),
+            "unknown argument: --now"
+        );
+    }
+
+    #[test]
+    fn every_command_parses_as_the_usage_has_it() {
+        for spec in COMMANDS.iter() {
+            let mut args: Vec<String> = spec.name.split(' ').map(String::from).collect();
+
+            // what it can't do without, with the values it takes filled in
+            args.extend(
+                spec.args
+                    .split_whitespace()
+                    .filter(|arg| !arg.starts_with('['))
+                    .map(|_| String::from("1")),
+            );
+
+            for option in OPTIONS.iter() {
+                if option.required && option.commands.contains(&spec.name) {
+                    args.push(option.flag.to_string());
+                    args.extend(
+                        option
+                            .value
+                            .and_then(|value| value.split('|').next())
+                            .map(String::from),
+                    );
+                }
+            }
+
+            let command = parse(args.into_iter()).unwrap().command;
+
+            assert_eq!(command == Command::Run, spec.name == "run", "{}", spec.name);
+        }
+    }
+
+    #[test]
+    fn usage_lists_every_command_and_fits_the_terminal() {
+        let usage = usage();
+
+        assert!(usage.starts_with("usage: up2date [run] [options]\n"));
+        assert!(usage.contains("\n       up2date desktop launch [--now]\n"));
+        assert!(
+            usage.contains("\n       up2date history export --format csv|json [--since <date>]\n")
+        );
+        assert!(usage.contains(
+            "\n  --format waybar\n                 print the status as a waybar module's JSON (status)\n"
+        ));
+        assert!(usage.ends_with("  -h, --help     show this message"));
+        assert!(usage.lines().all(|line| line.len() <= WIDTH), "{}", usage);
+    }
+
+    #[test]
+    fn unknown_arguments() {
+        assert_eq!(error(&["--frobnicate"]), "unknown argument: --frobnicate");
+        assert_eq!(error(&["frobnicate"]), "unknown argument: frobnicate");
+        // an option is only known to the commands it's for
+        assert_eq!(
+            error(&["check", "--preview"]),
+            "unknown argument: --preview"
+        );
+        assert_eq!(error(&["--only-group", "office"]), "unknown group: office");
+        assert_eq!(error(&["sudoers"]), "sudoers needs a subcommand: generate");
+        assert_eq!(
+            error(&["desktop", "open"]),
+            "desktop needs a subcommand: install, launch"
+        );
+    }
+
+    #[test]
+    fn missing_values() {
+        assert_eq!(error(&["--only-group"]), "--only-group needs <group>");
+        assert_eq!(error(&["--report-file"]), "--report-file needs <path>");
+        assert_eq!(
+            error(&["--report-file", "r.json", "--report-format"]),
+            "--report-format needs json|md|html"
+        );
+        assert_eq!(error(&["status", "--format"]), "--format needs waybar");
+        assert_eq!(
+            error(&["sudoers", "generate", "--output"]),
+            "--output needs <path>"
+        );
+    }
+
+    #[test]
+    fn conflicting_options() {
+        assert_eq!(
+            error(&["--preview", "--stdin"]),
+            "--preview can't be used with --stdin"
+        );
+        assert_eq!(
+            error(&["--explain", "--stdin"]),
+            "--explain can't be used with --stdin"
+        );
+        assert_eq!(
+            error(&["--report-format", "html"]),
+            "--report-format needs --report-file"
+        );
+        assert_eq!(
+            error(&["--preview", "--offline"]),
+            "--preview needs the network so it can't be used with --offline"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use scuttle::App;
+
+use crate::config::AssumeYes;
+use crate::detect::has_binary;
+use crate::parsers;
+use crate::preview::PendingUpdate;
+use crate::runner::Step;
+
+/// The package receipt the Command Line Tools install, `pkgutil` knows their version by it
+const RECEIPT: &str = "com.apple.pkg.CLTools_Executables";
+
+/// The Command Line Tools updates Software Update has, nothing when they're up to date
+/// or aren't installed
+///
+/// brew builds from source with them, so a stale install breaks brew upgrades without
+/// brew saying why.
+pub fn pending() -> Vec<PendingUpdate> {
+    if !has_binary("softwareupdate") {
+        return Vec::new();
+    }
+
+    let list = match scuttle::run_output(&App {
+        command: String::from("softwareupdate"),
+        args: vec!["--list".to_string()],
+    }) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(_) => return Vec::new(),
+    };
+    let installed = scuttle::run_output(&App {
+        command: String::from("pkgutil"),
+        args: vec![format!("--pkg-info={}", RECEIPT)],
+    })
+    .ok()
+    .filter(|output| output.status.success())
+    .and_then(|output| parsers::parse_pkgutil_version(&String::from_utf8_lossy(&output.stdout)));
+
+    parsers::parse_softwareupdate(&list, installed.as_deref())
+}
+
+/// The steps that install the pending Command Line Tools updates, one for each label
+///
+/// # Arguments
+///
+/// * `pending` - The updates from `pending()`
+/// * `install` - Whether to install them, ask first or leave them alone
+pub fn install_steps(pending: &[PendingUpdate], install: AssumeYes) -> Vec<Step> {
+    if install == AssumeYes::No {
+        return Vec::new();
+    }
+
+    pending
+        .iter()
+        .map(|update| Step {
+            app: App {
+                command: String::from("sudo"),
+                args: vec![
+                    "softwareupdate".to_string(),
+                    "--install".to_string(),
+                    update.package.clone(),
+                ],
+            },
+            ask: install == AssumeYes::Ask,
+            required: false,
+        })
+        .collect()
+}
@@ -0,0 +1,1523 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Settings read from `$XDG_CONFIG_HOME/up2date/config.toml`
+///
+/// Every setting has a default so a missing file, or a file that only sets a few
+/// values, behaves the same as before the config file existed.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// How many lines of stderr to show in the summary for each failed command
+    pub failure_lines: usize,
+    /// Whether a failing step stops the whole run, by the step's command, e.g. `[steps.nvim]`
+    pub steps: HashMap<String, StepPolicy>,
+    /// Groups of steps to turn off, e.g. `editors = false`, every group runs by default
+    pub groups: HashMap<Group, bool>,
+    /// Credentials handed to the steps that need them, by name
+    pub secrets: HashMap<String, Secret>,
+    pub apt: Apt,
+    pub pacman: Manager,
+    pub yay: Manager,
+    /// Which flatpak installations and remotes to update from
+    pub flatpak: Flatpak,
+    /// The docker compose and podman-compose projects to pull and restart
+    pub containers: Containers,
+    /// Upgrading a single k3s or k0s node, off by default
+    pub kubernetes: Kubernetes,
+    /// Where Nextcloud is and whether to upgrade it, for the selfhosted group
+    pub selfhosted: Selfhosted,
+    pub snapshot: Snapshot,
+    pub cleanup: Cleanup,
+    pub services: Services,
+    pub brew: Brew,
+    pub rust: Rust,
+    pub nvim: Nvim,
+    pub julia: Toggle,
+    pub r: Toggle,
+    pub haskell: Toggle,
+    pub dotnet: Dotnet,
+    pub conda: Conda,
+    pub sdkman: Manager,
+    pub powershell: Powershell,
+    pub windows: Windows,
+    /// helm repos and plugins, and the latest terraform through tfenv or tenv
+    pub infra: Toggle,
+    /// Check for known vulnerabilities with cargo audit and pip-audit after updating
+    pub audit: Toggle,
+    /// Where `up2date serve` listens and the token that lets it start runs
+    pub serve: Serve,
+    /// The MQTT broker to publish each run's results to, for Home Assistant
+    pub mqtt: Mqtt,
+    /// When and how `up2date check --notify` says updates are available
+    pub notify: Notify,
+    /// The packages whose changelogs the preview shows when they have an update
+    pub changelogs: Changelogs,
+    /// The `up2date-plugin-*` executables found on `PATH`
+    pub plugins: Plugins,
+    /// The Lua scripts in `scripts/` next to the config file
+    pub scripts: Scripts,
+    /// What happens when up2date itself is run as root
+    pub root: Root,
+    /// How the steps that need root get it when sudo has no terminal to ask on
+    pub sudo: Sudo,
+    /// When the launcher from `up2date desktop install` starts a run, and in what
+    pub desktop: Desktop,
+    /// When runs started by a timer or cron may update
+    pub schedule: Schedule,
+    /// How long to wait for another apt, dpkg or pacman to finish
+    pub locks: Locks,
+    /// What's printed before each command runs
+    pub banner: Banner,
+    /// Which pending updates may be applied
+    pub policy: Policy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            failure_lines: 10,
+            steps: HashMap::new(),
+            groups: HashMap::new(),
+            secrets: HashMap::new(),
+            apt: Apt::default(),
+            pacman: Manager::default(),
+            yay: Manager::default(),
+            flatpak: Flatpak::default(),
+            containers: Containers::default(),
+            kubernetes: Kubernetes::default(),
+            selfhosted: Selfhosted::default(),
+            snapshot: Snapshot::default(),
+            cleanup: Cleanup::default(),
+            services: Services::default(),
+            brew: Brew::default(),
+            rust: Rust::default(),
+            nvim: Nvim::default(),
+            julia: Toggle::default(),
+            r: Toggle::default(),
+            haskell: Toggle::default(),
+            dotnet: Dotnet::default(),
+            conda: Conda::default(),
+            sdkman: Manager::default(),
+            powershell: Powershell::default(),
+            windows: Windows::default(),
+            infra: Toggle::default(),
+            audit: Toggle::default(),
+            serve: Serve::default(),
+            mqtt: Mqtt::default(),
+            notify: Notify::default(),
+            changelogs: Changelogs::default(),
+            plugins: Plugins::default(),
+            scripts: Scripts::default(),
+            root: Root::default(),
+            sudo: Sudo::default(),
+            desktop: Desktop::default(),
+            schedule: Schedule::default(),
+            locks: Locks::default(),
+            banner: Banner::default(),
+            policy: Policy::default(),
+        }
+    }
+}
+
+/// The group every step belongs to, so related steps can be turned on or off together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Group {
+    /// The system package managers, snapshots and the checks after upgrading
+    System,
+    /// Removing orphans and old downloads
+    Cleanup,
+    /// rustup and cargo installed binaries
+    Rust,
+    /// Editor plugins
+    Editors,
+    /// Language toolchains and their package managers: julia, R, haskell, conda,
+    /// SDKMAN, go, composer, .NET, PowerShell, rbenv and pyenv
+    Languages,
+    /// Pulling new images for docker compose and podman-compose projects
+    Containers,
+    /// helm and terraform
+    Cloud,
+    /// Proton-GE and the Lutris runners, only once it's turned on
+    Gaming,
+    /// Pi-hole, GitLab and Nextcloud, only once it's turned on
+    Selfhosted,
+    /// The virus and rootkit definitions, only once it's turned on
+    Security,
+    /// Trimming SSDs and checking the disks and btrfs filesystems, only once it's turned on
+    Maintenance,
+    /// Checking for known vulnerabilities
+    Audit,
+}
+
+impl Group {
+    pub const ALL: [Group; 12] = [
+        Group::System,
+        Group::Cleanup,
+        Group::Rust,
+        Group::Editors,
+        Group::Languages,
+        Group::Containers,
+        Group::Cloud,
+        Group::Gaming,
+        Group::Selfhosted,
+        Group::Security,
+        Group::Maintenance,
+        Group::Audit,
+    ];
+
+    /// Whether the group runs when the config doesn't say, only the gaming, selfhosted,
+    /// security and maintenance groups have to be turned on
+    pub fn on_by_default(self) -> bool {
+        !matches!(
+            self,
+            Group::Gaming | Group::Selfhosted | Group::Security | Group::Maintenance
+        )
+    }
+
+    /// The group's name in the config file and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Group::System => "system",
+            Group::Cleanup => "cleanup",
+            Group::Rust => "rust",
+            Group::Editors => "editors",
+            Group::Languages => "languages",
+            Group::Containers => "containers",
+            Group::Cloud => "cloud",
+            Group::Gaming => "gaming",
+            Group::Selfhosted => "selfhosted",
+            Group::Security => "security",
+            Group::Maintenance => "maintenance",
+            Group::Audit => "audit",
+        }
+    }
+
+    /// Find a group by its name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the group, e.g. `system`
+    pub fn parse(name: &str) -> Option<Group> {
+        Group::ALL
+            .iter()
+            .copied()
+            .find(|group| group.name() == name)
+    }
+}
+
+/// A credential some steps need, e.g. a token for a private cargo registry
+///
+/// It's read from `from_env`, the keyring or an age encrypted file, in that order of
+/// preference, or from `env` itself when none of them are set. The value is only ever
+/// put in the environment of the steps that get it, never on a command line.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Secret {
+    /// The environment variable the steps see it in
+    pub env: String,
+    /// The steps that get it by their command, every step when empty
+    pub steps: Vec<String>,
+    /// Read it from this environment variable
+    pub from_env: Option<String>,
+    /// Look it up in the keyring under this key
+    pub keyring: Option<String>,
+    /// Read it from this age encrypted TOML file, under the secret's name
+    pub age_file: Option<PathBuf>,
+    /// The age identity that decrypts `age_file`
+    pub age_identity: Option<PathBuf>,
+}
+
+/// Whether a failing step stops the whole run
+///
+/// The system package managers are required by default and everything else carries on,
+/// `continue_on_error` wins when both are set.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct StepPolicy {
+    pub required: Option<bool>,
+    pub continue_on_error: bool,
+    /// Who the step has to run as, when it isn't what up2date assumes
+    pub run_as: Option<RunAs>,
+    /// How often the step runs at most, every run when it isn't set
+    pub frequency: Option<Frequency>,
+    /// What to run the step in so it can only write to `writable`, never for steps that
+    /// run as root
+    pub sandbox: Option<Sandbox>,
+    /// What a sandboxed step can write to besides `/tmp`, `~` being the home directory
+    pub writable: Vec<String>,
+    /// Regular expressions for the lines of the step's output that aren't shown, they're
+    /// still in what's parsed and kept of it
+    pub ignore: Vec<String>,
+    /// Regular expressions for the lines of the step's output that are shown in colour
+    /// and again in the summary
+    pub highlight: Vec<String>,
+}
+
+/// The tools a step can be sandboxed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sandbox {
+    Bwrap,
+    Firejail,
+    /// A transient `systemd-run --user` service with the filesystem made read-only
+    SystemdRun,
+}
+
+impl Sandbox {
+    /// The executable that starts the sandbox
+    pub fn binary(self) -> &'static str {
+        match self {
+            Sandbox::Bwrap => "bwrap",
+            Sandbox::Firejail => "firejail",
+            Sandbox::SystemdRun => "systemd-run",
+        }
+    }
+}
+
+/// How long a step waits after running before it runs again: `hourly`, `daily`,
+/// `weekly`, `monthly` or a number of hours or days like `12h` or `3d`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Frequency {
+    /// What the config said, to show in the summary
+    pub name: String,
+    pub seconds: u64,
+}
+
+impl TryFrom<String> for Frequency {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let seconds = match name.as_str() {
+            "hourly" => Some(3600),
+            "daily" => Some(86400),
+            "weekly" => Some(7 * 86400),
+            "monthly" => Some(30 * 86400),
+            other => match (other.strip_suffix('h'), other.strip_suffix('d')) {
+                (Some(hours), _) => hours.parse::<u64>().ok().map(|hours| hours * 3600),
+                (_, Some(days)) => days.parse::<u64>().ok().map(|days| days * 86400),
+                _ => None,
+            },
+        };
+
+        match seconds {
+            Some(seconds) if seconds > 0 => Ok(Frequency { name, seconds }),
+            _ => Err(format!(
+                "invalid frequency \"{}\", expected hourly, daily, weekly, monthly or a number of hours or days like \"12h\" or \"3d\"",
+                name
+            )),
+        }
+    }
+}
+
+/// Who a step runs as
+///
+/// Steps run as `root` get sudo put in front when they don't have it. Steps run as the
+/// `user` never get sudo and are skipped when up2date itself was started with sudo,
+/// since they'd fill the user's home directory with files owned by root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunAs {
+    User,
+    Root,
+}
+
+impl StepPolicy {
+    /// Whether a failure of the step stops the run
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - Whether the step is required when the config doesn't say
+    pub fn required(&self, default: bool) -> bool {
+        !self.continue_on_error && self.required.unwrap_or(default)
+    }
+}
+
+/// Settings shared by every package manager
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Manager {
+    pub assume_yes: AssumeYes,
+    #[serde(flatten)]
+    pub executable: Executable,
+}
+
+/// What a package manager is run as when it isn't its usual binary on `PATH`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Executable {
+    /// The binary to run instead, a name on `PATH` or a full path, e.g. `apt-fast` or
+    /// `/opt/homebrew/bin/brew`
+    pub command: Option<String>,
+    /// A command line to update with instead, `{command}` and `{args}` are filled in and
+    /// the arguments go at the end when they aren't there, e.g. `powerpill` or
+    /// `nice -n 19 {command} {args}`
+    pub wrapper: Option<String>,
+}
+
+impl Executable {
+    /// The binary to run, for the commands that only look at what's installed
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - The manager's usual binary, e.g. `apt-get`
+    pub fn binary(&self, default: &str) -> String {
+        self.command.clone().unwrap_or_else(|| default.to_string())
+    }
+
+    /// The command line for updating with the manager, put into the wrapper when there
+    /// is one
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - The manager's usual binary, e.g. `apt-get`
+    /// * `args` - The arguments for the manager
+    pub fn command_line(&self, default: &str, args: &[String]) -> Vec<String> {
+        let binary = self.binary(default);
+        let wrapper = match self.wrapper.as_deref() {
+            Some(wrapper) => wrapper,
+            None => {
+                return std::iter::once(binary)
+                    .chain(args.iter().cloned())
+                    .collect()
+            }
+        };
+        let mut words: Vec<&str> = wrapper.split_whitespace().collect();
+
+        if !words.contains(&"{args}") {
+            words.push("{args}");
+        }
+
+        words
+            .into_iter()
+            .flat_map(|word| match word {
+                "{command}" => vec![binary.clone()],
+                "{args}" => args.to_vec(),
+                word => vec![word.to_string()],
+            })
+            .collect()
+    }
+}
+
+/// What kind of machine apt is upgrading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AptProfile {
+    /// Upgrade without removing anything, leaving old kernels to autoremove
+    Desktop,
+    /// Upgrade like `apt full-upgrade` and purge all but the newest kernels, what
+    /// Proxmox needs and small `/boot` partitions want
+    Server,
+}
+
+/// Settings for updating with apt
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Apt {
+    pub assume_yes: AssumeYes,
+    #[serde(flatten)]
+    pub executable: Executable,
+    /// The profile to upgrade with, `server` on Proxmox and `desktop` everywhere else when
+    /// it isn't set
+    pub profile: Option<AptProfile>,
+    /// How many of the newest kernels the server profile keeps
+    pub keep_kernels: usize,
+    /// Install phased updates straight away instead of when their phase reaches this
+    /// machine
+    pub phased: bool,
+}
+
+impl Default for Apt {
+    fn default() -> Self {
+        Apt {
+            assume_yes: AssumeYes::Yes,
+            executable: Executable::default(),
+            profile: None,
+            keep_kernels: 2,
+            phased: false,
+        }
+    }
+}
+
+/// Settings for a step that only runs when it's been turned on
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Toggle {
+    pub enabled: bool,
+}
+
+/// The tools up2date can take and restore snapshots with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotTool {
+    Snapper,
+    Timeshift,
+    Zfs,
+}
+
+/// Settings for taking a snapshot before updating so `up2date rollback` can restore it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Snapshot {
+    /// The tool to take snapshots with, no snapshots are taken when it isn't set
+    pub tool: Option<SnapshotTool>,
+    /// The zfs dataset to snapshot, e.g. `rpool/ROOT/ubuntu`
+    pub dataset: Option<String>,
+}
+
+/// Settings for restarting Linux services still running outdated libraries after the
+/// system packages were upgraded
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Services {
+    /// Whether to restart them, by default they're only listed
+    pub restart: AssumeYes,
+    /// When not empty only these services are ever restarted
+    pub allow: Vec<String>,
+    /// Services that are never restarted, e.g. the display manager
+    pub deny: Vec<String>,
+}
+
+impl Services {
+    /// Whether a service may be restarted, `ssh` and `ssh.service` name the same service
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The systemd unit name of the service
+    pub fn allows(&self, service: &str) -> bool {
+        let matches = |name: &String| {
+            name == service || service.strip_suffix(".service") == Some(name.as_str())
+        };
+
+        (self.allow.is_empty() || self.allow.iter().any(matches)) && !self.deny.iter().any(matches)
+    }
+}
+
+impl Default for Services {
+    fn default() -> Self {
+        Services {
+            restart: AssumeYes::No,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+}
+
+/// Settings for the steps that remove things: orphaned packages and old downloads
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Cleanup {
+    /// Whether to remove what they would remove: true, false to only list it, or "ask"
+    /// (the default) to list it and ask first
+    pub remove: AssumeYes,
+    /// Packages that are never removed as orphans, e.g. optional dependencies that are
+    /// relied on
+    pub keep: Vec<String>,
+}
+
+impl Default for Cleanup {
+    fn default() -> Self {
+        Cleanup {
+            remove: AssumeYes::Ask,
+            keep: Vec::new(),
+        }
+    }
+}
+
+/// What happens to the steps that run as the user when up2date itself runs as root,
+/// where they'd install into root's home directory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserSteps {
+    /// Don't run them, the default
+    #[default]
+    Skip,
+    /// Run them as the user who started up2date with sudo, skipping them without one
+    Drop,
+    /// Run them as root anyway
+    Root,
+}
+
+/// Settings for running up2date as root
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Root {
+    pub user_steps: UserSteps,
+}
+
+/// What the steps that run with sudo use instead when there's no terminal for sudo to ask
+/// for the password on, started from a launcher or a timer in a desktop session
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Escalation {
+    /// sudo with the askpass helper when there is one, pkexec otherwise, the default
+    #[default]
+    Auto,
+    /// pkexec, the desktop's polkit agent asking for the password
+    Pkexec,
+    /// `sudo -A`, the askpass helper asking for the password
+    Askpass,
+    /// sudo as it is, failing when it needs a password
+    Sudo,
+}
+
+/// Settings for the steps that run with sudo
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Sudo {
+    pub without_terminal: Escalation,
+    /// The program `sudo -A` runs for the password, `SUDO_ASKPASS` when it's not set
+    pub askpass: Option<PathBuf>,
+}
+
+/// Settings for the launcher and the autostart entry `up2date desktop install` creates
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Desktop {
+    /// How long after the last run without failures they start another one
+    pub stale_after: Frequency,
+    /// The terminal the run is started in, the command goes at the end, e.g.
+    /// `wezterm start --`. The first of the usual terminals that's installed otherwise
+    pub terminal: Option<String>,
+}
+
+impl Default for Desktop {
+    fn default() -> Self {
+        Desktop {
+            stale_after: Frequency {
+                name: String::from("weekly"),
+                seconds: 7 * 86400,
+            },
+            terminal: None,
+        }
+    }
+}
+
+/// Which pending updates a run may apply, checked before anything is updated
+///
+/// Entries are a package's name, for every version, or `name@version` for updating it to
+/// that version only.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// The only packages that may be updated, any when it's empty
+    pub allow: Vec<String>,
+    /// Packages that may never be updated, even when they're allowed
+    pub deny: Vec<String>,
+    /// A script that gets the pending updates as JSON on stdin and blocks the run by
+    /// exiting with anything but 0
+    pub script: Option<PathBuf>,
+}
+
+impl Policy {
+    /// Whether there's anything to check the pending updates against
+    pub fn is_set(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty() || self.script.is_some()
+    }
+}
+
+/// Settings for the banner printed before each command runs
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Banner {
+    /// `{command}`, `{env}` (the names of the secrets' variables), `{cwd}` and `{time}`
+    /// are filled in, an empty format prints no banner at all
+    pub format: String,
+}
+
+impl Default for Banner {
+    fn default() -> Self {
+        Banner {
+            format: String::from("$ {command}"),
+        }
+    }
+}
+
+/// Settings for the runs started by a timer or cron
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Schedule {
+    /// The local times they may update in, e.g. `Mon-Fri 02:00-05:00`, any time when
+    /// empty
+    pub windows: Vec<String>,
+    /// Wait a random number of seconds up to this before updating, like systemd's
+    /// `RandomizedDelaySec` but for cron too
+    pub jitter: u64,
+    /// How many seconds the machine has to have been up before updating
+    pub boot_delay: u64,
+}
+
+/// Settings for waiting on the package manager's lock when something else is updating
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Locks {
+    /// How many seconds to wait for the lock, 0 stops the run right away
+    pub timeout: u64,
+    /// Whether to remove a pacman lock no pacman is holding, asks by default
+    pub remove_stale: AssumeYes,
+}
+
+impl Default for Locks {
+    fn default() -> Self {
+        Locks {
+            timeout: 300,
+            remove_stale: AssumeYes::Ask,
+        }
+    }
+}
+
+/// Settings for the plugins, executables named `up2date-plugin-<name>` on `PATH` that
+/// add steps of their own
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Plugins {
+    /// Whether to look for plugins at all, off by default since whatever is on `PATH`
+    /// with the name would run, as root under sudo
+    pub enabled: bool,
+    /// Plugins that are never run, by the name after `up2date-plugin-`
+    pub skip: Vec<String>,
+}
+
+/// Settings for the Lua scripts in `scripts/` next to the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Scripts {
+    /// The only commands the scripts' `up2date.output` runs, each a whole command line,
+    /// e.g. `opam list --upgradable --short`
+    pub output: Vec<String>,
+}
+
+/// A flatpak installation, the system-wide one or the user's own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Installation {
+    System,
+    User,
+}
+
+impl Installation {
+    /// The name that's also flatpak's flag for it, e.g. `system` for `--system`
+    pub fn name(self) -> &'static str {
+        match self {
+            Installation::System => "system",
+            Installation::User => "user",
+        }
+    }
+}
+
+/// Settings for updating flatpak apps and runtimes
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Flatpak {
+    pub assume_yes: AssumeYes,
+    /// The installations to update, both by default
+    pub installations: Vec<Installation>,
+    /// Only update what comes from these remotes, every remote when empty
+    pub remotes: Vec<String>,
+}
+
+impl Default for Flatpak {
+    fn default() -> Self {
+        Flatpak {
+            assume_yes: AssumeYes::Yes,
+            installations: vec![Installation::System, Installation::User],
+            remotes: Vec::new(),
+        }
+    }
+}
+
+/// What runs the compose projects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Docker,
+    Podman,
+}
+
+/// Settings for refreshing compose projects
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Containers {
+    /// The directories with a compose file in them, `~/` being the home directory
+    pub projects: Vec<PathBuf>,
+    /// What runs them, `docker compose` when docker is installed and otherwise
+    /// podman-compose, when it isn't set
+    pub engine: Option<Engine>,
+}
+
+/// Settings for keeping a single-node k3s or k0s cluster on the latest release
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Kubernetes {
+    /// Whether to look for a newer release at all
+    pub enabled: bool,
+    /// The k3s channel to follow, e.g. `stable`, `latest` or `v1.30`
+    pub channel: String,
+    /// Whether to run the install script for a newer release, only reported by default
+    pub upgrade: AssumeYes,
+}
+
+impl Default for Kubernetes {
+    fn default() -> Self {
+        Kubernetes {
+            enabled: false,
+            channel: String::from("stable"),
+            upgrade: AssumeYes::No,
+        }
+    }
+}
+
+/// Settings for the self-hosted apps that have their own updaters
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Selfhosted {
+    /// Where Nextcloud is installed, `/var/www/nextcloud` when it isn't set
+    pub nextcloud: Option<PathBuf>,
+    /// The user `occ` runs as, the web server's
+    pub nextcloud_user: String,
+    /// Whether to run `occ upgrade` once Nextcloud's files are newer than its database,
+    /// only reported by default
+    pub nextcloud_upgrade: bool,
+}
+
+impl Default for Selfhosted {
+    fn default() -> Self {
+        Selfhosted {
+            nextcloud: None,
+            nextcloud_user: String::from("www-data"),
+            nextcloud_upgrade: false,
+        }
+    }
+}
+
+/// Settings for updating Homebrew
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Brew {
+    /// Whether to restart running `brew services` whose formula was upgraded, asks by
+    /// default
+    pub restart_services: AssumeYes,
+    /// Whether to install the Command Line Tools updates Software Update has before
+    /// upgrading, off by default
+    pub command_line_tools: AssumeYes,
+    #[serde(flatten)]
+    pub executable: Executable,
+}
+
+impl Default for Brew {
+    fn default() -> Self {
+        Brew {
+            restart_services: AssumeYes::Ask,
+            command_line_tools: AssumeYes::No,
+            executable: Executable::default(),
+        }
+    }
+}
+
+/// Settings for updating rust toolchains
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Rust {
+    /// Components every installed toolchain should have, missing ones are added back
+    /// after updating
+    pub components: Vec<String>,
+    /// Toolchains to keep installed as well as whichever ones already are, e.g. `nightly`
+    pub toolchains: Vec<String>,
+    /// Targets to keep installed for the default toolchain, e.g. `wasm32-unknown-unknown`
+    pub targets: Vec<String>,
+}
+
+impl Default for Rust {
+    fn default() -> Self {
+        Rust {
+            components: vec![
+                String::from("clippy"),
+                String::from("rustfmt"),
+                String::from("rust-analyzer"),
+            ],
+            toolchains: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+}
+
+/// Settings for updating Neovim plugins
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Nvim {
+    /// The `NVIM_APPNAME` of every config to update, `nvim` being the default config.
+    /// When empty only the default config is updated.
+    pub appnames: Vec<String>,
+}
+
+/// Settings for updating .NET global tools
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Dotnet {
+    /// Also run `dotnet workload update`
+    pub workloads: bool,
+}
+
+/// Settings for the Windows package managers
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Windows {
+    /// The managers to use out of winget, choco and scoop. A package more than one of
+    /// them installed is upgraded by the first.
+    pub managers: Vec<String>,
+    /// The manager that upgrades a package whatever the order, by its choco or scoop
+    /// name or the last part of its winget id, lowercase, e.g. `git = "scoop"`
+    pub owners: HashMap<String, String>,
+}
+
+impl Default for Windows {
+    fn default() -> Self {
+        Windows {
+            managers: vec![
+                String::from("winget"),
+                String::from("scoop"),
+                String::from("choco"),
+            ],
+            owners: HashMap::new(),
+        }
+    }
+}
+
+/// Settings for updating PowerShell modules from the PowerShell Gallery
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Powershell {
+    /// Also download the latest help for every module with `Update-Help`
+    pub help: bool,
+}
+
+impl Default for Powershell {
+    fn default() -> Self {
+        Powershell { help: true }
+    }
+}
+
+/// Settings for updating conda or mamba environments
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Conda {
+    pub enabled: bool,
+    /// Named environments to update as well as `base`
+    pub environments: Vec<String>,
+    pub assume_yes: AssumeYes,
+}
+
+/// Settings for `up2date serve`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Serve {
+    /// The address and port to listen on
+    pub address: String,
+    /// The bearer token `POST /run` needs, looked up like any secret but never handed to
+    /// a step
+    pub token: Secret,
+}
+
+impl Default for Serve {
+    fn default() -> Self {
+        Serve {
+            address: String::from("127.0.0.1:7474"),
+            token: Secret {
+                env: String::from("UP2DATE_SERVE_TOKEN"),
+                ..Secret::default()
+            },
+        }
+    }
+}
+
+/// Settings for the notifications sent by `up2date check --notify`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Notify {
+    /// How many updates have to be pending before there's a notification
+    pub threshold: usize,
+    /// Whether a single security update is enough for a notification
+    pub security: bool,
+    /// Show a desktop notification
+    pub desktop: bool,
+    /// A URL to POST the pending updates to as JSON
+    pub webhook: Option<String>,
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Notify {
+            threshold: 1,
+            security: true,
+            desktop: true,
+            webhook: None,
+        }
+    }
+}
+
+/// Settings for showing what's in the updates of the packages that matter most before
+/// they're applied
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Changelogs {
+    /// The packages, by name or by the start of it followed by `*` like `linux-image-*`,
+    /// none by default
+    pub packages: Vec<String>,
+    /// How many lines of each changelog to show at most
+    pub lines: usize,
+}
+
+impl Default for Changelogs {
+    fn default() -> Self {
+        Changelogs {
+            packages: Vec::new(),
+            lines: 20,
+        }
+    }
+}
+
+impl Changelogs {
+    /// Whether a package's changelog is shown
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The package's name
+    pub fn includes(&self, package: &str) -> bool {
+        self.packages
+            .iter()
+            .any(|entry| match entry.strip_suffix('*') {
+                Some(prefix) => package.starts_with(prefix),
+                None => package == entry,
+            })
+    }
+}
+
+/// Settings for publishing each run's results over MQTT with `mosquitto_pub`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Mqtt {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    /// The broker password, looked up like any secret but never handed to a step
+    pub password: Option<Secret>,
+    /// The state is published to `<topic>/<hostname>/state`
+    pub topic: String,
+    /// Where Home Assistant looks for discovery messages
+    pub discovery_prefix: String,
+}
+
+impl Default for Mqtt {
+    fn default() -> Self {
+        Mqtt {
+            enabled: false,
+            host: String::from("localhost"),
+            port: 1883,
+            username: None,
+            password: None,
+            topic: String::from("up2date"),
+            discovery_prefix: String::from("homeassistant"),
+        }
+    }
+}
+
+/// Whether a package manager should be told not to prompt for confirmation
+///
+/// Written in the config as `assume_yes = true`, `assume_yes = false` or
+/// `assume_yes = "ask"`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "AssumeYesValue")]
+pub enum AssumeYes {
+    /// Pass the manager's `-y`/`--noconfirm` flag
+    #[default]
+    Yes,
+    /// Leave the flag off so the manager shows its change list and prompts
+    No,
+    /// Ask before running the command and pass the flag if accepted
+    Ask,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AssumeYesValue {
+    Bool(bool),
+    Word(String),
+}
+
+impl TryFrom<AssumeYesValue> for AssumeYes {
+    type Error = String;
+
+    fn try_from(value: AssumeYesValue) -> Result<Self, Self::Error> {
+        match value {
+            AssumeYesValue::Bool(true) => Ok(AssumeYes::Yes),
+            AssumeYesValue::Bool(false) => Ok(AssumeYes::No),
+            AssumeYesValue::Word(word) if word == "ask" => Ok(AssumeYes::Ask),
+            AssumeYesValue::Word(word) => Err(format!(
+                "invalid assume_yes value \"{}\", expected true, false or \"ask\"",
+                word
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// The file can't be edited without losing its formatting
+    Edit(PathBuf, toml_edit::TomlError),
+    Write(PathBuf, io::Error),
+    /// There's no home directory to keep the config file in
+    NoPath,
+    /// A file includes itself, directly or through other includes
+    Include(PathBuf),
+    /// `up2date enable` or `disable` was given a step it doesn't know
+    UnknownStep(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Read(path, error) => write!(f, "could not read {}: {}", path.display(), error),
+            Error::Parse(path, error) => write!(f, "could not parse {}: {}", path.display(), error),
+            Error::Edit(path, error) => write!(f, "could not parse {}: {}", path.display(), error),
+            Error::Write(path, error) => write!(f, "could not write {}: {}", path.display(), error),
+            Error::NoPath => write!(f, "could not find a home directory for the config file"),
+            Error::Include(path) => write!(f, "{} includes itself", path.display()),
+            Error::UnknownStep(step) => write!(
+                f,
+                "unknown step: {}, expected one of {}",
+                step,
+                STEPS.join(", ")
+            ),
+        }
+    }
+}
+
+/// The location of the config file, if a home directory can be found
+pub fn path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join("up2date").join("config.toml"))
+}
+
+/// The directory up2date keeps what it remembers between runs in, if a home directory
+/// can be found
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?)
+            .join(".local")
+            .join("state"),
+    };
+
+    Some(base.join("up2date"))
+}
+
+/// Merge `overlay` into `base`, tables are merged key by key and anything else in
+/// `overlay` replaces what's in `base`, arrays included
+///
+/// # Arguments
+///
+/// * `base` - The settings being overridden
+/// * `overlay` - The settings that win
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Read a config file and everything it includes into one value
+///
+/// `include = ["common.toml", "work.toml"]` paths are relative to the file that includes
+/// them. The included files are merged in order and the including file is merged over
+/// the result, so later includes win over earlier ones and the file itself wins over
+/// all of them.
+///
+/// # Arguments
+///
+/// * `path` - The file to read
+/// * `including` - The files that led to this one, to catch a file including itself
+fn read(path: &Path, including: &mut Vec<PathBuf>) -> Result<toml::Value, Error> {
+    // `./common.toml` and `common.toml` are the same file
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    if including.contains(&canonical) {
+        return Err(Error::Include(path.to_path_buf()));
+    }
+
+    let contents =
+        fs::read_to_string(path).map_err(|error| Error::Read(path.to_path_buf(), error))?;
+    let mut value: toml::Value =
+        toml::from_str(&contents).map_err(|error| Error::Parse(path.to_path_buf(), error))?;
+    let includes = match &mut value {
+        toml::Value::Table(table) => table.remove("include"),
+        _ => None,
+    };
+    let mut base = toml::Value::Table(toml::Table::new());
+
+    including.push(canonical);
+
+    if let Some(toml::Value::Array(includes)) = includes {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        for include in includes.iter().filter_map(toml::Value::as_str) {
+            merge(&mut base, read(&dir.join(include), including)?);
+        }
+    }
+
+    including.pop();
+    merge(&mut base, value);
+
+    Ok(base)
+}
+
+/// Read a config file and its includes, applying the `[host."<hostname>"]` section for
+/// this machine over everything else
+///
+/// # Arguments
+///
+/// * `path` - The config file
+fn resolve(path: &Path) -> Result<toml::Value, Error> {
+    let mut value = read(path, &mut Vec::new())?;
+
+    if let toml::Value::Table(table) = &mut value {
+        let hosts = table.remove("host");
+
+        if let (Some(toml::Value::Table(mut hosts)), Ok(hostname)) = (hosts, sys_info::hostname()) {
+            if let Some(overrides) = hosts.remove(&hostname) {
+                merge(&mut value, overrides);
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+/// Parse a config file and its includes, applying the `[host."<hostname>"]` section for
+/// this machine over everything else
+///
+/// # Arguments
+///
+/// * `path` - The config file
+fn parse(path: &Path) -> Result<Config, Error> {
+    resolve(path)?
+        .try_into()
+        .map_err(|error| Error::Parse(path.to_path_buf(), error))
+}
+
+/// Load the config file, falling back to the defaults when it doesn't exist
+pub fn load() -> Result<Config, Error> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    parse(&path)
+}
+
+/// The steps that only run once they're turned on with `enabled = true`
+const TOGGLES: [&str; 7] = [
+    "julia", "r", "haskell", "conda", "infra", "audit", "plugins",
+];
+
+/// Every step `up2date enable` and `disable` know about, the opt-in steps followed by
+/// the groups (`audit` is both)
+const STEPS: [&str; 19] = [
+    "julia",
+    "r",
+    "haskell",
+    "conda",
+    "infra",
+    "audit",
+    "plugins",
+    "nvim",
+    "system",
+    "cleanup",
+    "rust",
+    "editors",
+    "languages",
+    "containers",
+    "cloud",
+    "gaming",
+    "selfhosted",
+    "security",
+    "maintenance",
+];
+
+/// The table and key that turn a step on or off: `enabled` for the opt-in steps and the
+/// group for everything else, `nvim` being the editors group
+///
+/// # Arguments
+///
+/// * `step` - The step or group
+fn toggle(step: &str) -> Result<(&'static str, &'static str), Error> {
+    match TOGGLES.iter().find(|toggle| **toggle == step) {
+        Some(toggle) => Ok((*toggle, "enabled")),
+        None => match (step, Group::parse(step)) {
+            ("nvim", _) => Ok(("groups", Group::Editors.name())),
+            (_, Some(group)) => Ok(("groups", group.name())),
+            (_, None) => Err(Error::UnknownStep(step.to_string())),
+        },
+    }
+}
+
+/// Whether a step is turned on once the includes and this machine's host section are
+/// merged in, `None` when none of them say
+///
+/// # Arguments
+///
+/// * `step` - The step or group
+pub fn enabled(step: &str) -> Option<bool> {
+    let (table, key) = toggle(step).ok()?;
+
+    resolve(&path()?).ok()?.get(table)?.get(key)?.as_bool()
+}
+
+/// Turn a step on or off in the config file, keeping its comments and formatting
+///
+/// Opt-in steps are switched with their `enabled` setting and everything else with its
+/// group, `nvim` being the editors group. The file is created when it doesn't exist. When
+/// the file's own `[host."<hostname>"]` section for this machine sets it too, that's
+/// switched as well, an included file that does is left alone.
+///
+/// # Arguments
+///
+/// * `step` - The step or group to switch
+/// * `enabled` - Whether it should run
+pub fn set_enabled(step: &str, enabled: bool) -> Result<PathBuf, Error> {
+    let path = path().ok_or(Error::NoPath)?;
+    let (table, key) = toggle(step)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(Error::Read(path, error)),
+    };
+    let mut document = match contents.parse::<toml_edit::DocumentMut>() {
+        Ok(document) => document,
+        Err(error) => return Err(Error::Edit(path, error)),
+    };
+
+    document[table][key] = toml_edit::value(enabled);
+
+    // the host section wins over the top level, so it would undo the switch
+    let host = sys_info::hostname().ok().and_then(|hostname| {
+        document
+            .get_mut("host")?
+            .as_table_mut()?
+            .get_mut(&hostname)?
+            .as_table_mut()?
+            .get_mut(table)?
+            .as_table_mut()
+    });
+
+    if let Some(host) = host.filter(|host| host.contains_key(key)) {
+        host.insert(key, toml_edit::value(enabled));
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|error| Error::Write(path.clone(), error))?;
+    }
+
+    fs::write(&path, document.to_string()).map_err(|error| Error::Write(path.clone(), error))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write config files to a directory of their own, returning the directory
+    fn files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("up2date-config-{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        for (file, contents) in files.iter() {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        dir
+    }
+
+    fn toml(text: &str) -> toml::Value {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn merge_merges_tables_and_replaces_everything_else() {
+        let mut base = toml(
+            r#"
+            failure_lines = 5
+            [groups]
+            editors = false
+            gaming = false
+            [schedule]
+            windows = ["Mon 02:00-05:00", "Sat 10:00-12:00"]
+            "#,
+        );
+
+        merge(
+            &mut base,
+            toml(
+                r#"
+                failure_lines = 10
+                [groups]
+                gaming = true
+                [schedule]
+                windows = ["Sun 03:00-04:00"]
+                "#,
+            ),
+        );
+
+        assert_eq!(
+            base,
+            toml(
+                r#"
+                failure_lines = 10
+                [groups]
+                editors = false
+                gaming = true
+                [schedule]
+                windows = ["Sun 03:00-04:00"]
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn later_includes_win_and_the_file_wins_over_them() {
+        let dir = files(
+            "order",
+            &[
+                (
+                    "config.toml",
+                    "include = [\"common.toml\", \"work.toml\"]\nfailure_lines = 3\n",
+                ),
+                (
+                    "common.toml",
+                    "failure_lines = 1\n[groups]\neditors = false\ngaming = false\n",
+                ),
+                ("work.toml", "failure_lines = 2\n[groups]\ngaming = true\n"),
+            ],
+        );
+
+        assert_eq!(
+            read(&dir.join("config.toml"), &mut Vec::new()).unwrap(),
+            toml("failure_lines = 3\n[groups]\neditors = false\ngaming = true\n")
+        );
+    }
+
+    #[test]
+    fn includes_are_relative_to_the_file_that_includes_them() {
+        let dir = files("relative", &[]);
+
+        fs::create_dir_all(dir.join("hosts")).unwrap();
+        fs::write(dir.join("config.toml"), "include = [\"hosts/box.toml\"]\n").unwrap();
+        fs::write(dir.join("hosts/box.toml"), "include = [\"shared.toml\"]\n").unwrap();
+        fs::write(dir.join("hosts/shared.toml"), "failure_lines = 7\n").unwrap();
+
+        assert_eq!(
+            read(&dir.join("config.toml"), &mut Vec::new()).unwrap(),
+            toml("failure_lines = 7\n")
+        );
+    }
+
+    #[test]
+    fn a_file_including_itself_is_an_error() {
+        let dir = files(
+            "cycle",
+            &[
+                ("config.toml", "include = [\"a.toml\"]\n"),
+                ("a.toml", "include = [\"./config.toml\"]\n"),
+            ],
+        );
+
+        assert!(matches!(
+            read(&dir.join("config.toml"), &mut Vec::new()),
+            Err(Error::Include(_))
+        ));
+    }
+
+    #[test]
+    fn the_same_file_can_be_included_twice() {
+        let dir = files(
+            "twice",
+            &[
+                ("config.toml", "include = [\"a.toml\", \"b.toml\"]\n"),
+                ("a.toml", "include = [\"common.toml\"]\n"),
+                ("b.toml", "include = [\"common.toml\"]\n"),
+                ("common.toml", "failure_lines = 4\n"),
+            ],
+        );
+
+        assert_eq!(
+            read(&dir.join("config.toml"), &mut Vec::new()).unwrap(),
+            toml("failure_lines = 4\n")
+        );
+    }
+
+    #[test]
+    fn host_section_wins_over_the_includes() {
+        let hostname = sys_info::hostname().unwrap();
+        let dir = files(
+            "host",
+            &[
+                (
+                    "config.toml",
+                    &format!(
+                        "include = [\"common.toml\"]\n\
+                         [host.\"{}\".groups]\n\
+                         editors = true\n\
+                         [host.\"some-other-machine\".groups]\n\
+                         gaming = true\n",
+                        hostname
+                    ),
+                ),
+                ("common.toml", "[groups]\neditors = false\ngaming = false\n"),
+            ],
+        );
+        let config = parse(&dir.join("config.toml")).unwrap();
+
+        assert_eq!(config.groups.get(&Group::Editors), Some(&true));
+        assert_eq!(config.groups.get(&Group::Gaming), Some(&false));
+    }
+
+    #[test]
+    fn a_missing_include_is_an_error() {
+        let dir = files("missing", &[("config.toml", "include = [\"gone.toml\"]\n")]);
+
+        assert!(matches!(
+            read(&dir.join("config.toml"), &mut Vec::new()),
+            Err(Error::Read(path, _)) if path.ends_with("gone.toml")
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config;
+use crate::detect::has_binary;
+use crate::history;
+use crate::i18n::tr;
+use crate::report;
+use crate::runner::shell_quote;
+
+/// The terminals a run is started in when `[desktop] terminal` isn't set, the first one
+/// installed winning, each with what goes before the command
+const TERMINALS: [(&str, &[&str]); 8] = [
+    ("x-terminal-emulator", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("kitty", &[]),
+    ("alacritty", &["-e"]),
+    ("foot", &[]),
+    ("xterm", &["-e"]),
+];
+
+/// The name of the launcher and the autostart entry
+const ENTRY: &str = "up2date.desktop";
+
+/// A directory from an XDG variable, or the default under the home directory
+///
+/// # Arguments
+///
+/// * `variable` - e.g. `XDG_DATA_HOME`
+/// * `default` - Where it is under the home directory when the variable isn't set
+fn xdg_dir(variable: &str, default: &str) -> Option<PathBuf> {
+    match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => Some(PathBuf::from(env::var_os("HOME")?).join(default)),
+    }
+}
+
+/// Quote an `Exec` argument the way desktop entries need it when it has anything in it
+/// that would split it or be read as a field code
+fn exec_quote(arg: &str) -> String {
+    if arg
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-".contains(c))
+    {
+        arg.to_string()
+    } else {
+        let escaped: String = arg
+            .chars()
+            .flat_map(|c| match c {
+                '"' | '`' | '$' | '\\' => vec!['\\', c],
+                _ => vec![c],
+            })
+            .collect();
+
+        format!("\"{}\"", escaped.replace('%', "%%"))
+    }
+}
+
+/// A desktop entry that runs `up2date desktop launch` once it's gone stale
+///
+/// # Arguments
+///
+/// * `exe` - The up2date executable, quoted for `Exec`
+/// * `autostart` - Whether it's the entry started at login, which has no actions
+fn entry(exe: &str, autostart: bool) -> String {
+    let mut entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=up2date\n\
+         Comment=Update everything when it hasn't been for a while\n\
+         Exec={} desktop launch\n\
+         Icon=system-software-update\n\
+         Terminal=false\n\
+         Categories=System;\n",
+        exe
+    );
+
+    if autostart {
+        entry.push_str("X-GNOME-Autostart-enabled=true\n");
+    } else {
+        entry.push_str(&format!(
+            "Actions=now;\n\
+             \n\
+             [Desktop Action now]\n\
+             Name=Update now\n\
+             Exec={} desktop launch --now\n",
+            exe
+        ));
+    }
+
+    entry
+}
+
+/// Create the launcher in the applications directory, and the autostart entry, returning
+/// the files written
+///
+/// # Arguments
+///
+/// * `autostart` - Whether to start it at login too
+pub fn install(autostart: bool) -> io::Result<Vec<PathBuf>> {
+    let no_home = || io::Error::new(io::ErrorKind::NotFound, "no home directory");
+    let exe = exec_quote(&env::current_exe()?.display().to_string());
+    let mut entries = vec![(
+        xdg_dir("XDG_DATA_HOME", ".local/share")
+            .ok_or_else(no_home)?
+            .join("applications"),
+        false,
+    )];
+
+    if autostart {
+        entries.push((
+            xdg_dir("XDG_CONFIG_HOME", ".config")
+                .ok_or_else(no_home)?
+                .join("autostart"),
+            true,
+        ));
+    }
+
+    entries
+        .into_iter()
+        .map(|(dir, autostart)| {
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(ENTRY), entry(&exe, autostart))?;
+            Ok(dir.join(ENTRY))
+        })
+        .collect()
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// When the last run without a failed step started, `None` before there was one
+fn last_success() -> Option<u64> {
+    history::read().ok()?.iter().rev().find_map(|run| {
+        let failed = run["steps"]
+            .as_array()?
+            .iter()
+            .any(|step| step["success"].as_bool() == Some(false));
+
+        if failed {
+            None
+        } else {
+            run["started"].as_u64()
+        }
+    })
+}
+
+/// The terminal to start the run in, with everything that goes before the command
+///
+/// # Arguments
+///
+/// * `settings` - The `[desktop]` settings from the config
+fn terminal(settings: &config::Desktop) -> Option<Vec<String>> {
+    if let Some(terminal) = settings.terminal.as_ref() {
+        return Some(terminal.split_whitespace().map(String::from).collect())
+            .filter(|words: &Vec<String>| !words.is_empty());
+    }
+
+    TERMINALS
+        .iter()
+        .find(|(terminal, _)| has_binary(terminal))
+        .map(|(terminal, args)| {
+            std::iter::once(terminal)
+                .chain(args.iter())
+                .map(|word| word.to_string())
+                .collect()
+        })
+}
+
+/// Start a run in a terminal when the last run without failures is older than
+/// `[desktop] stale_after`, the terminal staying open once it's done until Enter is
+/// pressed
+///
+/// # Arguments
+///
+/// * `settings` - The `[desktop]` settings from the config
+/// * `right_away` - Whether to start it however recent the last run is
+pub fn launch(settings: &config::Desktop, right_away: bool) -> io::Result<()> {
+    let age = last_success().map(|started| now().saturating_sub(started));
+
+    if let Some(age) = age.filter(|age| !right_away && *age < settings.stale_after.seconds) {
+        println!(
+            "Updated {} ago, [desktop] stale_after is {}",
+            report::format_duration(age),
+            settings.stale_after.name
+        );
+        return Ok(());
+    }
+
+    let terminal = terminal(settings).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "no terminal found, set [desktop] terminal",
+        )
+    })?;
+    let script = format!(
+        "{}; printf '\\n%s ' {}; read _",
+        shell_quote(&env::current_exe()?.display().to_string()),
+        shell_quote(&tr("prompt-close", &[]))
+    );
+
+    Command::new(&terminal[0])
+        .args(&terminal[1..])
+        .args(["sh", "-c", &script])
+        .stdin(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// Where the distribution says what it is
+const OS_RELEASE: &str = "/etc/os-release";
+
+/// What was found out about the system the last time, so the quick commands don't have to
+/// look for every binary again
+///
+/// It's only trusted while `PATH`, the directories on it and `/etc/os-release` are the
+/// same as when it was written, installing or removing a binary changes its directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Cache {
+    /// `PATH` with each directory's modification time
+    path: Vec<(PathBuf, u64)>,
+    /// The modification time of `/etc/os-release`
+    os_release: u64,
+    /// The distribution id, once it's been looked up
+    release: Option<Option<String>>,
+    /// Whether each binary that was looked for is on `PATH`
+    binaries: HashMap<String, bool>,
+    /// Whether anything was added since it was read
+    #[serde(skip)]
+    changed: bool,
+}
+
+/// When a file or directory was last modified, in seconds since the Unix epoch, 0 when
+/// it can't be found
+fn modified(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// The file the cache is kept in
+fn cache_path() -> Option<PathBuf> {
+    Some(config::state_dir()?.join("detect.json"))
+}
+
+/// The cache for this run, what the last run left when it still holds and empty
+/// otherwise, `None` with shims since they change from one test to the next
+fn cache() -> Option<&'static Mutex<Cache>> {
+    static CACHE: OnceLock<Option<Mutex<Cache>>> = OnceLock::new();
+
+    CACHE
+        .get_or_init(|| {
+            if shims().is_some() {
+                return None;
+            }
+
+            let current = Cache {
+                path: env::var_os("PATH")
+                    .map(|paths| {
+                        env::split_paths(&paths)
+                            .map(|dir| {
+                                let modified = modified(&dir);
+
+                                (dir, modified)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                os_release: modified(Path::new(OS_RELEASE)),
+                ..Cache::default()
+            };
+            let saved: Option<Cache> = cache_path()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .and_then(|json| serde_json::from_str(&json).ok());
+
+            let cache = match saved {
+                Some(saved) if saved.path == current.path => Cache {
+                    binaries: saved.binaries,
+                    release: saved
+                        .release
+                        .filter(|_| saved.os_release == current.os_release),
+                    ..current
+                },
+                _ => current,
+            };
+
+            Some(Mutex::new(cache))
+        })
+        .as_ref()
+}
+
+/// Keep what this run found out for the next one, when it found out anything new
+pub fn save_cache() -> std::io::Result<()> {
+    let (cache, path) = match (cache(), cache_path()) {
+        (Some(cache), Some(path)) => (cache, path),
+        _ => return Ok(()),
+    };
+    let cache = cache.lock().unwrap_or_else(|error| error.into_inner());
+
+    if !cache.changed {
+        return Ok(());
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, serde_json::to_string(&*cache)? + "\n")
+}
+
+/// The directory of shim scripts every command is run from instead of what's on `PATH`,
+/// set with `UP2DATE_SHIMS` by the integration tests
+pub fn shims() -> Option<PathBuf> {
+    env::var_os("UP2DATE_SHIMS")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Resolve every command against the shim directory, when there is one, so nothing that's
+/// really installed is ever run or detected
+pub fn use_shims() {
+    if let Some(dir) = shims() {
+        env::set_var("PATH", dir);
+    }
+}
+
+/// The OS up2date acts as, with shims `UP2DATE_SHIM_OS` can stand in for another one
+pub fn os() -> &'static str {
+    static OS: OnceLock<String> = OnceLock::new();
+
+    OS.get_or_init(|| match (shims(), env::var("UP2DATE_SHIM_OS")) {
+        (Some(_), Ok(os)) => os,
+        _ => String::from(env::consts::OS),
+    })
+}
+
+/// The Linux distribution id, `None` on other platforms
+///
+/// With shims `UP2DATE_SHIM_RELEASE` stands in for `/etc/os-release`.
+pub fn release() -> Option<String> {
+    if shims().is_some() {
+        return env::var("UP2DATE_SHIM_RELEASE").ok();
+    }
+
+    if os() != "linux" {
+        return None;
+    }
+
+    if let Some(release) = cache()
+        .and_then(|cache| cache.lock().ok())
+        .and_then(|cache| cache.release.clone())
+    {
+        return release;
+    }
+
+    let release = match sys_info::linux_os_release() {
+        Ok(value) => value.id,
+        Err(error) => panic!("Error {}", error),
+    };
+
+    if let Some(mut cache) = cache().and_then(|cache| cache.lock().ok()) {
+        cache.release = Some(release.clone());
+        cache.changed = true;
+    }
+
+    release
+}
+
+/// The user who started up2date with sudo, `None` when it wasn't started with sudo
+pub fn sudo_user() -> Option<String> {
+    env::var("SUDO_USER")
+        .ok()
+        .filter(|user| !user.is_empty() && user != "root")
+}
+
+/// The home directory of `user`, as `sudo -H` gives it to what runs as them
+///
+/// # Arguments
+///
+/// * `user` - Who to look it up for
+pub fn home_of(user: &str) -> Option<String> {
+    let output = scuttle::run_output(&scuttle::App {
+        command: String::from("sudo"),
+        args: vec![
+            "-u".to_string(),
+            user.to_string(),
+            "-H".to_string(),
+            "sh".to_string(),
+            "-c".to_string(),
+            "printf %s \"$HOME\"".to_string(),
+        ],
+    })
+    .ok()
+    .filter(|output| output.status.success())?;
+
+    Some(String::from_utf8_lossy(&output.stdout).to_string()).filter(|home| !home.is_empty())
+}
+
+/// Whether up2date runs in a graphical session, where a password can be asked for in a
+/// dialog instead of a terminal
+pub fn graphical_session() -> bool {
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// Whether up2date is running as root, never on Windows
+pub fn is_root() -> bool {
+    static ROOT: OnceLock<bool> = OnceLock::new();
+
+    *ROOT.get_or_init(|| {
+        if cfg!(windows) {
+            return false;
+        }
+
+        let id = scuttle::run_output(&scuttle::App {
+            command: String::from("id"),
+            args: vec!["-u".to_string()],
+        });
+
+        match id {
+            Ok(output) => {
+                output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "0"
+            }
+            Err(_) => false,
+        }
+    })
+}
+
+/// Check whether an executable with the given name is somewhere on `PATH`, on Windows
+/// with or without its `.exe`
+///
+/// # Arguments
+///
+/// * `name` - The name of the binary to look for
+pub fn has_binary(name: &str) -> bool {
+    if let Some(mut cache) = cache().and_then(|cache| cache.lock().ok()) {
+        if let Some(found) = cache.binaries.get(name) {
+            return *found;
+        }
+
+        let found = find_binary(name);
+
+        cache.binaries.insert(name.to_string(), found);
+        cache.changed = true;
+
+        return found;
+    }
+
+    find_binary(name)
+}
+
+/// Look through every directory on `PATH` for a binary, on Windows with or without its
+/// `.exe`
+///
+/// # Arguments
+///
+/// * `name` - The name of the binary to look for
+fn find_binary(name: &str) -> bool {
+    binary_path(name).is_some()
+}
+
+/// The full path of the binary that runs for a name, the first one found on `PATH`
+///
+/// # Arguments
+///
+/// * `name` - The name of the binary to look for
+pub fn binary_path(name: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+
+    env::split_paths(&paths)
+        .flat_map(|dir| {
+            let exe = dir.join(format!("{}.exe", name));
+
+            [Some(dir.join(name)), Some(exe).filter(|_| cfg!(windows))]
+        })
+        .flatten()
+        .find(|path| is_executable(path))
+}
+
+/// Find every executable on `PATH` whose name starts with `prefix`, without its `.exe`
+/// on Windows, the first one found winning when a name is in more than one directory
+///
+/// # Arguments
+///
+/// * `prefix` - What the names start with, e.g. `up2date-plugin-`
+pub fn binaries_starting_with(prefix: &str) -> Vec<String> {
+    let paths = match env::var_os("PATH") {
+        Some(paths) => paths,
+        None => return Vec::new(),
+    };
+    let mut binaries: Vec<String> = Vec::new();
+
+    for dir in env::split_paths(&paths) {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut found: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| is_executable(&entry.path()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .map(|name| match name.strip_suffix(".exe") {
+                Some(name) if cfg!(windows) => name.to_string(),
+                _ => name,
+            })
+            .filter(|name| name.starts_with(prefix) && name.len() > prefix.len())
+            .filter(|name| !binaries.contains(name))
+            .collect();
+
+        found.sort();
+        binaries.append(&mut found);
+    }
+
+    binaries
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    match path.metadata() {
+        Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Check whether lazy.nvim has installed a plugin for a Neovim config
+///
+/// # Arguments
+///
+/// * `appname` - The `NVIM_APPNAME` of the config, `nvim` being the default config
+/// * `plugin` - The plugin's directory name, e.g. `mason.nvim`
+pub fn has_nvim_plugin(appname: &str, plugin: &str) -> bool {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".local").join("share"),
+            None => return false,
+        },
+    };
+
+    data_home.join(appname).join("lazy").join(plugin).is_dir()
+}
@@ -0,0 +1,260 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+use regex::Regex;
+
+use crate::config::StepPolicy;
+use crate::parsers;
+
+/// Bold yellow, what highlighted lines are shown in
+const HIGHLIGHT: &str = "\x1b[1;33m";
+
+/// Back to the terminal's own colours
+const RESET: &str = "\x1b[0m";
+
+/// The lines of a step's output that are hidden and the ones that are highlighted, as
+/// `[steps.<command>] ignore` and `highlight` say
+#[derive(Debug, Default, Clone)]
+pub struct OutputFilter {
+    ignore: Vec<Regex>,
+    highlight: Vec<Regex>,
+}
+
+impl OutputFilter {
+    /// Compile a step's patterns, warning about and leaving out the ones that aren't
+    /// regular expressions
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The step's name in the config
+    /// * `policy` - The step's settings
+    pub fn new(step: &str, policy: &StepPolicy) -> OutputFilter {
+        let compile = |patterns: &[String]| -> Vec<Regex> {
+            patterns
+                .iter()
+                .filter_map(|pattern| match Regex::new(pattern) {
+                    Ok(regex) => Some(regex),
+                    Err(error) => {
+                        eprintln!(
+                            "WARNING: [steps.{}] has a pattern that isn't valid, {}",
+                            step, error
+                        );
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        OutputFilter {
+            ignore: compile(&policy.ignore),
+            highlight: compile(&policy.highlight),
+        }
+    }
+
+    /// A filter that only leaves out the exit codes the bash shell steps print when a
+    /// pipeline fails
+    pub fn hiding_pipe_status() -> OutputFilter {
+        OutputFilter {
+            ignore: Regex::new(&format!("^{}", regex::escape(parsers::PIPE_STATUS)))
+                .into_iter()
+                .collect(),
+            highlight: Vec::new(),
+        }
+    }
+
+    /// Whether the output is passed on untouched
+    pub fn is_empty(&self) -> bool {
+        self.ignore.is_empty() && self.highlight.is_empty()
+    }
+
+    /// Whether a line is left out of what's shown
+    fn ignores(&self, line: &str) -> bool {
+        self.ignore.iter().any(|regex| regex.is_match(line))
+    }
+
+    /// Whether a line is highlighted, ignoring wins when a line matches both
+    fn highlights(&self, line: &str) -> bool {
+        !self.ignores(line) && self.highlight.iter().any(|regex| regex.is_match(line))
+    }
+
+    /// The lines of some output that are highlighted, for the summary
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Everything the step printed
+    pub fn highlighted(&self, output: &str) -> Vec<String> {
+        output
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| self.highlights(line))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Some output as it's shown, without the ignored lines
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Everything the step printed
+    /// * `color` - Whether highlighted lines are coloured
+    pub fn apply(&self, output: &str, color: bool) -> String {
+        output
+            .split_inclusive('\n')
+            .filter(|line| !self.ignores(line.trim_end()))
+            .map(|line| self.show(line, color))
+            .collect()
+    }
+
+    /// A line as it's shown, coloured when it's highlighted
+    fn show(&self, line: &str, color: bool) -> String {
+        let text = line.trim_end_matches(['\r', '\n']);
+
+        if color && self.highlights(text) {
+            format!("{}{}{}{}", HIGHLIGHT, text, RESET, &line[text.len()..])
+        } else {
+            line.to_string()
+        }
+    }
+}
+
+/// Whether the terminal gets colours, not when it's piped somewhere or `NO_COLOR` is set
+pub fn color() -> bool {
+    io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Passes a step's output on a line at a time with the filter applied, what's left of the
+/// last line once the step is done
+pub struct Filtered<W: Write> {
+    sink: W,
+    filter: OutputFilter,
+    color: bool,
+    /// The line that's still being printed
+    line: Vec<u8>,
+}
+
+impl<W: Write> Filtered<W> {
+    /// # Arguments
+    ///
+    /// * `sink` - Where the output goes
+    /// * `filter` - The step's patterns
+    pub fn new(sink: W, filter: OutputFilter) -> Filtered<W> {
+        Filtered {
+            sink,
+            filter,
+            color: color(),
+            line: Vec::new(),
+        }
+    }
+
+    /// Pass on the line that's been collected, unless it's ignored
+    fn pass_on(&mut self) -> io::Result<()> {
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+
+        self.line.clear();
+        self.sink
+            .write_all(self.filter.apply(&line, self.color).as_bytes())
+    }
+}
+
+impl<W: Write> Write for Filtered<W> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        for byte in buffer.iter() {
+            self.line.push(*byte);
+
+            if *byte == b'\n' {
+                self.pass_on()?;
+            }
+        }
+
+        Ok(buffer.len())
+    }
+
+    // a line is only passed on once it's complete, so there's nothing to flush early
+    fn flush(&mut self) -> io::Result<()> {
+        self.sink.flush()
+    }
+}
+
+impl<W: Write> Drop for Filtered<W> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            let _ = self.pass_on();
+            let _ = self.sink.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(ignore: &[&str], highlight: &[&str]) -> OutputFilter {
+        OutputFilter::new(
+            "test",
+            &StepPolicy {
+                ignore: ignore.iter().map(|pattern| pattern.to_string()).collect(),
+                highlight: highlight
+                    .iter()
+                    .map(|pattern| pattern.to_string())
+                    .collect(),
+                ..StepPolicy::default()
+            },
+        )
+    }
+
+    const OUTPUT: &str = "Get:1 http://archive curl\nWARNING: held back\r\nSetting up curl\nGet:2 http://archive WARNING\n";
+
+    #[test]
+    fn invalid_patterns_are_left_out() {
+        assert!(filter(&["("], &["[a-"]).is_empty());
+        assert!(!filter(&["(", "^Get:"], &[]).is_empty());
+    }
+
+    #[test]
+    fn apply_leaves_out_ignored_lines() {
+        assert_eq!(
+            filter(&["^Get:"], &[]).apply(OUTPUT, false),
+            "WARNING: held back\r\nSetting up curl\n"
+        );
+    }
+
+    #[test]
+    fn apply_colours_highlighted_lines_keeping_their_line_ending() {
+        assert_eq!(
+            filter(&["^Get:"], &["WARNING"]).apply(OUTPUT, true),
+            "\x1b[1;33mWARNING: held back\x1b[0m\r\nSetting up curl\n"
+        );
+        assert_eq!(
+            filter(&[], &["WARNING"]).apply("WARNING", true),
+            "\x1b[1;33mWARNING\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn apply_without_colour_passes_highlighted_lines_on_as_they_are() {
+        assert_eq!(filter(&[], &["WARNING"]).apply(OUTPUT, false), OUTPUT);
+    }
+
+    #[test]
+    fn highlighted_skips_what_is_ignored() {
+        assert_eq!(
+            filter(&["^Get:"], &["WARNING"]).highlighted(OUTPUT),
+            vec!["WARNING: held back"]
+        );
+    }
+
+    #[test]
+    fn filtered_passes_on_whole_lines() {
+        let mut shown = Vec::new();
+
+        {
+            let mut filtered = Filtered::new(&mut shown, filter(&["^Get:"], &[]));
+
+            filtered.write_all(b"Get:1 http").unwrap();
+            filtered.write_all(b"://archive\nSetting up").unwrap();
+            filtered.write_all(b" curl\nGet:2").unwrap();
+        }
+
+        assert_eq!(String::from_utf8(shown).unwrap(), "Setting up curl\n");
+    }
+}
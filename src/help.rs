@@ -0,0 +1,595 @@
+use std::process;
+
+use crate::cli::{self, Help};
+use crate::config::Group;
+
+/// What one step does, for `up2date help steps` and the man page
+struct StepDoc {
+    /// The step's name, the one `up2date enable` and `disable` take where they take it
+    name: &'static str,
+    group: Group,
+    /// What it does and why, in plain words, for `--explain`
+    about: &'static str,
+    /// The commands it runs, in order
+    runs: &'static [&'static str],
+    /// What has to be true for it to run
+    requires: &'static str,
+    /// Where it's set up in the config file
+    config: &'static str,
+}
+
+/// Every step up2date can run, in the order a run gets to them
+const STEPS: [StepDoc; 33] = [
+    StepDoc {
+        name: "snapshot",
+        group: Group::System,
+        about: "Takes a snapshot of the system before anything changes, so `up2date rollback` can put it back the way it was if an upgrade breaks something. It needs root because snapshots cover the whole filesystem.",
+        runs: &[
+            "sudo snapper create ...",
+            "sudo timeshift --create ...",
+            "sudo zfs snapshot <dataset>@up2date-<time>",
+        ],
+        requires: "[snapshot] tool set to snapper, timeshift or zfs",
+        config: "[snapshot] tool, dataset",
+    },
+    StepDoc {
+        name: "image",
+        group: Group::System,
+        about: "Updates the read-only system image. Immutable systems can't be changed package by package, the new image is downloaded next to the running one and used from the next boot.",
+        runs: &[
+            "rpm-ostree upgrade",
+            "sudo transactional-update --non-interactive dup",
+            "steamos-update check",
+            "steamos-update",
+        ],
+        requires: "a read-only root: Fedora Atomic or CoreOS, openSUSE MicroOS or SteamOS",
+        config: "",
+    },
+    StepDoc {
+        name: "apt",
+        group: Group::System,
+        about: "Refreshes apt's package lists, then installs every newer package from Ubuntu's or Debian's repositories. apt changes files all over the system, which is why it runs with sudo.",
+        runs: &[
+            "sudo apt-get update",
+            "sudo apt-get -y upgrade --allow-downgrades --with-new-pkgs",
+            "sudo apt-get -y dist-upgrade --allow-downgrades",
+        ],
+        requires: "Ubuntu, Pop!_OS or Debian, dist-upgrade with the server profile",
+        config: "[apt] assume_yes, profile, command, wrapper",
+    },
+    StepDoc {
+        name: "pacman",
+        group: Group::System,
+        about: "Brings the Arch keyring up to date so new packages' signatures can be checked, then syncs the package databases and upgrades every package. pacman manages the whole system, which is why it runs with sudo.",
+        runs: &[
+            "sudo pacman --noconfirm -S archlinux-keyring",
+            "sudo pacman --noconfirm -Syu",
+        ],
+        requires: "Arch Linux or EndeavourOS",
+        config: "[pacman] assume_yes, command, wrapper",
+    },
+    StepDoc {
+        name: "yay",
+        group: Group::System,
+        about: "Upgrades the packages installed from the AUR, which pacman doesn't know about. yay runs as you and asks sudo itself when it installs what it built.",
+        runs: &["yay --noconfirm -Syu"],
+        requires: "Arch Linux or EndeavourOS",
+        config: "[yay] assume_yes, command, wrapper",
+    },
+    StepDoc {
+        name: "flatpak",
+        group: Group::System,
+        about: "Updates the flatpak apps and runtimes, in the system-wide installation and in yours. They're sandboxed apps kept apart from the system packages, so they're updated separately.",
+        runs: &[
+            "flatpak remote-ls --updates --system --columns=origin,ref",
+            "flatpak update -y --system <refs>",
+            "flatpak update -y --user <refs>",
+        ],
+        requires: "Linux with flatpak",
+        config: "[flatpak] assume_yes, installations, remotes",
+    },
+    StepDoc {
+        name: "kubernetes",
+        group: Group::System,
+        about: "Checks whether a newer k3s or k0s release is out for this node and, when you've allowed it, reinstalls it with the official install script, which needs root.",
+        runs: &[
+            "curl -sfL https://get.k3s.io | INSTALL_K3S_CHANNEL=<channel> sh -",
+            "curl -sSLf https://get.k0s.sh | sh && k0s stop && k0s start",
+        ],
+        requires: "[kubernetes] enabled and k3s or k0s, upgrading only with [kubernetes] upgrade",
+        config: "[kubernetes] enabled, channel, upgrade",
+    },
+    StepDoc {
+        name: "assets",
+        group: Group::System,
+        about: "Rebuilds the font, desktop entry and icon caches after the upgrade added or replaced any, so new apps and fonts show up without logging out.",
+        runs: &[
+            "fc-cache -f",
+            "sudo update-desktop-database /usr/share/applications",
+            "sudo gtk-update-icon-cache --force --ignore-theme-index /usr/share/icons/hicolor",
+        ],
+        requires: "Linux, once the upgrade changed fonts, desktop entries or icons",
+        config: "",
+    },
+    StepDoc {
+        name: "services",
+        group: Group::System,
+        about: "Finds the services still running with the old versions of libraries the upgrade replaced and, when you've allowed it, restarts them so they pick up the fixes.",
+        runs: &["sudo needrestart -b", "sudo systemctl restart <service>"],
+        requires: "Linux with systemd, restarting only when [services] restart is on",
+        config: "[services] restart, allow, deny",
+    },
+    StepDoc {
+        name: "brew",
+        group: Group::System,
+        about: "Installs pending Command Line Tools updates when you've allowed it, updates Homebrew's list of formulae and upgrades every outdated one, then restarts the brew services whose formula changed. Homebrew runs as you, only Software Update needs sudo.",
+        runs: &[
+            "sudo softwareupdate --install <Command Line Tools label>",
+            "brew update",
+            "brew upgrade",
+            "brew services restart <formula>",
+        ],
+        requires: "macOS, the Command Line Tools only when [brew] command_line_tools is on",
+        config: "[brew] restart_services, command_line_tools, command, wrapper",
+    },
+    StepDoc {
+        name: "windows",
+        group: Group::System,
+        about: "Upgrades what winget, Chocolatey and Scoop installed, each package only once even when more than one of them knows about it.",
+        runs: &[
+            "winget upgrade --id <id> --exact --silent ...",
+            "choco upgrade <package> -y",
+            "scoop update <app>",
+        ],
+        requires: "Windows with winget, choco or scoop",
+        config: "[windows] owners",
+    },
+    StepDoc {
+        name: "cleanup",
+        group: Group::Cleanup,
+        about: "Removes what's no longer needed: packages nothing depends on, old kernels and Homebrew's cache. Nothing is removed until you've seen the list and agreed, or turned that on in the config.",
+        runs: &[
+            "sudo apt-get -y autoremove",
+            "sudo apt-get -y purge <old kernels>",
+            "sudo pacman --noconfirm -Rns <orphans>",
+            "yay --noconfirm -Rns <orphans>",
+            "brew cleanup",
+        ],
+        requires: "agreeing to what would be removed, unless [cleanup] remove is true",
+        config: "[cleanup] remove, keep, [apt] keep_kernels",
+    },
+    StepDoc {
+        name: "rust",
+        group: Group::Rust,
+        about: "Updates the Rust toolchains with rustup, adds back the toolchains, targets and components you want to keep, and reinstalls the crates installed with cargo that have newer versions.",
+        runs: &[
+            "rustup update",
+            "rustup toolchain install <toolchains>",
+            "rustup target add <targets>",
+            "rustup component add <component> --toolchain <toolchain>",
+            "cargo install <outdated crate>",
+        ],
+        requires: "rustup and cargo",
+        config: "[rust] components, toolchains, targets",
+    },
+    StepDoc {
+        name: "nvim",
+        group: Group::Editors,
+        about: "Updates Neovim's plugins with lazy.nvim, and the language servers and tools Mason installed, without opening an editor window.",
+        runs: &[
+            "nvim --headless \"+Lazy! sync\" +qa",
+            "nvim --headless +MasonToolsUpdateSync +qa",
+        ],
+        requires: "Neovim with lazy.nvim, Mason when it's installed",
+        config: "[nvim] appnames",
+    },
+    StepDoc {
+        name: "julia",
+        group: Group::Languages,
+        about: "Updates the packages in Julia's default environment.",
+        runs: &["julia -e \"using Pkg; Pkg.update()\""],
+        requires: "[julia] enabled and julia",
+        config: "[julia] enabled",
+    },
+    StepDoc {
+        name: "r",
+        group: Group::Languages,
+        about: "Updates the R packages installed from CRAN, without asking about each one.",
+        runs: &["R --no-save -e \"update.packages(ask = FALSE)\""],
+        requires: "[r] enabled and R",
+        config: "[r] enabled",
+    },
+    StepDoc {
+        name: "haskell",
+        group: Group::Languages,
+        about: "Updates ghcup and the Haskell tools it manages, and stack.",
+        runs: &["ghcup upgrade", "stack upgrade"],
+        requires: "[haskell] enabled and ghcup or stack",
+        config: "[haskell] enabled",
+    },
+    StepDoc {
+        name: "conda",
+        group: Group::Languages,
+        about: "Updates every package in the conda environments, base and the ones listed in the config.",
+        runs: &["mamba -y update --all -n <environment>"],
+        requires: "[conda] enabled and mamba or conda",
+        config: "[conda] enabled, environments, assume_yes",
+    },
+    StepDoc {
+        name: "sdkman",
+        group: Group::Languages,
+        about: "Updates SDKMAN itself and upgrades the SDKs it installed, Java, Gradle and the like, through a login shell since SDKMAN lives in shell functions.",
+        runs: &["bash -lc \"source .../sdkman-init.sh && sdk selfupdate && sdk upgrade\""],
+        requires: "SDKMAN in $SDKMAN_DIR or ~/.sdkman",
+        config: "[sdkman] assume_yes",
+    },
+    StepDoc {
+        name: "go",
+        group: Group::Languages,
+        about: "Reinstalls the latest version of every tool installed with `go install`.",
+        runs: &["go install <package>@latest"],
+        requires: "go",
+        config: "",
+    },
+    StepDoc {
+        name: "composer",
+        group: Group::Languages,
+        about: "Updates the PHP packages installed globally with composer.",
+        runs: &["env COMPOSER_NO_INTERACTION=1 composer global update"],
+        requires: "composer",
+        config: "",
+    },
+    StepDoc {
+        name: "dotnet",
+        group: Group::Languages,
+        about: "Updates the .NET global tools and, when turned on, the SDK workloads.",
+        runs: &["dotnet tool update -g <tool>", "dotnet workload update"],
+        requires: "dotnet, workloads only when [dotnet] workloads is on",
+        config: "[dotnet] workloads",
+    },
+    StepDoc {
+        name: "powershell",
+        group: Group::Languages,
+        about: "Updates the PowerShell modules from the gallery and the help files.",
+        runs: &[
+            "pwsh -NoProfile -NonInteractive -Command \"Update-Module -AcceptLicense -Force\"",
+            "pwsh -NoProfile -NonInteractive -Command \"Update-Help -Scope CurrentUser -Force\"",
+        ],
+        requires: "pwsh, or pwsh.exe under WSL",
+        config: "[powershell] help",
+    },
+    StepDoc {
+        name: "rbenv",
+        group: Group::Languages,
+        about: "Pulls the newest rbenv and its plugins with git, so the newest Ruby versions can be installed.",
+        runs: &[
+            "git -C $RBENV_ROOT pull --ff-only",
+            "git -C $RBENV_ROOT/plugins/<plugin> pull --ff-only",
+        ],
+        requires: "rbenv cloned with git into $RBENV_ROOT or ~/.rbenv",
+        config: "",
+    },
+    StepDoc {
+        name: "pyenv",
+        group: Group::Languages,
+        about: "Pulls the newest pyenv and its plugins, so the newest Python versions can be installed.",
+        runs: &[
+            "pyenv update",
+            "git -C $PYENV_ROOT pull --ff-only",
+            "git -C $PYENV_ROOT/plugins/<plugin> pull --ff-only",
+        ],
+        requires:
+            "pyenv cloned with git into $PYENV_ROOT or ~/.pyenv, pyenv update with pyenv-update",
+        config: "",
+    },
+    StepDoc {
+        name: "containers",
+        group: Group::Containers,
+        about: "Pulls newer images for the compose projects in the config, then recreates only the containers whose image changed.",
+        runs: &[
+            "docker compose -f <file> pull",
+            "docker compose -f <file> up -d",
+            "podman-compose -f <file> pull",
+            "podman-compose -f <file> up -d",
+        ],
+        requires: "[containers] projects and docker or podman-compose",
+        config: "[containers] projects, engine",
+    },
+    StepDoc {
+        name: "infra",
+        group: Group::Cloud,
+        about: "Refreshes the helm chart repositories and plugins, and installs the latest terraform with tfenv or tenv.",
+        runs: &[
+            "helm repo update",
+            "helm plugin update <plugin>",
+            "tfenv install latest",
+            "tenv tf install latest",
+        ],
+        requires: "[infra] enabled and helm, tfenv or tenv",
+        config: "[infra] enabled",
+    },
+    StepDoc {
+        name: "gaming",
+        group: Group::Gaming,
+        about: "Installs the newest Proton GE and the newest Lutris runners, which don't come from the package manager.",
+        runs: &[
+            "protonup-rs --quick-download",
+            "protonup -y",
+            "lutris --install-runner <runner>",
+        ],
+        requires: "[groups] gaming turned on and protonup-rs, protonup or lutris",
+        config: "[groups] gaming",
+    },
+    StepDoc {
+        name: "selfhosted",
+        group: Group::Selfhosted,
+        about: "Updates Pi-hole, reminds you GitLab comes with the system packages, and checks Nextcloud's apps for updates, upgrading its database only when you've allowed it. They run as root or Nextcloud's user because that's who owns their files.",
+        runs: &[
+            "sudo pihole -up",
+            "sudo -u <user> php <nextcloud>/occ update:check",
+            "sudo -u <user> php <nextcloud>/occ upgrade",
+            "sudo nextcloud.occ update:check",
+        ],
+        requires: "[groups] selfhosted turned on and pihole, gitlab-ctl or Nextcloud",
+        config: "[groups] selfhosted, [selfhosted] nextcloud, nextcloud_user, nextcloud_upgrade",
+    },
+    StepDoc {
+        name: "security",
+        group: Group::Security,
+        about: "Refreshes ClamAV's virus signatures, unless its own daemon already does, and rkhunter's data files. Both keep their databases where only root can write.",
+        runs: &["sudo freshclam", "sudo rkhunter --update"],
+        requires: "[groups] security turned on and freshclam without its daemon, or rkhunter",
+        config: "[groups] security",
+    },
+    StepDoc {
+        name: "maintenance",
+        group: Group::Maintenance,
+        about: "Trims the SSDs when no timer does, and checks how the btrfs filesystems and the disks' SMART health are doing. Nothing is changed besides the trim, problems end up in the summary.",
+        runs: &[
+            "sudo fstrim -av",
+            "sudo btrfs scrub status <mount>",
+            "sudo btrfs balance status <mount>",
+            "sudo smartctl -H <device>",
+        ],
+        requires: "[groups] maintenance turned on, fstrim only without fstrim.timer enabled",
+        config: "[groups] maintenance",
+    },
+    StepDoc {
+        name: "plugins",
+        group: Group::System,
+        about: "Asks the up2date-plugin-* executables on PATH whether they have anything to update and runs the steps they plan.",
+        runs: &["up2date-plugin-<name> detect", "up2date-plugin-<name> plan"],
+        requires: "[plugins] enabled and up2date-plugin-* executables on PATH, in the group each one picks",
+        config: "[plugins] enabled, skip",
+    },
+    StepDoc {
+        name: "audit",
+        group: Group::Audit,
+        about: "Checks what cargo and pip installed for known vulnerabilities once everything's updated, so you find out about what an update didn't fix yet.",
+        runs: &[
+            "cargo audit --json bin <binaries>",
+            "pip-audit --format json",
+        ],
+        requires: "[audit] enabled and cargo-audit or pip-audit",
+        config: "[audit] enabled",
+    },
+];
+
+/// Find a step by its name
+///
+/// # Arguments
+///
+/// * `name` - The step's name, e.g. `apt`
+fn step(name: &str) -> Option<&'static StepDoc> {
+    STEPS.iter().find(|step| step.name == name)
+}
+
+/// Describe one step: what it runs, what it needs and where it's set up
+///
+/// # Arguments
+///
+/// * `step` - The step to describe
+fn describe(step: &StepDoc) -> String {
+    let mut text = format!(
+        "{} (group {})\n\n{}\n\nruns:\n",
+        step.name,
+        step.group.name(),
+        step.about
+    );
+
+    for command in step.runs.iter() {
+        text.push_str(&format!("  {}\n", command));
+    }
+
+    text.push_str(&format!("\nrequires: {}\n", step.requires));
+
+    if !step.config.is_empty() {
+        text.push_str(&format!("config:   {}\n", step.config));
+    }
+
+    text
+}
+
+/// List every step with its group and what it needs, one per line
+fn list() -> String {
+    let width = STEPS.iter().map(|step| step.name.len()).max().unwrap_or(0);
+
+    STEPS
+        .iter()
+        .map(|step| {
+            format!(
+                "  {:width$}  {:9}  {}\n",
+                step.name,
+                step.group.name(),
+                step.requires,
+                width = width
+            )
+        })
+        .collect()
+}
+
+/// How many of the words of a command in a step's `runs` a command line starts with,
+/// placeholders like `<refs>`, `$RBENV_ROOT` and `...` standing for any word and sudo on
+/// either side not counting
+///
+/// # Arguments
+///
+/// * `run` - The command from `runs`
+/// * `words` - The command line about to run
+fn matching(run: &str, words: &[&str]) -> usize {
+    let words = words.iter().copied().skip_while(|word| *word == "sudo");
+
+    run.split_whitespace()
+        .skip_while(|word| *word == "sudo")
+        .zip(words)
+        .take_while(|(expected, word)| {
+            expected == word
+                || expected.starts_with('<')
+                || expected.starts_with('$')
+                || expected.contains("...")
+        })
+        .count()
+}
+
+/// The plain words `--explain` prints before a command runs, about the step it's part of
+/// when one of the steps runs it
+///
+/// # Arguments
+///
+/// * `command_line` - The command about to run, e.g. `sudo apt-get update`
+pub fn explain(command_line: &str) -> Option<String> {
+    let words: Vec<&str> = command_line.split_whitespace().collect();
+    let (score, step) = STEPS
+        .iter()
+        .map(|step| {
+            let score = step
+                .runs
+                .iter()
+                .map(|run| matching(run, &words))
+                .max()
+                .unwrap_or(0);
+
+            (score, step)
+        })
+        // the first step wins a tie, like apt-get's own step over cleanup's
+        .fold((0, None), |best, (score, step)| {
+            if score > best.0 {
+                (score, Some(step))
+            } else {
+                best
+            }
+        });
+
+    step.filter(|_| score > 0)
+        .map(|step| format!("{} ({}): {}", step.name, step.group.name(), step.about))
+}
+
+/// Print the help asked for with `up2date help`
+///
+/// # Arguments
+///
+/// * `topic` - What to print help about
+pub fn print(topic: &Help) {
+    match topic {
+        Help::Usage => print!("{}", long()),
+        Help::Steps(None) => print!("{}", list()),
+        Help::Steps(Some(name)) => match step(name) {
+            Some(step) => print!("{}", describe(step)),
+            None => {
+                eprintln!(
+                    "ERROR: unknown step: {}, expected one of {}",
+                    name,
+                    STEPS
+                        .iter()
+                        .map(|step| step.name)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                );
+                process::exit(2);
+            }
+        },
+    }
+}
+
+/// The usage followed by every step, what `up2date help` prints
+fn long() -> String {
+    format!(
+        "{}\n\nsteps (see up2date help steps <step>):\n{}",
+        cli::usage(),
+        list()
+    )
+}
+
+/// Escape text for roff, so a line starting with `.` or `'` isn't taken for a request
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Man page entries for the commands or the options
+///
+/// # Arguments
+///
+/// * `entries` - Each command or option with what it does
+fn entries(entries: Vec<(String, String)>) -> String {
+    entries
+        .iter()
+        .map(|(term, about)| format!(".TP\n.B {}\n{}\n", roff(term), roff(about)))
+        .collect()
+}
+
+/// The man page, in roff, built from the commands and options the parser takes and the
+/// steps so it can't drift from either
+pub fn man_page() -> String {
+    let mut man = format!(
+        ".TH UP2DATE 1 \"\" \"up2date {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    man.push_str(".SH NAME\nup2date \\- keep the system, languages and editors up to date\n");
+    man.push_str(".SH SYNOPSIS\n.nf\n");
+
+    for synopsis in cli::synopsis() {
+        man.push_str(&format!("up2date {}\n", roff(&synopsis)));
+    }
+
+    man.push_str(".fi\n.SH COMMANDS\n");
+    man.push_str(&entries(cli::commands()));
+    man.push_str(".SH OPTIONS\n");
+    man.push_str(&entries(cli::options()));
+    man.push_str(".SH STEPS\n");
+
+    for step in STEPS.iter() {
+        man.push_str(&format!(
+            ".TP\n.B {}\n{} group. Runs:\n.RS\n.nf\n",
+            roff(step.name),
+            roff(step.group.name())
+        ));
+
+        for command in step.runs.iter() {
+            man.push_str(&format!("{}\n", roff(command)));
+        }
+
+        man.push_str(&format!(".fi\n.RE\nRequires {}.\n", roff(step.requires)));
+
+        if !step.config.is_empty() {
+            man.push_str(&format!("Set up with {}.\n", roff(step.config)));
+        }
+    }
+
+    man.push_str(
+        ".SH FILES\n\
+         .TP\n\
+         .I $XDG_CONFIG_HOME/up2date/config.toml\n\
+         the config file, ~/.config/up2date/config.toml by default\n\
+         .TP\n\
+         .I $XDG_STATE_HOME/up2date\n\
+         the run history and the pending updates, ~/.local/state/up2date by default\n",
+    );
+    man
+}
@@ -0,0 +1,710 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::cli::Format;
+use crate::config;
+use crate::report::{self, Report};
+
+/// The blocks a sparkline is drawn with, lowest first
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How many of the latest runs of each step the sparkline shows
+const SPARKLINE_RUNS: usize = 20;
+
+/// The seconds in a day
+const DAY: u64 = 86400;
+
+/// The file every run's report is appended to, one JSON object per line
+pub fn path() -> Option<PathBuf> {
+    Some(config::state_dir()?.join("history.jsonl"))
+}
+
+/// Add a run's report to the history
+///
+/// # Arguments
+///
+/// * `report` - The report of the run that just finished
+pub fn append(report: &Report) -> io::Result<()> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    writeln!(file, "{}", serde_json::to_string(report)?)
+}
+
+/// Read every run in the history, oldest first
+///
+/// The runs are read as plain JSON so the history written by older versions, which
+/// may be missing fields, can still be read. Lines that can't be parsed are skipped.
+pub fn read() -> io::Result<Vec<Value>> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Vec::new()),
+    };
+
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Read only the most recent run in the history, without parsing the ones before it
+pub fn last() -> io::Result<Option<Value>> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .rev()
+            .find_map(|line| serde_json::from_str(line).ok())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// The number of days from 1970-01-01 to a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// The date a number of days from 1970-01-01 falls on, as year, month and day
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Parse a `YYYY-MM-DD` date into the start of that day (UTC), in seconds since the
+/// Unix epoch
+///
+/// # Arguments
+///
+/// * `date` - The date to parse
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    u64::try_from(days_from_civil(year, month, day))
+        .ok()
+        .map(|days| days * DAY)
+}
+
+/// Format seconds since the Unix epoch as a UTC date and time, e.g. `2024-03-05 14:07`
+///
+/// # Arguments
+///
+/// * `seconds` - The time to format
+pub fn format_date(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days((seconds / DAY) as i64);
+    let time = seconds % DAY;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60
+    )
+}
+
+/// Find a run in the history by its number in `up2date history` or by a date, which
+/// picks the last run started that day
+///
+/// # Arguments
+///
+/// * `runs` - The history, oldest first
+/// * `run` - The run's number or date
+fn find(runs: &[Value], run: &str) -> io::Result<usize> {
+    let found = if let Ok(number) = run.parse::<usize>() {
+        (1..=runs.len()).contains(&number).then(|| number - 1)
+    } else if let Some(start) = parse_date(run) {
+        runs.iter().rposition(|recorded| {
+            recorded["started"]
+                .as_u64()
+                .is_some_and(|started| (start..start + DAY).contains(&started))
+        })
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is neither a run number nor a YYYY-MM-DD date", run),
+        ));
+    };
+
+    found.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no run {} in the history", run),
+        )
+    })
+}
+
+/// Print every run in the history with the number `up2date history diff` knows it by
+pub fn print_runs() -> io::Result<()> {
+    let runs = read()?;
+
+    if runs.is_empty() {
+        println!("No runs recorded yet");
+        return Ok(());
+    }
+
+    for (index, run) in runs.iter().enumerate() {
+        let steps = run["steps"].as_array().map_or(&[][..], Vec::as_slice);
+        let failed = steps
+            .iter()
+            .filter(|step| step["success"].as_bool() == Some(false))
+            .count();
+        let changed: usize = steps
+            .iter()
+            .filter_map(|step| step["changes"].as_array())
+            .map(Vec::len)
+            .sum();
+
+        println!(
+            "{:>4}  {}  {:<11}  {} commands, {} failed, {} packages changed",
+            index + 1,
+            run["started"]
+                .as_u64()
+                .map_or(String::from("unknown time    "), format_date),
+            run["outcome"].as_str().unwrap_or("unknown"),
+            steps.len(),
+            failed,
+            changed
+        );
+    }
+
+    Ok(())
+}
+
+/// How each package changed over some runs, from its version before its first change to
+/// its version after its last, by package and leaving out the ones that ended up where
+/// they started
+///
+/// # Arguments
+///
+/// * `runs` - The runs, oldest first
+fn package_changes(runs: &[Value]) -> Vec<(String, Option<String>, Option<String>)> {
+    // the package, its version before the first change and after the last one
+    let mut packages: Vec<(String, Option<String>, Option<String>)> = Vec::new();
+
+    for change in runs
+        .iter()
+        .filter_map(|run| run["steps"].as_array())
+        .flatten()
+        .filter_map(|step| step["changes"].as_array())
+        .flatten()
+    {
+        let package = match change["package"].as_str() {
+            Some(package) => package,
+            None => continue,
+        };
+        let version = |key: &str| change[key].as_str().map(String::from);
+
+        match packages.iter_mut().find(|(name, _, _)| name == package) {
+            Some(entry) => entry.2 = version("to"),
+            None => packages.push((package.to_string(), version("from"), version("to"))),
+        }
+    }
+
+    packages.retain(|(_, before, after)| before != after);
+    packages.sort();
+    packages
+}
+
+/// The commands that failed in a run
+fn failed(run: &Value) -> Vec<String> {
+    run["steps"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice)
+        .iter()
+        .filter(|step| step["success"].as_bool() == Some(false))
+        .filter_map(|step| step["command"].as_str().map(String::from))
+        .collect()
+}
+
+/// Print how the packages changed between two runs
+///
+/// Every change recorded after the first run, up to and including the second, is
+/// collapsed into one line per package going from its version before the first change
+/// to its version after the last. Steps that failed in one run but not the other are
+/// listed too.
+///
+/// # Arguments
+///
+/// * `from` - The earlier run, by number or date
+/// * `to` - The later run, by number or date
+pub fn print_diff(from: &str, to: &str) -> io::Result<()> {
+    let runs = read()?;
+    let (mut first, mut last) = (find(&runs, from)?, find(&runs, to)?);
+
+    if first > last {
+        std::mem::swap(&mut first, &mut last);
+    }
+
+    let packages = package_changes(&runs[first + 1..=last]);
+    let started = |index: usize| {
+        runs[index]["started"]
+            .as_u64()
+            .map_or(String::from("an unknown time"), format_date)
+    };
+
+    println!(
+        "Between run {} ({}) and run {} ({}):",
+        first + 1,
+        started(first),
+        last + 1,
+        started(last)
+    );
+
+    if packages.is_empty() {
+        println!("no package changes were recorded");
+    } else {
+        println!("{} packages changed", packages.len());
+
+        for (package, before, after) in packages.iter() {
+            println!(
+                "    {} {} -> {}",
+                package,
+                before.as_deref().unwrap_or("(new)"),
+                after.as_deref().unwrap_or("(removed)")
+            );
+        }
+    }
+
+    let (failed_before, failed_after) = (failed(&runs[first]), failed(&runs[last]));
+
+    for command in failed_after
+        .iter()
+        .filter(|command| !failed_before.contains(command))
+    {
+        println!("now failing: `{}`", command);
+    }
+
+    for command in failed_before
+        .iter()
+        .filter(|command| !failed_after.contains(command))
+    {
+        println!("no longer failing: `{}`", command);
+    }
+
+    Ok(())
+}
+
+/// Quote a CSV field when it holds a comma, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Print the recorded runs for a spreadsheet or an inventory system
+///
+/// JSON is an array of the runs as they were recorded. CSV has a row for every step,
+/// or for every package a step changed with the step's columns repeated.
+///
+/// # Arguments
+///
+/// * `format` - What to print the runs as
+/// * `since` - Only the runs started on or after this, in seconds since the Unix epoch
+pub fn export(format: Format, since: Option<u64>) -> io::Result<()> {
+    let runs: Vec<Value> = read()?
+        .into_iter()
+        .filter(|run| match since {
+            Some(since) => run["started"]
+                .as_u64()
+                .is_some_and(|started| started >= since),
+            None => true,
+        })
+        .collect();
+    let mut stdout = io::stdout().lock();
+
+    if format == Format::Json {
+        return writeln!(stdout, "{}", serde_json::to_string_pretty(&runs)?);
+    }
+
+    write_csv(&mut stdout, &runs)
+}
+
+/// Write runs as CSV, a row for every step or for every package it changed
+///
+/// # Arguments
+///
+/// * `out` - Where the CSV goes
+/// * `runs` - The runs as they were recorded
+fn write_csv(out: &mut impl Write, runs: &[Value]) -> io::Result<()> {
+    writeln!(
+        out,
+        "started,outcome,command,group,success,seconds,code,package,from,to"
+    )?;
+
+    for run in runs.iter() {
+        for step in run["steps"].as_array().map_or(&[][..], Vec::as_slice) {
+            let text = |value: &Value| match value {
+                Value::Null => String::new(),
+                Value::String(text) => text.clone(),
+                value => value.to_string(),
+            };
+            let columns = [
+                run["started"].as_u64().map(format_date).unwrap_or_default(),
+                text(&run["outcome"]),
+                text(&step["command"]),
+                text(&step["group"]),
+                text(&step["success"]),
+                text(&step["seconds"]),
+                text(&step["code"]),
+            ]
+            .map(|column| csv_field(&column))
+            .join(",");
+            let changes = step["changes"].as_array().map_or(&[][..], Vec::as_slice);
+
+            if changes.is_empty() {
+                writeln!(out, "{},,,", columns)?;
+            }
+
+            for change in changes.iter() {
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    columns,
+                    csv_field(&text(&change["package"])),
+                    csv_field(&text(&change["from"])),
+                    csv_field(&text(&change["to"]))
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw a series of values as a row of blocks scaled between its lowest and highest
+///
+/// # Arguments
+///
+/// * `values` - The values to draw, oldest first
+fn sparkline(values: &[f64]) -> String {
+    let lowest = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let highest = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = highest - lowest;
+
+    values
+        .iter()
+        .map(|value| {
+            if range <= 0.0 {
+                SPARKS[0]
+            } else {
+                SPARKS[(((value - lowest) / range) * 7.0).round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// The runs of one step across the history
+struct StepStats {
+    command: String,
+    /// How long each run took in seconds, oldest first
+    seconds: Vec<f64>,
+    failed: usize,
+}
+
+/// Print how often up2date runs and, for every step, how often it fails and how long
+/// it's been taking, slowest first
+pub fn print_stats() -> io::Result<()> {
+    let runs = read()?;
+
+    if runs.is_empty() {
+        println!("No runs recorded yet");
+        return Ok(());
+    }
+
+    let started: Vec<u64> = runs
+        .iter()
+        .filter_map(|run| run["started"].as_u64())
+        .collect();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    println!("{} runs recorded", runs.len());
+
+    if let (Some(first), Some(last)) = (started.iter().min(), started.iter().max()) {
+        if started.len() > 1 {
+            println!(
+                "one every {} on average over the last {}",
+                report::format_duration((last - first) / (started.len() as u64 - 1)),
+                report::format_duration(now.saturating_sub(*first))
+            );
+        }
+
+        println!(
+            "last run {} ago",
+            report::format_duration(now.saturating_sub(*last))
+        );
+    }
+
+    let mut steps: Vec<StepStats> = Vec::new();
+
+    for step in runs
+        .iter()
+        .filter_map(|run| run["steps"].as_array())
+        .flatten()
+    {
+        let command = match step["command"].as_str() {
+            Some(command) => command,
+            None => continue,
+        };
+        let index = match steps.iter().position(|stats| stats.command == command) {
+            Some(index) => index,
+            None => {
+                steps.push(StepStats {
+                    command: command.to_string(),
+                    seconds: Vec::new(),
+                    failed: 0,
+                });
+                steps.len() - 1
+            }
+        };
+
+        if let Some(seconds) = step["seconds"].as_f64() {
+            steps[index].seconds.push(seconds);
+        }
+
+        if step["success"].as_bool() == Some(false) {
+            steps[index].failed += 1;
+        }
+    }
+
+    let average = |stats: &StepStats| {
+        if stats.seconds.is_empty() {
+            0.0
+        } else {
+            stats.seconds.iter().sum::<f64>() / stats.seconds.len() as f64
+        }
+    };
+
+    steps.sort_by(|a, b| average(b).total_cmp(&average(a)));
+
+    let width = steps
+        .iter()
+        .map(|stats| stats.command.len())
+        .max()
+        .unwrap_or(0)
+        .min(60);
+
+    println!();
+    println!(
+        "{:width$}  {:>5}  {:>6}  {:>8}  last {}",
+        "step",
+        "runs",
+        "failed",
+        "average",
+        SPARKLINE_RUNS,
+        width = width
+    );
+
+    for stats in steps.iter() {
+        let runs = stats.seconds.len().max(stats.failed);
+        let recent = &stats.seconds[stats.seconds.len().saturating_sub(SPARKLINE_RUNS)..];
+        let command: String = stats.command.chars().take(width).collect();
+
+        println!(
+            "{:width$}  {:>5}  {:>5.0}%  {:>7.1}s  {}",
+            command,
+            runs,
+            stats.failed as f64 * 100.0 / runs.max(1) as f64,
+            average(stats),
+            sparkline(recent),
+            width = width
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs as they're recorded in the history, one JSON object a line
+    fn runs(lines: &[&str]) -> Vec<Value> {
+        lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn change(
+        package: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> (String, Option<String>, Option<String>) {
+        (
+            package.to_string(),
+            from.map(String::from),
+            to.map(String::from),
+        )
+    }
+
+    #[test]
+    fn dates_round_trip() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-03-05"), Some(1_709_596_800));
+        assert_eq!(
+            format_date(1_709_596_800 + 14 * 3600 + 7 * 60),
+            "2024-03-05 14:07"
+        );
+        assert_eq!(
+            format_date(parse_date("2000-02-29").unwrap()),
+            "2000-02-29 00:00"
+        );
+    }
+
+    #[test]
+    fn dates_reject_what_isnt_a_date() {
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-03-00"), None);
+        assert_eq!(parse_date("2024-03"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn find_by_number_or_by_the_last_run_that_day() {
+        let runs = runs(&[
+            r#"{"started": 1709596800, "steps": []}"#,
+            r#"{"started": 1709600400, "steps": []}"#,
+            r#"{"started": 1709683200, "steps": []}"#,
+        ]);
+
+        assert_eq!(find(&runs, "1").unwrap(), 0);
+        assert_eq!(find(&runs, "3").unwrap(), 2);
+        assert_eq!(find(&runs, "2024-03-05").unwrap(), 1);
+        assert_eq!(
+            find(&runs, "4").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            find(&runs, "2024-03-07").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            find(&runs, "last week").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn package_changes_collapse_into_first_and_last_version() {
+        let runs = runs(&[
+            r#"{"steps": [{"command": "apt-get upgrade", "changes": [
+                {"package": "curl", "from": "8.4", "to": "8.5"},
+                {"package": "tzdata", "from": "2024a", "to": "2024b"}
+            ]}]}"#,
+            r#"{"steps": [{"command": "apt-get upgrade", "changes": [
+                {"package": "curl", "from": "8.5", "to": "8.6"},
+                {"package": "tzdata", "from": "2024b", "to": "2024a"},
+                {"package": "htop", "from": null, "to": "3.3"},
+                {"package": "nano", "from": "7.2", "to": null}
+            ]}]}"#,
+        ]);
+
+        assert_eq!(
+            package_changes(&runs),
+            vec![
+                change("curl", Some("8.4"), Some("8.6")),
+                change("htop", None, Some("3.3")),
+                change("nano", Some("7.2"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_they_need_it() {
+        assert_eq!(csv_field("apt-get upgrade"), "apt-get upgrade");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_has_a_row_for_each_step_or_change() {
+        let runs = runs(
+            &[r#"{"started": 1709596800, "outcome": "finished", "steps": [
+            {"command": "apt-get upgrade", "group": "system", "success": true,
+             "seconds": 12.5, "code": 0, "changes": [
+                {"package": "curl", "from": "8.4", "to": "8.5"},
+                {"package": "htop", "from": null, "to": "3.3"}
+            ]},
+            {"command": "echo a, b", "group": null, "success": false,
+             "seconds": 0.5, "code": null, "changes": []}
+        ]}"#],
+        );
+        let mut csv = Vec::new();
+
+        write_csv(&mut csv, &runs).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "started,outcome,command,group,success,seconds,code,package,from,to\n\
+             2024-03-05 00:00,finished,apt-get upgrade,system,true,12.5,0,curl,8.4,8.5\n\
+             2024-03-05 00:00,finished,apt-get upgrade,system,true,12.5,0,htop,,3.3\n\
+             2024-03-05 00:00,finished,\"echo a, b\",,false,0.5,,,,\n"
+        );
+    }
+
+    #[test]
+    fn failed_lists_only_the_failed_commands() {
+        let runs = runs(&[r#"{"steps": [
+            {"command": "apt-get upgrade", "success": true},
+            {"command": "flatpak update", "success": false},
+            {"command": "snap refresh", "success": false}
+        ]}"#]);
+
+        assert_eq!(failed(&runs[0]), vec!["flatpak update", "snap refresh"]);
+        assert!(failed(&serde_json::from_str(r#"{"outcome": "aborted"}"#).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// The messages in every language up2date speaks, English first since it's what
+/// anything missing from another language falls back to
+const CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+/// Read a catalog written in the part of Fluent's syntax up2date uses: `id = text` on one
+/// line, with `{ $name }` where an argument goes and `#` comments
+///
+/// # Arguments
+///
+/// * `source` - The catalog's `.ftl` text
+fn parse(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(id, text)| (id.trim(), text))
+        .collect()
+}
+
+/// The language the messages are shown in, from `LC_ALL`, `LC_MESSAGES` or `LANG` like
+/// gettext does, English when it isn't set or up2date doesn't speak it
+fn language() -> &'static str {
+    static LANGUAGE: OnceLock<&'static str> = OnceLock::new();
+
+    LANGUAGE.get_or_init(|| {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        // `de_DE.UTF-8` and `de` are both German
+        let language = locale
+            .split(['_', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        CATALOGS
+            .iter()
+            .map(|(name, _)| *name)
+            .find(|name| *name == language)
+            .unwrap_or("en")
+    })
+}
+
+/// Every catalog, by language
+fn catalogs() -> &'static HashMap<&'static str, HashMap<&'static str, &'static str>> {
+    static PARSED: OnceLock<HashMap<&'static str, HashMap<&'static str, &'static str>>> =
+        OnceLock::new();
+
+    PARSED.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(name, source)| (*name, parse(source)))
+            .collect()
+    })
+}
+
+/// Fill in a message's arguments, ones that aren't given are left as they are
+fn format(text: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut formatted = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = rest[start + 1..end].trim().trim_start_matches('$');
+
+        formatted.push_str(&rest[..start]);
+
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => formatted.push_str(&value.to_string()),
+            None => formatted.push_str(&rest[start..=end]),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    formatted.push_str(rest);
+    formatted
+}
+
+/// A message in the user's language, or in English when it hasn't been translated
+///
+/// # Arguments
+///
+/// * `id` - The message's id in `locales/*.ftl`, it's shown as is when no catalog has it
+/// * `args` - The values of the message's `{ $name }` arguments
+pub fn tr(id: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let catalogs = catalogs();
+    let text = [language(), "en"]
+        .iter()
+        .filter_map(|language| catalogs.get(language))
+        .find_map(|catalog| catalog.get(id))
+        .copied()
+        .unwrap_or(id);
+
+    format(text, args)
+}
+
+/// Whether an answer to a yes/no prompt is a yes, in English or the user's language
+///
+/// # Arguments
+///
+/// * `answer` - What the user typed
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+
+    ["y", "yes"].contains(&answer.as_str())
+        || tr("answer-yes", &[])
+            .split(',')
+            .any(|yes| yes.trim() == answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_translation_has_an_english_message_with_the_same_arguments() {
+        let english = parse(CATALOGS[0].1);
+        let arguments = |text: &str| {
+            let mut names: Vec<String> = text
+                .split('{')
+                .skip(1)
+                .filter_map(|part| part.split_once('}'))
+                .map(|(name, _)| name.trim().to_string())
+                .collect();
+
+            names.sort();
+            names
+        };
+
+        for (language, source) in CATALOGS.iter().skip(1) {
+            for (id, text) in parse(source) {
+                match english.get(id) {
+                    Some(english) => assert_eq!(
+                        arguments(english),
+                        arguments(text),
+                        "{} has other arguments in {}",
+                        id,
+                        language
+                    ),
+                    None => panic!("{} in {} isn't in en", id, language),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arguments_are_filled_in() {
+        assert_eq!(
+            format(
+                "{ $run } commands run, {$failed} failed, { $other }",
+                &[("run", &3), ("failed", &"no")]
+            ),
+            "3 commands run, no failed, { $other }"
+        );
+    }
+}
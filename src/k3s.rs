@@ -0,0 +1,120 @@
+use scuttle::App;
+
+use crate::config::{AssumeYes, Kubernetes};
+use crate::detect::has_binary;
+use crate::parsers;
+use crate::preview::PendingUpdate;
+use crate::runner::Step;
+
+/// Where k3s says which release each channel is on, the channel's URL redirecting to it
+const K3S_CHANNELS: &str = "https://update.k3s.io/v1-release/channels";
+
+/// The latest stable k0s release
+const K0S_STABLE: &str = "https://docs.k0sproject.io/stable.txt";
+
+/// Run a command and return what it printed, nothing when it fails
+///
+/// # Arguments
+///
+/// * `command_line` - The command and its arguments
+fn output(command_line: &[&str]) -> String {
+    match scuttle::run_output(&App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    }) {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => String::new(),
+    }
+}
+
+/// The newer k3s or k0s release this node could be upgraded to, nothing when it's on
+/// the latest one, neither is installed or `[kubernetes]` isn't turned on
+///
+/// # Arguments
+///
+/// * `settings` - The `[kubernetes]` settings from the config
+pub fn pending(settings: &Kubernetes) -> Vec<PendingUpdate> {
+    if !settings.enabled {
+        return Vec::new();
+    }
+
+    let (package, running, available) = if has_binary("k3s") {
+        let channel = format!("{}/{}", K3S_CHANNELS, settings.channel);
+
+        (
+            "k3s",
+            parsers::parse_kubernetes_version(&output(&["k3s", "--version"])),
+            parsers::parse_release_tag(&output(&[
+                "curl",
+                "--silent",
+                "--output",
+                "/dev/null",
+                "--write-out",
+                "%{redirect_url}",
+                &channel,
+            ])),
+        )
+    } else if has_binary("k0s") {
+        (
+            "k0s",
+            parsers::parse_kubernetes_version(&output(&["k0s", "version"])),
+            parsers::parse_kubernetes_version(&output(&["curl", "--silent", "--fail", K0S_STABLE])),
+        )
+    } else {
+        return Vec::new();
+    };
+
+    match (running, available) {
+        (Some(running), Some(available)) if running != available => vec![PendingUpdate {
+            manager: package,
+            package: package.to_string(),
+            from: Some(running),
+            to: Some(available),
+            security: false,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+/// The steps that rerun the install script to upgrade the node, nothing unless
+/// `[kubernetes] upgrade` allows it
+///
+/// The install scripts start the service again from its config file, so flags that were
+/// only passed to the first install are lost.
+///
+/// # Arguments
+///
+/// * `pending` - The release from `pending()`
+/// * `settings` - The `[kubernetes]` settings from the config
+pub fn upgrade_steps(pending: &[PendingUpdate], settings: &Kubernetes) -> Vec<Step> {
+    if settings.upgrade == AssumeYes::No {
+        return Vec::new();
+    }
+
+    pending
+        .iter()
+        .map(|update| {
+            let script = match update.manager {
+                "k3s" => format!(
+                    "curl -sfL https://get.k3s.io | INSTALL_K3S_CHANNEL={} sh -",
+                    settings.channel
+                ),
+                _ => String::from("curl -sSLf https://get.k0s.sh | sh && k0s stop && k0s start"),
+            };
+
+            Step {
+                app: App {
+                    command: String::from("sudo"),
+                    args: vec!["sh".to_string(), "-c".to_string(), script],
+                },
+                ask: settings.upgrade == AssumeYes::Ask,
+                required: false,
+            }
+        })
+        .collect()
+}
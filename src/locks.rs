@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+
+use crate::detect;
+
+/// The lock pacman keeps for as long as it's changing anything
+const PACMAN_LOCK: &str = "/var/lib/pacman/db.lck";
+
+/// The processes that hold the dpkg and apt locks while they run, as `/proc/<pid>/comm`
+/// has them, cut off at 15 characters
+const APT_HOLDERS: [&str; 5] = ["apt", "apt-get", "aptitude", "dpkg", "unattended-upgr"];
+
+/// Find a running process by name, returning its pid
+///
+/// Looking through `/proc` works without root, unlike opening the lock files.
+///
+/// # Arguments
+///
+/// * `names` - The names to look for
+fn running(names: &[&str]) -> Option<(String, u32)> {
+    let own = std::process::id();
+
+    fs::read_dir("/proc")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok()?.parse::<u32>().ok())
+        .filter(|pid| *pid != own)
+        .find_map(|pid| {
+            let name = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+            let name = name.trim();
+
+            names.contains(&name).then(|| (name.to_string(), pid))
+        })
+}
+
+/// The pacman lock when no pacman is running to hold it, left over from one that was
+/// killed or didn't get to finish, `None` when there's no such lock
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+pub fn stale(release: Option<&str>) -> Option<&'static str> {
+    if detect::shims().is_some() {
+        return None;
+    }
+
+    match release {
+        Some("arch") | Some("endeavouros")
+            if Path::new(PACMAN_LOCK).exists() && running(&["pacman"]).is_none() =>
+        {
+            Some(PACMAN_LOCK)
+        }
+        _ => None,
+    }
+}
+
+/// What's holding the package manager's lock, `None` when nothing is
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+pub fn holder(release: Option<&str>) -> Option<String> {
+    // the shims never hold anything, and whatever really is running doesn't matter
+    if detect::shims().is_some() {
+        return None;
+    }
+
+    match release {
+        Some("ubuntu") | Some("pop") | Some("debian") => {
+            running(&APT_HOLDERS).map(|(name, pid)| format!("{} (pid {})", name, pid))
+        }
+        Some("arch") | Some("endeavouros") if Path::new(PACMAN_LOCK).exists() => {
+            match running(&["pacman"]) {
+                Some((name, pid)) => Some(format!("{} (pid {})", name, pid)),
+                None => Some(PACMAN_LOCK.to_string()),
+            }
+        }
+        _ => None,
+    }
+}
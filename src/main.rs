@@ -1,251 +1,2082 @@
-use std::env::consts::OS;
-use sys_info::*;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process;
 
-use scuttle::{App, Args};
+use scuttle::App;
+
+use config::{AssumeYes, Config, Group};
+use i18n::tr;
+use pipeline::{Feed, Parser, Pipeline};
+use runner::{Runner, Step};
 
 extern crate scuttle;
 extern crate sys_info;
 
-/// Run a list of apps and print out the command and it's arguments before running
+mod assets;
+mod baseline;
+mod batch;
+mod boot;
+mod bundle;
+mod changelog;
+mod cli;
+mod clt;
+mod config;
+mod desktop;
+mod detect;
+mod filter;
+mod help;
+mod history;
+mod i18n;
+mod k3s;
+mod locks;
+mod maintenance;
+mod maps;
+mod mqtt;
+mod notify;
+mod parsers;
+mod pipeline;
+mod platform;
+mod plugins;
+mod policy;
+mod preview;
+mod pro;
+mod redact;
+mod render;
+mod report;
+mod runner;
+mod schedule;
+mod scripts;
+mod secrets;
+mod selfhosted;
+mod serve;
+mod snapshot;
+mod status;
+mod sudoers;
+mod template;
+mod unattended;
+mod windows;
+mod wizard;
+
+/// Ask the user a yes/no question on the terminal, defaulting to no
+///
+/// # Arguments
+///
+/// * `question` - The question to print before the `[y/N]` prompt
+fn confirm(question: &str) -> bool {
+    print!("{} {} ", question, tr("prompt-yes-no", &[]));
+
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+
+    let mut answer = String::new();
+
+    match io::stdin().read_line(&mut answer) {
+        Ok(_) => i18n::is_yes(&answer),
+        Err(_) => false,
+    }
+}
+
+/// Build a step for a package manager command that would normally prompt for confirmation
+///
+/// The manager's `assume_yes` policy decides whether `flag` is placed right after the
+/// manager's binary and whether the user is asked before the step runs.
+///
+/// # Arguments
+///
+/// * `assume_yes` - The package manager's `assume_yes` policy
+/// * `sudo` - Whether the manager has to be run with sudo, these manage the system so a
+///   failure stops the whole run unless the config says otherwise
+/// * `executable` - What the config runs the manager as instead of `binary`
+/// * `binary` - The package manager's binary
+/// * `flag` - The flag that stops the manager from prompting (`-y`, `--noconfirm`)
+/// * `args` - The arguments to pass after the flag
+fn manager_step(
+    assume_yes: AssumeYes,
+    sudo: bool,
+    executable: &config::Executable,
+    binary: &str,
+    flag: &str,
+    args: &[&str],
+) -> Step {
+    let mut manager_args: Vec<String> = Vec::new();
+
+    if assume_yes != AssumeYes::No {
+        manager_args.push(flag.to_string());
+    }
+
+    manager_args.extend(args.iter().map(|arg| arg.to_string()));
+
+    let mut command_line = executable.command_line(binary, &manager_args);
+
+    let app = if sudo {
+        App {
+            command: String::from("sudo"),
+            args: command_line,
+        }
+    } else {
+        App {
+            command: command_line.remove(0),
+            args: command_line,
+        }
+    };
+
+    Step {
+        app,
+        ask: assume_yes == AssumeYes::Ask,
+        required: sudo,
+    }
+}
+
+/// Build the app for a package manager command that doesn't prompt, run as the config
+/// says
+///
+/// # Arguments
+///
+/// * `executable` - What the config runs the manager as instead of `binary`
+/// * `binary` - The package manager's binary
+/// * `args` - The arguments for the manager
+fn manager_app(executable: &config::Executable, binary: &str, args: &[&str]) -> App {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let mut command_line = executable.command_line(binary, &args);
+
+    App {
+        command: command_line.remove(0),
+        args: command_line,
+    }
+}
+
+/// Whether apt upgrades with the server profile, which Proxmox hosts do unless the config
+/// says otherwise
+///
+/// # Arguments
+///
+/// * `apt` - The apt settings from the config
+fn apt_server(apt: &config::Apt) -> bool {
+    match apt.profile {
+        Some(profile) => profile == config::AptProfile::Server,
+        None => detect::has_binary("pveversion"),
+    }
+}
+
+/// The `apt-get` arguments that upgrade everything, the server profile letting packages
+/// be removed or replaced along the way
+///
+/// # Arguments
+///
+/// * `apt` - The apt settings from the config
+fn apt_upgrade_args(apt: &config::Apt) -> Vec<&'static str> {
+    let mut args = if apt_server(apt) {
+        vec!["dist-upgrade", "--allow-downgrades"]
+    } else {
+        vec!["upgrade", "--allow-downgrades", "--with-new-pkgs"]
+    };
+
+    if apt.phased {
+        args.extend(["-o", "APT::Get::Always-Include-Phased-Updates=true"]);
+    }
+
+    args
+}
+
+/// The kernels the server profile purges, all but the newest `[apt] keep_kernels` and the
+/// running one, with the ones `[cleanup] keep` holds on to left out
+///
+/// # Arguments
+///
+/// * `config` - The user's config
+fn old_kernels(config: &Config) -> Vec<String> {
+    let installed = match scuttle::run_output(&App {
+        command: String::from("dpkg-query"),
+        args: vec![
+            "-W".to_string(),
+            "-f".to_string(),
+            "${Package} ${db:Status-Abbrev}\n".to_string(),
+            "linux-image-[0-9]*".to_string(),
+            "proxmox-kernel-[0-9]*".to_string(),
+            "pve-kernel-[0-9]*".to_string(),
+        ],
+    }) {
+        // it exits with 1 when a pattern matches nothing, after listing the rest
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(error) => panic!("panic:{}", error),
+    };
+    let running = match scuttle::run_output(&App {
+        command: String::from("uname"),
+        args: vec!["-r".to_string()],
+    }) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        Err(error) => panic!("panic:{}", error),
+    };
+
+    parsers::parse_old_kernels(&installed, &running, config.apt.keep_kernels)
+        .into_iter()
+        .filter(|kernel| !config.cleanup.keep.contains(kernel))
+        .collect()
+}
+
+/// Build an App that runs an Ex command in a headless Neovim and quits
+///
+/// # Arguments
+///
+/// * `appname` - The `NVIM_APPNAME` of the config to use, `nvim` being the default config
+/// * `command` - The Ex command to run
+fn neovim_app(appname: &str, command: &str) -> App {
+    let args = vec![
+        "--headless".to_string(),
+        format!("+{}", command),
+        "+qa".to_string(),
+    ];
+
+    if appname == "nvim" {
+        App {
+            command: String::from("nvim"),
+            args,
+        }
+    } else {
+        // scuttle can't set environment variables so let env do it, which also
+        // shows which config is being updated in the echoed command
+        App {
+            command: String::from("env"),
+            args: [
+                vec![format!("NVIM_APPNAME={}", appname), "nvim".to_string()],
+                args,
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// Build the apps that update rust and install any configured toolchains and targets
+///
+/// `rustup toolchain install` and `rustup target add` leave anything that's already
+/// current alone so they're run after `rustup update` rather than checked first.
+///
+/// # Arguments
+///
+/// * `rust` - The rust settings from the config file
+fn rust_apps(rust: &config::Rust) -> Vec<App> {
+    let mut apps = vec![App {
+        command: String::from("rustup"),
+        args: vec!["update".to_string()],
+    }];
+
+    if !rust.toolchains.is_empty() {
+        apps.push(App {
+            command: String::from("rustup"),
+            args: [
+                vec!["toolchain".to_string(), "install".to_string()],
+                rust.toolchains.clone(),
+            ]
+            .concat(),
+        });
+    }
+
+    if !rust.targets.is_empty() {
+        apps.push(App {
+            command: String::from("rustup"),
+            args: [
+                vec!["target".to_string(), "add".to_string()],
+                rust.targets.clone(),
+            ]
+            .concat(),
+        });
+    }
+
+    apps
+}
+
+/// Build the apps that update Neovim's plugins, one for each configured `NVIM_APPNAME`
+///
+/// Configs that have mason.nvim installed also get their Mason packages (language
+/// servers, formatters, ...) updated.
+///
+/// # Arguments
+///
+/// * `nvim` - The Neovim settings from the config
+fn neovim_apps(nvim: &config::Nvim) -> Vec<App> {
+    let default_appnames = vec![String::from("nvim")];
+    let appnames = if nvim.appnames.is_empty() {
+        &default_appnames
+    } else {
+        &nvim.appnames
+    };
+    let mut apps: Vec<App> = Vec::new();
+
+    for appname in appnames.iter() {
+        apps.push(neovim_app(appname, "Lazy! sync"));
+
+        if detect::has_nvim_plugin(appname, "mason-tool-installer.nvim") {
+            apps.push(neovim_app(appname, "MasonToolsUpdateSync"));
+        } else if detect::has_nvim_plugin(appname, "mason.nvim") {
+            apps.push(neovim_app(appname, "MasonUpdate"));
+        }
+    }
+
+    apps
+}
+
+/// Build the apps for the optional language toolchains that are turned on and installed
+///
+/// # Arguments
+///
+/// * `config` - The user's config
+fn toolchain_apps(config: &Config) -> Vec<App> {
+    let mut apps: Vec<App> = Vec::new();
+
+    if config.julia.enabled && detect::has_binary("julia") {
+        apps.push(App {
+            command: String::from("julia"),
+            args: vec!["-e".to_string(), "using Pkg; Pkg.update()".to_string()],
+        });
+    }
+
+    if config.r.enabled && detect::has_binary("R") {
+        // update.packages() refuses to run non-interactively without a CRAN mirror
+        apps.push(App {
+            command: String::from("R"),
+            args: vec![
+                "--no-save".to_string(),
+                "-e".to_string(),
+                "if (getOption(\"repos\")[\"CRAN\"] == \"@CRAN@\") options(repos = c(CRAN = \"https://cloud.r-project.org\")); update.packages(ask = FALSE)".to_string(),
+            ],
+        });
+    }
+
+    if config.haskell.enabled {
+        if detect::has_binary("ghcup") {
+            apps.push(App {
+                command: String::from("ghcup"),
+                args: vec!["upgrade".to_string()],
+            });
+        }
+
+        if detect::has_binary("stack") {
+            apps.push(App {
+                command: String::from("stack"),
+                args: vec!["upgrade".to_string()],
+            });
+        }
+    }
+
+    apps
+}
+
+/// Build the apps that update the PowerShell modules installed from the PowerShell
+/// Gallery and their help, if PowerShell is installed
+///
+/// Under WSL the Windows PowerShell is reached through interop as `pwsh.exe` when
+/// there's no Linux one. The profile is skipped so a slow or interactive one can't get
+/// in the way.
+///
+/// # Arguments
+///
+/// * `powershell` - The PowerShell settings from the config
+fn powershell_apps(powershell: &config::Powershell) -> Vec<App> {
+    let binary = match ["pwsh", "pwsh.exe"]
+        .into_iter()
+        .find(|binary| detect::has_binary(binary))
+    {
+        Some(binary) => binary,
+        None => return Vec::new(),
+    };
+    let pwsh = |command: &str| App {
+        command: String::from(binary),
+        args: vec![
+            "-NoProfile".to_string(),
+            "-NonInteractive".to_string(),
+            "-Command".to_string(),
+            command.to_string(),
+        ],
+    };
+    let mut apps = vec![pwsh("Update-Module -AcceptLicense -Force")];
+
+    if powershell.help {
+        apps.push(pwsh("Update-Help -Scope CurrentUser -Force"));
+    }
+
+    apps
+}
+
+/// Build the steps that update the conda base environment and any configured environments
+///
+/// mamba is used when it's installed since it resolves updates much faster than conda.
+///
+/// # Arguments
+///
+/// * `conda` - The conda settings from the config
+fn conda_steps(conda: &config::Conda) -> Vec<Step> {
+    let binary = if detect::has_binary("mamba") {
+        "mamba"
+    } else if detect::has_binary("conda") {
+        "conda"
+    } else {
+        return Vec::new();
+    };
+
+    std::iter::once("base")
+        .chain(conda.environments.iter().map(String::as_str))
+        .map(|environment| {
+            manager_step(
+                conda.assume_yes,
+                false,
+                &config::Executable::default(),
+                binary,
+                "-y",
+                &["update", "--all", "-n", environment],
+            )
+        })
+        .collect()
+}
+
+/// Build the step that updates SDKMAN and the candidates it installed, if SDKMAN is installed
+///
+/// `sdk` is a shell function rather than a binary so it has to be loaded into a shell first.
+///
+/// # Arguments
+///
+/// * `sdkman` - The SDKMAN settings from the config
+fn sdkman_step(sdkman: &config::Manager) -> Option<Step> {
+    let sdkman_dir = match std::env::var_os("SDKMAN_DIR") {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => std::path::PathBuf::from(std::env::var_os("HOME")?).join(".sdkman"),
+    };
+    let init = sdkman_dir.join("bin").join("sdkman-init.sh");
+
+    if !init.is_file() {
+        return None;
+    }
+
+    // sdkman_auto_answer is SDKMAN's own -y
+    let upgrade = if sdkman.assume_yes == AssumeYes::No {
+        "sdk upgrade"
+    } else {
+        "sdkman_auto_answer=true sdk upgrade"
+    };
+
+    Some(Step {
+        app: App {
+            command: String::from("bash"),
+            args: vec![
+                "-lc".to_string(),
+                format!(
+                    "source '{}' && sdk selfupdate && {}",
+                    init.display().to_string().replace('\'', "'\\''"),
+                    upgrade
+                ),
+            ],
+        },
+        ask: sdkman.assume_yes == AssumeYes::Ask,
+        required: false,
+    })
+}
+
+/// Build the apps that update rbenv and pyenv, and their plugins, where they were installed
+/// with git
+///
+/// New Ruby and Python versions only become installable once ruby-build and pyenv's own
+/// definitions are pulled. pyenv-update pulls pyenv and every plugin by itself so
+/// `pyenv update` is used when it's there. Installs from a package manager are left to it.
+fn version_manager_apps() -> Vec<App> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let git_pull = |dir: &std::path::Path| App {
+        command: String::from("git"),
+        args: vec![
+            "-C".to_string(),
+            dir.display().to_string(),
+            "pull".to_string(),
+            "--ff-only".to_string(),
+        ],
+    };
+    let mut apps: Vec<App> = Vec::new();
+
+    for (manager, root) in [("rbenv", "RBENV_ROOT"), ("pyenv", "PYENV_ROOT")] {
+        let root = match (std::env::var_os(root), home.as_ref()) {
+            (Some(root), _) if !root.is_empty() => PathBuf::from(root),
+            (_, Some(home)) => home.join(format!(".{}", manager)),
+            _ => continue,
+        };
+
+        if !root.join(".git").exists() {
+            continue;
+        }
+
+        let plugins = root.join("plugins");
+
+        if manager == "pyenv" && plugins.join("pyenv-update").is_dir() {
+            apps.push(App {
+                command: String::from("pyenv"),
+                args: vec!["update".to_string()],
+            });
+            continue;
+        }
+
+        apps.push(git_pull(&root));
+
+        let mut installed: Vec<PathBuf> = match std::fs::read_dir(&plugins) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|plugin| plugin.join(".git").exists())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        installed.sort();
+        apps.extend(installed.iter().map(|plugin| git_pull(plugin)));
+    }
+
+    apps
+}
+
+/// Build the apps for the infra tools group that are installed
+///
+/// # Arguments
+///
+/// * `infra` - The infra tools settings from the config
+fn infra_apps(infra: &config::Toggle) -> Vec<App> {
+    let mut apps: Vec<App> = Vec::new();
+
+    if !infra.enabled {
+        return apps;
+    }
+
+    if detect::has_binary("helm") {
+        apps.push(App {
+            command: String::from("helm"),
+            args: vec!["repo".to_string(), "update".to_string()],
+        });
+    }
+
+    if detect::has_binary("tfenv") {
+        apps.push(App {
+            command: String::from("tfenv"),
+            args: vec!["install".to_string(), "latest".to_string()],
+        });
+    }
+
+    if detect::has_binary("tenv") {
+        apps.push(App {
+            command: String::from("tenv"),
+            args: vec![
+                "tf".to_string(),
+                "install".to_string(),
+                "latest".to_string(),
+            ],
+        });
+    }
+
+    apps
+}
+
+/// Build the apps for the gaming group that are installed
+///
+/// protonup-rs installs the latest Proton-GE for Steam and the latest Wine-GE for Lutris
+/// in one go, protonup only the Proton-GE. Every other runner Lutris has downloaded is
+/// installed again at its latest version.
+fn gaming_apps() -> Vec<App> {
+    let mut apps: Vec<App> = Vec::new();
+
+    if detect::has_binary("protonup-rs") {
+        apps.push(App {
+            command: String::from("protonup-rs"),
+            args: vec!["--quick-download".to_string()],
+        });
+    } else if detect::has_binary("protonup") {
+        apps.push(App {
+            command: String::from("protonup"),
+            args: vec!["-y".to_string()],
+        });
+    }
+
+    if detect::has_binary("lutris") {
+        let data_home = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+            _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")),
+        };
+        let mut runners: Vec<String> = data_home
+            .and_then(|dir| std::fs::read_dir(dir.join("lutris").join("runners")).ok())
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    // wine keeps every version side by side, protonup-rs adds new ones
+                    .filter(|runner| runner != "wine")
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        runners.sort();
+        apps.extend(runners.into_iter().map(|runner| App {
+            command: String::from("lutris"),
+            args: vec!["--install-runner".to_string(), runner],
+        }));
+    }
+
+    apps
+}
+
+/// The files docker compose and podman-compose look for in a project's directory, in the
+/// order they look for them
+const COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+/// Pull new images for each compose project and start its containers again, the pull
+/// and the `up -d` of every project
+///
+/// # Arguments
+///
+/// * `containers` - The compose settings from the config
+fn compose_apps(containers: &config::Containers) -> Vec<(App, App)> {
+    let engine = match containers.engine {
+        Some(engine) => engine,
+        None if detect::has_binary("docker") => config::Engine::Docker,
+        None => config::Engine::Podman,
+    };
+    let compose: Vec<String> = match engine {
+        config::Engine::Docker => vec!["docker".to_string(), "compose".to_string()],
+        config::Engine::Podman => vec!["podman-compose".to_string()],
+    };
+
+    if !detect::has_binary(&compose[0]) {
+        return Vec::new();
+    }
+
+    let mut apps: Vec<(App, App)> = Vec::new();
+
+    for project in containers.projects.iter() {
+        let dir = secrets::expand(project);
+        let file = match COMPOSE_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|file| file.is_file())
+        {
+            Some(file) => file,
+            None => {
+                eprintln!(
+                    "WARNING: there's no compose file in {}, skipping it",
+                    dir.display()
+                );
+                continue;
+            }
+        };
+        let app = |args: &[&str]| App {
+            command: compose[0].clone(),
+            args: compose[1..]
+                .iter()
+                .cloned()
+                .chain(["-f".to_string(), file.display().to_string()])
+                .chain(args.iter().map(|arg| arg.to_string()))
+                .collect(),
+        };
+
+        apps.push((app(&["pull"]), app(&["up", "-d"])));
+    }
+
+    apps
+}
+
+/// Build the apps for the security group that are installed
+///
+/// freshclam can't run while the clamav-freshclam daemon holds its lock, and with the
+/// daemon running the definitions are already refreshed several times a day.
+fn security_apps() -> Vec<App> {
+    let mut apps: Vec<App> = Vec::new();
+
+    if detect::has_binary("freshclam") {
+        let daemon = detect::has_binary("systemctl")
+            && scuttle::run_output(&App {
+                command: String::from("systemctl"),
+                args: vec![
+                    "is-active".to_string(),
+                    "--quiet".to_string(),
+                    "clamav-freshclam".to_string(),
+                ],
+            })
+            .is_ok_and(|output| output.status.success());
+
+        if !daemon {
+            apps.push(App {
+                command: String::from("sudo"),
+                args: vec!["freshclam".to_string()],
+            });
+        }
+    }
+
+    if detect::has_binary("rkhunter") {
+        apps.push(App {
+            command: String::from("sudo"),
+            args: vec!["rkhunter".to_string(), "--update".to_string()],
+        });
+    }
+
+    apps
+}
+
+/// Run the updaters of the self-hosted apps that have their own
+///
+/// GitLab comes from the system package manager, which doesn't know about its required
+/// upgrade stops, so it's only mentioned. Nextcloud's `occ upgrade` is only run when
+/// `[selfhosted] nextcloud_upgrade` allows it, otherwise its need is reported.
+///
+/// # Arguments
+///
+/// * `runner` - The runner to run the updaters with
+/// * `settings` - The `[selfhosted]` settings from the config
+/// * `offline` - Whether to skip what needs the network
+fn selfhosted_updates(runner: &mut Runner, settings: &config::Selfhosted, offline: bool) {
+    let network: Vec<App> = selfhosted::pihole()
+        .into_iter()
+        .chain(selfhosted::occ(settings, &["update:check"]))
+        .collect();
+
+    if offline {
+        for app in network.iter() {
+            runner.skip_app(app, "reason-needs-network");
+        }
+    } else {
+        runner.run_apps(&network);
+    }
+
+    if let Some(version) = selfhosted::gitlab() {
+        runner.warn(vec![tr("selfhosted-gitlab", &[("version", &version)])]);
+    }
+
+    if !runner.is_planning() && selfhosted::nextcloud_needs_upgrade(settings) {
+        match selfhosted::occ(settings, &["upgrade"]) {
+            Some(upgrade) if settings.nextcloud_upgrade => runner.run_apps(&[upgrade]),
+            _ => runner.warn(vec![tr("selfhosted-nextcloud-upgrade", &[])]),
+        }
+    }
+}
+
+/// Audit what's installed for known vulnerabilities once everything is updated
+///
+/// # Arguments
+///
+/// * `runner` - The runner to add the vulnerabilities to
+fn audit(runner: &mut Runner) {
+    if detect::has_binary("cargo-audit") {
+        let cargo_home = match std::env::var_os("CARGO_HOME") {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")),
+        };
+        let installed = scuttle::run_output(&App {
+            command: String::from("cargo"),
+            args: vec!["install".to_string(), "--list".to_string()],
+        });
+
+        if let (Some(cargo_home), Ok(output)) = (cargo_home, installed) {
+            // the binaries are the indented lines under each crate
+            let binaries: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|line| line.starts_with(' '))
+                .map(|line| {
+                    cargo_home
+                        .join("bin")
+                        .join(line.trim())
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+
+            if !binaries.is_empty() {
+                let cargo_audit = App {
+                    command: String::from("cargo"),
+                    args: [
+                        vec!["audit".to_string(), "--json".to_string(), "bin".to_string()],
+                        binaries,
+                    ]
+                    .concat(),
+                };
+
+                runner.run_audit(&cargo_audit, parsers::parse_cargo_audit);
+            }
+        }
+    }
+
+    if detect::has_binary("pip-audit") {
+        let pip_audit = App {
+            command: String::from("pip-audit"),
+            args: vec!["--format".to_string(), "json".to_string()],
+        };
+
+        runner.run_audit(&pip_audit, parsers::parse_pip_audit);
+    }
+}
+
+fn main() {
+    // the integration tests run everything against a directory of shim scripts
+    detect::use_shims();
+
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("{}", cli::usage());
+            process::exit(2);
+        }
+    };
+
+    if options.help {
+        println!("{}", cli::usage());
+        return;
+    }
+
+    // none of them needs the config or to know which distribution this is
+    match options.command {
+        cli::Command::Help(ref topic) => return help::print(topic),
+        cli::Command::Man => return print!("{}", help::man_page()),
+        cli::Command::Status(format) => return status::print(format),
+        _ => {}
+    }
+
+    // offer to set things up the first time up2date is run by hand
+    if let Some(path) = config::path() {
+        let interactive =
+            options.command == cli::Command::Run && !options.stdin && io::stdin().is_terminal();
+
+        if interactive && !path.exists() {
+            let written = if confirm(&tr("prompt-first-run", &[])) {
+                wizard::run(&path)
+            } else {
+                wizard::skip(&path)
+            };
+
+            if let Err(error) = written {
+                eprintln!("WARNING: could not write {}: {}", path.display(), error);
+            }
+        }
+    }
+
+    let config: Config = match config::load() {
+        Ok(config) => config,
+        Err(error) => panic!("ERROR: {}", error),
+    };
+
+    let release = detect::release();
+
+    match options.command {
+        cli::Command::Run => run(&config, &options, release.as_deref()),
+        cli::Command::Fetch => fetch(&config, &options, release.as_deref()),
+        cli::Command::Check => check(&config, &options, release.as_deref()),
+        cli::Command::Plan => {
+            for command in plan(&config, &options, release.as_deref()) {
+                println!("{}", command);
+            }
+        }
+        cli::Command::Sudoers(ref output) => {
+            sudoers(&config, &options, release.as_deref(), output.as_deref())
+        }
+        cli::Command::Rollback => rollback(&config),
+        cli::Command::Stats => {
+            if let Err(error) = history::print_stats() {
+                eprintln!("ERROR: could not read the history: {}", error);
+                process::exit(1);
+            }
+        }
+        cli::Command::History(ref command) => {
+            let shown = match command {
+                cli::History::List => history::print_runs(),
+                cli::History::Diff(from, to) => history::print_diff(from, to),
+                cli::History::Export { format, since } => history::export(*format, *since),
+            };
+
+            if let Err(error) = shown {
+                eprintln!("ERROR: {}", error);
+                process::exit(1);
+            }
+        }
+        cli::Command::Serve => {
+            if let Err(error) = serve::serve(&config) {
+                eprintln!(
+                    "ERROR: could not serve on {}: {}",
+                    config.serve.address, error
+                );
+                process::exit(1);
+            }
+        }
+        cli::Command::ReportBundle(ref output) => match bundle::write(output.as_deref()) {
+            Ok(path) => println!("Wrote {}", path.display()),
+            Err(error) => {
+                eprintln!("ERROR: could not write the bundle: {}", error);
+                process::exit(1);
+            }
+        },
+        // answered before the config was loaded
+        cli::Command::Help(_) | cli::Command::Man | cli::Command::Status(_) => {}
+        cli::Command::Desktop(cli::Desktop::Install { autostart }) => {
+            match desktop::install(autostart) {
+                Ok(paths) => {
+                    for path in paths {
+                        println!("Wrote {}", path.display());
+                    }
+                }
+                Err(error) => {
+                    eprintln!("ERROR: could not install the launcher: {}", error);
+                    process::exit(1);
+                }
+            }
+        }
+        cli::Command::Desktop(cli::Desktop::Launch { now }) => {
+            if let Err(error) = desktop::launch(&config.desktop, now) {
+                eprintln!("ERROR: could not start a run: {}", error);
+                process::exit(1);
+            }
+        }
+        cli::Command::Enable(ref step) => set_enabled(step, true),
+        cli::Command::Disable(ref step) => set_enabled(step, false),
+    }
+
+    if let Err(error) = detect::save_cache() {
+        eprintln!("WARNING: could not remember what was detected: {}", error);
+    }
+}
+
+/// List what every package manager would change without applying anything
+///
+/// How many updates are pending is remembered for `up2date serve` and MQTT, and with
+/// `--notify` the user is told when there are enough of them.
+///
+/// # Arguments
+///
+/// * `config` - The user's config
+/// * `options` - The command line options
+/// * `release` - The Linux distribution id, `None` on other platforms
+fn check(config: &Config, options: &cli::Options, release: Option<&str>) {
+    let preview = if options.json {
+        // the package lists are refreshed quietly so only the JSON is printed
+        let preview = preview::gather(None, config, release);
+
+        match serde_json::to_string_pretty(&preview) {
+            Ok(json) => println!("{}", json),
+            Err(error) => panic!("ERROR: {}", error),
+        }
+
+        preview
+    } else {
+        let mut runner = Runner::new(config, None, &[]);
+        let preview = preview::gather(Some(&mut runner), config, release);
+
+        preview::print(&preview);
+        preview
+    };
+
+    if let Err(error) = preview::record(&preview) {
+        eprintln!("WARNING: could not remember the pending updates: {}", error);
+    }
+
+    if options.notify && notify::due(&config.notify, &preview) {
+        notify::send(&config.notify, &preview);
+    }
+}
+
+/// Download system updates without installing them
+///
+/// The packages are left in each manager's cache so `up2date run --offline-ok` can
+/// install them later without a network connection.
+///
+/// # Arguments
+///
+/// * `config` - The user's config
+/// * `options` - The command line options
+/// * `release` - The Linux distribution id, `None` on other platforms
+fn fetch(config: &Config, options: &cli::Options, release: Option<&str>) {
+    let mut runner = Runner::new(
+        config,
+        options
+            .report_file
+            .as_deref()
+            .map(|path| (path, options.report_format)),
+        &[],
+    );
+
+    if detect::os() == "linux" {
+        runner.wait_for_lock(release, &config.locks);
+
+        match (platform::immutable(release), release) {
+            (Some(image), _) => match image.download_step() {
+                Some(step) => runner.run_steps(&[step]),
+                None => {
+                    println!();
+                    println!(
+                        "Nothing to fetch, {} can't download without updating",
+                        image.name()
+                    );
+                }
+            },
+            (None, Some("ubuntu") | Some("pop") | Some("debian")) => {
+                let apt_update = App {
+                    command: String::from("sudo"),
+                    args: config
+                        .apt
+                        .executable
+                        .command_line("apt-get", &["update".to_string()]),
+                };
+                let apt_download = manager_step(
+                    config.apt.assume_yes,
+                    true,
+                    &config.apt.executable,
+                    "apt-get",
+                    "-y",
+                    &[vec!["-d"], apt_upgrade_args(&config.apt)].concat(),
+                );
+
+                runner.run_apps(&[apt_update]);
+                runner.run_steps(&[apt_download]);
+            }
+            (None, Some("arch") | Some("endeavouros")) => {
+                let pacman_download = manager_step(
+                    config.pacman.assume_yes,
+                    true,
+                    &config.pacman.executable,
+                    "pacman",
+                    "--noconfirm",
+                    &["-Syuw"],
+                );
+
+                runner.run_steps(&[pacman_download]);
+            }
+            (None, Some(os_name)) => panic!("ERROR: not sure what OS this is:{}", os_name),
+            (None, None) => panic!("ERROR: not sure what OS this is"),
+        }
+    }
+
+    if detect::os() == "macos" {
+        let brew_update = manager_app(&config.brew.executable, "brew", &["update"]);
+        // fetch whatever `brew outdated` lists, except what's pinned and won't be upgraded
+        let brew_fetch = Pipeline {
+            producer: App {
+                command: config.brew.executable.binary("brew"),
+                args: vec![
+                    "outdated".to_string(),
+                    "--formula".to_string(),
+                    "--json=v2".to_string(),
+                ],
+            },
+            parser: Parser::Json(parsers::brew_outdated_formulae),
+            consumer: Step {
+                app: manager_app(&config.brew.executable, "brew", &["fetch", "--formula"]),
+                ask: false,
+                required: false,
+            },
+            feed: Feed::Args,
+        };
+
+        runner.run_apps(&[brew_update]);
+        runner.run_pipeline(&brew_fetch);
+    }
+
+    runner.finish(release);
+}
+
+/// Turn a step on or off in the config file and say where it was changed
+///
+/// # Arguments
+///
+/// * `step` - The step or group to switch
+/// * `enabled` - Whether it should run
+fn set_enabled(step: &str, enabled: bool) {
+    match config::set_enabled(step, enabled) {
+        Ok(path) => {
+            println!(
+                "{} {} in {}",
+                if enabled { "Enabled" } else { "Disabled" },
+                step,
+                path.display()
+            );
+
+            if config::enabled(step).is_some_and(|effective| effective != enabled) {
+                eprintln!(
+                    "WARNING: {} is still {}, a file {} includes sets it for this machine",
+                    step,
+                    if enabled { "off" } else { "on" },
+                    path.display()
+                );
+            }
+        }
+        Err(error) => {
+            eprintln!("ERROR: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+/// List the snapshots taken before updating and restore the one the user picks
+///
+/// # Arguments
+///
+/// * `config` - The user's config
+fn rollback(config: &Config) {
+    let tool = match config.snapshot.tool {
+        Some(tool) => tool,
+        None => {
+            eprintln!(
+                "ERROR: no snapshot tool is set in the config, so there's nothing to roll back to"
+            );
+            process::exit(1);
+        }
+    };
+    let snapshots = snapshot::list(&config.snapshot);
+
+    if snapshots.is_empty() {
+        println!("{}", tr("snapshots-none", &[]));
+        return;
+    }
+
+    for (number, snapshot) in snapshots.iter().enumerate() {
+        println!("{:>3}  {}  {}", number + 1, snapshot.id, snapshot.date);
+    }
+
+    print!(
+        "{} ",
+        tr("prompt-restore-which", &[("count", &snapshots.len())])
+    );
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    let picked = match io::stdin().read_line(&mut answer) {
+        Ok(_) => answer
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| snapshots.get(number.wrapping_sub(1))),
+        Err(_) => None,
+    };
+
+    let snapshot = match picked {
+        Some(snapshot) => snapshot,
+        None => {
+            println!("{}", tr("restore-nothing", &[]));
+            return;
+        }
+    };
+
+    if !confirm(&tr("prompt-restore", &[("snapshot", &snapshot.id)])) {
+        return;
+    }
+
+    let mut runner = Runner::new(config, None, &[]);
+
+    runner.run_apps(&[snapshot::restore_app(tool, snapshot)]);
+    runner.print_summary();
+
+    if tool != config::SnapshotTool::Zfs {
+        println!("{}", tr("restore-reboot", &[("snapshot", &snapshot.id)]));
+    }
+}
+
+/// Run the steps a plugin or a script planned, unless its group is turned off
+///
+/// They're skipped when offline since there's no telling whether they need the network.
 ///
 /// # Arguments
 ///
-/// * `apps` - A vector of apps to run
-fn run_apps(apps: &[App]) {
-    for app in apps.iter() {
-        println!("");
-        println!("========================");
-        println!("$ {} {}", app.command, Args(app.args.to_owned()));
-        println!("========================");
+/// * `runner` - Runs the steps
+/// * `options` - The command line options
+/// * `group` - The group the plugin or script picked
+/// * `plan` - Asks the plugin or script for its steps
+fn run_planned(
+    runner: &mut Runner,
+    options: &cli::Options,
+    group: Group,
+    plan: impl FnOnce() -> Vec<Step>,
+) {
+    if !runner.group(group) {
+        return;
+    }
 
-        match scuttle::run_status(app) {
-            Err(error) => panic!("panic{}", error),
-            Ok(_status) => continue,
-        };
+    let steps = plan();
+
+    if options.offline {
+        for step in steps.iter() {
+            runner.skip_app(&step.app, "reason-may-need-network");
+        }
+    } else {
+        runner.run_steps(&steps);
     }
 }
 
-/// Run an app, check its output, conditionally run a second app
+/// Update everything
+///
+/// # Arguments
 ///
-/// Should be passed an array with exactly 2 Apps.
-/// The first App is run and its output is checked.
-/// If there is output, that is appended to the second
-/// Apps argument list and that App is run
+/// * `config` - The user's config
+/// * `options` - The command line options
+/// * `release` - The Linux distribution id, `None` on other platforms
+fn run(config: &Config, options: &cli::Options, release: Option<&str>) {
+    // what's held back until a step is done includes its prompts
+    if options.changed_only {
+        if let Some(section) = prompting_manager(config) {
+            eprintln!(
+                "ERROR: --changed-only would hide the prompts of [{}] assume_yes = false, set it to true or \"ask\"",
+                section
+            );
+            process::exit(1);
+        }
+    }
+
+    // a run by hand is never held to the windows
+    if schedule::scheduled(options.scheduled) && !options.force {
+        match schedule::outside_windows(&config.schedule) {
+            Ok(true) => {
+                println!(
+                    "{}",
+                    tr(
+                        "schedule-outside",
+                        &[("windows", &config.schedule.windows.join(", "))]
+                    )
+                );
+                return;
+            }
+            Ok(false) => {}
+            Err(error) => panic!("ERROR: {}", error),
+        }
+    }
+
+    if schedule::scheduled(options.scheduled) {
+        schedule::wait(&config.schedule);
+    }
+
+    let mut runner = Runner::new(
+        config,
+        options
+            .report_file
+            .as_deref()
+            .map(|path| (path, options.report_format)),
+        &options.only_groups,
+    );
+
+    if options.confirm {
+        match confirm_plan(config, options, release) {
+            Some(removals) => runner.approve(removals),
+            None => return,
+        }
+    }
+
+    let policy = config.policy.is_set() && !options.stdin;
+
+    if policy && (options.offline || options.offline_ok) {
+        eprintln!(
+            "WARNING: [policy] isn't checked without refreshing the package lists, the pending updates aren't known"
+        );
+    }
+
+    if options.preview || (policy && !options.offline && !options.offline_ok) {
+        let preview = preview::gather(Some(&mut runner), config, release);
+
+        runner.note_lists_refreshed();
+
+        if options.preview {
+            preview::print(&preview);
+        }
+
+        let blocked = policy::check(&config.policy, &preview.pending);
+
+        if !blocked.is_empty() {
+            for reason in blocked.iter() {
+                eprintln!("ERROR: {}", reason);
+            }
+
+            runner.abort(&tr("error-policy", &[]));
+        }
+
+        if options.preview && !confirm(&tr("prompt-apply", &[])) {
+            return;
+        }
+    }
+
+    if options.changed_only {
+        runner.show_changed_only();
+    }
+
+    if options.explain {
+        runner.explain_steps();
+    }
+
+    if options.open_report {
+        runner.open_report();
+    }
+
+    update(&mut runner, config, options, release);
+    runner.finish(release);
+}
+
+/// The first section whose package manager runs without its yes flag and so asks before
+/// changing anything, `None` when all of them run on their own
 ///
 /// # Arguments
 ///
-/// * `apps` - A vector of exactly 2 Apps
+/// * `config` - The user's config
+fn prompting_manager(config: &Config) -> Option<&'static str> {
+    [
+        ("apt", config.apt.assume_yes),
+        ("pacman", config.pacman.assume_yes),
+        ("yay", config.yay.assume_yes),
+        ("flatpak", config.flatpak.assume_yes),
+        ("conda", config.conda.assume_yes),
+        ("sdkman", config.sdkman.assume_yes),
+    ]
+    .iter()
+    .find(|(_, assume_yes)| *assume_yes == AssumeYes::No)
+    .map(|(section, _)| *section)
+}
+
+/// Plan the whole run, print every command and everything it would remove and ask once
+/// whether to go ahead, before anything has been changed
 ///
-/// # Examples
-/// ```
-/// let first_app = App {
-///     command: String::from("some-command"),
-///     args: vec![String::from("some-argument")]
-/// };
-/// let second_app = App {
-///     command: String::from("some-command"),
-///     args: vec!&[String::from("some-argument")]
-/// };
+/// Returns what the plan would remove when the user agrees, `None` when they don't.
+///
+/// # Arguments
 ///
-/// let apps_with_response: &[App] = &[first_app, second_app];
-/// run_with_response(apps_with_response);
-/// ```
-fn run_with_response(apps: &[App]) {
-    let first = &apps[0];
-    let second = &apps[1];
+/// * `config` - The user's config
+/// * `options` - The command line options
+/// * `release` - The Linux distribution id, `None` on other platforms
+fn confirm_plan(
+    config: &Config,
+    options: &cli::Options,
+    release: Option<&str>,
+) -> Option<Vec<String>> {
+    let mut planner = Runner::planning(config, &options.only_groups);
 
-    match scuttle::run_output(&first) {
-        Ok(result) => {
-            if result.stdout.len() > 0 {
-                let orphans = String::from_utf8_lossy(&result.stdout);
-                let mut args: Vec<String> = orphans.split('\n').map(String::from).collect();
+    update(&mut planner, config, options, release);
 
-                // sometimes the last entry is empty so find and remove it
-                for i in (0..args.len()).rev() {
-                    if args[i] == "" {
-                        args.swap_remove(i);
-                    }
-                }
+    println!();
+    println!("{}", tr("plan-title", &[]));
 
-                let second_with_orphans = App {
-                    command: second.command.clone(),
-                    args: [&second.args[..], &args[..]].concat(),
-                };
+    for command in planner.planned() {
+        println!("    {}", command);
+    }
 
-                run_apps(&[second_with_orphans]);
-            }
+    if !planner.planned_removals().is_empty() {
+        println!("{}", tr("plan-removals", &[]));
+
+        for removal in planner.planned_removals() {
+            println!("    {}", removal);
         }
-        Err(error) => panic!("{}", error),
     }
+
+    confirm(&tr("prompt-apply", &[])).then(|| planner.planned_removals().to_vec())
+}
+
+/// The commands a run would run on this machine, in order, each with its group
+///
+/// Nothing that changes anything is run, the commands that only list what's outdated or
+/// orphaned still are so the commands that use what they print can be filled in. The
+/// checks that need root or the network aren't: needrestart is planned as a command, and
+/// the k3s, Command Line Tools, image and Nextcloud upgrades they'd find are left out.
+///
+/// # Arguments
+///
+/// * `config` - The user's config
+/// * `options` - The command line options
+/// * `release` - The Linux distribution id, `None` on other platforms
+fn plan(config: &Config, options: &cli::Options, release: Option<&str>) -> Vec<String> {
+    let mut runner = Runner::planning(config, &options.only_groups);
+
+    update(&mut runner, config, options, release);
+    runner.planned()
 }
 
-/// Parse the output of `cargo install --list` and build a command to update the apps from the list
+/// Write the sudoers snippet for the commands the plan runs with sudo, for the user who
+/// runs up2date, the one who started it with sudo when it's run as root
 ///
 /// # Arguments
 ///
-/// * `app` - An app of type `App`
-fn run_with_cargo(app: App) {
-    match scuttle::run_output(&app) {
-        Ok(output) => match std::str::from_utf8(&output.stdout) {
-            Ok(result) => {
-                result.lines().for_each(move |line| {
-                    if !line.starts_with(' ') {
-                        let parts: Vec<&str> = line.split(' ').collect();
-                        let cargo_app = parts[0];
+/// * `config` - The user's config
+/// * `options` - The command line options
+/// * `release` - The Linux distribution id, `None` on other platforms
+/// * `output` - Where to write it, stdout when it's `None`, since planning prints what
+///   it skips on stdout too
+fn sudoers(
+    config: &Config,
+    options: &cli::Options,
+    release: Option<&str>,
+    output: Option<&std::path::Path>,
+) {
+    let user = match detect::sudo_user().or_else(|| std::env::var("USER").ok()) {
+        Some(user) if !user.is_empty() => user,
+        _ => {
+            eprintln!("ERROR: could not tell who runs up2date, USER isn't set");
+            process::exit(1);
+        }
+    };
+    let mut runner = Runner::planning(config, &options.only_groups);
 
-                        // someone has reserved a couple of names I'm using on crates.io so skip it
-                        if cargo_app != "tm" && cargo_app != "project" {
-                            let cargo_install_app = App {
-                                command: String::from("cargo"),
-                                args: vec!["install".to_string(), cargo_app.to_string()],
-                            };
+    update(&mut runner, config, options, release);
 
-                            run_apps(&[cargo_install_app]);
-                        }
-                    }
-                });
+    let snippet = sudoers::generate(
+        runner.planned_privileged(),
+        &user,
+        &sys_info::hostname().unwrap_or(String::from("this machine")),
+    );
+
+    match output {
+        Some(path) => match std::fs::write(path, snippet) {
+            Ok(()) => println!("Wrote {}", path.display()),
+            Err(error) => {
+                eprintln!("ERROR: could not write {}: {}", path.display(), error);
+                process::exit(1);
             }
-            Err(error) => println!("error:{}", error),
         },
-        Err(error) => panic!("panic:{}", error),
-    };
+        None => print!("{}", snippet),
+    }
 }
 
-fn main() {
-    if OS == "linux" {
-        let release = match linux_os_release() {
-            Ok(value) => value.id,
-            Err(error) => panic!("Error {}", error),
+/// Run every enabled group's steps
+///
+/// # Arguments
+///
+/// * `runner` - Runs the steps, or only notes them down when planning
+/// * `config` - The user's config
+/// * `options` - The command line options
+/// * `release` - The Linux distribution id, `None` on other platforms
+fn update(runner: &mut Runner, config: &Config, options: &cli::Options, release: Option<&str>) {
+    if detect::is_root() {
+        let user_steps = match (config.root.user_steps, detect::sudo_user()) {
+            (config::UserSteps::Drop, Some(user)) => format!("run as {}", user),
+            (config::UserSteps::Root, _) => String::from("use root's home directory"),
+            _ => String::from("are skipped"),
         };
 
-        match release.as_deref() {
-            Some("ubuntu") | Some("pop") => {
-                let apt_update = App {
-                    command: String::from("sudo"),
-                    args: vec!["apt-get".to_string(), "update".to_string()],
-                };
-                let apt_upgrade = App {
-                    command: String::from("sudo"),
-                    args: vec![
-                        "apt-get".to_string(),
-                        "upgrade".to_string(),
-                        "-y".to_string(),
-                        "--allow-downgrades".to_string(),
-                        "--with-new-pkgs".to_string(),
-                    ],
+        eprintln!(
+            "WARNING: up2date is running as root, cargo, rustup, nvim, brew and the other steps that install into a home directory {}",
+            user_steps
+        );
+    }
+
+    // with --offline-ok the package databases are left as they were when the updates
+    // were fetched so the upgrades only need what's already in the cache
+    let refresh = !options.offline_ok && !options.offline;
+    let run_system = |runner: &mut Runner, steps: &[Step]| {
+        if options.offline {
+            runner.run_offline(steps)
+        } else {
+            runner.run_steps(steps)
+        }
+    };
+
+    if options.stdin {
+        match batch::read() {
+            Ok(steps) => run_system(runner, &steps),
+            Err(error) => panic!("ERROR: {}", error),
+        }
+
+        return;
+    }
+
+    if runner.group(Group::System) {
+        if let Some(app) = snapshot::create_app(&config.snapshot) {
+            // nothing is worth updating without something to roll back to
+            if !runner.run_app(&app).success() {
+                runner.abort(&tr("error-snapshot-failed", &[]));
+            }
+        }
+
+        // these are easily lost among everything else an upgrade changes
+        let baseline_before = baseline::installed(config, release);
+        let assets_before = assets::stamp();
+
+        if detect::os() == "linux" {
+            runner.wait_for_lock(release, &config.locks);
+
+            // a read-only root is replaced as a whole, its package manager can't touch it
+            match (platform::immutable(release), release) {
+                (Some(image), _) => {
+                    if let Some(app) = image.read_only_manager() {
+                        runner.skip_app(&app, "reason-read-only-root");
+                    }
+
+                    match image.update_step(refresh) {
+                        Some(step) if !runner.is_planning() && !image.has_update() => {
+                            runner.skip_app(&step.app, "reason-up-to-date")
+                        }
+                        Some(step) => run_system(runner, &[step]),
+                        None => {
+                            println!();
+                            println!("Skipping {}, it needs the network", image.name());
+                        }
+                    }
+                }
+                (None, Some("ubuntu") | Some("pop") | Some("debian")) => {
+                    let apt_update = App {
+                        command: String::from("sudo"),
+                        args: config
+                            .apt
+                            .executable
+                            .command_line("apt-get", &["update".to_string()]),
+                    };
+                    let mut upgrade_args = apt_upgrade_args(&config.apt);
+
+                    if options.offline {
+                        // fail rather than reach for the network when something wasn't fetched
+                        upgrade_args.push("--no-download");
+                    }
+
+                    let apt_upgrade = manager_step(
+                        config.apt.assume_yes,
+                        true,
+                        &config.apt.executable,
+                        "apt-get",
+                        "-y",
+                        &upgrade_args,
+                    );
+
+                    let automatic = unattended::detect(release);
+
+                    if refresh {
+                        // unattended-upgrades gets apt's timer to refresh the lists daily
+                        if runner.lists_refreshed() {
+                            runner.skip_app(&apt_update, "reason-lists-refreshed");
+                        } else if automatic.is_some() && unattended::lists_fresh() {
+                            runner.skip_app(&apt_update, "reason-lists-fresh");
+                        } else {
+                            runner.run_apps(&[apt_update]);
+                        }
+                    }
+
+                    // apt only mentions what it leaves out halfway through its output
+                    let simulation = match scuttle::run_output(&App {
+                        command: config.apt.executable.binary("apt-get"),
+                        args: std::iter::once("-s")
+                            .chain(apt_upgrade_args(&config.apt))
+                            .map(String::from)
+                            .collect(),
+                    }) {
+                        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+                        Err(error) => panic!("panic:{}", error),
+                    };
+                    let (kept_back, phased) = parsers::parse_apt_held(&simulation);
+
+                    run_system(runner, &[apt_upgrade]);
+                    runner.held_back(kept_back, phased);
+
+                    if let Some(automatic) = automatic {
+                        runner.automatic(automatic);
+                    }
+
+                    if let Some(pro) = pro::status(release) {
+                        runner.pro(pro);
+                    }
+                }
+                (None, Some("arch") | Some("endeavouros")) => {
+                    let pacman_keyring = manager_step(
+                        config.pacman.assume_yes,
+                        true,
+                        &config.pacman.executable,
+                        "pacman",
+                        "--noconfirm",
+                        &["-S", "archlinux-keyring"],
+                    );
+                    let pacman_update = manager_step(
+                        config.pacman.assume_yes,
+                        true,
+                        &config.pacman.executable,
+                        "pacman",
+                        "--noconfirm",
+                        &[if refresh { "-Syu" } else { "-Su" }],
+                    );
+                    let yay_update = manager_step(
+                        config.yay.assume_yes,
+                        false,
+                        &config.yay.executable,
+                        "yay",
+                        "--noconfirm",
+                        &["-Syu"],
+                    );
+                    let steps: Vec<Step> = if refresh {
+                        vec![pacman_keyring, pacman_update, yay_update]
+                    } else {
+                        // the keyring and the AUR can't be updated from the cache
+                        if options.offline {
+                            runner.skip_app(&pacman_keyring.app, "reason-needs-network");
+                            runner.skip_app(&yay_update.app, "reason-needs-network");
+                        }
+
+                        vec![pacman_update]
+                    };
+
+                    run_system(runner, &steps);
+                }
+                (None, Some(os_name)) => {
+                    panic!("ERROR: not sure what OS this is:{}", os_name)
+                }
+                (None, None) => panic!("ERROR: not sure what OS this is"),
+            }
+
+            if detect::has_binary("flatpak") {
+                if options.offline {
+                    println!();
+                    println!("Skipping flatpak, it needs the network");
+                } else {
+                    runner.run_with_flatpak(&config.flatpak);
+                }
+            }
+
+            // finding the latest release needs the network as well, so a plan leaves it out
+            if config.kubernetes.enabled && !options.offline && !runner.is_planning() {
+                let pending = k3s::pending(&config.kubernetes);
+                let upgrades = k3s::upgrade_steps(&pending, &config.kubernetes);
+
+                if upgrades.is_empty() {
+                    runner.warn(
+                        pending
+                            .iter()
+                            .map(|update| {
+                                tr(
+                                    "kubernetes-available",
+                                    &[
+                                        ("distribution", &update.package),
+                                        ("available", &update.to.as_deref().unwrap_or("")),
+                                        ("running", &update.from.as_deref().unwrap_or("")),
+                                    ],
+                                )
+                            })
+                            .collect(),
+                    );
+                } else {
+                    runner.run_steps(&upgrades);
+                }
+            }
+
+            runner.warn(boot::check(release));
+            runner.run_with_service_restarts(&config.services);
+        }
+
+        if detect::os() == "macos" {
+            // brew builds from source against the Command Line Tools, so they go first
+            let clt_pending = if runner.is_planning() {
+                Vec::new()
+            } else {
+                clt::pending()
+            };
+            let clt_installs = clt::install_steps(&clt_pending, config.brew.command_line_tools);
+
+            if options.offline {
+                for step in clt_installs.iter() {
+                    runner.skip_app(&step.app, "reason-needs-network");
+                }
+            } else {
+                runner.run_steps(&clt_installs);
+            }
+
+            let brew_update = manager_app(&config.brew.executable, "brew", &["update"]);
+            let brew_upgrade = manager_app(&config.brew.executable, "brew", &["upgrade"]);
+
+            if refresh && !runner.lists_refreshed() {
+                runner.run_apps(&[brew_update]);
+            } else {
+                if refresh {
+                    runner.skip_app(&brew_update, "reason-lists-refreshed");
+                }
+
+                // brew upgrade would otherwise update itself first
+                std::env::set_var("HOMEBREW_NO_AUTO_UPDATE", "1");
+            }
+
+            // remember what's about to be upgraded so running services can be restarted
+            let brew_outdated = App {
+                command: config.brew.executable.binary("brew"),
+                args: vec![
+                    "outdated".to_string(),
+                    "--formula".to_string(),
+                    "--quiet".to_string(),
+                ],
+            };
+            let upgraded: Vec<String> = match scuttle::run_output(&brew_outdated) {
+                Ok(output) => String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .map(String::from)
+                    .collect(),
+                Err(error) => panic!("panic:{}", error),
+            };
+
+            run_system(
+                runner,
+                &[Step {
+                    app: brew_upgrade,
+                    ask: false,
+                    required: true,
+                }],
+            );
+
+            if !upgraded.is_empty() {
+                runner.run_with_brew_services(&upgraded, &config.brew);
+            }
+        }
+
+        if detect::os() == "windows" {
+            if options.offline {
+                println!();
+                println!("Skipping winget, choco and scoop, they need the network");
+            } else {
+                windows::upgrade(runner, &config.windows, refresh);
+            }
+        }
+
+        runner.baseline(baseline::changed(
+            &baseline_before,
+            &baseline::installed(config, release),
+        ));
+
+        // not every package's hooks rebuild the caches it should
+        runner.run_apps(&assets::refresh_apps(&assets_before));
+    }
+
+    // everything in the cleanup group removes something so it's only run once the user
+    // has seen what and agreed, or turned removing on in the config
+    if runner.group(Group::Cleanup) {
+        runner.wait_for_lock(release, &config.locks);
+
+        match release {
+            Some("ubuntu") | Some("pop") | Some("debian") => {
+                let simulation = match scuttle::run_output(&App {
+                    command: config.apt.executable.binary("apt-get"),
+                    args: vec!["-s".to_string(), "autoremove".to_string()],
+                }) {
+                    Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+                    Err(error) => panic!("panic:{}", error),
                 };
-                let apt_remove = App {
-                    command: String::from("sudo"),
-                    args: vec![
-                        "apt-get".to_string(),
-                        "autoremove".to_string(),
-                        "-y".to_string(),
-                    ],
+                let (kept, candidates): (Vec<_>, Vec<_>) = parsers::parse_apt(&simulation)
+                    .into_iter()
+                    .partition(|pending| config.cleanup.keep.contains(&pending.package));
+                let removals: Vec<String> = candidates
+                    .iter()
+                    .map(|pending| {
+                        format!(
+                            "{} {}",
+                            pending.package,
+                            pending.from.as_deref().unwrap_or("")
+                        )
+                    })
+                    .collect();
+                // autoremove can't leave anything out, so once something is kept the
+                // rest is removed by name
+                let mut apt_remove = if kept.is_empty() {
+                    manager_step(
+                        config.apt.assume_yes,
+                        true,
+                        &config.apt.executable,
+                        "apt-get",
+                        "-y",
+                        &["autoremove"],
+                    )
+                } else {
+                    let mut args = vec!["remove"];
+
+                    args.extend(candidates.iter().map(|pending| pending.package.as_str()));
+                    println!();
+                    println!(
+                        "Keeping {}, mark them with `apt-mark manual` so they stop showing up",
+                        kept.iter()
+                            .map(|pending| pending.package.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    );
+                    manager_step(
+                        config.apt.assume_yes,
+                        true,
+                        &config.apt.executable,
+                        "apt-get",
+                        "-y",
+                        &args,
+                    )
                 };
-                let apps: &[App] = &[apt_update, apt_upgrade, apt_remove];
 
-                run_apps(apps);
+                if runner.confirm_removal(&apt_remove.app, &removals, config.cleanup.remove) {
+                    apt_remove.ask &= config.cleanup.remove == AssumeYes::Yes;
+                    run_system(runner, &[apt_remove]);
+                }
+
+                // autoremove keeps every kernel that was installed by hand, which on a
+                // server is all of them
+                if apt_server(&config.apt) {
+                    let kernels = old_kernels(config);
+                    let mut args = vec!["purge"];
+
+                    args.extend(kernels.iter().map(String::as_str));
+
+                    let mut kernel_purge = manager_step(
+                        config.apt.assume_yes,
+                        true,
+                        &config.apt.executable,
+                        "apt-get",
+                        "-y",
+                        &args,
+                    );
+
+                    if runner.confirm_removal(&kernel_purge.app, &kernels, config.cleanup.remove) {
+                        kernel_purge.ask &= config.cleanup.remove == AssumeYes::Yes;
+                        run_system(runner, &[kernel_purge]);
+                    }
+                }
             }
             Some("arch") | Some("endeavouros") => {
-                let pacman_keyring = App {
-                    command: String::from("sudo"),
-                    args: vec![
-                        "pacman".to_string(),
-                        "--noconfirm".to_string(),
-                        "-S".to_string(),
-                        "archlinux-keyring".to_string(),
-                    ],
-                };
-                let pacman_update = App {
-                    command: String::from("sudo"),
-                    args: vec![
-                        "pacman".to_string(),
-                        "--noconfirm".to_string(),
-                        "-Syu".to_string(),
-                    ],
-                };
                 let pacman_orphan_check = App {
-                    command: String::from("pacman"),
+                    command: config.pacman.executable.binary("pacman"),
                     args: vec!["-Qtdq".to_string()],
                 };
-                let pacman_orphan_remove = App {
-                    command: String::from("sudo"),
-                    args: vec![
-                        "pacman".to_string(),
-                        "--noconfirm".to_string(),
-                        "-Rns".to_string(),
-                    ],
-                };
-
-                let yay_update = App {
-                    command: String::from("yay"),
-                    args: vec!["--noconfirm".to_string(), "-Syu".to_string()],
-                };
+                let pacman_orphan_remove = manager_step(
+                    config.pacman.assume_yes,
+                    true,
+                    &config.pacman.executable,
+                    "pacman",
+                    "--noconfirm",
+                    &["-Rns"],
+                );
                 let yay_orphan_check = App {
-                    command: String::from("yay"),
+                    command: config.yay.executable.binary("yay"),
                     args: vec!["-Qtdq".to_string()],
                 };
-                let yay_orphan_remove = App {
-                    command: String::from("yay"),
-                    args: vec!["--noconfirm".to_string(), "-Rns".to_string()],
-                };
-                let apps: &[App] = &[pacman_keyring, pacman_update, yay_update];
-                let apps_with_response: &[App] = &[
-                    pacman_orphan_check,
-                    pacman_orphan_remove,
-                    yay_orphan_check,
-                    yay_orphan_remove,
-                ];
+                let yay_orphan_remove = manager_step(
+                    config.yay.assume_yes,
+                    false,
+                    &config.yay.executable,
+                    "yay",
+                    "--noconfirm",
+                    &["-Rns"],
+                );
+                runner.run_orphan_removal(
+                    &pacman_orphan_check,
+                    &pacman_orphan_remove,
+                    &config.cleanup,
+                );
+                runner.run_orphan_removal(&yay_orphan_check, &yay_orphan_remove, &config.cleanup);
+            }
+            _ => {}
+        }
+
+        if detect::os() == "macos" {
+            let brew_cleanup = manager_app(&config.brew.executable, "brew", &["cleanup"]);
+            // `Would remove: /Users/me/Library/Caches/Homebrew/node--20.1.0 (12.3MB)`
+            let removals: Vec<String> = match scuttle::run_output(&App {
+                command: config.brew.executable.binary("brew"),
+                args: vec!["cleanup".to_string(), "--dry-run".to_string()],
+            }) {
+                Ok(output) => String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("Would remove: "))
+                    .map(String::from)
+                    .collect(),
+                Err(error) => panic!("panic:{}", error),
+            };
 
-                run_apps(apps);
-                run_with_response(apps_with_response);
+            if runner.confirm_removal(&brew_cleanup, &removals, config.cleanup.remove) {
+                run_system(
+                    runner,
+                    &[Step {
+                        app: brew_cleanup,
+                        ask: false,
+                        required: false,
+                    }],
+                );
             }
-            Some(os_name) => panic!("ERROR: not sure what OS this is:{}", os_name),
-            None => panic!("ERROR: not sure what OS this is"),
         }
     }
 
-    if OS == "macos" {
-        let brew_update = App {
-            command: String::from("brew"),
-            args: vec!["update".to_string()],
+    if runner.group(Group::Rust) {
+        // update rust, should be the same on all platforms
+        let rust_updates: Vec<App> = rust_apps(&config.rust);
+        // update all rust apps installed with cargo
+        let cargo_list_apps = App {
+            command: String::from("cargo"),
+            args: vec!["install".to_string(), "--list".to_string()],
         };
-        let brew_upgrade = App {
-            command: String::from("brew"),
-            args: vec!["upgrade".to_string()],
-        };
-        let brew_cleanup = App {
-            command: String::from("brew"),
-            args: vec!["cleanup".to_string()],
+
+        if options.offline {
+            for app in rust_updates.iter().chain([&cargo_list_apps]) {
+                runner.skip_app(app, "reason-needs-network");
+            }
+        } else {
+            runner.run_apps(&rust_updates);
+            runner.run_with_rust_components(&config.rust.components);
+            runner.run_with_cargo(cargo_list_apps);
+        }
+    }
+
+    if runner.group(Group::Editors) {
+        // update vim, once for every config
+        let neovim_updates: Vec<App> = neovim_apps(&config.nvim);
+
+        if options.offline {
+            for app in neovim_updates.iter() {
+                runner.skip_app(app, "reason-needs-network");
+            }
+        } else {
+            runner.run_apps(&neovim_updates);
+        }
+    }
+
+    if runner.group(Group::Languages) {
+        // update julia, R and haskell packages when they're turned on
+        let toolchain_updates: Vec<App> = toolchain_apps(config);
+        // update conda environments when turned on
+        let conda_updates: Vec<Step> = if config.conda.enabled {
+            conda_steps(&config.conda)
+        } else {
+            Vec::new()
         };
-        let apps: &[App] = &[brew_update, brew_upgrade, brew_cleanup];
+        // update SDKMAN and the JVMs, gradle, maven, ... it manages
+        let sdkman_update: Option<Step> = sdkman_step(&config.sdkman);
+        // update PowerShell Gallery modules and their help
+        let powershell_updates: Vec<App> = powershell_apps(&config.powershell);
+        // update rbenv, pyenv and their plugins so new versions can be installed
+        let version_manager_updates: Vec<App> = version_manager_apps();
+
+        if options.offline {
+            for app in toolchain_updates
+                .iter()
+                .chain(powershell_updates.iter())
+                .chain(version_manager_updates.iter())
+            {
+                runner.skip_app(app, "reason-needs-network");
+            }
+
+            for step in conda_updates.iter().chain(sdkman_update.iter()) {
+                runner.skip_app(&step.app, "reason-needs-network");
+            }
+        } else {
+            runner.run_apps(&toolchain_updates);
+            runner.run_steps(&conda_updates);
+            runner.run_steps(sdkman_update.as_slice());
+
+            // update all go tools installed with go install
+            if detect::has_binary("go") {
+                runner.run_with_go();
+            }
+
+            // update all global composer packages
+            if detect::has_binary("composer") {
+                let composer_update = App {
+                    command: String::from("env"),
+                    args: vec![
+                        "COMPOSER_NO_INTERACTION=1".to_string(),
+                        "composer".to_string(),
+                        "global".to_string(),
+                        "update".to_string(),
+                    ],
+                };
+
+                runner.run_app_with(&composer_update, parsers::parse_composer);
+            }
+
+            // update all .NET global tools, and the SDK workloads if asked to
+            if detect::has_binary("dotnet") {
+                runner.run_with_dotnet();
 
-        run_apps(apps);
+                if config.dotnet.workloads {
+                    runner.run_apps(&[App {
+                        command: String::from("dotnet"),
+                        args: vec!["workload".to_string(), "update".to_string()],
+                    }]);
+                }
+            }
+
+            runner.run_apps(&powershell_updates);
+            runner.run_apps(&version_manager_updates);
+        }
     }
 
-    // update rust, should be the same on all platforms
-    let rust_update = App {
-        command: String::from("rustup"),
-        args: vec!["update".to_string()],
-    };
-    // update vim
-    let neovim_update = App {
-        command: String::from("nvim"),
-        args: vec![
-            "--headless".to_string(),
-            "+Lazy! sync".to_string(),
-            "+qa".to_string(),
-        ],
-    };
-    let apps: &[App] = &[rust_update, neovim_update];
+    if !config.containers.projects.is_empty() && runner.group(Group::Containers) {
+        for (pull, up) in compose_apps(&config.containers) {
+            if options.offline {
+                runner.skip_app(&pull, "reason-needs-network");
+                runner.skip_app(&up, "reason-needs-network");
+            } else if runner.run_app(&pull).success() {
+                // only restart with images that were all pulled
+                runner.run_with_recreated(&up);
+            }
+        }
+    }
 
-    run_apps(apps);
+    if runner.group(Group::Cloud) {
+        // update helm repos and terraform versions when turned on
+        let infra_updates: Vec<App> = infra_apps(&config.infra);
 
-    // update all rust apps installed with cargo
-    let cargo_list_apps = App {
-        command: String::from("cargo"),
-        args: vec!["install".to_string(), "--list".to_string()],
-    };
+        if options.offline {
+            for app in infra_updates.iter() {
+                runner.skip_app(app, "reason-needs-network");
+            }
+        } else {
+            runner.run_apps(&infra_updates);
+
+            if config.infra.enabled && detect::has_binary("helm") {
+                runner.run_with_helm_plugins();
+            }
+        }
+    }
+
+    if runner.group(Group::Gaming) {
+        let gaming_updates: Vec<App> = gaming_apps();
+
+        if options.offline {
+            for app in gaming_updates.iter() {
+                runner.skip_app(app, "reason-needs-network");
+            }
+        } else {
+            runner.run_apps(&gaming_updates);
+        }
+    }
+
+    if runner.group(Group::Selfhosted) {
+        selfhosted_updates(runner, &config.selfhosted, options.offline);
+    }
+
+    if runner.group(Group::Security) {
+        let security_updates: Vec<App> = security_apps();
+
+        if options.offline {
+            for app in security_updates.iter() {
+                runner.skip_app(app, "reason-needs-network");
+            }
+        } else {
+            runner.run_apps(&security_updates);
+        }
+    }
+
+    // trimming and the health checks don't need the network
+    if runner.group(Group::Maintenance) {
+        let trim: Vec<App> = maintenance::trim().into_iter().collect();
+
+        runner.run_apps(&trim);
+        runner.health(maintenance::health());
+    }
+
+    // the steps from any up2date-plugin-* on PATH, in the group each plugin picked
+    if config.plugins.enabled {
+        for plugin in plugins::discover(&config.plugins, release) {
+            run_planned(runner, options, plugin.group, || {
+                plugins::steps(&plugin, release)
+            });
+        }
+    }
+
+    // and from the Lua scripts next to the config file
+    #[cfg(feature = "lua")]
+    {
+        let context = scripts::Context {
+            release,
+            planning: runner.is_planning(),
+            settings: &config.scripts,
+        };
+
+        for script in scripts::discover(&context, |group| runner.group(group)) {
+            run_planned(runner, options, script.group, || {
+                scripts::steps(&script, &context)
+            });
+        }
+    }
 
-    run_with_cargo(cargo_list_apps);
+    #[cfg(not(feature = "lua"))]
+    scripts::warn_unsupported();
+
+    // the audit tools fetch their advisory databases first
+    if config.audit.enabled && !options.offline && runner.group(Group::Audit) {
+        audit(runner);
+    }
 }
@@ -0,0 +1,123 @@
+use std::fs;
+
+use scuttle::App;
+
+use crate::detect::{self, has_binary};
+use crate::parsers;
+use crate::report::Health;
+
+/// Build an app from a command line
+fn app(command_line: &[&str]) -> App {
+    App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    }
+}
+
+/// Run a check and return its stdout, whatever it exited with since the health checks
+/// exit non-zero for what they find
+fn check_output(command_line: &[&str]) -> String {
+    match scuttle::run_output(&app(command_line)) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Trim every mounted filesystem that supports it, `None` when fstrim isn't installed
+/// or the systemd timer already trims them every week
+pub fn trim() -> Option<App> {
+    if !has_binary("fstrim") {
+        return None;
+    }
+
+    let timer = has_binary("systemctl")
+        && scuttle::run_output(&app(&[
+            "systemctl",
+            "is-enabled",
+            "--quiet",
+            "fstrim.timer",
+        ]))
+        .is_ok_and(|output| output.status.success());
+
+    (!timer).then(|| app(&["sudo", "fstrim", "-av"]))
+}
+
+/// Where each btrfs filesystem is mounted, once for each filesystem
+fn btrfs_mounts() -> Vec<String> {
+    // the shims have no filesystems, and the real ones don't matter
+    if detect::shims().is_some() {
+        return Vec::new();
+    }
+
+    let mut devices: Vec<String> = Vec::new();
+    let mut mounts: Vec<String> = Vec::new();
+
+    // /dev/nvme0n1p2 / btrfs rw,relatime,ssd,subvol=/@ 0 0
+    for line in fs::read_to_string("/proc/mounts")
+        .unwrap_or_default()
+        .lines()
+    {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if let [device, mount, "btrfs", ..] = fields[..] {
+            if !devices.iter().any(|known| known == device) {
+                devices.push(device.to_string());
+                mounts.push(mount.to_string());
+            }
+        }
+    }
+
+    mounts
+}
+
+/// How the disks and filesystems are doing: the last btrfs scrub, a running balance
+/// and each disk's SMART health
+pub fn health() -> Vec<Health> {
+    let mut health: Vec<Health> = Vec::new();
+
+    if has_binary("btrfs") {
+        for mount in btrfs_mounts() {
+            if let Some(errors) = parsers::parse_scrub_status(&check_output(&[
+                "sudo", "btrfs", "scrub", "status", &mount,
+            ])) {
+                health.push(Health {
+                    check: format!("btrfs scrub {}", mount),
+                    ok: errors == "no errors found",
+                    status: errors,
+                });
+            }
+
+            if let Some(balance) = parsers::parse_balance_status(&check_output(&[
+                "sudo", "btrfs", "balance", "status", &mount,
+            ])) {
+                health.push(Health {
+                    check: format!("btrfs balance {}", mount),
+                    ok: true,
+                    status: balance,
+                });
+            }
+        }
+    }
+
+    if has_binary("smartctl") {
+        for device in parsers::parse_smart_scan(&check_output(&["smartctl", "--scan"])) {
+            let command_line: Vec<&str> = ["sudo", "smartctl", "-H"]
+                .into_iter()
+                .chain(device.iter().map(String::as_str))
+                .collect();
+
+            if let Some(status) = parsers::parse_smart_health(&check_output(&command_line)) {
+                health.push(Health {
+                    check: format!("SMART {}", device[0]),
+                    ok: status == "PASSED" || status == "OK",
+                    status,
+                });
+            }
+        }
+    }
+
+    health
+}
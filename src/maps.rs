@@ -0,0 +1,56 @@
+use std::fs;
+
+use crate::detect;
+use crate::parsers;
+
+/// A running process that still uses a shared library that was replaced on disk
+pub struct Stale {
+    pub pid: u32,
+    /// Its name, as `/proc/<pid>/comm` has it
+    pub name: String,
+    /// The system service it belongs to, `None` for anything else
+    pub service: Option<String>,
+}
+
+/// Find the processes still using replaced shared libraries by looking through
+/// `/proc/*/maps`, for when neither needrestart nor dnf is there to ask
+///
+/// Only the processes whose maps can be read are found, which without root means only
+/// the user's own. Nothing is found with shims, whatever is really running doesn't
+/// matter then.
+pub fn scan() -> Vec<Stale> {
+    if detect::shims().is_some() {
+        return Vec::new();
+    }
+
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let own = std::process::id();
+    let mut stale: Vec<Stale> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok()?.parse::<u32>().ok())
+        .filter(|pid| *pid != own)
+        .filter_map(|pid| {
+            let maps = fs::read_to_string(format!("/proc/{}/maps", pid)).ok()?;
+
+            if parsers::parse_deleted_libraries(&maps).is_empty() {
+                return None;
+            }
+
+            Some(Stale {
+                pid,
+                name: fs::read_to_string(format!("/proc/{}/comm", pid))
+                    .map(|name| name.trim().to_string())
+                    .unwrap_or_default(),
+                service: fs::read_to_string(format!("/proc/{}/cgroup", pid))
+                    .ok()
+                    .and_then(|cgroup| parsers::parse_cgroup_service(&cgroup)),
+            })
+        })
+        .collect();
+
+    stale.sort_by_key(|process| process.pid);
+    stale
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use scuttle::App;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config;
+use crate::history;
+use crate::preview;
+use crate::report::Report;
+use crate::runner::run_captured;
+use crate::secrets;
+
+/// The device every sensor of this machine belongs to in Home Assistant
+#[derive(Serialize)]
+struct Device<'a> {
+    identifiers: [&'a str; 1],
+    name: &'a str,
+    manufacturer: &'static str,
+}
+
+/// A Home Assistant MQTT discovery message for one sensor
+#[derive(Serialize)]
+struct Discovery<'a> {
+    name: &'static str,
+    unique_id: String,
+    state_topic: &'a str,
+    value_template: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_class: Option<&'static str>,
+    device: &'a Device<'a>,
+}
+
+/// The state of this machine, published after every run
+#[derive(Serialize)]
+struct State {
+    outcome: String,
+    failed: usize,
+    packages_changed: usize,
+    /// The pending updates the scheduled checks last found, `None` before the first one
+    pending: Option<u64>,
+    reboot_pending: bool,
+    /// When the run started, as an ISO 8601 time for Home Assistant
+    last_run: String,
+}
+
+/// The sensors published for every machine: their Home Assistant component, id, name,
+/// `device_class` and the template that picks their value out of the state
+const SENSORS: &[(&str, &str, &str, Option<&str>, &str)] = &[
+    (
+        "sensor",
+        "failed",
+        "Failed commands",
+        None,
+        "{{ value_json.failed }}",
+    ),
+    (
+        "sensor",
+        "packages_changed",
+        "Packages changed",
+        None,
+        "{{ value_json.packages_changed }}",
+    ),
+    (
+        "sensor",
+        "pending",
+        "Pending updates",
+        None,
+        "{{ value_json.pending }}",
+    ),
+    (
+        "sensor",
+        "last_run",
+        "Last update",
+        Some("timestamp"),
+        "{{ value_json.last_run }}",
+    ),
+    (
+        "binary_sensor",
+        "reboot_pending",
+        "Reboot pending",
+        Some("problem"),
+        "{{ 'ON' if value_json.reboot_pending else 'OFF' }}",
+    ),
+];
+
+/// Publish a retained message with `mosquitto_pub`
+///
+/// The password is handed over in a mosquitto_pub options file in a private
+/// `XDG_CONFIG_HOME` so it's never on a command line.
+///
+/// # Arguments
+///
+/// * `settings` - The broker to publish to
+/// * `config_home` - The `XDG_CONFIG_HOME` holding the options file, if there's a password
+/// * `topic` - The topic to publish to
+/// * `payload` - The message
+fn publish(
+    settings: &config::Mqtt,
+    config_home: Option<&Path>,
+    topic: &str,
+    payload: &str,
+) -> io::Result<()> {
+    let mut args = vec![
+        String::from("-h"),
+        settings.host.clone(),
+        String::from("-p"),
+        settings.port.to_string(),
+        String::from("-r"),
+        String::from("-t"),
+        topic.to_string(),
+        String::from("-m"),
+        payload.to_string(),
+    ];
+
+    if let Some(username) = settings.username.as_ref() {
+        args.push(String::from("-u"));
+        args.push(username.clone());
+    }
+
+    let app = App {
+        command: String::from("mosquitto_pub"),
+        args,
+    };
+    let config_home = config_home.map(|path| path.to_string_lossy().into_owned());
+    let env: Vec<(&str, &str)> = config_home
+        .as_deref()
+        .map(|path| ("XDG_CONFIG_HOME", path))
+        .into_iter()
+        .collect();
+    let captured = run_captured(&app, &env)?;
+
+    if captured.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "mosquitto_pub failed publishing to {}",
+            topic
+        )))
+    }
+}
+
+/// Publish the state of this machine after a run, and the discovery messages that make
+/// Home Assistant show it as a device with a sensor for each value
+///
+/// # Arguments
+///
+/// * `settings` - The broker and topics from `[mqtt]`
+/// * `report` - The report of the run that just finished
+pub fn publish_report(settings: &config::Mqtt, report: &Report) -> io::Result<()> {
+    let hostname = sys_info::hostname().map_err(|error| io::Error::other(error.to_string()))?;
+    let node = format!(
+        "up2date_{}",
+        hostname.replace(|c: char| !c.is_alphanumeric(), "_")
+    );
+    let state_topic = format!("{}/{}/state", settings.topic, hostname);
+    let device = Device {
+        identifiers: [&node],
+        name: &hostname,
+        manufacturer: "up2date",
+    };
+
+    let password = settings.password.as_ref().and_then(|password| {
+        secrets::resolve(&HashMap::from([(String::from("mqtt"), password.clone())]))
+            .pop()
+            .map(|secret| secret.value)
+    });
+    let config_home = match password {
+        Some(password) => {
+            let dir = match config::state_dir() {
+                Some(dir) => dir.join("mqtt"),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no home directory to hand the password over in",
+                    ))
+                }
+            };
+
+            fs::create_dir_all(&dir)?;
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+            }
+
+            fs::write(dir.join("mosquitto_pub"), format!("-P {}\n", password))?;
+            Some(dir)
+        }
+        None => None,
+    };
+
+    let published = (|| {
+        for (component, id, name, device_class, value_template) in SENSORS.iter() {
+            let discovery = Discovery {
+                name,
+                unique_id: format!("{}_{}", node, id),
+                state_topic: &state_topic,
+                value_template,
+                device_class: *device_class,
+                device: &device,
+            };
+
+            publish(
+                settings,
+                config_home.as_deref(),
+                &format!(
+                    "{}/{}/{}/{}/config",
+                    settings.discovery_prefix, component, node, id
+                ),
+                &serde_json::to_string(&discovery)?,
+            )?;
+        }
+
+        let pending = preview::pending_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+            .and_then(|pending| pending["pending"].as_u64());
+        let state = State {
+            outcome: serde_json::to_value(report.outcome)?
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            failed: report.steps.iter().filter(|step| !step.success).count(),
+            packages_changed: report.steps.iter().map(|step| step.changes.len()).sum(),
+            pending,
+            reboot_pending: report.reboot_pending_since.is_some(),
+            last_run: format!(
+                "{}:00+00:00",
+                history::format_date(report.started).replace(' ', "T")
+            ),
+        };
+
+        publish(
+            settings,
+            config_home.as_deref(),
+            &state_topic,
+            &serde_json::to_string(&state)?,
+        )
+    })();
+
+    if let Some(dir) = config_home {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    published
+}
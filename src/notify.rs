@@ -0,0 +1,119 @@
+use scuttle::App;
+use serde::Serialize;
+
+use crate::config;
+use crate::detect;
+use crate::preview::{PendingUpdate, Preview};
+
+/// What's posted to the webhook
+#[derive(Serialize)]
+struct Message<'a> {
+    hostname: String,
+    /// The same text the desktop notification shows
+    text: &'a str,
+    pending: &'a [PendingUpdate],
+}
+
+/// Whether what a check found is worth a notification: at least `threshold` updates,
+/// or any security update when those always count
+///
+/// # Arguments
+///
+/// * `settings` - The notification settings from the config
+/// * `preview` - What the check found
+pub fn due(settings: &config::Notify, preview: &Preview) -> bool {
+    let security = preview.pending.iter().any(|update| update.security);
+
+    (!preview.pending.is_empty() && preview.pending.len() >= settings.threshold)
+        || (settings.security && security)
+}
+
+/// Run a notification command, a failure is only warned about
+fn run(app: &App) {
+    match scuttle::run_output(app) {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => eprintln!(
+            "WARNING: {} failed: {}",
+            app.command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(error) => eprintln!("WARNING: could not run {}: {}", app.command, error),
+    }
+}
+
+/// Tell the user updates are available, on the desktop and through the webhook
+///
+/// The desktop notification uses notify-send on Linux and osascript on macOS. The
+/// webhook gets a JSON POST with the hostname, the text and every pending update.
+///
+/// # Arguments
+///
+/// * `settings` - The notification settings from the config
+/// * `preview` - What the check found
+pub fn send(settings: &config::Notify, preview: &Preview) {
+    let security = preview
+        .pending
+        .iter()
+        .filter(|update| update.security)
+        .count();
+    let text = if security > 0 {
+        format!(
+            "{} updates available, {} of them security updates",
+            preview.pending.len(),
+            security
+        )
+    } else {
+        format!("{} updates available", preview.pending.len())
+    };
+
+    println!("{}", text);
+
+    if settings.desktop {
+        match detect::os() {
+            "linux" => run(&App {
+                command: String::from("notify-send"),
+                args: vec![
+                    "--app-name=up2date".to_string(),
+                    "up2date".to_string(),
+                    text.clone(),
+                ],
+            }),
+            "macos" => run(&App {
+                command: String::from("osascript"),
+                args: vec![
+                    "-e".to_string(),
+                    format!(
+                        "display notification \"{}\" with title \"up2date\"",
+                        text.replace('\\', "\\\\").replace('"', "\\\"")
+                    ),
+                ],
+            }),
+            _ => {}
+        }
+    }
+
+    if let Some(webhook) = settings.webhook.as_ref() {
+        let message = Message {
+            hostname: sys_info::hostname().unwrap_or_default(),
+            text: &text,
+            pending: &preview.pending,
+        };
+
+        match serde_json::to_string(&message) {
+            Ok(json) => run(&App {
+                command: String::from("curl"),
+                args: vec![
+                    "--fail".to_string(),
+                    "--silent".to_string(),
+                    "--show-error".to_string(),
+                    "--header".to_string(),
+                    "Content-Type: application/json".to_string(),
+                    "--data".to_string(),
+                    json,
+                    webhook.clone(),
+                ],
+            }),
+            Err(error) => eprintln!("WARNING: could not build the webhook message: {}", error),
+        }
+    }
+}
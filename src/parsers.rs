@@ -0,0 +1,2039 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::preview::PendingUpdate;
+use crate::report::{ChangedPackage, Note, Pro, Sizes, Vulnerability};
+use crate::snapshot::{Snapshot, DESCRIPTION};
+use crate::windows::Outdated;
+
+/// Every line that isn't empty, e.g. the orphans `pacman -Qtdq` lists
+pub fn parse_lines(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The packages in `cargo install --list`, the unindented lines
+///
+/// ```text
+/// ripgrep v14.1.0:
+///     rg
+/// ```
+pub fn parse_cargo_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with(' '))
+        .filter_map(|line| line.split(' ').next())
+        .filter(|package| !package.is_empty())
+        // someone has reserved a couple of names I'm using on crates.io so skip them
+        .filter(|package| *package != "tm" && *package != "project")
+        .map(String::from)
+        .collect()
+}
+
+/// The packages the binaries in `go version -m` were built from
+///
+/// ```text
+/// /home/me/go/bin/gopls: go1.22.0
+///         path    golang.org/x/tools/gopls
+///         mod     golang.org/x/tools/gopls        v0.15.0 h1:...
+/// ```
+pub fn parse_go_version(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(
+            |line| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                // binaries built from a local checkout can't be installed @latest
+                ["path", package] if package != "command-line-arguments" => {
+                    Some(package.to_string())
+                }
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// The tools in `dotnet tool list -g`, under the row of dashes
+///
+/// ```text
+/// Package Id      Version      Commands
+/// -------------------------------------
+/// dotnetsay       2.1.4        dotnetsay
+/// ```
+pub fn parse_dotnet_tools(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(String::from)
+        .collect()
+}
+
+/// The formulae in `brew outdated --json=v2` that aren't pinned
+pub fn brew_outdated_formulae(outdated: &Value) -> Vec<String> {
+    outdated["formulae"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice)
+        .iter()
+        .filter(|formula| formula["pinned"].as_bool() != Some(true))
+        .filter_map(|formula| formula["name"].as_str())
+        .map(String::from)
+        .collect()
+}
+
+/// Parse the `Inst` and `Remv` lines of `apt-get -s upgrade`
+///
+/// `Inst libfoo [1.0-1] (1.1-1 Ubuntu:22.04/jammy-updates [amd64])`
+pub fn parse_apt(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (action, rest) = line.split_once(' ')?;
+            let (package, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+            // a new package has no `[version]`, only the `[arch]` inside the parentheses
+            let installed = rest.split('(').next().unwrap_or(rest);
+            let from = between(installed, '[', ']').map(String::from);
+            let details = between(rest, '(', ')').unwrap_or("");
+            let to = details.split_whitespace().next().map(String::from);
+            // the pocket, e.g. `Ubuntu:22.04/jammy-security`
+            let security = details.contains("-security");
+
+            match action {
+                "Inst" => Some(PendingUpdate {
+                    manager: "apt",
+                    package: package.to_string(),
+                    from,
+                    to,
+                    security,
+                }),
+                "Remv" => Some(PendingUpdate {
+                    manager: "apt",
+                    package: package.to_string(),
+                    from,
+                    to: None,
+                    security: false,
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Sum the sizes printed by `apt-get -qq --print-uris upgrade`
+///
+/// `'http://archive.ubuntu.com/.../libfoo_1.1-1_amd64.deb' libfoo_1.1-1_amd64.deb 12345 SHA256:...`
+pub fn parse_apt_uris(output: &str) -> u64 {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(2)?.parse::<u64>().ok())
+        .sum()
+}
+
+/// Parse `package old -> new` lines as printed by `pacman -Qu`, `checkupdates` and `yay -Qua`
+pub fn parse_arrows(manager: &'static str, output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+
+            match parts.as_slice() {
+                [package, from, "->", to, ..] => Some(PendingUpdate {
+                    manager,
+                    package: package.to_string(),
+                    from: Some(from.to_string()),
+                    to: Some(to.to_string()),
+                    security: false,
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parse `brew outdated --json=v2`
+///
+/// `{"formulae": [{"name": "node", "installed_versions": ["20.1.0", "20.2.0"],
+/// "current_version": "21.0.0", ...}], "casks": [...]}`, casks look the same. Pinned
+/// formulae are left out since `brew upgrade` won't touch them.
+pub fn parse_brew(output: &str) -> Vec<PendingUpdate> {
+    let outdated: Value = match serde_json::from_str(output) {
+        Ok(outdated) => outdated,
+        Err(_) => return Vec::new(),
+    };
+
+    ["formulae", "casks"]
+        .iter()
+        .filter_map(|kind| outdated[*kind].as_array())
+        .flatten()
+        .filter(|package| package["pinned"].as_bool() != Some(true))
+        .filter_map(|package| {
+            Some(PendingUpdate {
+                manager: "brew",
+                package: package["name"].as_str()?.to_string(),
+                from: package["installed_versions"]
+                    .as_array()
+                    .and_then(|versions| versions.last())
+                    .and_then(Value::as_str)
+                    .map(String::from),
+                to: package["current_version"].as_str().map(String::from),
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// Parse what `dnf --assumeno upgrade` would do
+///
+/// The `Upgrading:`, `Installing:`, `Removing:` sections (and their `dependencies`
+/// variants) list ` name arch version repo size`. dnf4 doesn't print the version being
+/// replaced so it's looked up in `installed`.
+///
+/// # Arguments
+///
+/// * `output` - What `dnf --assumeno upgrade` printed
+/// * `installed` - The installed version of each package, from `rpm -qa`
+pub fn parse_dnf(output: &str, installed: &HashMap<String, String>) -> Vec<PendingUpdate> {
+    let mut pending: Vec<PendingUpdate> = Vec::new();
+    // whether the current section installs, upgrades or removes, `None` outside one
+    let mut section: Option<&str> = None;
+
+    for line in output.lines() {
+        if !line.starts_with(' ') {
+            section = ["Upgrading", "Installing", "Removing", "Downgrading"]
+                .into_iter()
+                .find(|action| line.starts_with(action));
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        // dnf5 prints the version being replaced on a line of its own
+        if let ["replacing", _, _, version, ..] = fields[..] {
+            if let Some(last) = pending.last_mut() {
+                last.from = Some(version.to_string());
+            }
+            continue;
+        }
+
+        if let (Some(action), [package, _, version, ..]) = (section, &fields[..]) {
+            pending.push(PendingUpdate {
+                manager: "dnf",
+                package: package.to_string(),
+                from: match action {
+                    "Installing" => None,
+                    _ => installed.get(*package).cloned(),
+                },
+                to: match action {
+                    "Removing" => None,
+                    _ => Some(version.to_string()),
+                },
+                security: false,
+            });
+        }
+    }
+
+    pending
+}
+
+/// Mark the pending dnf updates that `dnf updateinfo list --security` has an advisory for
+///
+/// `FEDORA-2024-1a2b3c4d5e Important/Sec.  kernel-6.8.4-200.fc39.x86_64`
+///
+/// # Arguments
+///
+/// * `pending` - The pending updates to mark
+/// * `advisories` - What `dnf updateinfo list --security` printed
+pub fn mark_dnf_security(pending: &mut [PendingUpdate], advisories: &str) {
+    let packages: Vec<&str> = advisories
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(2))
+        .collect();
+
+    for update in pending.iter_mut().filter(|update| update.manager == "dnf") {
+        let prefix = format!("{}-", update.package);
+
+        // the rest has to be the version, not a longer package name like kernel-core
+        update.security = packages.iter().any(|nevra| {
+            nevra
+                .strip_prefix(&prefix)
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        });
+    }
+}
+
+/// Parse `rpm -qa --queryformat '%{NAME} %{VERSION}-%{RELEASE}\n'` into the installed
+/// version of each package
+pub fn parse_rpm(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(package, version)| (package.to_string(), version.to_string()))
+        .collect()
+}
+
+/// Parse the `Update available` lines of `rustup check`
+///
+/// `stable-x86_64-unknown-linux-gnu - Update available : 1.76.0 (07dca489a 2024-02-04) -> 1.77.0 (aedd173a2 2024-03-17)`
+pub fn parse_rustup(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (toolchain, versions) = line.split_once(" - Update available : ")?;
+            let (from, to) = versions.split_once(" -> ")?;
+
+            Some(PendingUpdate {
+                manager: "rustup",
+                package: toolchain.to_string(),
+                from: from.split_whitespace().next().map(String::from),
+                to: to.split_whitespace().next().map(String::from),
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// Whether `rustup component list --installed` for a toolchain lists a component
+///
+/// Most components are listed with the toolchain's host triple and a few, like
+/// `rust-src`, without one:
+///
+/// ```text
+/// cargo-x86_64-unknown-linux-gnu
+/// clippy-x86_64-unknown-linux-gnu
+/// rust-src
+/// rust-std-wasm32-unknown-unknown
+/// ```
+///
+/// # Arguments
+///
+/// * `installed` - What rustup listed
+/// * `component` - The component, e.g. `clippy`
+/// * `toolchain` - The toolchain, e.g. `stable-x86_64-unknown-linux-gnu`
+pub fn has_rust_component(installed: &str, component: &str, toolchain: &str) -> bool {
+    installed.lines().map(str::trim).any(|line| {
+        line == component
+            || line
+                .strip_prefix(component)
+                .is_some_and(|host| host.starts_with('-') && toolchain.ends_with(host))
+    })
+}
+
+/// The Command Line Tools updates in `softwareupdate --list`, everything else it lists is
+/// left to Software Update
+///
+/// Each update is a label followed by its details:
+///
+/// ```text
+/// * Label: Command Line Tools for Xcode-15.3
+///     Title: Command Line Tools for Xcode, Version: 15.3, Size: 707501KiB, Recommended: YES,
+/// ```
+///
+/// # Arguments
+///
+/// * `output` - What `softwareupdate --list` printed
+/// * `installed` - The installed version, `None` when it's unknown
+pub fn parse_softwareupdate(output: &str, installed: Option<&str>) -> Vec<PendingUpdate> {
+    let mut pending: Vec<PendingUpdate> = Vec::new();
+    let mut lines = output.lines();
+
+    while let Some(line) = lines.next() {
+        let label = match line.trim().strip_prefix("* Label: ") {
+            Some(label) if label.starts_with("Command Line Tools") => label,
+            _ => continue,
+        };
+        let version = lines.next().and_then(|details| {
+            details
+                .split(", ")
+                .find_map(|detail| detail.trim().strip_prefix("Version: "))
+        });
+
+        pending.push(PendingUpdate {
+            manager: "softwareupdate",
+            package: label.to_string(),
+            from: installed.map(String::from),
+            to: version.map(String::from),
+            security: false,
+        });
+    }
+
+    pending
+}
+
+/// The version in `pkgutil --pkg-info`, the line `version: 15.1.0.0.1.1700200546`
+pub fn parse_pkgutil_version(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("version: "))
+        .map(|version| version.trim().to_string())
+}
+
+/// The refs in `flatpak remote-ls --updates --columns=origin,ref` that come from one of
+/// `remotes`, or from any remote when it's empty
+///
+/// `flathub\tapp/org.mozilla.firefox/x86_64/stable`
+///
+/// # Arguments
+///
+/// * `output` - What `flatpak remote-ls` printed
+/// * `remotes` - The remotes to update from
+pub fn parse_flatpak_updates(output: &str, remotes: &[String]) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter(|(origin, _)| remotes.is_empty() || remotes.iter().any(|remote| remote == origin))
+        .map(|(_, reference)| reference.trim().to_string())
+        .filter(|reference| !reference.is_empty())
+        .collect()
+}
+
+/// The kernels that can be purged from `dpkg-query -W -f '${Package} ${db:Status-Abbrev}\n'`,
+/// every installed one but the newest `keep` and the one that's running
+///
+/// `linux-image-6.1.0-17-amd64 ii `, Proxmox's are `proxmox-kernel-6.5.13-1-pve-signed`
+/// and `pve-kernel-5.15.131-2-pve`. Packages that were removed but not purged (`rc`)
+/// are left to autoremove.
+///
+/// # Arguments
+///
+/// * `output` - What `dpkg-query` printed
+/// * `running` - The running kernel's release, as `uname -r` prints it
+/// * `keep` - How many of the newest kernels to keep
+pub fn parse_old_kernels(output: &str, running: &str, keep: usize) -> Vec<String> {
+    // 6.1.0-17-amd64 sorts as [6, 1, 0, 17]
+    let version = |package: &str| -> Vec<u64> {
+        package
+            .split(['-', '.'])
+            .filter_map(|part| part.parse::<u64>().ok())
+            .collect()
+    };
+    let mut installed: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, status)| status.starts_with("ii"))
+        .map(|(package, _)| package)
+        .collect();
+
+    installed.sort_by_key(|package| std::cmp::Reverse(version(package)));
+    installed
+        .into_iter()
+        .skip(keep)
+        .filter(|package| running.is_empty() || !package.contains(running))
+        .map(String::from)
+        .collect()
+}
+
+/// The packages listed under `heading` in apt's output, on the indented lines that follow
+/// it
+///
+/// # Arguments
+///
+/// * `output` - What apt printed
+/// * `heading` - The line the list starts after, e.g.
+///   `The following packages have been kept back:`
+fn apt_list(output: &str, heading: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| *line != heading)
+        .skip(1)
+        .take_while(|line| line.starts_with(' '))
+        .flat_map(|line| line.split_whitespace().map(String::from))
+        .collect()
+}
+
+/// What an apt upgrade leaves out, the packages it keeps back (held, pinned or waiting on
+/// a dependency) and the phased updates that haven't reached this machine yet
+pub fn parse_apt_held(output: &str) -> (Vec<String>, Vec<String>) {
+    (
+        apt_list(output, "The following packages have been kept back:"),
+        apt_list(
+            output,
+            "The following upgrades have been deferred due to phasing:",
+        ),
+    )
+}
+
+/// Parse `pro status --format json`, `None` when it isn't JSON
+///
+/// `{"attached": true, "services": [{"name": "esm-infra", "entitled": "yes",
+/// "status": "enabled"}, ...]}`, only the services that are enabled are kept.
+pub fn parse_pro_status(output: &str) -> Option<Pro> {
+    let status: Value = serde_json::from_str(output).ok()?;
+
+    Some(Pro {
+        attached: status["attached"].as_bool()?,
+        services: status["services"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|service| service["status"].as_str() == Some("enabled"))
+            .filter_map(|service| service["name"].as_str().map(String::from))
+            .collect(),
+        livepatch: None,
+    })
+}
+
+/// The state of the running kernel's livepatch in `canonical-livepatch status --format
+/// json`, e.g. `applied` or `nothing-to-apply`, with the patch version when there is one
+///
+/// `{"Status": [{"Kernel": "5.15.0-97.107-generic", "Running": true, "Livepatch":
+/// {"CheckState": "checked", "State": "applied", "Version": "99.1"}}]}`
+pub fn parse_livepatch_status(output: &str) -> Option<String> {
+    let status: Value = serde_json::from_str(output).ok()?;
+    let livepatch = &status["Status"]
+        .as_array()?
+        .iter()
+        .find(|kernel| kernel["Running"].as_bool() == Some(true))?["Livepatch"];
+    let state = livepatch["State"].as_str()?;
+
+    match livepatch["Version"].as_str() {
+        Some(version) if !version.is_empty() => Some(format!("{} {}", state, version)),
+        _ => Some(state.to_string()),
+    }
+}
+
+/// Whether `occ status --output=json` says Nextcloud's database needs `occ upgrade`
+///
+/// `{"installed": true, "versionstring": "29.0.4", "maintenance": false,
+/// "needsDbUpgrade": true}`
+pub fn parse_occ_needs_upgrade(output: &str) -> bool {
+    serde_json::from_str::<Value>(output)
+        .ok()
+        .and_then(|status| status["needsDbUpgrade"].as_bool())
+        .unwrap_or(false)
+}
+
+/// The shared libraries in `/proc/<pid>/maps` that were deleted or replaced since the
+/// process mapped them, each listed once
+///
+/// `7f3a1c400000-7f3a1c4a1000 r-xp 00000000 08:01 1836420  /usr/lib/libssl.so.3 (deleted)`,
+/// memfd and shared memory mappings are never on disk to begin with so they're left out.
+pub fn parse_deleted_libraries(maps: &str) -> Vec<String> {
+    let mut libraries: Vec<String> = Vec::new();
+
+    for line in maps.lines() {
+        let path = match line.split_whitespace().nth(5) {
+            Some(path) => line[line.find(path).unwrap_or(0)..].trim_end(),
+            None => continue,
+        };
+        let library = match path.strip_suffix(" (deleted)") {
+            Some(library) if library.contains(".so") => library,
+            _ => continue,
+        };
+
+        if library.starts_with("/memfd:") || library.starts_with("/dev/shm/") {
+            continue;
+        }
+
+        if !libraries.iter().any(|known| known == library) {
+            libraries.push(library.to_string());
+        }
+    }
+
+    libraries
+}
+
+/// The system service a process belongs to from `/proc/<pid>/cgroup`, `None` when it
+/// isn't part of one, e.g. `ssh.service` for `0::/system.slice/ssh.service`
+pub fn parse_cgroup_service(cgroup: &str) -> Option<String> {
+    cgroup.lines().find_map(|line| {
+        let path = line.splitn(3, ':').nth(2)?;
+        let unit = path.strip_prefix("/system.slice/")?.split('/').next()?;
+
+        unit.ends_with(".service").then(|| unit.to_string())
+    })
+}
+
+/// The containers `docker compose up -d` recreated, e.g. `nextcloud-app-1` for
+/// `✔ Container nextcloud-app-1  Recreated`
+pub fn parse_compose_recreated(output: &str) -> Vec<String> {
+    let mut containers: Vec<String> = Vec::new();
+
+    for line in output.lines() {
+        let words: Vec<&str> = line
+            .split_whitespace()
+            .skip_while(|word| *word != "Container")
+            .collect();
+
+        if let ["Container", name, "Recreated"] = words[..] {
+            if !containers.iter().any(|known| known == name) {
+                containers.push(name.to_string());
+            }
+        }
+    }
+
+    containers
+}
+
+/// Add a line to the note about a package, starting a new note unless the last one is
+/// about the same package
+fn add_note(notes: &mut Vec<Note>, manager: &'static str, package: Option<&str>, line: &str) {
+    match notes.last_mut() {
+        Some(note) if note.package.as_deref() == package => note.lines.push(line.to_string()),
+        _ => notes.push(Note {
+            manager,
+            package: package.map(String::from),
+            lines: vec![line.to_string()],
+        }),
+    }
+}
+
+/// The caveats `brew upgrade` and `brew install` print at the end, each formula's under
+/// its own `==> <formula>` heading after `==> Caveats` when there's more than one
+pub fn parse_brew_caveats(output: &str) -> Vec<Note> {
+    let mut notes: Vec<Note> = Vec::new();
+    let mut caveats = false;
+    let mut formula: Option<&str> = None;
+
+    for line in output.lines() {
+        match line.strip_prefix("==> ") {
+            Some("Caveats") => {
+                caveats = true;
+                formula = None;
+            }
+            // a heading that isn't a formula, e.g. `==> Summary`, ends them
+            Some(heading) if heading == "Summary" || heading.contains(' ') => caveats = false,
+            Some(heading) if caveats => formula = Some(heading),
+            _ if caveats && !line.trim().is_empty() => {
+                add_note(&mut notes, "brew", formula, line.trim_end())
+            }
+            _ => {}
+        }
+    }
+
+    notes
+}
+
+/// What the install scripts of the packages pacman or yay upgraded printed, along with
+/// pacman's own warnings about them like a `.pacnew` being left
+///
+/// The new optional dependencies pacman lists are left out, so are the post-transaction
+/// hooks.
+pub fn parse_pacman_messages(output: &str) -> Vec<Note> {
+    const VERBS: [&str; 5] = [
+        "installing ",
+        "upgrading ",
+        "reinstalling ",
+        "downgrading ",
+        "removing ",
+    ];
+
+    let mut notes: Vec<Note> = Vec::new();
+    let mut changes = false;
+    let mut optional = false;
+    let mut package: Option<String> = None;
+
+    for line in output.lines() {
+        if line.starts_with(":: Processing package changes") {
+            changes = true;
+            continue;
+        }
+
+        if line.starts_with(":: Running post-transaction hooks") {
+            changes = false;
+        }
+
+        if !changes || line.trim().is_empty() {
+            continue;
+        }
+
+        // `upgrading linux...`, with `(1/3) ` in front of it on a terminal
+        let action = match line.split_once(") ") {
+            Some((count, action)) if count.starts_with('(') => action,
+            _ => line,
+        };
+
+        if let Some(name) = VERBS.iter().find_map(|verb| action.strip_prefix(verb)) {
+            package = name
+                .split_whitespace()
+                .next()
+                .map(|name| name.trim_end_matches('.').to_string());
+            optional = false;
+        } else if line.contains("ptional dependencies for ") {
+            optional = true;
+        } else if !(optional && line.starts_with(' ')) {
+            optional = false;
+            add_note(&mut notes, "pacman", package.as_deref(), line.trim());
+        }
+    }
+
+    notes
+}
+
+/// The news apt-listchanges showed while apt upgraded, one note for each package's
+/// entry in its `NEWS.Debian`
+pub fn parse_apt_news(output: &str) -> Vec<Note> {
+    let mut notes: Vec<Note> = Vec::new();
+    let mut news = false;
+    let mut package: Option<&str> = None;
+
+    for line in output.lines() {
+        if line.starts_with("apt-listchanges: News") {
+            news = true;
+            continue;
+        }
+
+        if !news || line.trim().is_empty() || line.starts_with("---") {
+            continue;
+        }
+
+        match package {
+            // `openssh (1:9.7p1-1) unstable; urgency=medium` starts an entry
+            None if line.contains(" (") && line.contains("; urgency=") => {
+                package = line.split_whitespace().next();
+            }
+            // whatever comes after the last entry isn't news anymore
+            None => news = false,
+            // ` -- Colin Watson <cjwatson@debian.org>  Mon, 11 Mar 2024` ends it
+            Some(_) if line.starts_with(" -- ") => package = None,
+            Some(name) => add_note(&mut notes, "apt", Some(name), line.trim()),
+        }
+    }
+
+    notes
+}
+
+/// The changes in what `apt-get changelog` printed since the installed version, the
+/// newest first
+///
+/// # Arguments
+///
+/// * `output` - The changelog
+/// * `installed` - The installed version, every entry is new when there isn't one
+pub fn parse_debian_changelog(output: &str, installed: Option<&str>) -> Vec<String> {
+    let mut changes: Vec<String> = Vec::new();
+
+    for line in output.lines() {
+        // `openssl (3.0.13-1~deb12u2) bookworm-security; urgency=medium`
+        if !line.starts_with(' ') && line.contains("; urgency=") {
+            let version = between(line, '(', ')');
+
+            if version.is_some() && version == installed {
+                break;
+            }
+        } else if let Some(change) = line.trim_start().strip_prefix("* ") {
+            changes.push(change.to_string());
+        }
+    }
+
+    changes
+}
+
+/// The release in what `k3s --version`, `k0s version` or k0s' `stable.txt` printed, e.g.
+/// `v1.30.4+k3s1` for `k3s version v1.30.4+k3s1 (98262b5d)`
+pub fn parse_kubernetes_version(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .find(|word| {
+            word.strip_prefix('v')
+                .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .map(|version| version.to_string())
+}
+
+/// The release a GitHub release URL points at, e.g. `v1.30.4+k3s1` for
+/// `https://github.com/k3s-io/k3s/releases/tag/v1.30.4+k3s1`
+pub fn parse_release_tag(url: &str) -> Option<String> {
+    let (_, tag) = url.trim().rsplit_once("/tag/")?;
+
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// What the last `btrfs scrub status` found, e.g. `no errors found` or `csum=2` for
+/// `Error summary:    csum=2`, `None` when the filesystem was never scrubbed
+pub fn parse_scrub_status(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Error summary:")
+            .map(|errors| errors.trim().to_string())
+    })
+}
+
+/// The balance `btrfs balance status` says is running or paused, `None` for
+/// `No balance found on '/'`
+pub fn parse_balance_status(output: &str) -> Option<String> {
+    let status = output.lines().next()?.trim();
+
+    (!status.is_empty() && !status.starts_with("No balance found")).then(|| status.to_string())
+}
+
+/// The devices `smartctl --scan` found, each as the arguments that select it, e.g.
+/// `/dev/sda -d sat` for `/dev/sda -d sat # /dev/sda [SAT], ATA device`
+pub fn parse_smart_scan(output: &str) -> Vec<Vec<String>> {
+    output
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .map(|device| {
+            device
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .filter(|device| !device.is_empty())
+        .collect()
+}
+
+/// The overall health `smartctl -H` reports, `PASSED` or `FAILED!` for ATA disks and
+/// `OK` for NVMe ones
+pub fn parse_smart_health(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (label, status) = line.split_once(':')?;
+
+        (label.ends_with("self-assessment test result") || label == "SMART Health Status")
+            .then(|| status.trim().to_string())
+    })
+}
+
+/// What the bash shell steps print to stderr when a pipeline fails, followed by the exit
+/// code of each command in it
+pub const PIPE_STATUS: &str = "up2date: pipeline exit codes";
+
+/// The exit codes of each command in the last pipeline of a shell step that failed, from
+/// the `PIPE_STATUS` lines in its stderr
+pub fn parse_pipe_status(stderr: &str) -> Vec<i32> {
+    stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix(PIPE_STATUS))
+        .next_back()
+        .map(|codes| {
+            codes
+                .split_whitespace()
+                .filter_map(|code| code.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The text between the first `open` and the following `close`
+fn between(text: &str, open: char, close: char) -> Option<&str> {
+    let start = text.find(open)? + open.len_utf8();
+    let end = text[start..].find(close)? + start;
+
+    Some(&text[start..end])
+}
+
+/// Turn an amount like `1,234.5` with a unit like `kB`, `MiB` or dnf's `M` into bytes
+fn parse_amount(amount: &str, unit: &str) -> Option<i64> {
+    let (amount, sign) = match amount.strip_prefix('-') {
+        Some(amount) => (amount, -1.0),
+        None => (amount, 1.0),
+    };
+    let amount: f64 = amount.replace(',', "").parse().ok()?;
+    let multiplier: f64 = match unit.trim_end_matches('.') {
+        "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "k" | "KiB" => 1024.0,
+        "M" | "MiB" => 1024.0 * 1024.0,
+        "G" | "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+
+    Some((sign * amount * multiplier).round() as i64)
+}
+
+/// Parse the first `<amount> <unit>` pair in some text
+fn first_amount(text: &str) -> Option<i64> {
+    let mut words = text.split_whitespace();
+
+    parse_amount(words.next()?, words.next()?)
+}
+
+/// Find the download size and disk usage change in a package manager's output
+///
+/// Understands apt (`Need to get ...`, `After this operation, ...`), pacman
+/// (`Total Download Size:`, `Net Upgrade Size:`) and dnf (`Total download size:`,
+/// `Installed size:`, dnf5's `Need to download ...`).
+///
+/// # Arguments
+///
+/// * `output` - What the package manager printed to stdout
+pub fn parse_sizes(output: &str) -> Sizes {
+    let mut sizes = Sizes::default();
+    let mut installed: Option<i64> = None;
+    let mut removed: Option<i64> = None;
+    let mut net: Option<i64> = None;
+
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("Need to get ") {
+            // apt, `1,234 kB/12.3 MB` when some of the archives are already cached
+            let amount = rest.split('/').next().unwrap_or(rest);
+
+            sizes.download = first_amount(amount).map(|bytes| bytes as u64);
+        } else if let Some(rest) = line.strip_prefix("After this operation, ") {
+            // apt and dnf5
+            net = first_amount(rest).map(|bytes| {
+                if rest.contains("freed") {
+                    -bytes
+                } else {
+                    bytes
+                }
+            });
+        } else if let Some(rest) = line.split("Need to download ").nth(1) {
+            // dnf5, `Total size of inbound packages is 412 MiB. Need to download 412 MiB.`
+            sizes.download = first_amount(rest).map(|bytes| bytes as u64);
+        } else if let Some(rest) = line
+            .strip_prefix("Total Download Size:")
+            .or_else(|| line.strip_prefix("Total download size:"))
+        {
+            sizes.download = first_amount(rest).map(|bytes| bytes as u64);
+        } else if let Some(rest) = line.strip_prefix("Net Upgrade Size:") {
+            net = first_amount(rest);
+        } else if let Some(rest) = line
+            .strip_prefix("Total Installed Size:")
+            .or_else(|| line.strip_prefix("Installed size:"))
+        {
+            installed = first_amount(rest);
+        } else if let Some(rest) = line
+            .strip_prefix("Total Removed Size:")
+            .or_else(|| line.strip_prefix("Freed space:"))
+        {
+            removed = first_amount(rest);
+        }
+    }
+
+    sizes.disk_delta = net.or(match (installed, removed) {
+        (None, None) => None,
+        (installed, removed) => Some(installed.unwrap_or(0) - removed.unwrap_or(0)),
+    });
+
+    sizes
+}
+
+/// Find the packages composer changed in its output
+///
+/// ```text
+///   - Upgrading vimeo/psalm (5.1.0 => 5.2.0)
+///   - Installing phpunit/phpunit (10.5.0)
+///   - Removing psr/log (1.1.4)
+/// ```
+///
+/// # Arguments
+///
+/// * `output` - What composer printed, it writes its progress to stderr
+pub fn parse_composer(output: &str) -> Vec<ChangedPackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().strip_prefix("- ")?;
+            let (action, rest) = line.split_once(' ')?;
+            let (package, versions) = rest.split_once(" (")?;
+            let versions = versions.strip_suffix(')')?;
+            let (from, to) = match (action, versions.split_once(" => ")) {
+                ("Upgrading" | "Downgrading", Some((from, to))) => (Some(from), Some(to)),
+                ("Installing", None) => (None, Some(versions)),
+                ("Removing", None) => (Some(versions), None),
+                _ => return None,
+            };
+
+            Some(ChangedPackage {
+                package: package.to_string(),
+                from: from.map(String::from),
+                to: to.map(String::from),
+            })
+        })
+        .collect()
+}
+
+/// Find the vulnerabilities in `cargo audit --json`, one report for each binary when
+/// auditing binaries
+///
+/// Warnings such as unmaintained crates are kept apart from the vulnerabilities in the
+/// report so they're left out without looking.
+///
+/// ```text
+/// {"vulnerabilities": {"found": true, "count": 1, "list": [{
+///   "advisory": {"id": "RUSTSEC-2020-0071", "package": "time", ...},
+///   "package": {"name": "time", "version": "0.1.45", ...}, ...}]},
+///  "warnings": {"unmaintained": [...]}}
+/// ```
+///
+/// # Arguments
+///
+/// * `output` - What `cargo audit --json` printed
+pub fn parse_cargo_audit(output: &str) -> Vec<Vulnerability> {
+    serde_json::Deserializer::from_str(output)
+        .into_iter::<Value>()
+        .map_while(Result::ok)
+        .flat_map(|report| {
+            report["vulnerabilities"]["list"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+        })
+        .filter_map(|found| {
+            Some(Vulnerability {
+                tool: "cargo audit",
+                package: found["package"]["name"].as_str()?.to_string(),
+                version: found["package"]["version"].as_str()?.to_string(),
+                advisory: found["advisory"]["id"].as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Find the vulnerabilities in `pip-audit --format json`, each dependency with the
+/// vulnerabilities found in it
+///
+/// ```text
+/// {"dependencies": [{"name": "django", "version": "3.2.0",
+///   "vulns": [{"id": "PYSEC-2021-98", "fix_versions": ["3.2.2"], ...}]}], "fixes": []}
+/// ```
+///
+/// pip-audit before 2.5 printed the list of dependencies on its own.
+///
+/// # Arguments
+///
+/// * `output` - What `pip-audit --format json` printed
+pub fn parse_pip_audit(output: &str) -> Vec<Vulnerability> {
+    let report: Value = match serde_json::from_str(output) {
+        Ok(report) => report,
+        Err(_) => return Vec::new(),
+    };
+    let dependencies = match (&report["dependencies"], &report) {
+        (Value::Array(dependencies), _) | (_, Value::Array(dependencies)) => dependencies,
+        _ => return Vec::new(),
+    };
+
+    dependencies
+        .iter()
+        .flat_map(|dependency| {
+            dependency["vulns"]
+                .as_array()
+                .map_or(&[][..], Vec::as_slice)
+                .iter()
+                .filter_map(move |vulnerability| {
+                    Some(Vulnerability {
+                        tool: "pip-audit",
+                        package: dependency["name"].as_str()?.to_string(),
+                        version: dependency["version"].as_str()?.to_string(),
+                        advisory: vulnerability["id"].as_str()?.to_string(),
+                    })
+                })
+        })
+        .collect()
+}
+
+/// Split a table that's lined up in columns under a header into its cells
+///
+/// winget and scoop pad their tables with spaces, the header is found by its column
+/// titles and the row of dashes under it is skipped. The table ends at the first empty
+/// line. Only what's after the last carriage return of each line counts, which drops
+/// winget's progress spinner.
+///
+/// # Arguments
+///
+/// * `output` - What the manager printed
+/// * `columns` - The titles of the columns to read, in the order they're printed
+pub fn parse_table(output: &str, columns: &[&str]) -> Vec<Vec<String>> {
+    let mut lines = output
+        .lines()
+        .map(|line| line.rsplit('\r').next().unwrap_or(line));
+    let mut starts: Vec<usize> = Vec::new();
+
+    for line in lines.by_ref() {
+        let header: Vec<char> = line.chars().collect();
+        let mut from = 0;
+
+        starts.clear();
+
+        for column in columns.iter() {
+            let title: Vec<char> = column.chars().collect();
+
+            match (from..=header.len().saturating_sub(title.len()))
+                .find(|&start| header[start..].starts_with(&title))
+            {
+                Some(start) => {
+                    starts.push(start);
+                    from = start + title.len();
+                }
+                None => break,
+            }
+        }
+
+        if starts.len() == columns.len() {
+            break;
+        }
+    }
+
+    if starts.len() != columns.len() {
+        return Vec::new();
+    }
+
+    lines
+        .skip_while(|line| !line.trim().is_empty() && line.trim_matches(['-', ' ']).is_empty())
+        .take_while(|line| !line.trim().is_empty())
+        .map(|line| {
+            let row: Vec<char> = line.chars().collect();
+
+            starts
+                .iter()
+                .enumerate()
+                .map(|(index, &start)| {
+                    let end = starts.get(index + 1).copied().unwrap_or(row.len());
+
+                    row.get(start.min(row.len())..end.min(row.len()))
+                        .unwrap_or(&[])
+                        .iter()
+                        .collect::<String>()
+                        .trim()
+                        .to_string()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Parse `winget upgrade --include-pinned`, marking the ids in `winget pin list`
+///
+/// # Arguments
+///
+/// * `upgrades` - What `winget upgrade --include-pinned` printed
+/// * `pins` - What `winget pin list` printed
+pub fn parse_winget(upgrades: &str, pins: &str) -> Vec<Outdated> {
+    let pinned: Vec<String> = parse_table(pins, &["Name", "Id", "Version"])
+        .into_iter()
+        .map(|row| row[1].clone())
+        .collect();
+
+    parse_table(upgrades, &["Name", "Id", "Version", "Available"])
+        .into_iter()
+        .filter(|row| !row[1].is_empty() && !row[3].is_empty())
+        .map(|row| Outdated {
+            pinned: pinned.contains(&row[1]),
+            id: row[1].clone(),
+            version: row[2].clone(),
+            // the Source column follows Available
+            available: row[3].split_whitespace().next().unwrap_or("").to_string(),
+        })
+        .collect()
+}
+
+/// Parse `choco outdated -r`, `name|current|available|pinned` on each line
+pub fn parse_choco(output: &str) -> Vec<Outdated> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.trim().split('|').collect();
+
+            match fields[..] {
+                [name, version, available, pinned] => Some(Outdated {
+                    id: name.to_string(),
+                    version: version.to_string(),
+                    available: available.to_string(),
+                    pinned: pinned == "true",
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parse `scoop status`, a held package says so in its Info column
+pub fn parse_scoop(output: &str) -> Vec<Outdated> {
+    parse_table(
+        output,
+        &[
+            "Name",
+            "Installed Version",
+            "Latest Version",
+            "Missing Dependencies",
+            "Info",
+        ],
+    )
+    .into_iter()
+    .filter(|row| !row[0].is_empty() && !row[2].is_empty())
+    .map(|row| Outdated {
+        pinned: row[4].contains("Held package"),
+        id: row[0].clone(),
+        version: row[1].clone(),
+        available: row[2].clone(),
+    })
+    .collect()
+}
+
+/// Parse `snapper list --columns number,date,description`
+///
+/// ```text
+///  # | Date                     | Description
+/// ---+--------------------------+-------------------
+///  0 |                          | current
+/// 42 | Tue 09 Jan 2024 10:00:01 | up2date pre-update
+/// ```
+pub fn parse_snapper(output: &str) -> Vec<Snapshot> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split('|').map(str::trim).collect();
+
+            match columns.as_slice() {
+                [id, date, DESCRIPTION] => Some(Snapshot {
+                    id: id.to_string(),
+                    date: date.to_string(),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parse `timeshift --list`, the snapshot's name is the date it was taken
+///
+/// ```text
+/// Num     Name                 Tags  Description
+/// ------------------------------------------------------------------------------
+/// 0    >  2024-01-09_10-00-01  O     up2date pre-update
+/// ```
+pub fn parse_timeshift(output: &str) -> Vec<Snapshot> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .skip(1)
+        .filter(|line| line.trim_end().ends_with(DESCRIPTION))
+        .filter_map(|line| {
+            let name = line.split_whitespace().find(|column| {
+                column.chars().next().is_some_and(|c| c.is_ascii_digit()) && column.contains('_')
+            })?;
+
+            Some(Snapshot {
+                id: name.to_string(),
+                date: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parse `zfs list -H -t snapshot -o name,creation`, the columns are separated by a tab
+///
+/// `rpool/ROOT/ubuntu@up2date-1704794401<tab>Tue Jan  9 10:00 2024`
+pub fn parse_zfs(output: &str) -> Vec<Snapshot> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (id, date) = line.split_once('\t')?;
+
+            if !id.contains("@up2date-") {
+                return None;
+            }
+
+            Some(Snapshot {
+                id: id.to_string(),
+                date: date.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// What package managers print when there was nothing for them to do, lowercased
+const NOTHING_TO_DO: [&str; 9] = [
+    // apt-get, at the start of a line so `10 upgraded` doesn't count
+    "\n0 upgraded, 0 newly installed, 0 to remove",
+    // pacman, yay, dnf and flatpak
+    "there is nothing to do",
+    "nothing to do.",
+    // git, and everything that reports what git said
+    "already up to date",
+    "already up-to-date",
+    // composer
+    "nothing to install, update or remove",
+    // mamba and conda
+    "all requested packages already installed",
+    // winget
+    "no installed package found matching input criteria",
+    // choco
+    "upgraded 0/",
+];
+
+/// Whether a step's output says it had nothing to do: it printed nothing at all, every
+/// toolchain `rustup update` looked at was unchanged, or it printed something a package
+/// manager only prints when nothing changed
+///
+/// # Arguments
+///
+/// * `output` - What the step printed to stdout
+pub fn is_nothing_to_do(output: &str) -> bool {
+    let output = format!("\n{}", output.to_lowercase());
+
+    if output.trim().is_empty() {
+        return true;
+    }
+
+    // `stable-x86_64-unknown-linux-gnu unchanged - rustc 1.77.0 (aedd173a2 2024-03-17)`
+    if output.contains(" unchanged - ") && !output.contains(" updated - ") {
+        return true;
+    }
+
+    NOTHING_TO_DO.iter().any(|phrase| output.contains(phrase))
+}
+
+/// The packages unattended-upgrades upgraded the last time it ran, from its log
+///
+/// Every run starts with `Starting unattended upgrades script` and lists what it's about
+/// to upgrade on a line of its own:
+///
+/// `2024-03-05 06:17:47,890 INFO Packages that will be upgraded: libexpat1 tzdata`
+pub fn parse_unattended_upgrades(log: &str) -> Vec<String> {
+    let last_run = log
+        .lines()
+        .rev()
+        .take_while(|line| !line.contains("Starting unattended upgrades script"));
+    let mut upgraded: Vec<String> = last_run
+        .filter_map(|line| line.split_once("Packages that will be upgraded: "))
+        .flat_map(|(_, packages)| packages.split_whitespace().map(String::from))
+        .collect();
+
+    upgraded.sort();
+    upgraded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(
+        manager: &'static str,
+        package: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+        security: bool,
+    ) -> PendingUpdate {
+        PendingUpdate {
+            manager,
+            package: package.to_string(),
+            from: from.map(String::from),
+            to: to.map(String::from),
+            security,
+        }
+    }
+
+    fn outdated(id: &str, version: &str, available: &str, pinned: bool) -> Outdated {
+        Outdated {
+            id: id.to_string(),
+            version: version.to_string(),
+            available: available.to_string(),
+            pinned,
+        }
+    }
+
+    fn vulnerability(
+        tool: &'static str,
+        package: &str,
+        version: &str,
+        advisory: &str,
+    ) -> Vulnerability {
+        Vulnerability {
+            tool,
+            package: package.to_string(),
+            version: version.to_string(),
+            advisory: advisory.to_string(),
+        }
+    }
+
+    #[test]
+    fn lines_skips_empty_lines() {
+        assert_eq!(
+            parse_lines(include_str!("../tests/fixtures/pacman-qtdq.txt")),
+            vec!["gnome-common", "python-mako"]
+        );
+    }
+
+    #[test]
+    fn cargo_list_skips_binaries_and_reserved_names() {
+        assert_eq!(
+            parse_cargo_list(include_str!("../tests/fixtures/cargo-install-list.txt")),
+            vec!["cargo-audit", "ripgrep"]
+        );
+    }
+
+    #[test]
+    fn go_version_skips_local_builds() {
+        assert_eq!(
+            parse_go_version(include_str!("../tests/fixtures/go-version-m.txt")),
+            vec![
+                "golang.org/x/tools/gopls",
+                "honnef.co/go/tools/cmd/staticcheck"
+            ]
+        );
+    }
+
+    #[test]
+    fn dotnet_tools_starts_under_the_dashes() {
+        assert_eq!(
+            parse_dotnet_tools(include_str!("../tests/fixtures/dotnet-tool-list.txt")),
+            vec!["dotnet-ef", "dotnetsay"]
+        );
+    }
+
+    #[test]
+    fn brew_outdated_formulae_skips_pinned() {
+        let outdated: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/brew-outdated.json")).unwrap();
+
+        assert_eq!(brew_outdated_formulae(&outdated), vec!["node"]);
+    }
+
+    #[test]
+    fn apt_simulation() {
+        assert_eq!(
+            parse_apt(include_str!("../tests/fixtures/apt-get-s-upgrade.txt")),
+            vec![
+                pending("apt", "libfoo0", Some("0.9-2"), None, false),
+                pending(
+                    "apt",
+                    "linux-image-6.5.0-27-generic",
+                    None,
+                    Some("6.5.0-27.28~22.04.1"),
+                    false
+                ),
+                pending(
+                    "apt",
+                    "libssl3",
+                    Some("3.0.2-0ubuntu1.14"),
+                    Some("3.0.2-0ubuntu1.15"),
+                    true
+                ),
+                pending(
+                    "apt",
+                    "openssl",
+                    Some("3.0.2-0ubuntu1.14"),
+                    Some("3.0.2-0ubuntu1.15"),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn apt_new_package_arch_isnt_its_version() {
+        assert_eq!(
+            parse_apt(
+                "Inst htop (3.3.0-4 Ubuntu:24.04/noble [amd64])\n\
+                 Inst libcurl4 (8.5.0-2ubuntu10.1 Ubuntu:24.04/noble-security [arm64]) []\n"
+            ),
+            vec![
+                pending("apt", "htop", None, Some("3.3.0-4"), false),
+                pending("apt", "libcurl4", None, Some("8.5.0-2ubuntu10.1"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn apt_held_kept_back_and_phased() {
+        let (kept_back, phased) =
+            parse_apt_held(include_str!("../tests/fixtures/apt-get-s-upgrade-held.txt"));
+
+        assert_eq!(kept_back, vec!["linux-generic", "linux-headers-generic"]);
+        assert_eq!(
+            phased,
+            vec!["gnome-shell", "gnome-shell-common", "libmutter-12-0"]
+        );
+    }
+
+    #[test]
+    fn apt_uris_sums_sizes() {
+        assert_eq!(
+            parse_apt_uris(include_str!("../tests/fixtures/apt-get-print-uris.txt")),
+            1902998 + 1186418
+        );
+    }
+
+    #[test]
+    fn arrows() {
+        assert_eq!(
+            parse_arrows("pacman", include_str!("../tests/fixtures/pacman-qu.txt")),
+            vec![
+                pending(
+                    "pacman",
+                    "linux",
+                    Some("6.7.9.arch1-1"),
+                    Some("6.8.1.arch1-1"),
+                    false
+                ),
+                pending("pacman", "openssl", Some("3.2.1-1"), Some("3.2.1-2"), false),
+                pending(
+                    "pacman",
+                    "vim",
+                    Some("9.1.0000-1"),
+                    Some("9.1.0142-1"),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn brew_skips_pinned_and_keeps_casks() {
+        assert_eq!(
+            parse_brew(include_str!("../tests/fixtures/brew-outdated.json")),
+            vec![
+                pending("brew", "node", Some("20.2.0"), Some("21.0.0"), false),
+                pending("brew", "firefox", Some("121.0"), Some("121.0.1"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn brew_without_json() {
+        assert_eq!(parse_brew("Error: No available formula"), vec![]);
+    }
+
+    #[test]
+    fn dnf4_looks_up_installed_versions() {
+        let installed = parse_rpm(include_str!("../tests/fixtures/rpm-qa.txt"));
+
+        assert_eq!(
+            parse_dnf(
+                include_str!("../tests/fixtures/dnf4-upgrade.txt"),
+                &installed
+            ),
+            vec![
+                pending("dnf", "kernel", None, Some("6.8.4-200.fc39"), false),
+                pending(
+                    "dnf",
+                    "kernel-core",
+                    Some("6.6.8-200.fc39"),
+                    Some("6.8.4-200.fc39"),
+                    false
+                ),
+                pending(
+                    "dnf",
+                    "openssl-libs",
+                    Some("3.1.1-1.fc39"),
+                    Some("1:3.1.1-4.fc39"),
+                    false
+                ),
+                pending("dnf", "kernel", Some("6.6.8-200.fc39"), None, false),
+                pending("dnf", "kernel-modules", None, Some("6.8.4-200.fc39"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn dnf5_reads_replacing_lines() {
+        assert_eq!(
+            parse_dnf(
+                include_str!("../tests/fixtures/dnf5-upgrade.txt"),
+                &HashMap::new()
+            ),
+            vec![pending(
+                "dnf",
+                "openssl-libs",
+                Some("1:3.1.1-4.fc40"),
+                Some("1:3.1.4-2.fc40"),
+                false
+            )]
+        );
+    }
+
+    #[test]
+    fn dnf_security_matches_whole_names() {
+        let mut updates = vec![
+            pending("dnf", "kernel", None, Some("6.8.4-200.fc39"), false),
+            pending("dnf", "kernel-core", None, Some("6.8.4-200.fc39"), false),
+            pending("dnf", "openssl-libs", None, Some("1:3.1.1-4.fc39"), false),
+        ];
+
+        mark_dnf_security(
+            &mut updates,
+            include_str!("../tests/fixtures/dnf-updateinfo-security.txt"),
+        );
+
+        assert_eq!(
+            updates
+                .iter()
+                .map(|update| update.security)
+                .collect::<Vec<bool>>(),
+            vec![false, true, true]
+        );
+    }
+
+    #[test]
+    fn rpm() {
+        let installed = parse_rpm(include_str!("../tests/fixtures/rpm-qa.txt"));
+
+        assert_eq!(installed.len(), 3);
+        assert_eq!(installed["openssl-libs"], "3.1.1-1.fc39");
+    }
+
+    #[test]
+    fn rustup_skips_up_to_date() {
+        assert_eq!(
+            parse_rustup(include_str!("../tests/fixtures/rustup-check.txt")),
+            vec![pending(
+                "rustup",
+                "stable-x86_64-unknown-linux-gnu",
+                Some("1.76.0"),
+                Some("1.77.0"),
+                false
+            )]
+        );
+    }
+
+    #[test]
+    fn rust_components_with_or_without_the_host() {
+        let installed = "cargo-x86_64-unknown-linux-gnu\n\
+                         clippy-x86_64-unknown-linux-gnu\n\
+                         rust-src\n\
+                         rust-std-wasm32-unknown-unknown\n";
+        let toolchain = "stable-x86_64-unknown-linux-gnu";
+
+        assert!(has_rust_component(installed, "clippy", toolchain));
+        assert!(has_rust_component(installed, "rust-src", toolchain));
+        assert!(!has_rust_component(installed, "rustfmt", toolchain));
+        // a component for another target isn't the host's
+        assert!(!has_rust_component(installed, "rust-std", toolchain));
+        // nor is a longer component that starts with the same name
+        assert!(!has_rust_component(installed, "rust", toolchain));
+    }
+
+    #[test]
+    fn softwareupdate_only_command_line_tools() {
+        let installed =
+            parse_pkgutil_version(include_str!("../tests/fixtures/pkgutil-pkg-info.txt"));
+
+        assert_eq!(installed.as_deref(), Some("15.1.0.0.1.1700200546"));
+        assert_eq!(
+            parse_softwareupdate(
+                include_str!("../tests/fixtures/softwareupdate-list.txt"),
+                installed.as_deref()
+            ),
+            vec![pending(
+                "softwareupdate",
+                "Command Line Tools for Xcode-15.3",
+                Some("15.1.0.0.1.1700200546"),
+                Some("15.3"),
+                false
+            )]
+        );
+        assert!(parse_softwareupdate("No new software available.\n", None).is_empty());
+    }
+
+    #[test]
+    fn flatpak_updates_only_from_the_remotes() {
+        let output = include_str!("../tests/fixtures/flatpak-remote-ls.txt");
+
+        assert_eq!(
+            parse_flatpak_updates(output, &["flathub".to_string()]),
+            vec![
+                "app/org.mozilla.firefox/x86_64/stable",
+                "runtime/org.freedesktop.Platform.GL.default/x86_64/23.08",
+                "runtime/org.gnome.Platform/x86_64/45",
+            ]
+        );
+        assert_eq!(parse_flatpak_updates(output, &[]).len(), 4);
+    }
+
+    #[test]
+    fn old_kernels_keep_the_newest_and_the_running_one() {
+        assert_eq!(
+            parse_old_kernels(
+                include_str!("../tests/fixtures/dpkg-query-kernels.txt"),
+                "6.1.0-15-amd64",
+                2
+            ),
+            vec![
+                "linux-image-6.1.0-18-amd64",
+                "linux-image-6.1.0-17-amd64",
+                "pve-kernel-5.15.131-2-pve",
+            ]
+        );
+    }
+
+    #[test]
+    fn sizes_apt() {
+        assert_eq!(
+            parse_sizes(include_str!("../tests/fixtures/apt-get-upgrade.txt")),
+            Sizes {
+                download: Some(1_234_000),
+                disk_delta: Some(88_000_000),
+            }
+        );
+    }
+
+    #[test]
+    fn sizes_pacman() {
+        assert_eq!(
+            parse_sizes(include_str!("../tests/fixtures/pacman-syu.txt")),
+            Sizes {
+                download: Some(146_035_180),
+                disk_delta: Some(-1_572_864),
+            }
+        );
+    }
+
+    #[test]
+    fn sizes_dnf() {
+        assert_eq!(
+            parse_sizes(include_str!("../tests/fixtures/dnf4-upgrade.txt")),
+            Sizes {
+                download: Some(80 * 1024 * 1024),
+                disk_delta: None,
+            }
+        );
+        assert_eq!(
+            parse_sizes(include_str!("../tests/fixtures/dnf5-upgrade.txt")),
+            Sizes {
+                download: Some(2 * 1024 * 1024),
+                disk_delta: Some(12 * 1024),
+            }
+        );
+    }
+
+    #[test]
+    fn composer() {
+        assert_eq!(
+            parse_composer(include_str!("../tests/fixtures/composer-update.txt")),
+            vec![
+                ChangedPackage {
+                    package: String::from("psr/log"),
+                    from: Some(String::from("1.1.4")),
+                    to: None,
+                },
+                ChangedPackage {
+                    package: String::from("vimeo/psalm"),
+                    from: Some(String::from("5.1.0")),
+                    to: Some(String::from("5.2.0")),
+                },
+                ChangedPackage {
+                    package: String::from("phpunit/phpunit"),
+                    from: None,
+                    to: Some(String::from("10.5.0")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cargo_audit_skips_warnings() {
+        assert_eq!(
+            parse_cargo_audit(include_str!("../tests/fixtures/cargo-audit.json")),
+            vec![vulnerability(
+                "cargo audit",
+                "time",
+                "0.1.45",
+                "RUSTSEC-2020-0071"
+            )]
+        );
+    }
+
+    #[test]
+    fn pip_audit() {
+        assert_eq!(
+            parse_pip_audit(include_str!("../tests/fixtures/pip-audit.json")),
+            vec![
+                vulnerability("pip-audit", "django", "3.2.0", "PYSEC-2021-98"),
+                vulnerability("pip-audit", "django", "3.2.0", "PYSEC-2021-99"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pro_keeps_the_enabled_services() {
+        assert_eq!(
+            parse_pro_status(include_str!("../tests/fixtures/pro-status.json")),
+            Some(Pro {
+                attached: true,
+                services: vec![
+                    "esm-apps".to_string(),
+                    "esm-infra".to_string(),
+                    "livepatch".to_string()
+                ],
+                livepatch: None,
+            })
+        );
+        assert_eq!(
+            parse_livepatch_status(include_str!(
+                "../tests/fixtures/canonical-livepatch-status.json"
+            )),
+            Some("applied 99.1".to_string())
+        );
+    }
+
+    #[test]
+    fn deleted_libraries_skip_memory_mappings() {
+        assert_eq!(
+            parse_deleted_libraries(include_str!("../tests/fixtures/proc-maps.txt")),
+            vec!["/usr/lib/x86_64-linux-gnu/libssl.so.3"]
+        );
+    }
+
+    #[test]
+    fn occ_needs_upgrade() {
+        assert!(parse_occ_needs_upgrade(include_str!(
+            "../tests/fixtures/occ-status.json"
+        )));
+        assert!(!parse_occ_needs_upgrade("Nextcloud is not installed\n"));
+    }
+
+    #[test]
+    fn compose_recreated_skips_running_containers() {
+        assert_eq!(
+            parse_compose_recreated(include_str!("../tests/fixtures/docker-compose-up.txt")),
+            vec!["nextcloud-app-1", "nextcloud-cron-1"]
+        );
+    }
+
+    #[test]
+    fn kubernetes_versions() {
+        assert_eq!(
+            parse_kubernetes_version("k3s version v1.30.4+k3s1 (98262b5d)\ngo version go1.22.5\n")
+                .as_deref(),
+            Some("v1.30.4+k3s1")
+        );
+        assert_eq!(
+            parse_kubernetes_version("v1.30.4+k0s.0\n").as_deref(),
+            Some("v1.30.4+k0s.0")
+        );
+        assert_eq!(
+            parse_release_tag("https://github.com/k3s-io/k3s/releases/tag/v1.31.0+k3s1").as_deref(),
+            Some("v1.31.0+k3s1")
+        );
+        assert_eq!(parse_release_tag(""), None);
+    }
+
+    #[test]
+    fn disk_health() {
+        assert_eq!(
+            parse_smart_scan(include_str!("../tests/fixtures/smartctl-scan.txt")),
+            vec![
+                vec!["/dev/sda", "-d", "sat"],
+                vec!["/dev/nvme0", "-d", "nvme"]
+            ]
+        );
+        assert_eq!(
+            parse_smart_health(include_str!("../tests/fixtures/smartctl-h.txt")).as_deref(),
+            Some("PASSED")
+        );
+        assert_eq!(
+            parse_smart_health("SMART Health Status: OK\n").as_deref(),
+            Some("OK")
+        );
+        assert_eq!(
+            parse_scrub_status(include_str!("../tests/fixtures/btrfs-scrub-status.txt")).as_deref(),
+            Some("csum=2")
+        );
+        assert_eq!(parse_balance_status("No balance found on '/'\n"), None);
+    }
+
+    #[test]
+    fn notes_to_read_after_upgrading() {
+        let note = |manager, package: &str, lines: &[&str]| Note {
+            manager,
+            package: Some(package.to_string()),
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+        };
+
+        assert_eq!(
+            parse_brew_caveats(include_str!("../tests/fixtures/brew-upgrade-caveats.txt")),
+            vec![
+                note(
+                    "brew",
+                    "postgresql@16",
+                    &[
+                        "This formula has created a default database cluster with:",
+                        "  initdb --locale=C -E UTF-8 /opt/homebrew/var/postgresql@16",
+                        "To restart postgresql@16 after an upgrade:",
+                        "  brew services restart postgresql@16",
+                    ]
+                ),
+                note(
+                    "brew",
+                    "node",
+                    &[
+                        "Bash completion has been installed to:",
+                        "  /opt/homebrew/etc/bash_completion.d",
+                    ]
+                ),
+            ]
+        );
+        assert_eq!(
+            parse_pacman_messages(include_str!("../tests/fixtures/pacman-upgrade.txt")),
+            vec![
+                note(
+                    "pacman",
+                    "postgresql",
+                    &[
+                        "==> PostgreSQL was updated to a new major version (17).",
+                        "==> You need to dump and restore your databases, see the wiki.",
+                    ]
+                ),
+                note(
+                    "pacman",
+                    "pacman-mirrorlist",
+                    &["warning: /etc/pacman.d/mirrorlist installed as /etc/pacman.d/mirrorlist.pacnew"]
+                ),
+            ]
+        );
+        assert_eq!(
+            parse_apt_news(include_str!("../tests/fixtures/apt-listchanges-news.txt")),
+            vec![note(
+                "apt",
+                "openssh",
+                &[
+                    "OpenSSH 9.7 no longer accepts DSA keys, move to Ed25519 keys before",
+                    "upgrading.",
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn debian_changelog_since_the_installed_version() {
+        let changelog = include_str!("../tests/fixtures/apt-changelog.txt");
+
+        assert_eq!(
+            parse_debian_changelog(changelog, Some("3.0.13-1~deb12u1")),
+            vec![
+                "Fix CVE-2024-5535: SSL_select_next_proto buffer overread.",
+                "Fix CVE-2024-4741: use after free in SSL_free_buffers.",
+            ]
+        );
+        assert_eq!(parse_debian_changelog(changelog, None).len(), 5);
+    }
+
+    #[test]
+    fn pipe_status_of_the_last_failed_pipeline() {
+        assert_eq!(
+            parse_pipe_status(
+                "curl: (6) Could not resolve host\n\
+                 up2date: pipeline exit codes 6 0\n\
+                 grep: missing.txt: No such file\n\
+                 up2date: pipeline exit codes 0 2 0\n"
+            ),
+            vec![0, 2, 0]
+        );
+        assert_eq!(parse_pipe_status("E: Unable to locate package\n"), vec![]);
+    }
+
+    #[test]
+    fn cgroup_service_only_system_services() {
+        assert_eq!(
+            parse_cgroup_service("0::/system.slice/ssh.service\n").as_deref(),
+            Some("ssh.service")
+        );
+        assert_eq!(
+            parse_cgroup_service("0::/user.slice/user-1000.slice/user@1000.service/app.slice\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn table_drops_the_spinner() {
+        let rows = parse_table(
+            include_str!("../tests/fixtures/winget-upgrade.txt"),
+            &["Name", "Id", "Version", "Available"],
+        );
+
+        assert_eq!(
+            rows[0],
+            vec!["Git", "Git.Git", "2.43.0", "2.45.1        winget"]
+        );
+        assert_eq!(rows[1][0], "Microsoft PowerToys (Pre…");
+    }
+
+    #[test]
+    fn table_without_its_header() {
+        assert!(parse_table("No installed package found", &["Name", "Id"]).is_empty());
+    }
+
+    #[test]
+    fn winget_marks_pins() {
+        assert_eq!(
+            parse_winget(
+                include_str!("../tests/fixtures/winget-upgrade.txt"),
+                include_str!("../tests/fixtures/winget-pin-list.txt")
+            ),
+            vec![
+                outdated("Git.Git", "2.43.0", "2.45.1", true),
+                outdated("Microsoft.PowerToys", "0.79.0", "0.81.0", false),
+                outdated("7zip.7zip", "23.01", "24.05", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn choco() {
+        assert_eq!(
+            parse_choco(include_str!("../tests/fixtures/choco-outdated.txt")),
+            vec![
+                outdated("7zip", "23.1.0", "24.5.0", false),
+                outdated("git", "2.43.0", "2.45.1", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn scoop_marks_held() {
+        assert_eq!(
+            parse_scoop(include_str!("../tests/fixtures/scoop-status.txt")),
+            vec![
+                outdated("nodejs", "21.6.1", "21.7.1", false),
+                outdated("python", "3.12.1", "3.12.2", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_to_do_only_without_changes() {
+        assert!(is_nothing_to_do(""));
+        assert!(is_nothing_to_do(
+            "0 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.\n"
+        ));
+        assert!(is_nothing_to_do(
+            ":: Starting full system upgrade...\n there is nothing to do\n"
+        ));
+        assert!(is_nothing_to_do(
+            "  stable-x86_64-unknown-linux-gnu unchanged - rustc 1.77.0 (aedd173a2 2024-03-17)\n"
+        ));
+        assert!(!is_nothing_to_do(include_str!(
+            "../tests/fixtures/apt-get-upgrade.txt"
+        )));
+        assert!(!is_nothing_to_do(include_str!(
+            "../tests/fixtures/pacman-syu.txt"
+        )));
+        assert!(!is_nothing_to_do(
+            "  stable-x86_64-unknown-linux-gnu updated - rustc 1.77.1 (7cf61ebde 2024-03-27)\n  nightly-x86_64-unknown-linux-gnu unchanged - rustc 1.79.0-nightly\n"
+        ));
+    }
+
+    #[test]
+    fn unattended_upgrades_only_the_last_run() {
+        let log = include_str!("../tests/fixtures/unattended-upgrades.log");
+        let second_run = &log[..log.find("2024-03-06").unwrap()];
+
+        assert!(parse_unattended_upgrades(log).is_empty());
+        assert_eq!(
+            parse_unattended_upgrades(second_run),
+            vec!["libexpat1", "tzdata"]
+        );
+    }
+
+    #[test]
+    fn snapshots_only_up2dates() {
+        let ids = |snapshots: Vec<Snapshot>| -> Vec<String> {
+            snapshots.into_iter().map(|snapshot| snapshot.id).collect()
+        };
+
+        assert_eq!(
+            ids(parse_snapper(include_str!(
+                "../tests/fixtures/snapper-list.txt"
+            ))),
+            vec!["42"]
+        );
+        assert_eq!(
+            ids(parse_timeshift(include_str!(
+                "../tests/fixtures/timeshift-list.txt"
+            ))),
+            vec!["2024-01-09_10-00-01"]
+        );
+        assert_eq!(
+            ids(parse_zfs(include_str!("../tests/fixtures/zfs-list.txt"))),
+            vec!["rpool/ROOT/ubuntu@up2date-1704794401"]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use scuttle::App;
+use serde_json::Value;
+
+use crate::parsers;
+use crate::runner::Step;
+
+/// How a producer's output is turned into the items a consumer gets
+pub enum Parser {
+    /// Every line that isn't empty
+    Lines,
+    /// One whitespace separated field of every line, after skipping header lines
+    Field { index: usize, skip: usize },
+    /// Items picked out of output that's JSON, nothing when it isn't
+    Json(fn(&Value) -> Vec<String>),
+    /// Anything the other parsers can't handle
+    Custom(fn(&str) -> Vec<String>),
+}
+
+impl Parser {
+    /// Find the items in everything a producer printed to stdout
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - What the producer printed
+    pub fn parse(&self, output: &str) -> Vec<String> {
+        match self {
+            Parser::Lines => parsers::parse_lines(output),
+            Parser::Field { index, skip } => output
+                .lines()
+                .skip(*skip)
+                .filter_map(|line| line.split_whitespace().nth(*index))
+                .map(String::from)
+                .collect(),
+            Parser::Json(pick) => match serde_json::from_str(output) {
+                Ok(json) => pick(&json),
+                Err(_) => Vec::new(),
+            },
+            Parser::Custom(parse) => parse(output),
+        }
+    }
+}
+
+/// How the consumer is run with the items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feed {
+    /// Once, with every item added to the end of its arguments
+    Args,
+    /// Once per item, with `{}` in its arguments replaced by the item
+    Each,
+}
+
+/// A producer command whose parsed output drives a consumer command, e.g. the orphans
+/// `pacman -Qtdq` lists removed by `pacman -Rns`, or each tool `dotnet tool list -g`
+/// lists updated with `dotnet tool update -g {}`
+///
+/// Nothing runs when the producer finds no items.
+pub struct Pipeline {
+    /// Lists the items, it's run quietly and isn't part of the report
+    pub producer: App,
+    pub parser: Parser,
+    /// The step that's run with the items
+    pub consumer: Step,
+    pub feed: Feed,
+}
+
+impl Pipeline {
+    /// Build the steps the consumer runs as, for what the producer printed
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - What the producer printed to stdout
+    pub fn consumers(&self, output: &str) -> Vec<Step> {
+        let items = self.parser.parse(output);
+
+        if items.is_empty() {
+            return Vec::new();
+        }
+
+        let step = |args: Vec<String>| Step {
+            app: App {
+                command: self.consumer.app.command.clone(),
+                args,
+            },
+            ask: self.consumer.ask,
+            required: self.consumer.required,
+        };
+
+        match self.feed {
+            Feed::Args => vec![step([&self.consumer.app.args[..], &items[..]].concat())],
+            Feed::Each => items
+                .iter()
+                .map(|item| {
+                    step(
+                        self.consumer
+                            .app
+                            .args
+                            .iter()
+                            .map(|arg| arg.replace("{}", item))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
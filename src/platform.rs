@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::Path;
+
+use scuttle::App;
+
+use crate::detect::{self, has_binary};
+use crate::runner::Step;
+
+/// How a system whose root is read-only is updated, as a whole image instead of package
+/// by package
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Immutable {
+    /// Fedora Silverblue, Kinoite and the other Atomic desktops, and CoreOS
+    RpmOstree,
+    /// openSUSE MicroOS and Aeon, snapshots updated with zypper inside
+    TransactionalUpdate,
+    /// SteamOS on the Steam Deck
+    SteamOs,
+}
+
+/// Whether `/` is mounted read-only, the last mount on it being the one that counts
+fn root_read_only() -> bool {
+    let mounts = match fs::read_to_string("/proc/mounts") {
+        Ok(mounts) => mounts,
+        Err(_) => return false,
+    };
+
+    mounts
+        .lines()
+        .filter_map(
+            |line| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [_, "/", _, options, ..] => Some(options),
+                _ => None,
+            },
+        )
+        .next_back()
+        .is_some_and(|options| options.split(',').any(|option| option == "ro"))
+}
+
+/// Build a step that replaces the system image, which stops the run when it fails
+fn image_step(command_line: &[&str]) -> Step {
+    Step {
+        app: App {
+            command: command_line[0].to_string(),
+            args: command_line[1..]
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect(),
+        },
+        ask: false,
+        required: true,
+    }
+}
+
+impl Immutable {
+    /// What updates the image, e.g. `rpm-ostree`
+    pub fn name(self) -> &'static str {
+        match self {
+            Immutable::RpmOstree => "rpm-ostree",
+            Immutable::TransactionalUpdate => "transactional-update",
+            Immutable::SteamOs => "steamos-update",
+        }
+    }
+
+    /// The step that stages the new image for the next boot, `None` when it can't be
+    /// staged without the network
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh` - Whether to fetch what's new, otherwise only what was downloaded with
+    ///   `up2date fetch` is used
+    pub fn update_step(self, refresh: bool) -> Option<Step> {
+        match (self, refresh) {
+            (Immutable::RpmOstree, true) => Some(image_step(&["rpm-ostree", "upgrade"])),
+            (Immutable::RpmOstree, false) => {
+                Some(image_step(&["rpm-ostree", "upgrade", "--cache-only"]))
+            }
+            (Immutable::TransactionalUpdate, true) => Some(image_step(&[
+                "sudo",
+                "transactional-update",
+                "--non-interactive",
+                "dup",
+            ])),
+            // it gets root through polkit itself, the way the Steam client runs it
+            (Immutable::SteamOs, true) => Some(image_step(&["steamos-update"])),
+            (_, false) => None,
+        }
+    }
+
+    /// Whether there's a newer image to stage, as far as the updater can say without
+    /// staging it
+    ///
+    /// `steamos-update check` exits with 7 when SteamOS is up to date, the other updaters
+    /// work that out for themselves.
+    pub fn has_update(self) -> bool {
+        if self != Immutable::SteamOs {
+            return true;
+        }
+
+        match scuttle::run_output(&App {
+            command: String::from("steamos-update"),
+            args: vec!["check".to_string()],
+        }) {
+            Ok(output) => output.status.code() != Some(7),
+            Err(_) => true,
+        }
+    }
+
+    /// The package manager that's still installed but whose changes the next image throws
+    /// away, so it's left alone
+    pub fn read_only_manager(self) -> Option<App> {
+        match self {
+            Immutable::SteamOs if has_binary("pacman") => Some(App {
+                command: String::from("sudo"),
+                args: vec![
+                    "pacman".to_string(),
+                    "--noconfirm".to_string(),
+                    "-Syu".to_string(),
+                ],
+            }),
+            _ => None,
+        }
+    }
+
+    /// The step that downloads the new image without staging it, `None` when there's no
+    /// way to
+    pub fn download_step(self) -> Option<Step> {
+        match self {
+            Immutable::RpmOstree => Some(image_step(&["rpm-ostree", "upgrade", "--download-only"])),
+            _ => None,
+        }
+    }
+}
+
+/// How the system is updated when its root is read-only, `None` when the package manager
+/// can change it directly
+///
+/// With shims only the distribution id is looked at, nothing on this machine.
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+pub fn immutable(release: Option<&str>) -> Option<Immutable> {
+    let real = detect::shims().is_none();
+
+    match release {
+        Some("steamos") => Some(Immutable::SteamOs),
+        Some("opensuse-microos") | Some("opensuse-aeon") => Some(Immutable::TransactionalUpdate),
+        _ if real && Path::new("/run/ostree-booted").exists() && has_binary("rpm-ostree") => {
+            Some(Immutable::RpmOstree)
+        }
+        _ if real && root_read_only() && has_binary("transactional-update") => {
+            Some(Immutable::TransactionalUpdate)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,131 @@
+use std::process::{Command, Stdio};
+
+use scuttle::App;
+use serde::Deserialize;
+
+use crate::batch;
+use crate::config::{self, Group};
+use crate::detect;
+use crate::runner::Step;
+
+/// What every plugin's name starts with on `PATH`
+const PREFIX: &str = "up2date-plugin-";
+
+/// An `up2date-plugin-*` executable that found something to update on this machine
+pub struct Plugin {
+    pub binary: String,
+    /// The group its steps are run in
+    pub group: Group,
+}
+
+/// What a plugin prints for `detect`
+#[derive(Debug, Deserialize)]
+struct Detected {
+    detected: bool,
+    #[serde(default)]
+    group: Option<Group>,
+}
+
+/// Run a plugin with one of the protocol's commands and return what it printed
+///
+/// The plugin is told the OS and the Linux distribution id in `UP2DATE_OS` and
+/// `UP2DATE_RELEASE` so it doesn't have to work them out again.
+///
+/// # Arguments
+///
+/// * `binary` - The plugin's executable
+/// * `command` - `detect` or `plan`
+/// * `release` - The Linux distribution id, `None` on other platforms
+fn query(binary: &str, command: &str, release: Option<&str>) -> Result<String, String> {
+    let output = Command::new(binary)
+        .arg(command)
+        .env("UP2DATE_OS", detect::os())
+        .env("UP2DATE_RELEASE", release.unwrap_or(""))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| error.to_string())?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Find the plugins on `PATH` and ask each one whether it has anything to update here
+///
+/// A plugin answers `detect` with `{"detected": true, "group": "system"}` on stdout, the
+/// group being `system` when it's left out. Plugins that fail or print something else
+/// are warned about and left out.
+///
+/// # Arguments
+///
+/// * `settings` - The plugin settings from the config
+/// * `release` - The Linux distribution id, `None` on other platforms
+pub fn discover(settings: &config::Plugins, release: Option<&str>) -> Vec<Plugin> {
+    let mut plugins: Vec<Plugin> = Vec::new();
+
+    for binary in detect::binaries_starting_with(PREFIX) {
+        // skipped by the name after the prefix, e.g. `proxmox` for `up2date-plugin-proxmox`
+        if settings
+            .skip
+            .iter()
+            .any(|name| binary[PREFIX.len()..] == *name)
+        {
+            continue;
+        }
+
+        let detected = query(&binary, "detect", release).and_then(|output| {
+            serde_json::from_str::<Detected>(&output).map_err(|error| error.to_string())
+        });
+
+        match detected {
+            Ok(Detected {
+                detected: true,
+                group,
+            }) => plugins.push(Plugin {
+                binary,
+                group: group.unwrap_or(Group::System),
+            }),
+            Ok(_) => {}
+            Err(error) => eprintln!("WARNING: {} detect failed: {}", binary, error),
+        }
+    }
+
+    plugins
+}
+
+/// Ask a plugin for the steps it would like run
+///
+/// A plugin answers `plan` with the same list of apps `up2date run --stdin` takes. When
+/// it prints nothing at all `<plugin> run` is its only step, for plugins that would
+/// rather do the updating themselves.
+///
+/// # Arguments
+///
+/// * `plugin` - The plugin to ask
+/// * `release` - The Linux distribution id, `None` on other platforms
+pub fn steps(plugin: &Plugin, release: Option<&str>) -> Vec<Step> {
+    let planned = query(&plugin.binary, "plan", release).and_then(|output| match output.trim() {
+        "" => Ok(vec![Step {
+            app: App {
+                command: plugin.binary.clone(),
+                args: vec!["run".to_string()],
+            },
+            ask: false,
+            required: false,
+        }]),
+        output => batch::parse(output).map_err(|error| match error {
+            batch::Error::Parse(error) => error,
+            error => error.to_string(),
+        }),
+    });
+
+    match planned {
+        Ok(steps) => steps,
+        Err(error) => {
+            eprintln!("WARNING: {} plan failed: {}", plugin.binary, error);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::config;
+use crate::i18n::tr;
+use crate::preview::PendingUpdate;
+use crate::secrets;
+
+/// Whether an entry of `[policy] allow` or `deny` is about an update: `name` covers every
+/// version of the package, `name@version` only updating it to that version
+///
+/// # Arguments
+///
+/// * `entry` - The entry from the config
+/// * `update` - The pending update
+fn matches(entry: &str, update: &PendingUpdate) -> bool {
+    match entry.split_once('@') {
+        Some((package, version)) => {
+            update.package == package && update.to.as_deref() == Some(version)
+        }
+        None => update.package == entry,
+    }
+}
+
+/// Ask the policy script about the pending updates
+///
+/// The script gets the same list `up2date check --json` prints under `pending` on stdin
+/// and lets the updates through by exiting with 0. Anything else blocks the run, with
+/// what it printed as the reason.
+///
+/// # Arguments
+///
+/// * `script` - The script to run, `~/` being the home directory
+/// * `pending` - Every pending update
+fn ask(script: &Path, pending: &[PendingUpdate]) -> Result<(), String> {
+    let json = serde_json::to_string(pending).map_err(|error| error.to_string())?;
+    let mut child = Command::new(secrets::expand(script))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(json.as_bytes())
+            .map_err(|error| error.to_string())?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    let reason = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    match (output.status.success(), output.status.code()) {
+        (true, _) => Ok(()),
+        (false, _) if !reason.is_empty() => Err(reason),
+        (false, Some(code)) => Err(format!("exit code {}", code)),
+        (false, None) => Err(String::from("killed by a signal")),
+    }
+}
+
+/// Why the pending updates can't be applied, nothing when `[policy]` lets all of them
+/// through
+///
+/// # Arguments
+///
+/// * `policy` - The policy from the config
+/// * `pending` - Every pending update
+pub fn check(policy: &config::Policy, pending: &[PendingUpdate]) -> Vec<String> {
+    let mut blocked: Vec<String> = Vec::new();
+
+    for update in pending.iter() {
+        let version = update.to.as_deref().unwrap_or("");
+
+        if policy.deny.iter().any(|entry| matches(entry, update)) {
+            blocked.push(tr(
+                "policy-denied",
+                &[("package", &update.package), ("version", &version)],
+            ));
+        } else if !policy.allow.is_empty()
+            && !policy.allow.iter().any(|entry| matches(entry, update))
+        {
+            blocked.push(tr(
+                "policy-not-allowed",
+                &[("package", &update.package), ("version", &version)],
+            ));
+        }
+    }
+
+    if let Some(script) = policy.script.as_deref() {
+        if let Err(reason) = ask(script, pending) {
+            blocked.push(tr(
+                "policy-script-blocked",
+                &[("script", &script.display()), ("reason", &reason)],
+            ));
+        }
+    }
+
+    blocked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(package: &str, to: &str) -> PendingUpdate {
+        PendingUpdate {
+            manager: "apt",
+            package: package.to_string(),
+            from: Some(String::from("1.0")),
+            to: Some(to.to_string()),
+            security: false,
+        }
+    }
+
+    fn policy(allow: &[&str], deny: &[&str]) -> config::Policy {
+        config::Policy {
+            allow: allow.iter().map(|entry| entry.to_string()).collect(),
+            deny: deny.iter().map(|entry| entry.to_string()).collect(),
+            script: None,
+        }
+    }
+
+    #[test]
+    fn entry_matches_every_version_or_only_one() {
+        assert!(matches("curl", &update("curl", "8.5")));
+        assert!(matches("curl@8.5", &update("curl", "8.5")));
+        assert!(!matches("curl@8.6", &update("curl", "8.5")));
+        assert!(!matches("curl", &update("libcurl4", "8.5")));
+    }
+
+    #[test]
+    fn entry_with_a_version_never_matches_a_removal() {
+        let removal = PendingUpdate {
+            to: None,
+            ..update("curl", "8.5")
+        };
+
+        assert!(matches("curl", &removal));
+        assert!(!matches("curl@8.5", &removal));
+    }
+
+    #[test]
+    fn empty_policy_lets_everything_through() {
+        assert!(check(&policy(&[], &[]), &[update("curl", "8.5")]).is_empty());
+    }
+
+    #[test]
+    fn deny_blocks_only_what_it_lists() {
+        let pending = [update("curl", "8.5"), update("linux-image", "6.8")];
+        let blocked = check(&policy(&[], &["linux-image"]), &pending);
+
+        assert_eq!(blocked.len(), 1);
+        assert!(blocked[0].contains("linux-image"));
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let blocked = check(&policy(&["curl"], &["curl@8.5"]), &[update("curl", "8.5")]);
+
+        assert_eq!(blocked.len(), 1);
+        assert!(blocked[0].contains("curl"));
+    }
+
+    #[test]
+    fn allow_blocks_everything_it_leaves_out() {
+        let pending = [
+            update("curl", "8.5"),
+            update("openssl", "3.1"),
+            update("openssl", "3.2"),
+        ];
+        let blocked = check(&policy(&["curl", "openssl@3.2"], &[]), &pending);
+
+        assert_eq!(blocked.len(), 1);
+        assert!(blocked[0].contains("openssl") && blocked[0].contains("3.1"));
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use scuttle::App;
+use serde::Serialize;
+
+use crate::changelog::{self, Changelog};
+use crate::clt;
+use crate::config;
+use crate::detect::{self, has_binary};
+use crate::i18n::tr;
+use crate::k3s;
+use crate::parsers;
+use crate::report;
+use crate::runner::Runner;
+
+/// A single change a package manager intends to make
+///
+/// Every backend's simulation is parsed into these so the preview, `up2date check` and
+/// its notifications all work from the same list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingUpdate {
+    pub manager: &'static str,
+    pub package: String,
+    /// The installed version, `None` when the package is new
+    pub from: Option<String>,
+    /// The version that will be installed, `None` when the package is being removed
+    pub to: Option<String>,
+    /// Whether the update comes from a security advisory or repository, as far as the
+    /// manager says
+    pub security: bool,
+}
+
+/// Everything the package managers intend to change
+#[derive(Debug, Default, Serialize)]
+pub struct Preview {
+    pub pending: Vec<PendingUpdate>,
+    /// Bytes each manager will download, for managers that report it
+    pub downloads: Vec<(&'static str, u64)>,
+    /// What's in the updates of the packages `[changelogs]` lists
+    pub changelogs: Vec<Changelog>,
+}
+
+/// Build an App from a command line
+fn app(command_line: &[&str]) -> App {
+    App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    }
+}
+
+/// Run a check command and return its stdout
+///
+/// Check commands often exit non-zero just to say there is nothing to do so only a
+/// failure to run the command at all is treated as an error.
+fn check_output(command_line: &[&str]) -> String {
+    match scuttle::run_output(&app(command_line)) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(error) => panic!("panic:{}", error),
+    }
+}
+
+/// Refresh a manager's package lists, through the runner so it's shown and counted or
+/// quietly when there isn't one
+///
+/// # Arguments
+///
+/// * `runner` - The runner to refresh through, `None` to refresh quietly
+/// * `command_line` - The refresh command
+fn refresh(runner: &mut Option<&mut Runner>, command_line: &[&str]) {
+    match runner {
+        Some(runner) => runner.run_apps(&[app(command_line)]),
+        None => {
+            check_output(command_line);
+        }
+    }
+}
+
+/// Run the check and simulate commands for every manager on this system
+///
+/// Package lists are refreshed first where the manager needs it, nothing is installed
+/// or removed.
+///
+/// # Arguments
+///
+/// * `runner` - The runner used for refreshing the package lists, `None` to refresh
+///   them without printing anything, e.g. when the result is printed as JSON
+/// * `config` - The config, for the `[kubernetes]` settings and the binaries to check with
+/// * `release` - The Linux distribution id, `None` on other platforms
+pub fn gather(
+    mut runner: Option<&mut Runner>,
+    config: &config::Config,
+    release: Option<&str>,
+) -> Preview {
+    let mut preview = Preview::default();
+
+    match release {
+        Some("ubuntu") | Some("pop") | Some("debian") => {
+            let apt = config.apt.executable.binary("apt-get");
+            let update = config
+                .apt
+                .executable
+                .command_line("apt-get", &["update".to_string()]);
+
+            refresh(
+                &mut runner,
+                &std::iter::once("sudo")
+                    .chain(update.iter().map(String::as_str))
+                    .collect::<Vec<&str>>(),
+            );
+
+            let simulation = check_output(&[&apt, "-s", "upgrade", "--with-new-pkgs"]);
+            let uris = check_output(&[&apt, "-qq", "--print-uris", "upgrade", "--with-new-pkgs"]);
+
+            preview.pending.extend(parsers::parse_apt(&simulation));
+            preview
+                .downloads
+                .push(("apt", parsers::parse_apt_uris(&uris)));
+        }
+        Some("arch") | Some("endeavouros") => {
+            // checkupdates syncs a temporary copy of the database, pacman -Qu can only
+            // report on whatever was synced last
+            let pacman = if has_binary("checkupdates") {
+                check_output(&["checkupdates"])
+            } else {
+                check_output(&[&config.pacman.executable.binary("pacman"), "-Qu"])
+            };
+
+            preview
+                .pending
+                .extend(parsers::parse_arrows("pacman", &pacman));
+
+            if has_binary("yay") {
+                preview.pending.extend(parsers::parse_arrows(
+                    "yay",
+                    &check_output(&[&config.yay.executable.binary("yay"), "-Qua"]),
+                ));
+            }
+        }
+        Some("fedora") | Some("rhel") | Some("centos") | Some("rocky") | Some("almalinux") => {
+            // --assumeno refreshes the metadata when it's stale and answers no
+            let simulation = check_output(&["sudo", "dnf", "--assumeno", "upgrade"]);
+            let installed = parsers::parse_rpm(&check_output(&[
+                "rpm",
+                "-qa",
+                "--queryformat",
+                "%{NAME} %{VERSION}-%{RELEASE}\n",
+            ]));
+
+            let mut pending = parsers::parse_dnf(&simulation, &installed);
+
+            parsers::mark_dnf_security(
+                &mut pending,
+                &check_output(&["dnf", "updateinfo", "list", "--security", "--quiet"]),
+            );
+            preview.pending.extend(pending);
+        }
+        _ => {}
+    }
+
+    if detect::os() == "linux" {
+        preview.pending.extend(k3s::pending(&config.kubernetes));
+    }
+
+    if detect::os() == "macos" {
+        let brew = config.brew.executable.binary("brew");
+
+        refresh(&mut runner, &[&brew, "update"]);
+
+        preview.pending.extend(parsers::parse_brew(&check_output(&[
+            &brew,
+            "outdated",
+            "--json=v2",
+        ])));
+        preview.pending.extend(clt::pending());
+    }
+
+    if has_binary("rustup") {
+        preview
+            .pending
+            .extend(parsers::parse_rustup(&check_output(&["rustup", "check"])));
+    }
+
+    preview.changelogs = changelog::gather(&preview.pending, config);
+    preview
+}
+
+/// The file the scheduled checks leave the number of pending updates in
+pub fn pending_path() -> Option<PathBuf> {
+    Some(config::state_dir()?.join("pending.json"))
+}
+
+/// What the last check found, as `pending.json` holds it
+#[derive(Serialize)]
+struct Checked {
+    /// When the check ran, in seconds since the Unix epoch
+    checked: u64,
+    pending: usize,
+    security: usize,
+    /// How many of the pending updates each manager has
+    managers: BTreeMap<&'static str, usize>,
+}
+
+/// Remember how many updates are pending for `up2date serve` and the MQTT sensors
+///
+/// # Arguments
+///
+/// * `preview` - What the check found
+pub fn record(preview: &Preview) -> io::Result<()> {
+    let path = match pending_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let checked = Checked {
+        checked: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+        pending: preview.pending.len(),
+        security: preview
+            .pending
+            .iter()
+            .filter(|update| update.security)
+            .count(),
+        managers: preview
+            .pending
+            .iter()
+            .fold(BTreeMap::new(), |mut managers, update| {
+                *managers.entry(update.manager).or_insert(0) += 1;
+                managers
+            }),
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, serde_json::to_string(&checked)? + "\n")
+}
+
+/// Print one merged list of everything the package managers intend to change
+pub fn print(preview: &Preview) {
+    println!();
+    println!("========================");
+    println!("{}", tr("preview-title", &[]));
+    println!("========================");
+
+    if preview.pending.is_empty() {
+        println!("{}", tr("preview-nothing", &[]));
+    }
+
+    let manager_width = preview
+        .pending
+        .iter()
+        .map(|pending| pending.manager.len())
+        .max()
+        .unwrap_or(0);
+    let package_width = preview
+        .pending
+        .iter()
+        .map(|pending| pending.package.len())
+        .max()
+        .unwrap_or(0);
+
+    for pending in preview.pending.iter() {
+        println!(
+            "{:manager_width$}  {:package_width$}  {} -> {}",
+            pending.manager,
+            pending.package,
+            pending
+                .from
+                .clone()
+                .unwrap_or_else(|| tr("package-new", &[])),
+            pending
+                .to
+                .clone()
+                .unwrap_or_else(|| tr("package-removed", &[])),
+            manager_width = manager_width,
+            package_width = package_width,
+        );
+    }
+
+    for (manager, bytes) in preview.downloads.iter() {
+        if *bytes > 0 {
+            println!(
+                "{}",
+                tr(
+                    "preview-download",
+                    &[("manager", manager), ("size", &report::format_size(*bytes)),]
+                )
+            );
+        }
+    }
+
+    for changelog in preview.changelogs.iter() {
+        println!();
+        println!(
+            "{}",
+            tr(
+                "preview-changelog",
+                &[
+                    ("package", &changelog.package),
+                    ("to", &changelog.to.as_deref().unwrap_or_default())
+                ]
+            )
+        );
+
+        for change in changelog.changes.iter() {
+            println!("    {}", change);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use scuttle::App;
+
+use crate::detect::has_binary;
+use crate::parsers;
+use crate::report::Pro;
+
+/// Run a status command and return its stdout, `None` when it can't be run
+fn status_output(command: &str, args: &[&str]) -> Option<String> {
+    let output = scuttle::run_output(&App {
+        command: command.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+    })
+    .ok()?;
+
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// What Ubuntu Pro covers on this machine and what livepatch has applied, `None`
+/// when the Pro client isn't installed
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+pub fn status(release: Option<&str>) -> Option<Pro> {
+    if release != Some("ubuntu") || !has_binary("pro") {
+        return None;
+    }
+
+    let mut pro =
+        parsers::parse_pro_status(&status_output("pro", &["status", "--format", "json"])?)?;
+
+    // the livepatch client only answers once the service is turned on
+    if pro.services.iter().any(|service| service == "livepatch")
+        && has_binary("canonical-livepatch")
+    {
+        pro.livepatch = status_output("canonical-livepatch", &["status", "--format", "json"])
+            .and_then(|output| parsers::parse_livepatch_status(&output));
+    }
+
+    Some(pro)
+}
@@ -0,0 +1,321 @@
+use crate::history;
+use crate::i18n::tr;
+use crate::report::{Block, Report};
+
+/// How the HTML report looks, kept in the file so it can be opened or sent on its own
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; }
+h1 { margin-bottom: 0; }
+.meta { color: #666; margin-top: 0; }
+.detail { margin: 0 0 0 1.5rem; color: #a00; }
+pre { background: #f4f4f4; padding: 0.5rem 1rem; overflow-x: auto; }
+table { border-collapse: collapse; margin: 0.5rem 0 1rem; }
+th, td { text-align: left; padding: 0.2rem 1rem 0.2rem 0; border-bottom: 1px solid #ddd; }
+td { font-family: ui-monospace, monospace; }
+@media (prefers-color-scheme: dark) {
+  body { background: #1e1e1e; color: #ddd; }
+  pre { background: #2a2a2a; }
+  .meta { color: #999; }
+  .detail { color: #f77; }
+  th, td { border-color: #444; }
+}
+";
+
+/// Escape text for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape a line of the summary for HTML, the commands it quotes in backticks as code
+fn inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(index, part)| match index % 2 {
+            1 => format!("<code>{}</code>", escape(part)),
+            _ => escape(part),
+        })
+        .collect()
+}
+
+/// Escape a line of the summary for Markdown, leaving what it quotes in backticks as code
+fn markdown_inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(index, part)| match index % 2 {
+            1 => format!("`{}`", part),
+            _ => part
+                .chars()
+                .flat_map(|c| match c {
+                    '\\' | '*' | '_' | '[' | ']' | '<' | '>' | '|' => vec!['\\', c],
+                    _ => vec![c],
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Where the run ran, when it started and how it ended, under the title
+fn meta(report: &Report) -> String {
+    tr(
+        "report-meta",
+        &[
+            ("host", &sys_info::hostname().unwrap_or_default()),
+            ("started", &history::format_date(report.started)),
+            ("outcome", &report.outcome.name()),
+        ],
+    )
+}
+
+/// The summary of a run as a self-contained HTML page, with each step's changed packages
+/// in a table and what the commands printed as it was printed
+///
+/// # Arguments
+///
+/// * `report` - The run's report
+pub fn html(report: &Report) -> String {
+    let mut body = String::new();
+    let mut list = false;
+
+    for block in report.summary() {
+        if list && !matches!(block, Block::Item(_)) {
+            body.push_str("</ul>\n");
+            list = false;
+        }
+
+        match block {
+            Block::Title(title) => {
+                body.push_str(&format!("<h1>{}</h1>\n", escape(&title)));
+                body.push_str(&format!(
+                    "<p class=\"meta\">{}</p>\n",
+                    escape(&meta(report))
+                ));
+            }
+            Block::Heading(heading) => body.push_str(&format!("<h2>{}</h2>\n", inline(&heading))),
+            Block::Line(line) => body.push_str(&format!("<p>{}</p>\n", inline(&line))),
+            Block::Detail(line) => {
+                body.push_str(&format!("<p class=\"detail\">{}</p>\n", inline(&line)))
+            }
+            Block::Item(item) => {
+                if !list {
+                    body.push_str("<ul>\n");
+                    list = true;
+                }
+
+                body.push_str(&format!("<li>{}</li>\n", inline(&item)));
+            }
+            Block::Output(lines) => {
+                body.push_str(&format!("<pre>{}</pre>\n", escape(&lines.join("\n"))))
+            }
+            Block::Changes(changes) => {
+                body.push_str(&format!(
+                    "<table>\n<tr><th>{}</th><th>{}</th><th>{}</th></tr>\n",
+                    escape(&tr("report-package", &[])),
+                    escape(&tr("report-from", &[])),
+                    escape(&tr("report-to", &[]))
+                ));
+
+                for change in changes.iter() {
+                    let (from, to) = change.versions();
+
+                    body.push_str(&format!(
+                        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                        escape(&change.package),
+                        escape(&from),
+                        escape(&to)
+                    ));
+                }
+
+                body.push_str("</table>\n");
+            }
+            Block::Break => {}
+        }
+    }
+
+    if list {
+        body.push_str("</ul>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>up2date: {}</title>\n\
+         <style>\n{}</style>\n\
+         </head>\n\
+         <body>\n{}</body>\n\
+         </html>\n",
+        escape(&meta(report)),
+        STYLE,
+        body
+    )
+}
+
+/// The summary of a run as a Markdown document for a wiki or a ticket, with each step's
+/// changed packages in a table and what the commands printed in code blocks
+///
+/// # Arguments
+///
+/// * `report` - The run's report
+pub fn markdown(report: &Report) -> String {
+    let mut document = String::new();
+    let mut list = false;
+
+    for block in report.summary() {
+        let item = matches!(block, Block::Item(_) | Block::Detail(_));
+
+        // a list ends with a blank line, only once it's done
+        if list && !item {
+            document.push('\n');
+        }
+
+        list = item;
+
+        match block {
+            Block::Title(title) => {
+                document.push_str(&format!("# {}\n\n", markdown_inline(&title)));
+                document.push_str(&format!("*{}*\n\n", markdown_inline(&meta(report))));
+            }
+            Block::Heading(heading) => {
+                document.push_str(&format!("## {}\n\n", markdown_inline(&heading)))
+            }
+            Block::Line(line) => document.push_str(&format!("{}\n\n", markdown_inline(&line))),
+            Block::Detail(line) | Block::Item(line) => {
+                document.push_str(&format!("- {}\n", markdown_inline(&line)))
+            }
+            Block::Output(lines) => {
+                // a fence longer than any run of backticks in the output
+                let fence = "`".repeat(
+                    lines
+                        .iter()
+                        .map(|line| line.split(|c| c != '`').map(str::len).max().unwrap_or(0))
+                        .max()
+                        .unwrap_or(0)
+                        .max(2)
+                        + 1,
+                );
+
+                document.push_str(&format!(
+                    "{}text\n{}\n{}\n\n",
+                    fence,
+                    lines.join("\n"),
+                    fence
+                ));
+            }
+            Block::Changes(changes) => {
+                let cell = |text: &str| text.replace('|', "\\|");
+
+                document.push_str(&format!(
+                    "| {} | {} | {} |\n|---|---|---|\n",
+                    tr("report-package", &[]),
+                    tr("report-from", &[]),
+                    tr("report-to", &[])
+                ));
+
+                for change in changes.iter() {
+                    let (from, to) = change.versions();
+
+                    document.push_str(&format!(
+                        "| {} | {} | {} |\n",
+                        cell(&change.package),
+                        cell(&from),
+                        cell(&to)
+                    ));
+                }
+
+                document.push('\n');
+            }
+            Block::Break => {}
+        }
+    }
+
+    format!("{}\n", document.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Note;
+
+    /// A report with a note that has markup in it
+    fn report() -> Report {
+        Report {
+            notes: vec![Note {
+                manager: "brew",
+                package: Some(String::from("<pkg>")),
+                lines: vec![
+                    String::from("if a < b && c > d then"),
+                    String::from("  run `echo \"hi\"`"),
+                ],
+            }],
+            ..Report::default()
+        }
+    }
+
+    #[test]
+    fn escape_escapes_markup() {
+        assert_eq!(
+            escape("<a href=\"x\">&amp;</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;amp;&lt;/a&gt;"
+        );
+        assert_eq!(escape("plain text"), "plain text");
+    }
+
+    #[test]
+    fn inline_quotes_backticks_as_code() {
+        assert_eq!(
+            inline("Skipping `sudo apt-get <update>`, it & more"),
+            "Skipping <code>sudo apt-get &lt;update&gt;</code>, it &amp; more"
+        );
+        // an unmatched backtick still opens a code span, nothing is left unescaped
+        assert_eq!(inline("a `<b>"), "a <code>&lt;b&gt;</code>");
+    }
+
+    #[test]
+    fn html_escapes_everything_commands_printed() {
+        let html = html(&report());
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<p>brew &lt;pkg&gt;:</p>"));
+        assert!(html.contains(
+            "<pre>if a &lt; b &amp;&amp; c &gt; d then\n  run `echo &quot;hi&quot;`</pre>"
+        ));
+        assert!(!html.contains("<pkg>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn markdown_inline_escapes_outside_code() {
+        assert_eq!(
+            markdown_inline("a *b* _c_ [d](e) <f> | g\\h"),
+            "a \\*b\\* \\_c\\_ \\[d\\](e) \\<f\\> \\| g\\\\h"
+        );
+        assert_eq!(
+            markdown_inline("Skipping `rm -rf *_old`, *really*"),
+            "Skipping `rm -rf *_old`, \\*really\\*"
+        );
+    }
+
+    #[test]
+    fn markdown_fences_output_longer_than_its_backticks() {
+        let document = markdown(&report());
+
+        assert!(document.contains("brew \\<pkg\\>:\n\n"));
+        assert!(document.contains("```text\nif a < b && c > d then\n  run `echo \"hi\"`\n```\n"));
+
+        let fenced = markdown(&Report {
+            notes: vec![Note {
+                manager: "pacman",
+                package: None,
+                lines: vec![String::from("```rust"), String::from("````")],
+            }],
+            ..Report::default()
+        });
+
+        assert!(fenced.contains("`````text\n```rust\n````\n`````\n"));
+        assert!(fenced.ends_with("`````\n"));
+    }
+}
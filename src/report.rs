@@ -0,0 +1,654 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::config::Group;
+use crate::i18n::tr;
+use crate::render;
+
+/// How much a step downloaded and how much it changed the disk usage by, as far as
+/// the package manager said so in its output
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Sizes {
+    pub download: Option<u64>,
+    pub disk_delta: Option<i64>,
+}
+
+/// A package that a step installed, upgraded or removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedPackage {
+    pub package: String,
+    /// The version before the step ran, `None` when the package is new
+    pub from: Option<String>,
+    /// The version after the step ran, `None` when the package was removed
+    pub to: Option<String>,
+}
+
+impl ChangedPackage {
+    /// The versions before and after, saying when the package is new or was removed
+    pub fn versions(&self) -> (String, String) {
+        (
+            self.from.clone().unwrap_or_else(|| tr("package-new", &[])),
+            self.to
+                .clone()
+                .unwrap_or_else(|| tr("package-removed", &[])),
+        )
+    }
+}
+
+/// A part of the summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// What the summary is
+    Title(String),
+    /// What starts a part of its own, a group's steps or the notes
+    Heading(String),
+    /// A line on its own
+    Line(String),
+    /// More about the line before, e.g. which command of a pipeline failed
+    Detail(String),
+    /// One of a list the line before introduces
+    Item(String),
+    /// What a command printed, as it printed it
+    Output(Vec<String>),
+    /// The packages a step changed
+    Changes(Vec<ChangedPackage>),
+    /// A gap before what follows the groups
+    Break,
+}
+
+/// A known vulnerability an audit tool found in something that's installed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Vulnerability {
+    /// The audit tool that found it
+    pub tool: &'static str,
+    pub package: String,
+    pub version: String,
+    /// The advisory id, e.g. `RUSTSEC-2020-0071`
+    pub advisory: String,
+}
+
+/// What the distribution's own automatic updates, e.g. unattended-upgrades, did the last
+/// time they ran
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Automatic {
+    pub service: &'static str,
+    /// When it last ran, in seconds since the Unix epoch, `None` if it hasn't yet
+    pub last_run: Option<u64>,
+    /// The packages it upgraded then, when its log could be read
+    pub upgraded: Vec<String>,
+}
+
+/// What Ubuntu Pro covers on the machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pro {
+    /// Whether the machine is attached to a subscription, nothing is covered otherwise
+    pub attached: bool,
+    /// The services that are turned on, e.g. `esm-infra` and `livepatch`
+    pub services: Vec<String>,
+    /// What livepatch did to the running kernel, e.g. `applied 99.1`, when it's on
+    pub livepatch: Option<String>,
+}
+
+/// How a disk or filesystem is doing, from the maintenance group's checks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Health {
+    /// What was checked, e.g. `SMART /dev/sda` or `btrfs scrub /`
+    pub check: String,
+    /// What the check said, e.g. `PASSED` or `no errors found`
+    pub status: String,
+    /// Whether that's nothing to worry about
+    pub ok: bool,
+}
+
+/// Something a package manager said to read once it had installed or upgraded a package,
+/// e.g. brew's caveats
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Note {
+    pub manager: &'static str,
+    /// The package it's about, when the manager said
+    pub package: Option<String>,
+    pub lines: Vec<String>,
+}
+
+/// What flatpak updated in one installation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Flatpaked {
+    /// `system` or `user`
+    pub installation: &'static str,
+    /// The ids of the apps that were updated
+    pub apps: Vec<String>,
+    /// The ids of the runtimes that were updated, extensions included
+    pub runtimes: Vec<String>,
+}
+
+/// The outcome of one command run as part of an update
+#[derive(Debug, Serialize)]
+pub struct StepResult {
+    pub command: String,
+    /// The command and each of its arguments exactly as they were passed to the process,
+    /// with only the credentials hidden
+    pub argv: Vec<String>,
+    /// The group the step belongs to, `None` for apps read from stdin and checks
+    pub group: Option<Group>,
+    pub success: bool,
+    /// How long the command took
+    pub seconds: f64,
+    /// The exit code, `None` if the command was killed by a signal
+    pub code: Option<i32>,
+    pub sizes: Sizes,
+    /// The end of what the command printed to stderr, only kept when it failed
+    pub stderr: Vec<String>,
+    /// The exit code of each command in the pipeline that failed, for shell steps run
+    /// with bash
+    pub pipeline: Vec<i32>,
+    /// The lines of its output that matched `[steps.<command>] highlight`
+    pub highlighted: Vec<String>,
+    /// The packages the step changed, for steps whose output is understood
+    pub changes: Vec<ChangedPackage>,
+}
+
+/// What the report file is written as
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The report as it is, for other tools, the default
+    #[default]
+    Json,
+    /// The summary as a Markdown document, for a wiki or a ticket
+    Markdown,
+    /// The summary as a self-contained HTML page
+    Html,
+}
+
+/// How a run ended
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// Everything that was meant to run did, even if some commands failed
+    #[default]
+    Completed,
+    /// The run was stopped early because a command failed
+    Aborted,
+    /// up2date itself panicked
+    Panicked,
+    /// The run was stopped with Ctrl-C
+    Interrupted,
+}
+
+impl Outcome {
+    /// How the run ended, in words
+    pub fn name(self) -> String {
+        match self {
+            Outcome::Completed => tr("outcome-completed", &[]),
+            Outcome::Aborted => tr("outcome-aborted", &[]),
+            Outcome::Panicked => tr("outcome-panicked", &[]),
+            Outcome::Interrupted => tr("outcome-interrupted", &[]),
+        }
+    }
+}
+
+/// Everything that happened during a run
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub outcome: Outcome,
+    /// When the run started, in seconds since the Unix epoch
+    pub started: u64,
+    pub steps: Vec<StepResult>,
+    /// The total download size of every step that reported one
+    pub downloaded: Option<u64>,
+    /// The total disk usage change of every step that reported one
+    pub disk_delta: Option<i64>,
+    /// Known vulnerabilities still installed after updating, when auditing is turned on
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// Services that were restarted because their packages were upgraded
+    pub restarted: Vec<String>,
+    /// Services still running outdated libraries that weren't restarted
+    pub needs_restart: Vec<String>,
+    /// Processes outside of any service still using replaced libraries, as `name (pid)`
+    pub stale_processes: Vec<String>,
+    /// The time zone, certificate and GeoIP packages the system upgrade changed
+    pub baseline: Vec<ChangedPackage>,
+    /// Compose containers that were recreated, most often for a newly pulled image
+    pub recreated: Vec<String>,
+    /// Upgrades that were skipped because the package is pinned or held
+    pub pinned: Vec<String>,
+    /// Packages apt kept back, because they're held or pinned or their dependencies changed
+    pub kept_back: Vec<String>,
+    /// Phased updates apt deferred until their phase reaches this machine
+    pub phased: Vec<String>,
+    /// Steps that were skipped because their frequency says they ran recently enough
+    pub not_due: Vec<String>,
+    /// What the automatic updates had already done, when they're turned on
+    pub automatic: Option<Automatic>,
+    /// What Ubuntu Pro covers, on Ubuntu when its client is installed
+    pub pro: Option<Pro>,
+    /// How the disks and filesystems are doing, when the maintenance group ran
+    pub health: Vec<Health>,
+    /// What flatpak updated, for each installation it updated something in
+    pub flatpak: Vec<Flatpaked>,
+    /// Problems found by the checks that run after updating
+    pub warnings: Vec<String>,
+    /// What the package managers said to read after upgrading, brew's caveats, messages
+    /// from pacman's install scripts and the news apt-listchanges showed
+    pub notes: Vec<Note>,
+    /// How long the system had been up when the run finished, in seconds
+    pub uptime: Option<u64>,
+    /// When a reboot was first found to be pending, in seconds since the Unix epoch
+    pub reboot_pending_since: Option<u64>,
+}
+
+impl Report {
+    /// Add the result of a step, including its sizes in the totals
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The result of the step that just ran
+    pub fn push(&mut self, step: StepResult) {
+        if let Some(download) = step.sizes.download {
+            self.downloaded = Some(self.downloaded.unwrap_or(0) + download);
+        }
+
+        if let Some(delta) = step.sizes.disk_delta {
+            self.disk_delta = Some(self.disk_delta.unwrap_or(0) + delta);
+        }
+
+        self.steps.push(step);
+    }
+
+    /// Write the report as JSON, or its summary as a document
+    ///
+    /// It's written next to `path` first and moved into place so readers never see a
+    /// half written file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Where to write the report
+    /// * `format` - What to write it as
+    pub fn write(&self, path: &Path, format: Format) -> io::Result<()> {
+        let contents = match format {
+            Format::Json => serde_json::to_string_pretty(self)? + "\n",
+            Format::Markdown => render::markdown(self),
+            Format::Html => render::html(self),
+        };
+        let mut partial = path.as_os_str().to_owned();
+
+        partial.push(".partial");
+        fs::write(&partial, contents)?;
+        fs::rename(&partial, path)
+    }
+
+    /// What happened once everything has run, as the parts of the summary so it can be
+    /// printed and written as a document alike
+    pub fn summary(&self) -> Vec<Block> {
+        let failed: Vec<&StepResult> = self.steps.iter().filter(|step| !step.success).collect();
+        let mut blocks = vec![
+            Block::Title(tr("summary-title", &[])),
+            Block::Line(tr(
+                "summary-commands",
+                &[("run", &self.steps.len()), ("failed", &failed.len())],
+            )),
+        ];
+
+        // the groups in the order they ran, steps without one come first
+        let mut groups: Vec<Option<Group>> = Vec::new();
+
+        for step in self.steps.iter() {
+            if !groups.contains(&step.group) {
+                groups.push(step.group);
+            }
+        }
+
+        groups.sort_by_key(Option::is_some);
+
+        for group in groups.iter() {
+            let steps: Vec<&StepResult> = self
+                .steps
+                .iter()
+                .filter(|step| step.group == *group)
+                .collect();
+
+            if let Some(group) = group {
+                blocks.push(Block::Heading(tr(
+                    "summary-group",
+                    &[
+                        ("group", &group.name()),
+                        ("run", &steps.len()),
+                        ("failed", &steps.iter().filter(|step| !step.success).count()),
+                    ],
+                )));
+            }
+
+            for step in steps.iter().filter(|step| !step.success) {
+                blocks.push(Block::Line(match step.code {
+                    Some(code) => tr(
+                        "summary-failed-code",
+                        &[("command", &step.command), ("code", &code)],
+                    ),
+                    None => tr("summary-failed-signal", &[("command", &step.command)]),
+                }));
+
+                // a lone command failing says nothing the exit code didn't
+                if step.pipeline.len() > 1 {
+                    let count = step.pipeline.len();
+
+                    for (position, code) in step.pipeline.iter().enumerate() {
+                        if *code != 0 {
+                            blocks.push(Block::Detail(tr(
+                                "summary-failed-pipeline",
+                                &[
+                                    ("position", &(position + 1)),
+                                    ("count", &count),
+                                    ("code", code),
+                                ],
+                            )));
+                        }
+                    }
+                }
+
+                if !step.stderr.is_empty() {
+                    blocks.push(Block::Output(step.stderr.clone()));
+                }
+            }
+
+            for step in steps.iter().filter(|step| !step.highlighted.is_empty()) {
+                blocks.push(Block::Line(tr(
+                    "summary-highlighted",
+                    &[("command", &step.command)],
+                )));
+                blocks.push(Block::Output(step.highlighted.clone()));
+            }
+
+            for step in steps.iter().filter(|step| !step.changes.is_empty()) {
+                blocks.push(Block::Line(tr(
+                    "summary-changed",
+                    &[("command", &step.command), ("count", &step.changes.len())],
+                )));
+                blocks.push(Block::Changes(step.changes.clone()));
+            }
+        }
+
+        if groups.iter().any(Option::is_some) {
+            blocks.push(Block::Break);
+        }
+
+        if !self.vulnerabilities.is_empty() {
+            blocks.push(Block::Line(tr(
+                "summary-vulnerabilities",
+                &[("count", &self.vulnerabilities.len())],
+            )));
+
+            for vulnerability in self.vulnerabilities.iter() {
+                blocks.push(Block::Item(tr(
+                    "summary-vulnerability",
+                    &[
+                        ("package", &vulnerability.package),
+                        ("version", &vulnerability.version),
+                        ("advisory", &vulnerability.advisory),
+                        ("tool", &vulnerability.tool),
+                    ],
+                )));
+            }
+        }
+
+        if !self.baseline.is_empty() {
+            let packages: Vec<String> = self
+                .baseline
+                .iter()
+                .map(|change| {
+                    format!(
+                        "{} {} -> {}",
+                        change.package,
+                        change
+                            .from
+                            .clone()
+                            .unwrap_or_else(|| tr("package-new", &[])),
+                        change.to.clone().unwrap_or_default()
+                    )
+                })
+                .collect();
+
+            blocks.push(Block::Line(tr(
+                "summary-baseline",
+                &[("packages", &packages.join(", "))],
+            )));
+        }
+
+        for health in self.health.iter() {
+            blocks.push(Block::Line(tr(
+                "summary-health",
+                &[("check", &health.check), ("status", &health.status)],
+            )));
+        }
+
+        let lists = [
+            ("summary-restarted", "services", &self.restarted),
+            ("summary-needs-restart", "services", &self.needs_restart),
+            (
+                "summary-stale-processes",
+                "processes",
+                &self.stale_processes,
+            ),
+            ("summary-recreated", "containers", &self.recreated),
+            ("summary-pinned", "packages", &self.pinned),
+            ("summary-kept-back", "packages", &self.kept_back),
+            ("summary-phased", "packages", &self.phased),
+        ];
+
+        for (id, name, list) in lists {
+            if !list.is_empty() {
+                blocks.push(Block::Line(tr(id, &[(name, &list.join(", "))])));
+            }
+        }
+
+        match self.pro.as_ref() {
+            Some(pro) if !pro.attached => {
+                blocks.push(Block::Line(tr("summary-pro-detached", &[])));
+            }
+            Some(pro) => {
+                let services = if pro.services.is_empty() {
+                    tr("summary-pro-nothing", &[])
+                } else {
+                    pro.services.join(", ")
+                };
+
+                blocks.push(Block::Line(tr("summary-pro", &[("services", &services)])));
+
+                if let Some(livepatch) = pro.livepatch.as_ref() {
+                    blocks.push(Block::Line(tr(
+                        "summary-livepatch",
+                        &[("state", livepatch)],
+                    )));
+                }
+            }
+            None => {}
+        }
+
+        for flatpaked in self.flatpak.iter() {
+            blocks.push(Block::Line(tr(
+                "summary-flatpak",
+                &[
+                    ("installation", &flatpaked.installation),
+                    ("apps", &flatpaked.apps.len()),
+                    ("runtimes", &flatpaked.runtimes.len()),
+                ],
+            )));
+        }
+
+        if let Some(automatic) = self.automatic.as_ref() {
+            let ago = automatic.last_run.map(|last_run| {
+                format_duration(
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|now| now.as_secs().saturating_sub(last_run))
+                        .unwrap_or(0),
+                )
+            });
+            let line = match ago {
+                Some(ago) if !automatic.upgraded.is_empty() => tr(
+                    "summary-automatic-upgraded",
+                    &[
+                        ("service", &automatic.service),
+                        ("ago", &ago),
+                        ("packages", &automatic.upgraded.join(", ")),
+                    ],
+                ),
+                Some(ago) => tr(
+                    "summary-automatic",
+                    &[("service", &automatic.service), ("ago", &ago)],
+                ),
+                None => tr(
+                    "summary-automatic-never",
+                    &[("service", &automatic.service)],
+                ),
+            };
+
+            blocks.push(Block::Line(line));
+        }
+
+        if !self.not_due.is_empty() {
+            blocks.push(Block::Line(tr(
+                "summary-not-due",
+                &[("commands", &self.not_due.join(", "))],
+            )));
+        }
+
+        for warning in self.warnings.iter() {
+            blocks.push(Block::Line(tr("summary-warning", &[("warning", warning)])));
+        }
+
+        let mut boot: Vec<String> = Vec::new();
+
+        if let Some(uptime) = self.uptime {
+            boot.push(tr(
+                "summary-uptime",
+                &[("duration", &format_duration(uptime))],
+            ));
+        }
+
+        if let Some(since) = self.reboot_pending_since {
+            let pending = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_secs().saturating_sub(since))
+                .unwrap_or(0);
+
+            boot.push(tr(
+                "summary-reboot-pending",
+                &[("duration", &format_duration(pending))],
+            ));
+        }
+
+        if !boot.is_empty() {
+            blocks.push(Block::Line(boot.join(", ")));
+        }
+
+        let mut sizes: Vec<String> = Vec::new();
+
+        if let Some(bytes) = self.downloaded {
+            sizes.push(tr("summary-downloaded", &[("size", &format_size(bytes))]));
+        }
+
+        if let Some(delta) = self.disk_delta {
+            sizes.push(tr("summary-disk", &[("delta", &format_delta(delta))]));
+        }
+
+        if !sizes.is_empty() {
+            blocks.push(Block::Line(sizes.join(", ")));
+        }
+
+        // last so it's what's left on the screen
+        if !self.notes.is_empty() {
+            blocks.push(Block::Heading(tr("summary-notes", &[])));
+        }
+
+        for note in self.notes.iter() {
+            blocks.push(Block::Line(match note.package.as_deref() {
+                Some(package) => format!("{} {}:", note.manager, package),
+                None => format!("{}:", note.manager),
+            }));
+            blocks.push(Block::Output(note.lines.clone()));
+        }
+
+        blocks
+    }
+
+    /// Print what happened once everything has run
+    pub fn print_summary(&self) {
+        for block in self.summary() {
+            match block {
+                Block::Title(title) => {
+                    println!();
+                    println!("========================");
+                    println!("{}", title);
+                    println!("========================");
+                }
+                Block::Heading(heading) => {
+                    println!();
+                    println!("{}", heading);
+                }
+                Block::Line(line) => println!("{}", line),
+                Block::Detail(line) => println!("  {}", line),
+                Block::Item(line) => println!("    {}", line),
+                Block::Output(lines) => {
+                    for line in lines.iter() {
+                        println!("    {}", line);
+                    }
+                }
+                Block::Changes(changes) => {
+                    for change in changes.iter() {
+                        let (from, to) = change.versions();
+
+                        println!("    {} {} -> {}", change.package, from, to);
+                    }
+                }
+                Block::Break => println!(),
+            }
+        }
+    }
+}
+/// Format a number of bytes for people, e.g. `412.3 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Format a change in bytes with its sign, e.g. `+88.0 MB`
+pub fn format_delta(bytes: i64) -> String {
+    let sign = if bytes < 0 { '-' } else { '+' };
+
+    format!("{}{}", sign, format_size(bytes.unsigned_abs()))
+}
+
+/// Format a number of seconds in the largest whole unit, e.g. `12 days` or `3 hours`
+///
+/// # Arguments
+///
+/// * `seconds` - The duration to format
+pub fn format_duration(seconds: u64) -> String {
+    let (amount, unit) = match seconds {
+        0..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+
+    if amount == 1 {
+        format!("{} {}", amount, unit)
+    } else {
+        format!("{} {}s", amount, unit)
+    }
+}
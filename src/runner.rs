@@ -0,0 +1,2413 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use scuttle::App;
+
+use crate::boot;
+use crate::config::{self, AssumeYes, Config, Group, RunAs, Sandbox, UserSteps};
+use crate::confirm;
+use crate::detect::{self, has_binary};
+use crate::filter::{self, Filtered, OutputFilter};
+use crate::help;
+use crate::history;
+use crate::i18n::tr;
+use crate::locks;
+use crate::maps;
+use crate::mqtt;
+use crate::parsers;
+use crate::pipeline::{Feed, Parser, Pipeline};
+use crate::redact::{Redacting, Redactor};
+use crate::render;
+use crate::report::{
+    self, format_duration, Automatic, ChangedPackage, Flatpaked, Health, Note, Outcome, Pro,
+    Report, StepResult, Vulnerability,
+};
+use crate::secrets;
+
+/// The package managers that keep what they install in the user's home directory, they
+/// run as the user unless `[steps.<command>] run_as` says otherwise
+const USER_STEPS: [&str; 10] = [
+    "brew", "cargo", "composer", "ghcup", "go", "nvim", "pipx", "rustup", "stack", "yay",
+];
+
+/// The shells whose `-c` scripts are run with pipefail when they pipe anything
+const SHELLS: [&str; 3] = ["bash", "zsh", "sh"];
+
+/// The sudo options that take a value as the next word, e.g. the user of `-u <user>`
+const SUDO_VALUE_OPTIONS: [&str; 11] = [
+    "-C", "-D", "-R", "-T", "-U", "-g", "-h", "-p", "-r", "-t", "-u",
+];
+
+/// An app to run along with whether the user should be asked first
+pub struct Step {
+    pub app: App,
+    pub ask: bool,
+    /// Whether a failure stops the whole run, `[steps.<command>]` in the config can
+    /// change this
+    pub required: bool,
+}
+
+/// What a command printed while it ran
+pub struct Captured {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Copy everything from `source` to `sink` as it arrives, keeping a copy
+///
+/// Output is passed on as soon as it's read rather than a line at a time so prompts
+/// without a trailing newline still show up.
+fn tee<R, W>(mut source: R, mut sink: W) -> JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut captured = Vec::new();
+        let mut buffer = [0; 8192];
+
+        loop {
+            match source.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(length) => {
+                    let _ = sink.write_all(&buffer[..length]);
+                    let _ = sink.flush();
+
+                    captured.extend_from_slice(&buffer[..length]);
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+
+        captured
+    })
+}
+
+/// Run an app with its output going to the terminal as usual while also capturing it
+///
+/// # Arguments
+///
+/// * `app` - The app to run
+/// * `env` - Variables to set for the app on top of up2date's own environment, e.g. the
+///   secrets it needs
+pub fn run_captured(app: &App, env: &[(&str, &str)]) -> io::Result<Captured> {
+    capture(
+        app,
+        env,
+        Some(&OutputFilter::default()),
+        &Redactor::new(Vec::new()),
+    )
+}
+
+/// Run an app and capture its output, only passing it on to the terminal when there's a
+/// filter to pass it through
+///
+/// Every step's output is parsed for the summary, so stdout and stderr are always pipes
+/// and only stdin is the terminal. That's on purpose even though a command then sees it
+/// isn't writing to a terminal and may leave out its colours and progress bars: a pty
+/// would need a dependency of its own and would mix stderr into stdout, which the report
+/// keeps apart.
+///
+/// # Arguments
+///
+/// * `app` - The app to run
+/// * `env` - Variables to set for the app on top of up2date's own environment
+/// * `echo` - What's left out of and highlighted in the output as it goes to the
+///   terminal, `None` when nothing is shown
+/// * `redactor` - What hides the credentials in the output that's shown
+fn capture(
+    app: &App,
+    env: &[(&str, &str)],
+    echo: Option<&OutputFilter>,
+    redactor: &Redactor,
+) -> io::Result<Captured> {
+    let sink = |terminal: Box<dyn Write + Send>| -> Box<dyn Write + Send> {
+        let shown: Box<dyn Write + Send> = match echo {
+            Some(filter) if filter.is_empty() => terminal,
+            Some(filter) => Box::new(Filtered::new(terminal, filter.clone())),
+            None => return Box::new(io::sink()),
+        };
+
+        Box::new(Redacting::new(shown, redactor.clone()))
+    };
+    let mut child = Command::new(&app.command)
+        .args(&app.args)
+        .envs(env.iter().copied())
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|stdout| tee(stdout, sink(Box::new(io::stdout()))));
+    // the exit codes bash's pipefail trap prints are only for the report
+    let stderr_sink: Box<dyn Write + Send> = if app
+        .args
+        .iter()
+        .any(|arg| arg.contains(parsers::PIPE_STATUS))
+        && echo.is_some()
+    {
+        Box::new(Filtered::new(
+            sink(Box::new(io::stderr())),
+            OutputFilter::hiding_pipe_status(),
+        ))
+    } else {
+        sink(Box::new(io::stderr()))
+    };
+    let stderr = child.stderr.take().map(|stderr| tee(stderr, stderr_sink));
+    let status = child.wait()?;
+    let collect = |handle: Option<JoinHandle<Vec<u8>>>| match handle.map(JoinHandle::join) {
+        Some(Ok(bytes)) => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => String::new(),
+    };
+
+    Ok(Captured {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+/// The last `count` non-empty lines of some output
+fn last_lines(output: &str, count: usize) -> Vec<String> {
+    let lines: Vec<&str> = output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Lock the report, still handing it out if a panic happened while it was locked
+fn lock(report: &Mutex<Report>) -> MutexGuard<'_, Report> {
+    report.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Write the report file, if one was asked for
+fn save(report: &Mutex<Report>, report_file: Option<&(PathBuf, report::Format)>) {
+    if let Some((path, format)) = report_file {
+        if let Err(error) = lock(report).write(path, *format) {
+            eprintln!("ERROR: could not write {}: {}", path.display(), error);
+        }
+    }
+}
+
+/// Runs apps and keeps track of what happened to each of them
+///
+/// The runner doubles as a guard for the report file: it's written when the runner is
+/// dropped, including while unwinding from a panic, and from a Ctrl-C handler.
+pub struct Runner {
+    report: Arc<Mutex<Report>>,
+    report_file: Option<(PathBuf, report::Format)>,
+    /// How many lines of stderr to keep from each failed command
+    failure_lines: usize,
+    /// Whether a failure stops the run, by step name
+    steps: HashMap<String, config::StepPolicy>,
+    /// The groups turned on or off in the config
+    groups: HashMap<Group, bool>,
+    /// The only groups to run, given on the command line
+    only_groups: Vec<Group>,
+    /// The group the steps being run belong to
+    group: Option<Group>,
+    /// The secrets from the config, only looked up once a step is about to run
+    secret_settings: HashMap<String, config::Secret>,
+    /// Credentials to put in the environment of the steps that need them, `None` until
+    /// they're looked up
+    secrets: Option<Vec<secrets::Secret>>,
+    /// Hides the secrets, and anything else shaped like a credential, in banners and the
+    /// report
+    redactor: Redactor,
+    /// Where to publish the results once the run is finished
+    mqtt: config::Mqtt,
+    /// The commands that would have run, by group, when only planning a run
+    plan: Option<Vec<(Option<Group>, String)>>,
+    /// The commands noted down while planning that run with sudo, without the `sudo`
+    privileged: Vec<Vec<String>>,
+    /// How sudo asks for the password when there's no terminal
+    sudo: config::Sudo,
+    /// What `sudo` is replaced with when there's no terminal for it, once it's been
+    /// looked up, `None` when sudo is run as it is
+    escalation: Option<Option<Vec<String>>>,
+    /// What happens to the steps that run as the user when up2date runs as root
+    user_steps: UserSteps,
+    /// When each step last succeeded, as it was remembered when the run started
+    last_ran: HashMap<String, u64>,
+    /// The steps that succeeded during this run and when
+    ran: HashMap<String, u64>,
+    /// Only show the steps that changed something or failed, once they're done
+    changed_only: bool,
+    /// What's printed before each command, nothing when it's empty
+    banner: String,
+    /// What the planned removals would remove, or what was agreed to be removed once
+    /// the plan was confirmed
+    removals: Vec<String>,
+    /// Whether the whole run was confirmed up front, so nothing in it asks again
+    approved: bool,
+    /// The patterns for the output of the steps that have any, by step name
+    filters: HashMap<String, OutputFilter>,
+    /// Say what each command does and wait for Enter before running it
+    explain: bool,
+    /// Open the summary in the browser once the run is done
+    open_report: bool,
+    /// Whether the package lists were already refreshed for the preview or `[policy]`
+    lists_refreshed: bool,
+}
+
+impl Runner {
+    /// # Arguments
+    ///
+    /// * `config` - The user's config
+    /// * `report_file` - Where to write the report once the run ends, however it ends, and
+    ///   what as
+    pub fn new(
+        config: &Config,
+        report_file: Option<(&Path, report::Format)>,
+        only_groups: &[Group],
+    ) -> Runner {
+        let report = Arc::new(Mutex::new(Report {
+            started: now(),
+            ..Report::default()
+        }));
+        let report_file = report_file.map(|(path, format)| (path.to_path_buf(), format));
+
+        if let Some(file) = report_file.clone() {
+            let report = Arc::clone(&report);
+            let handler = ctrlc::set_handler(move || {
+                lock(&report).outcome = Outcome::Interrupted;
+                save(&report, Some(&file));
+                process::exit(130);
+            });
+
+            if let Err(error) = handler {
+                eprintln!(
+                    "WARNING: the report won't be written if interrupted: {}",
+                    error
+                );
+            }
+        }
+
+        Runner {
+            report,
+            report_file,
+            failure_lines: config.failure_lines,
+            steps: config.steps.clone(),
+            groups: config.groups.clone(),
+            only_groups: only_groups.to_vec(),
+            group: None,
+            secret_settings: config.secrets.clone(),
+            secrets: None,
+            redactor: Redactor::new(Vec::new()),
+            mqtt: config.mqtt.clone(),
+            plan: None,
+            privileged: Vec::new(),
+            sudo: config.sudo.clone(),
+            escalation: None,
+            user_steps: config.root.user_steps,
+            last_ran: read_last_ran(),
+            ran: HashMap::new(),
+            changed_only: false,
+            banner: config.banner.format.clone(),
+            removals: Vec::new(),
+            approved: false,
+            filters: config
+                .steps
+                .iter()
+                .map(|(step, policy)| (step.clone(), OutputFilter::new(step, policy)))
+                .filter(|(_, filter)| !filter.is_empty())
+                .collect(),
+            explain: false,
+            open_report: false,
+            lists_refreshed: false,
+        }
+    }
+
+    /// A runner that only notes down the commands it's given instead of running them, and
+    /// never asks about anything
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The user's config
+    /// * `only_groups` - The only groups to plan, every enabled group when empty
+    pub fn planning(config: &Config, only_groups: &[Group]) -> Runner {
+        let mut runner = Runner::new(config, None, only_groups);
+
+        runner.plan = Some(Vec::new());
+        runner
+    }
+
+    /// Hold back what each step prints until it's done and only show the steps that
+    /// changed something, failed or printed something that isn't known to mean there
+    /// was nothing to do
+    pub fn show_changed_only(&mut self) {
+        self.changed_only = true;
+    }
+
+    /// Say what each command does before it runs, from the steps' docs, and wait for
+    /// Enter, for whoever doesn't trust what up2date runs with sudo yet
+    pub fn explain_steps(&mut self) {
+        self.explain = true;
+    }
+
+    /// Print that an app is being skipped and why
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app that won't be run
+    /// * `reason` - The id of the message saying why it won't be run, e.g.
+    ///   `reason-needs-network`
+    pub fn skip_app(&self, app: &App, reason: &str) {
+        let command = self.redactor.redact(&command_line(app));
+
+        println!();
+        println!(
+            "{}",
+            tr(
+                "step-skipped",
+                &[("command", &command), ("reason", &tr(reason, &[]))]
+            )
+        );
+    }
+
+    /// Remember that the package lists were just refreshed for the preview or `[policy]`,
+    /// so the run doesn't refresh them a second time
+    pub fn note_lists_refreshed(&mut self) {
+        self.lists_refreshed = true;
+    }
+
+    /// Whether the package lists were already refreshed before the run started
+    pub fn lists_refreshed(&self) -> bool {
+        self.lists_refreshed
+    }
+
+    /// Write the summary as an HTML page once the run is done and open it in the browser,
+    /// for the runs whose summary is too long for the terminal
+    pub fn open_report(&mut self) {
+        self.open_report = true;
+    }
+
+    /// Write the summary to `report.html` in the state directory and open it, when it was
+    /// asked for
+    fn show_report(&self) {
+        if !self.open_report {
+            return;
+        }
+
+        let path = match config::state_dir() {
+            Some(dir) => dir.join("report.html"),
+            None => {
+                eprintln!("WARNING: no home directory to write the report to");
+                return;
+            }
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, render::html(&lock(&self.report))));
+
+        if let Err(error) = written {
+            eprintln!("WARNING: could not write {}: {}", path.display(), error);
+            return;
+        }
+
+        let path = path.display().to_string();
+        let opener = match detect::os() {
+            "macos" => App {
+                command: String::from("open"),
+                args: vec![path],
+            },
+            // start's first quoted argument is the window title
+            "windows" => App {
+                command: String::from("cmd"),
+                args: vec![
+                    "/c".to_string(),
+                    "start".to_string(),
+                    "\"\"".to_string(),
+                    path,
+                ],
+            },
+            _ => App {
+                command: String::from("xdg-open"),
+                args: vec![path],
+            },
+        };
+
+        if let Err(error) = Command::new(&opener.command)
+            .args(&opener.args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            eprintln!(
+                "WARNING: could not open the report with {}: {}",
+                opener.command, error
+            );
+        }
+    }
+
+    /// Explain the command that's about to run and wait for Enter, stopping the run when
+    /// there's nobody to press it
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app about to run
+    fn wait_for_go_ahead(&mut self, app: &App) {
+        let command = self.redactor.redact(&command_line(app));
+
+        println!();
+        println!(
+            "{}",
+            help::explain(&command).unwrap_or_else(|| tr("explain-unknown", &[]))
+        );
+        println!("  $ {}", command);
+        print!("{} ", tr("prompt-explain", &[]));
+
+        let mut answer = String::new();
+        let answered = io::stdout().flush().is_ok()
+            && io::stdin()
+                .read_line(&mut answer)
+                .is_ok_and(|read| read > 0);
+
+        if !answered {
+            self.abort(&tr("error-explain-stopped", &[]));
+        }
+    }
+
+    /// The commands noted down while planning, each with the group it's in, e.g.
+    /// `system: sudo apt-get update`
+    pub fn planned(&self) -> Vec<String> {
+        self.plan
+            .iter()
+            .flatten()
+            .map(|(group, command)| match group {
+                Some(group) => format!("{}: {}", group.name(), command),
+                None => command.clone(),
+            })
+            .collect()
+    }
+
+    /// The commands noted down while planning that run as root through sudo, each as the
+    /// command and its args, the ones that only switch to another user left out
+    pub fn planned_privileged(&self) -> &[Vec<String>] {
+        &self.privileged
+    }
+
+    /// Print the banner for the command that's about to run, as `[banner] format` says,
+    /// returning the command with any credentials hidden
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app that's about to run
+    /// * `spawned` - What's started for it, the app itself unless it's sandboxed
+    fn banner(&self, app: &App, spawned: &App) -> String {
+        let command = self.redactor.redact(&command_line(spawned));
+
+        if self.banner.is_empty() {
+            return command;
+        }
+
+        let env: Vec<&str> = self.env(app).into_iter().map(|(name, _)| name).collect();
+        let cwd = env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        let line = self
+            .banner
+            .replace("{env}", &env.join(" "))
+            .replace("{cwd}", &cwd)
+            .replace("{time}", &history::format_date(now()))
+            .replace("{command}", &command);
+
+        println!();
+        println!("========================");
+        println!("{}", line.trim());
+        println!("========================");
+
+        command
+    }
+
+    /// Everything the planned removals would remove, e.g. `libfoo0 0.9-2`
+    pub fn planned_removals(&self) -> &[String] {
+        &self.removals
+    }
+
+    /// Run without asking about the steps again now the whole plan has been agreed to,
+    /// removals included as long as they don't remove more than the plan said
+    ///
+    /// # Arguments
+    ///
+    /// * `removals` - What the plan said would be removed
+    pub fn approve(&mut self, removals: Vec<String>) {
+        self.approved = true;
+        self.removals = removals;
+    }
+
+    /// Note down a command instead of running it, returning whether the runner is planning
+    fn note(&mut self, app: &App) -> bool {
+        let command = self.redactor.redact(&command_line(app));
+
+        match self.plan.as_mut() {
+            Some(plan) => {
+                plan.push((self.group, command));
+
+                if app.command == "sudo"
+                    && app.args.first().is_some_and(|arg| !arg.starts_with('-'))
+                {
+                    self.privileged.push(app.args.clone());
+                }
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the runner only notes down the commands, for the checks that would have to
+    /// be run to know which commands there are
+    pub fn is_planning(&self) -> bool {
+        self.plan.is_some()
+    }
+
+    /// Check whether a step should run, asking first if it needs it unless only planning
+    fn accepts(&self, step: &Step) -> bool {
+        self.plan.is_some() || self.approved || accepted(step, &self.redactor)
+    }
+
+    /// Print what happened so far
+    pub fn print_summary(&self) {
+        lock(&self.report).print_summary();
+    }
+
+    /// Start running the steps in a group, returning whether the group is turned on
+    ///
+    /// `--only-group` wins over the config, the steps that follow are counted under this
+    /// group in the summary. A group that's off by default is skipped without saying so
+    /// until it's turned on or off somewhere.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group the following steps belong to
+    pub fn group(&mut self, group: Group) -> bool {
+        let configured = self.groups.get(&group).copied();
+        let enabled = if self.only_groups.is_empty() {
+            configured.unwrap_or(group.on_by_default())
+        } else {
+            self.only_groups.contains(&group)
+        };
+
+        if enabled {
+            self.group = Some(group);
+        } else if configured.is_some() || !self.only_groups.is_empty() {
+            println!();
+            println!("{}", tr("group-skipped", &[("group", &group.name())]));
+        }
+
+        enabled
+    }
+
+    /// Look up the secrets the first time a step is about to run, so planning and the
+    /// commands that run nothing never ask secret-tool or age for them
+    fn resolve_secrets(&mut self) {
+        if self.secrets.is_some() {
+            return;
+        }
+
+        let secrets = secrets::resolve(&self.secret_settings);
+
+        self.redactor = Redactor::new(secrets.iter().map(|secret| secret.value.clone()).collect());
+        self.secrets = Some(secrets);
+    }
+
+    /// The environment variables holding the secrets `app` gets
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app about to run
+    fn env(&self, app: &App) -> Vec<(&str, &str)> {
+        let name = step_name(app);
+
+        self.secrets
+            .iter()
+            .flatten()
+            .filter(|secret| {
+                secret.steps.is_empty() || secret.steps.iter().any(|step| step == name)
+            })
+            .map(|secret| (secret.env.as_str(), secret.value.as_str()))
+            .collect()
+    }
+
+    /// Whether a failure of `app` should stop the whole run
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app that failed
+    /// * `default` - Whether the step is required when the config doesn't say
+    fn required(&self, app: &App, default: bool) -> bool {
+        match self.steps.get(step_name(app)) {
+            Some(policy) => policy.required(default),
+            None => default,
+        }
+    }
+
+    /// Who `app` has to run as: what the config says, `root` when it's run with sudo and
+    /// `user` for the package managers that install into the home directory
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app about to run
+    fn run_as(&self, app: &App) -> Option<RunAs> {
+        let name = step_name(app);
+
+        match self.steps.get(name).and_then(|policy| policy.run_as) {
+            Some(run_as) => Some(run_as),
+            None if app.command == "sudo" => Some(RunAs::Root),
+            None if USER_STEPS.contains(&name) => Some(RunAs::User),
+            None => None,
+        }
+    }
+
+    /// The app as it has to be run for who it runs as, `None` when it can't be run at all
+    /// because up2date is running as root and `[root] user_steps` says to skip it
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app about to run
+    fn as_user(&mut self, app: &App) -> Option<App> {
+        let run_as = self.run_as(app);
+        let app = match run_as {
+            Some(RunAs::User) if app.command == "sudo" && !app.args.is_empty() => App {
+                command: app.args[0].clone(),
+                args: app.args[1..].to_vec(),
+            },
+            Some(RunAs::Root) if app.command != "sudo" && !detect::is_root() => App {
+                command: String::from("sudo"),
+                args: [vec![app.command.clone()], app.args.clone()].concat(),
+            },
+            _ => App {
+                command: app.command.clone(),
+                args: app.args.clone(),
+            },
+        };
+
+        if run_as != Some(RunAs::User) || !detect::is_root() {
+            return Some(app);
+        }
+
+        match (self.user_steps, detect::sudo_user()) {
+            (UserSteps::Root, _) => Some(app),
+            // -H so it gets the user's home directory, not root's
+            (UserSteps::Drop, Some(user)) => Some(App {
+                command: String::from("sudo"),
+                args: [
+                    vec!["-u".to_string(), user, "-H".to_string(), app.command],
+                    app.args,
+                ]
+                .concat(),
+            }),
+            _ => {
+                let command = self.redactor.redact(&command_line(&app));
+
+                self.warn(vec![format!(
+                    "not running `{}` as root, it installs into the home directory. Run up2date without sudo to update it, or see [root] user_steps",
+                    command
+                )]);
+                None
+            }
+        }
+    }
+
+    /// Check whether `app` is due to run by its `[steps.<command>] frequency`, printing
+    /// that it's skipped and adding it to the report when it isn't
+    ///
+    /// Every step with the same name is due or not together for the whole run, going by
+    /// when one of them last succeeded before it started.
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app about to run
+    fn due(&mut self, app: &App) -> bool {
+        let name = step_name(app);
+        let (frequency, last_ran) = match (
+            self.steps
+                .get(name)
+                .and_then(|policy| policy.frequency.as_ref()),
+            self.last_ran.get(name),
+        ) {
+            (Some(frequency), Some(last_ran)) => (frequency, *last_ran),
+            _ => return true,
+        };
+        let ago = now().saturating_sub(last_ran);
+
+        if ago >= frequency.seconds {
+            return true;
+        }
+
+        let command = self.redactor.redact(&command_line(app));
+        let reason = tr(
+            "reason-not-due",
+            &[
+                ("frequency", &frequency.name),
+                ("ago", &format_duration(ago)),
+            ],
+        );
+
+        println!();
+        println!(
+            "{}",
+            tr(
+                "step-skipped",
+                &[("command", &command), ("reason", &reason)]
+            )
+        );
+        lock(&self.report).not_due.push(command);
+
+        false
+    }
+
+    /// Remember that `app` succeeded just now, for the steps that only run so often
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app that succeeded
+    fn remember_ran(&mut self, app: &App) {
+        self.ran.insert(step_name(app).to_string(), now());
+
+        let mut last_ran = self.last_ran.clone();
+
+        last_ran.extend(self.ran.clone());
+        write_last_ran(&last_ran);
+    }
+
+    /// Wait for another apt, dpkg or pacman to let go of the package manager's lock, so the
+    /// system package manager doesn't fail halfway through the run on it
+    ///
+    /// A pacman lock left over with no pacman running is removed first if `[locks]
+    /// remove_stale` says so, or the user agrees, and stops the run otherwise. The run is
+    /// also stopped when the lock is still held after `[locks] timeout`. Nothing is
+    /// waited for or removed while planning.
+    ///
+    /// # Arguments
+    ///
+    /// * `release` - The Linux distribution id
+    /// * `settings` - The lock settings from the config
+    pub fn wait_for_lock(&mut self, release: Option<&str>, settings: &config::Locks) {
+        if self.plan.is_some() {
+            return;
+        }
+
+        if let Some(lock) = locks::stale(release) {
+            println!();
+
+            let remove = match settings.remove_stale {
+                AssumeYes::Yes => true,
+                AssumeYes::No => false,
+                AssumeYes::Ask => confirm(&tr("prompt-remove-lock", &[("lock", &lock)])),
+            };
+
+            if !remove {
+                self.abort(&tr("error-stale-lock", &[("lock", &lock)]));
+            }
+
+            self.run_app(&App {
+                command: String::from("sudo"),
+                args: vec!["rm".to_string(), "-f".to_string(), lock.to_string()],
+            });
+        }
+
+        let mut holder = match locks::holder(release) {
+            Some(holder) => holder,
+            None => return,
+        };
+        let start = Instant::now();
+        let mut reported = Instant::now();
+
+        if settings.timeout > 0 {
+            println!();
+            println!(
+                "{}",
+                tr(
+                    "lock-waiting",
+                    &[("holder", &holder), ("timeout", &settings.timeout)]
+                )
+            );
+        }
+
+        while start.elapsed().as_secs() < settings.timeout {
+            thread::sleep(Duration::from_secs(1));
+
+            holder = match locks::holder(release) {
+                Some(holder) => holder,
+                None => return,
+            };
+
+            if reported.elapsed().as_secs() >= 30 {
+                reported = Instant::now();
+                println!(
+                    "{}",
+                    tr(
+                        "lock-still-waiting",
+                        &[
+                            ("holder", &holder),
+                            (
+                                "left",
+                                &settings.timeout.saturating_sub(start.elapsed().as_secs()),
+                            ),
+                        ]
+                    )
+                );
+            }
+        }
+
+        self.abort(&tr("error-locked", &[("holder", &holder)]));
+    }
+
+    /// The app with `sudo` replaced by what `[sudo] without_terminal` says when there's no
+    /// terminal for sudo to ask for the password on, as it is otherwise
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app about to run
+    fn escalated(&mut self, app: &App) -> App {
+        let sudo = &self.sudo;
+        let escalation = self.escalation.get_or_insert_with(|| escalation(sudo));
+
+        match escalation {
+            Some(prefix)
+                if app.command == "sudo"
+                    && app.args.first().is_some_and(|arg| !arg.starts_with('-')) =>
+            {
+                App {
+                    command: prefix[0].clone(),
+                    args: [prefix[1..].to_vec(), app.args.clone()].concat(),
+                }
+            }
+            _ => App {
+                command: app.command.clone(),
+                args: app.args.clone(),
+            },
+        }
+    }
+
+    /// The app inside the sandbox its `[steps.<command>] sandbox` asks for, only able to
+    /// write to `writable` and `/tmp`, or as it is when it has no sandbox
+    ///
+    /// Steps that run as root aren't sandboxed, a sandbox started with sudo wouldn't
+    /// contain much. The steps made to run as the user who started up2date with sudo
+    /// are, the sandbox running as that user with `~` being their home directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app about to run, as it runs for who it runs as
+    fn sandboxed(&mut self, app: &App) -> App {
+        let unchanged = App {
+            command: app.command.clone(),
+            args: app.args.clone(),
+        };
+        let (sandbox, writable) = match self.steps.get(step_name(app)) {
+            Some(config::StepPolicy {
+                sandbox: Some(sandbox),
+                writable,
+                ..
+            }) => (*sandbox, writable.clone()),
+            _ => return unchanged,
+        };
+
+        let (user, inner) = match dropped(app) {
+            Some((user, inner)) => (Some(user), inner),
+            None if app.command == "sudo" => {
+                let command = self.redactor.redact(&command_line(app));
+
+                self.warn(vec![format!(
+                    "not sandboxing `{}`, it runs as root",
+                    command
+                )]);
+                return unchanged;
+            }
+            None => (None, unchanged),
+        };
+        // only looked up when it's needed, for the user it takes running sudo
+        let home = OnceCell::new();
+        let home = || {
+            home.get_or_init(|| match user {
+                Some(user) => detect::home_of(user).unwrap_or_default(),
+                None => env::var("HOME").unwrap_or_default(),
+            })
+            .clone()
+        };
+        let writable: Vec<String> = writable
+            .iter()
+            .map(|path| match path.strip_prefix('~') {
+                Some(rest) => format!("{}{}", home(), rest),
+                None => path.clone(),
+            })
+            .collect();
+        let words =
+            |words: &[&str]| -> Vec<String> { words.iter().map(|word| word.to_string()).collect() };
+        let mut args: Vec<String> = match sandbox {
+            Sandbox::Bwrap => {
+                let mut args = words(&[
+                    "--ro-bind",
+                    "/",
+                    "/",
+                    "--dev",
+                    "/dev",
+                    "--proc",
+                    "/proc",
+                    "--tmpfs",
+                    "/tmp",
+                    "--die-with-parent",
+                ]);
+
+                for path in writable.iter() {
+                    args.extend([String::from("--bind"), path.clone(), path.clone()]);
+                }
+
+                args
+            }
+            Sandbox::Firejail => {
+                let mut args = vec![String::from("--quiet"), format!("--read-only={}", home())];
+
+                args.extend(writable.iter().map(|path| format!("--read-write={}", path)));
+                args
+            }
+            Sandbox::SystemdRun => {
+                let mut args = words(&[
+                    "--user",
+                    "--pipe",
+                    "--wait",
+                    "--quiet",
+                    "--collect",
+                    "--same-dir",
+                    "-p",
+                    "ProtectSystem=strict",
+                    "-p",
+                    "ProtectHome=read-only",
+                ]);
+
+                for path in writable.iter() {
+                    args.extend([String::from("-p"), format!("ReadWritePaths={}", path)]);
+                }
+
+                // the unit doesn't inherit the environment, only what's passed on by name
+                args.push(String::from("--setenv=PATH"));
+                args.extend(
+                    self.env(app)
+                        .into_iter()
+                        .map(|(name, _)| format!("--setenv={}", name)),
+                );
+                args
+            }
+        };
+
+        args.push(String::from("--"));
+        args.push(inner.command);
+        args.extend(inner.args);
+
+        match user {
+            Some(user) => App {
+                command: String::from("sudo"),
+                args: [
+                    vec![
+                        "-u".to_string(),
+                        user.to_string(),
+                        "-H".to_string(),
+                        String::from(sandbox.binary()),
+                    ],
+                    args,
+                ]
+                .concat(),
+            },
+            None => App {
+                command: String::from(sandbox.binary()),
+                args,
+            },
+        }
+    }
+
+    /// Stop the whole run, printing the summary, writing the report and adding the run to
+    /// the history first
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - Why the run is being stopped
+    pub fn abort(&mut self, message: &str) -> ! {
+        eprintln!("ERROR: {}", message);
+        lock(&self.report).outcome = Outcome::Aborted;
+        self.print_summary();
+        self.show_report();
+
+        if let Err(error) = history::append(&lock(&self.report)) {
+            eprintln!("WARNING: could not add this run to the history: {}", error);
+        }
+
+        // exiting skips the drop guard
+        save(&self.report, self.report_file.as_ref());
+        process::exit(1);
+    }
+
+    /// Print out the command and its arguments then run it
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app to run
+    pub fn run_app(&mut self, app: &App) -> ExitStatus {
+        self.run_app_with(app, |_| Vec::new())
+    }
+
+    /// Run an app like `run_app`, finding out which packages it changed from its output
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app to run
+    /// * `parse` - Finds the changed packages in everything the app printed
+    pub fn run_app_with(
+        &mut self,
+        app: &App,
+        parse: fn(&str) -> Vec<ChangedPackage>,
+    ) -> ExitStatus {
+        self.run_captured(app, parse).0
+    }
+
+    /// Run an app like `run_app_with`, also returning everything it printed, nothing when
+    /// it wasn't run
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The app to run
+    /// * `parse` - Finds the changed packages in everything the app printed
+    fn run_captured(
+        &mut self,
+        app: &App,
+        parse: fn(&str) -> Vec<ChangedPackage>,
+    ) -> (ExitStatus, String) {
+        let app = &match self.as_user(app) {
+            Some(app) => app,
+            None => return (ExitStatus::default(), String::new()),
+        };
+
+        if !self.due(app) {
+            return (ExitStatus::default(), String::new());
+        }
+
+        let spawned = &self.sandboxed(app);
+
+        if self.note(spawned) {
+            return (ExitStatus::default(), String::new());
+        }
+
+        self.resolve_secrets();
+
+        if self.explain {
+            self.wait_for_go_ahead(app);
+        }
+
+        let spawned = &self.escalated(spawned);
+
+        let command = if self.changed_only {
+            self.redactor.redact(&command_line(spawned))
+        } else {
+            self.banner(app, spawned)
+        };
+        let filter = self
+            .filters
+            .get(step_name(app))
+            .cloned()
+            .unwrap_or_default();
+        let start = Instant::now();
+        let echo = (!self.changed_only).then_some(&filter);
+        let mut captured = match capture(&pipefail(spawned), &self.env(app), echo, &self.redactor) {
+            Err(error) => panic!("panic:{}", error),
+            Ok(captured) => captured,
+        };
+
+        // nothing that's kept in the report should hold a credential
+        captured.stdout = self.redactor.redact(&captured.stdout);
+        captured.stderr = self.redactor.redact(&captured.stderr);
+
+        let success = captured.status.success();
+        let output = format!("{}\n{}", captured.stdout, captured.stderr);
+        let changes = parse(&output);
+
+        lock(&self.report).notes.extend(notes(app, &output));
+
+        if self.changed_only
+            && (!success || !changes.is_empty() || !parsers::is_nothing_to_do(&captured.stdout))
+        {
+            self.banner(app, spawned);
+            print!("{}", filter.apply(&captured.stdout, filter::color()));
+            eprint!("{}", filter.apply(&captured.stderr, filter::color()));
+        }
+
+        lock(&self.report).push(StepResult {
+            command: command.clone(),
+            argv: std::iter::once(&spawned.command)
+                .chain(spawned.args.iter())
+                .map(|word| self.redactor.redact(word))
+                .collect(),
+            group: self.group,
+            success,
+            seconds: start.elapsed().as_secs_f64(),
+            code: captured.status.code(),
+            sizes: parsers::parse_sizes(&captured.stdout),
+            stderr: if success {
+                Vec::new()
+            } else {
+                let stderr: Vec<&str> = captured
+                    .stderr
+                    .lines()
+                    .filter(|line| !line.starts_with(parsers::PIPE_STATUS))
+                    .collect();
+
+                last_lines(&stderr.join("\n"), self.failure_lines)
+            },
+            pipeline: if success {
+                Vec::new()
+            } else {
+                parsers::parse_pipe_status(&captured.stderr)
+            },
+            highlighted: filter.highlighted(&output),
+            changes,
+        });
+
+        if success {
+            self.remember_ran(app);
+        } else if self.required(app, false) {
+            self.abort(&tr("error-required-failed", &[("command", &command)]));
+        }
+
+        (captured.status, output)
+    }
+
+    /// Finish a run: print the summary, add the report to the history and publish it over
+    /// MQTT when that's turned on
+    ///
+    /// # Arguments
+    ///
+    /// * `release` - The Linux distribution id, `None` on other platforms
+    pub fn finish(&mut self, release: Option<&str>) {
+        self.record_boot(release);
+        self.print_summary();
+        self.show_report();
+
+        if let Err(error) = history::append(&lock(&self.report)) {
+            eprintln!("WARNING: could not add this run to the history: {}", error);
+        }
+
+        if self.mqtt.enabled {
+            if let Err(error) = mqtt::publish_report(&self.mqtt, &lock(&self.report)) {
+                eprintln!(
+                    "WARNING: could not publish the results over MQTT: {}",
+                    error
+                );
+            }
+        }
+    }
+
+    /// Record the uptime and how long a reboot has been pending for the summary
+    ///
+    /// # Arguments
+    ///
+    /// * `release` - The Linux distribution id, `None` on other platforms
+    pub fn record_boot(&mut self, release: Option<&str>) {
+        let mut report = lock(&self.report);
+
+        report.uptime = boot::uptime();
+        report.reboot_pending_since = boot::reboot_pending_since(release);
+    }
+
+    /// Add the upgrades skipped because of a pin to the report
+    ///
+    /// # Arguments
+    ///
+    /// * `packages` - Each pinned package with what it would have been upgraded to
+    pub fn pinned(&mut self, packages: Vec<String>) {
+        lock(&self.report).pinned.extend(packages);
+    }
+
+    /// Add how the disks and filesystems are doing to the report, warning about any that
+    /// aren't well
+    ///
+    /// # Arguments
+    ///
+    /// * `health` - What each check found
+    pub fn health(&mut self, health: Vec<Health>) {
+        self.warn(
+            health
+                .iter()
+                .filter(|health| !health.ok)
+                .map(|health| {
+                    tr(
+                        "summary-health",
+                        &[("check", &health.check), ("status", &health.status)],
+                    )
+                })
+                .collect(),
+        );
+        lock(&self.report).health.extend(health);
+    }
+
+    /// Add the time zone, certificate and GeoIP packages that were upgraded to the report
+    ///
+    /// # Arguments
+    ///
+    /// * `changed` - The packages and their versions before and after
+    pub fn baseline(&mut self, changed: Vec<ChangedPackage>) {
+        lock(&self.report).baseline.extend(changed);
+    }
+
+    /// Add what apt left out of the upgrade to the report
+    ///
+    /// # Arguments
+    ///
+    /// * `kept_back` - The packages apt kept back
+    /// * `phased` - The phased updates it deferred
+    pub fn held_back(&mut self, kept_back: Vec<String>, phased: Vec<String>) {
+        let mut report = lock(&self.report);
+
+        report.kept_back.extend(kept_back);
+        report.phased.extend(phased);
+    }
+
+    /// Add what Ubuntu Pro covers to the report
+    ///
+    /// # Arguments
+    ///
+    /// * `pro` - The Pro client's status
+    pub fn pro(&mut self, pro: Pro) {
+        lock(&self.report).pro = Some(pro);
+    }
+
+    /// Add what the distribution's automatic updates already did to the report
+    ///
+    /// # Arguments
+    ///
+    /// * `automatic` - What the automatic updates did the last time they ran
+    pub fn automatic(&mut self, automatic: Automatic) {
+        lock(&self.report).automatic = Some(automatic);
+    }
+
+    /// Add problems found by a check to the report
+    ///
+    /// # Arguments
+    ///
+    /// * `warnings` - A message for each problem
+    pub fn warn(&mut self, warnings: Vec<String>) {
+        for warning in warnings.iter() {
+            eprintln!("WARNING: {}", warning);
+        }
+
+        lock(&self.report).warnings.extend(warnings);
+    }
+
+    /// Run an audit tool and add the vulnerabilities it finds to the report
+    ///
+    /// Audit tools exit non-zero when they find something so the command itself isn't
+    /// counted as a failed step. They're asked for JSON, which isn't for reading, so what
+    /// they print is only kept for `parse` and the summary lists what they found.
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The audit tool to run
+    /// * `parse` - Finds the vulnerabilities in the tool's stdout
+    pub fn run_audit(&mut self, app: &App, parse: fn(&str) -> Vec<Vulnerability>) {
+        let app = &match self.as_user(app) {
+            Some(app) => app,
+            None => return,
+        };
+
+        if !self.due(app) {
+            return;
+        }
+
+        let spawned = &self.sandboxed(app);
+
+        if self.note(spawned) {
+            return;
+        }
+
+        self.resolve_secrets();
+
+        self.banner(app, spawned);
+
+        match capture(spawned, &self.env(app), None, &self.redactor) {
+            Ok(captured) => lock(&self.report)
+                .vulnerabilities
+                .extend(parse(&captured.stdout)),
+            Err(error) => panic!("panic:{}", error),
+        }
+
+        self.remember_ran(app);
+    }
+
+    /// Run a list of apps and print out the command and it's arguments before running
+    ///
+    /// # Arguments
+    ///
+    /// * `apps` - A vector of apps to run
+    pub fn run_apps(&mut self, apps: &[App]) {
+        for app in apps.iter() {
+            self.run_app(app);
+        }
+    }
+
+    /// Run a list of steps, asking the user first for any step that needs it
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - A vector of steps to run
+    pub fn run_steps(&mut self, steps: &[Step]) {
+        for step in steps.iter() {
+            if self.accepts(step)
+                && !self.run_app(&step.app).success()
+                && self.required(&step.app, step.required)
+            {
+                let command = self.redactor.redact(&command_line(&step.app));
+
+                self.abort(&tr("error-required-failed", &[("command", &command)]));
+            }
+        }
+    }
+
+    /// Run a list of steps that have to succeed without a network connection
+    ///
+    /// Any failure stops the whole run since it most likely means a package that was
+    /// never fetched had to be downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - A vector of steps to run
+    pub fn run_offline(&mut self, steps: &[Step]) {
+        for step in steps.iter() {
+            if self.accepts(step) && !self.run_app(&step.app).success() {
+                let command = self.redactor.redact(&command_line(&step.app));
+
+                self.abort(&tr("error-offline-failed", &[("command", &command)]));
+            }
+        }
+    }
+
+    /// Decide whether a step that removes things should run, after listing exactly what it
+    /// would remove
+    ///
+    /// The `[cleanup] remove` policy has the last word: `true` removes without asking,
+    /// `"ask"` (the default) asks, which is a no when nobody can answer, and `false`
+    /// only lists what would go.
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The step that removes things
+    /// * `removals` - Everything it would remove, as its simulation said
+    /// * `policy` - The `[cleanup] remove` policy
+    pub fn confirm_removal(&mut self, app: &App, removals: &[String], policy: AssumeYes) -> bool {
+        if self.plan.is_some() {
+            let remove = !removals.is_empty() && policy != AssumeYes::No;
+
+            if remove {
+                self.removals.extend(removals.iter().cloned());
+            }
+
+            return remove;
+        }
+
+        let command = self.redactor.redact(&command_line(app));
+
+        println!();
+
+        if removals.is_empty() {
+            if !self.changed_only {
+                println!("{}", tr("removal-nothing", &[("command", &command)]));
+            }
+
+            return false;
+        }
+
+        println!("{}", tr("removal-list", &[("command", &command)]));
+
+        for removal in removals.iter() {
+            println!("    {}", removal);
+        }
+
+        match policy {
+            AssumeYes::Yes => true,
+            AssumeYes::Ask
+                if self.approved
+                    && removals
+                        .iter()
+                        .all(|removal| self.removals.contains(removal)) =>
+            {
+                true
+            }
+            AssumeYes::Ask => confirm(&tr("prompt-remove", &[])),
+            AssumeYes::No => {
+                println!("{}", tr("removal-off", &[]));
+                false
+            }
+        }
+    }
+
+    /// Remove orphaned packages, the ones `check` lists, once the user has agreed to what
+    /// `remove` would take with them
+    ///
+    /// What would be removed comes from `remove` itself with `--print`, so the
+    /// dependencies `-s` pulls in are listed too. Packages in `[cleanup] keep` are never
+    /// removed: they're left out of the orphans, and if `-s` would still take one of them
+    /// the dependencies are left alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `check` - Lists the orphans one per line, e.g. `pacman -Qtdq`
+    /// * `remove` - Removes the packages given as extra arguments, e.g. `pacman -Rns`
+    /// * `cleanup` - The `[cleanup]` settings
+    pub fn run_orphan_removal(&mut self, check: &App, remove: &Step, cleanup: &config::Cleanup) {
+        let orphans: Vec<String> = match scuttle::run_output(check) {
+            Ok(result) => Parser::Lines
+                .parse(&String::from_utf8_lossy(&result.stdout))
+                .into_iter()
+                .filter(|orphan| !cleanup.keep.contains(orphan))
+                .collect(),
+            Err(error) => panic!("{}", error),
+        };
+
+        if orphans.is_empty() {
+            return;
+        }
+
+        // the same command with --print lists the targets without removing anything
+        let binary = remove
+            .app
+            .args
+            .iter()
+            .find(|arg| arg.as_str() == "pacman" || arg.as_str() == "yay")
+            .unwrap_or(&remove.app.command);
+        let targets = |args: &[String]| -> Vec<String> {
+            let print = App {
+                command: binary.clone(),
+                args: [
+                    &args
+                        .iter()
+                        .filter(|arg| arg.starts_with('-') && arg.as_str() != "--noconfirm")
+                        .cloned()
+                        .collect::<Vec<String>>()[..],
+                    &[
+                        String::from("--print"),
+                        String::from("--print-format"),
+                        String::from("%n %v"),
+                    ],
+                    &orphans[..],
+                ]
+                .concat(),
+            };
+
+            match scuttle::run_output(&print) {
+                Ok(result) if result.status.success() => {
+                    Parser::Lines.parse(&String::from_utf8_lossy(&result.stdout))
+                }
+                // fall back to the orphans themselves
+                _ => orphans.clone(),
+            }
+        };
+        let mut args = remove.app.args.clone();
+        let mut removals = targets(&args);
+        let kept = |removals: &[String]| {
+            removals.iter().any(|removal| {
+                let package = removal.split_whitespace().next().unwrap_or("");
+
+                cleanup.keep.iter().any(|keep| keep == package)
+            })
+        };
+
+        if kept(&removals) {
+            // -Rns would take a kept package along as a dependency, -Rn only the orphans
+            for arg in args.iter_mut().filter(|arg| arg.starts_with("-R")) {
+                *arg = arg.replace('s', "");
+            }
+
+            removals = targets(&args);
+        }
+
+        let remove_orphans = Step {
+            app: App {
+                command: remove.app.command.clone(),
+                args: [&args[..], &orphans[..]].concat(),
+            },
+            // confirm_removal has already asked unless removing is turned on outright
+            ask: remove.ask && cleanup.remove == AssumeYes::Yes,
+            required: remove.required,
+        };
+
+        if self.confirm_removal(&remove_orphans.app, &removals, cleanup.remove) {
+            self.run_steps(&[remove_orphans]);
+        }
+    }
+
+    /// Run a pipeline's producer quietly, parse what it printed and run the consumer
+    /// with the items it found, nothing runs when there are none
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - The producer, how its output is parsed and the consumer it feeds
+    ///
+    /// # Examples
+    /// ```
+    /// let pipeline = Pipeline {
+    ///     producer: App {
+    ///         command: String::from("brew"),
+    ///         args: vec![String::from("outdated"), String::from("--quiet")],
+    ///     },
+    ///     parser: Parser::Lines,
+    ///     consumer: Step {
+    ///         app: App {
+    ///             command: String::from("brew"),
+    ///             args: vec![String::from("fetch")],
+    ///         },
+    ///         ask: false,
+    ///         required: false,
+    ///     },
+    ///     feed: Feed::Args,
+    /// };
+    ///
+    /// runner.run_pipeline(&pipeline);
+    /// ```
+    pub fn run_pipeline(&mut self, pipeline: &Pipeline) {
+        match scuttle::run_output(&pipeline.producer) {
+            Ok(output) => {
+                let steps = pipeline.consumers(&String::from_utf8_lossy(&output.stdout));
+
+                self.run_steps(&steps);
+            }
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Update the apps and runtimes flatpak has updates for, in each installation and only
+    /// from the remotes the config allows, so the summary can tell them apart
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The flatpak settings from the config
+    pub fn run_with_flatpak(&mut self, settings: &config::Flatpak) {
+        for installation in settings.installations.iter() {
+            let flag = format!("--{}", installation.name());
+            let updates = match scuttle::run_output(&App {
+                command: String::from("flatpak"),
+                args: vec![
+                    "remote-ls".to_string(),
+                    "--updates".to_string(),
+                    flag.clone(),
+                    "--columns=origin,ref".to_string(),
+                ],
+            }) {
+                Ok(output) => parsers::parse_flatpak_updates(
+                    &String::from_utf8_lossy(&output.stdout),
+                    &settings.remotes,
+                ),
+                Err(error) => panic!("panic:{}", error),
+            };
+
+            if updates.is_empty() {
+                continue;
+            }
+
+            let mut args = vec!["update".to_string()];
+
+            if settings.assume_yes != AssumeYes::No {
+                args.push("-y".to_string());
+            }
+
+            args.push(flag);
+            args.extend(updates.iter().cloned());
+
+            let step = Step {
+                app: App {
+                    command: String::from("flatpak"),
+                    args,
+                },
+                ask: settings.assume_yes == AssumeYes::Ask,
+                required: false,
+            };
+
+            if !self.accepts(&step) {
+                continue;
+            }
+
+            // nothing was updated when it was only noted down
+            if self.run_app(&step.app).success() && self.plan.is_none() {
+                // app/org.mozilla.firefox/x86_64/stable
+                let (apps, runtimes): (Vec<_>, Vec<_>) = updates
+                    .iter()
+                    .filter_map(|reference| reference.split_once('/'))
+                    .map(|(kind, rest)| (kind, rest.split('/').next().unwrap_or(rest)))
+                    .partition(|(kind, _)| *kind == "app");
+
+                lock(&self.report).flatpak.push(Flatpaked {
+                    installation: installation.name(),
+                    apps: apps.into_iter().map(|(_, id)| id.to_string()).collect(),
+                    runtimes: runtimes.into_iter().map(|(_, id)| id.to_string()).collect(),
+                });
+            }
+        }
+    }
+
+    /// Start a compose project's containers, adding the ones that were recreated to the
+    /// report
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The `up -d` of the project
+    pub fn run_with_recreated(&mut self, app: &App) -> ExitStatus {
+        let (status, output) = self.run_captured(app, |_| Vec::new());
+
+        if status.success() {
+            lock(&self.report)
+                .recreated
+                .extend(parsers::parse_compose_recreated(&output));
+        }
+
+        status
+    }
+
+    /// Reinstall every app `cargo install --list` lists to update it
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The `cargo install --list` app
+    pub fn run_with_cargo(&mut self, app: App) {
+        self.run_pipeline(&Pipeline {
+            producer: app,
+            parser: Parser::Custom(parsers::parse_cargo_list),
+            consumer: Step {
+                app: App {
+                    command: String::from("cargo"),
+                    args: vec!["install".to_string(), "{}".to_string()],
+                },
+                ask: false,
+                required: false,
+            },
+            feed: Feed::Each,
+        });
+    }
+
+    /// Find the Go binaries in `$GOBIN` (or `$GOPATH/bin`) and reinstall each at its latest version
+    pub fn run_with_go(&mut self) {
+        let go_env = |name: &str| match scuttle::run_output(&App {
+            command: String::from("go"),
+            args: vec!["env".to_string(), name.to_string()],
+        }) {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            Err(error) => panic!("panic:{}", error),
+        };
+
+        let bin = match go_env("GOBIN") {
+            gobin if !gobin.is_empty() => PathBuf::from(gobin),
+            _ => match std::env::split_paths(&go_env("GOPATH")).next() {
+                Some(gopath) => gopath.join("bin"),
+                None => return,
+            },
+        };
+
+        if !bin.is_dir() {
+            return;
+        }
+
+        self.run_pipeline(&Pipeline {
+            producer: App {
+                command: String::from("go"),
+                args: vec![
+                    "version".to_string(),
+                    "-m".to_string(),
+                    bin.to_string_lossy().into_owned(),
+                ],
+            },
+            parser: Parser::Custom(parsers::parse_go_version),
+            consumer: Step {
+                app: App {
+                    command: String::from("go"),
+                    args: vec!["install".to_string(), "{}@latest".to_string()],
+                },
+                ask: false,
+                required: false,
+            },
+            feed: Feed::Each,
+        });
+    }
+
+    /// Restart services, asking first for any that need it, and report the ones that were
+    ///
+    /// # Arguments
+    ///
+    /// * `restarts` - The name of each service and the step that restarts it
+    pub fn run_restarts(&mut self, restarts: &[(String, Step)]) {
+        for (service, step) in restarts.iter() {
+            if self.accepts(step) && self.run_app(&step.app).success() {
+                lock(&self.report).restarted.push(service.to_string());
+            }
+        }
+    }
+
+    /// Find the services still running outdated libraries after a system upgrade and
+    /// restart the ones the config allows
+    ///
+    /// `needrestart -b` is used where it's installed, otherwise `dnf needs-restarting -s`.
+    /// With neither `/proc/*/maps` is searched for replaced libraries, the processes that
+    /// aren't part of a service are only listed.
+    ///
+    /// ```text
+    /// NEEDRESTART-VER: 3.6
+    /// NEEDRESTART-SVC: ssh.service
+    /// NEEDRESTART-SVC: systemd-journald.service
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `services` - Whether and which services to restart
+    pub fn run_with_service_restarts(&mut self, services: &config::Services) {
+        let list_app = if has_binary("needrestart") {
+            App {
+                command: String::from("sudo"),
+                args: vec!["needrestart".to_string(), "-b".to_string()],
+            }
+        } else if has_binary("dnf") {
+            App {
+                command: String::from("dnf"),
+                args: vec!["needs-restarting".to_string(), "-s".to_string()],
+            }
+        } else {
+            let mut outdated: Vec<String> = Vec::new();
+
+            for process in maps::scan() {
+                match process.service {
+                    Some(service) if !outdated.contains(&service) => outdated.push(service),
+                    Some(_) => {}
+                    None => lock(&self.report)
+                        .stale_processes
+                        .push(format!("{} ({})", process.name, process.pid)),
+                }
+            }
+
+            self.restart_outdated(outdated.iter().map(String::as_str), services);
+            return;
+        };
+
+        // it needs root, the restarts it finds are only known once it's run
+        if self.note(&list_app) {
+            return;
+        }
+
+        match scuttle::run_output(&list_app) {
+            Ok(output) => {
+                let result = String::from_utf8_lossy(&output.stdout);
+                let outdated = result.lines().filter_map(|line| {
+                    if list_app.args[0] == "needrestart" {
+                        line.strip_prefix("NEEDRESTART-SVC:").map(str::trim)
+                    } else {
+                        Some(line.trim()).filter(|service| service.ends_with(".service"))
+                    }
+                });
+
+                self.restart_outdated(outdated, services);
+            }
+            Err(error) => panic!("panic:{}", error),
+        };
+    }
+
+    /// Restart the services running outdated libraries that the config allows, listing
+    /// the rest in the summary
+    ///
+    /// # Arguments
+    ///
+    /// * `outdated` - The services running outdated libraries
+    /// * `services` - Whether and which services to restart
+    fn restart_outdated<'a>(
+        &mut self,
+        outdated: impl Iterator<Item = &'a str>,
+        services: &config::Services,
+    ) {
+        let mut restarts: Vec<(String, Step)> = Vec::new();
+
+        for service in outdated {
+            if services.restart == AssumeYes::No || !services.allows(service) {
+                lock(&self.report).needs_restart.push(service.to_string());
+                continue;
+            }
+
+            let step = Step {
+                app: App {
+                    command: String::from("sudo"),
+                    args: vec![
+                        "systemctl".to_string(),
+                        "restart".to_string(),
+                        service.to_string(),
+                    ],
+                },
+                ask: services.restart == AssumeYes::Ask,
+                required: false,
+            };
+
+            restarts.push((service.to_string(), step));
+        }
+
+        self.run_restarts(&restarts);
+    }
+
+    /// Parse the output of `brew services list` and restart each started service whose
+    /// formula was just upgraded
+    ///
+    /// ```text
+    /// Name          Status  User File
+    /// postgresql@16 started me   ~/Library/LaunchAgents/homebrew.mxcl.postgresql@16.plist
+    /// redis         none
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `upgraded` - The formulae that were upgraded
+    /// * `brew` - The `[brew]` settings, `restart_services` saying whether to restart, ask
+    ///   first or only say what needs restarting
+    pub fn run_with_brew_services(&mut self, upgraded: &[String], brew: &config::Brew) {
+        let policy = brew.restart_services;
+        let list_app = App {
+            command: brew.executable.binary("brew"),
+            args: vec!["services".to_string(), "list".to_string()],
+        };
+
+        match scuttle::run_output(&list_app) {
+            Ok(output) => {
+                let result = String::from_utf8_lossy(&output.stdout);
+                let mut restarts: Vec<(String, Step)> = Vec::new();
+
+                for line in result.lines().skip(1) {
+                    let mut columns = line.split_whitespace();
+
+                    if let (Some(service), Some("started")) = (columns.next(), columns.next()) {
+                        if !upgraded.iter().any(|formula| formula == service) {
+                            continue;
+                        }
+
+                        let app = App {
+                            command: brew.executable.binary("brew"),
+                            args: vec![
+                                "services".to_string(),
+                                "restart".to_string(),
+                                service.to_string(),
+                            ],
+                        };
+
+                        if policy == AssumeYes::No {
+                            self.skip_app(&app, "reason-restart-services-off");
+                        } else {
+                            let step = Step {
+                                app,
+                                ask: policy == AssumeYes::Ask,
+                                required: false,
+                            };
+
+                            restarts.push((service.to_string(), step));
+                        }
+                    }
+                }
+
+                self.run_restarts(&restarts);
+            }
+            Err(error) => panic!("panic:{}", error),
+        };
+    }
+
+    /// Add back any of the expected components missing from an installed toolchain
+    ///
+    /// Nightly updates occasionally drop a component that isn't available that day so
+    /// every toolchain from `rustup toolchain list` is checked after updating.
+    ///
+    /// ```text
+    /// stable-x86_64-unknown-linux-gnu (default)
+    /// nightly-x86_64-unknown-linux-gnu
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `components` - The components every toolchain should have
+    pub fn run_with_rust_components(&mut self, components: &[String]) {
+        let list_app = App {
+            command: String::from("rustup"),
+            args: vec!["toolchain".to_string(), "list".to_string()],
+        };
+
+        match scuttle::run_output(&list_app) {
+            Ok(output) => {
+                let result = String::from_utf8_lossy(&output.stdout);
+                let toolchains = result
+                    .lines()
+                    .filter_map(|line| line.split_whitespace().next());
+
+                for toolchain in toolchains {
+                    let installed_app = App {
+                        command: String::from("rustup"),
+                        args: vec![
+                            "component".to_string(),
+                            "list".to_string(),
+                            "--installed".to_string(),
+                            "--toolchain".to_string(),
+                            toolchain.to_string(),
+                        ],
+                    };
+                    let installed = match scuttle::run_output(&installed_app) {
+                        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+                        Err(error) => panic!("panic:{}", error),
+                    };
+
+                    let missing = components.iter().filter(|component| {
+                        !parsers::has_rust_component(&installed, component, toolchain)
+                    });
+
+                    for component in missing {
+                        let component_add_app = App {
+                            command: String::from("rustup"),
+                            args: vec![
+                                "component".to_string(),
+                                "add".to_string(),
+                                component.to_string(),
+                                "--toolchain".to_string(),
+                                toolchain.to_string(),
+                            ],
+                        };
+
+                        self.run_apps(&[component_add_app]);
+                    }
+                }
+            }
+            Err(error) => panic!("panic:{}", error),
+        };
+    }
+
+    /// Update each global tool `dotnet tool list -g` lists
+    pub fn run_with_dotnet(&mut self) {
+        self.run_pipeline(&Pipeline {
+            producer: App {
+                command: String::from("dotnet"),
+                args: vec!["tool".to_string(), "list".to_string(), "-g".to_string()],
+            },
+            parser: Parser::Custom(parsers::parse_dotnet_tools),
+            consumer: Step {
+                app: App {
+                    command: String::from("dotnet"),
+                    args: vec![
+                        "tool".to_string(),
+                        "update".to_string(),
+                        "-g".to_string(),
+                        "{}".to_string(),
+                    ],
+                },
+                ask: false,
+                required: false,
+            },
+            feed: Feed::Each,
+        });
+    }
+
+    /// Update each plugin `helm plugin list` lists, the first column under its header
+    ///
+    /// ```text
+    /// NAME    VERSION DESCRIPTION
+    /// diff    3.9.4   Preview helm upgrade changes as a diff
+    /// ```
+    pub fn run_with_helm_plugins(&mut self) {
+        self.run_pipeline(&Pipeline {
+            producer: App {
+                command: String::from("helm"),
+                args: vec!["plugin".to_string(), "list".to_string()],
+            },
+            parser: Parser::Field { index: 0, skip: 1 },
+            consumer: Step {
+                app: App {
+                    command: String::from("helm"),
+                    args: vec!["plugin".to_string(), "update".to_string(), "{}".to_string()],
+                },
+                ask: false,
+                required: false,
+            },
+            feed: Feed::Each,
+        });
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            lock(&self.report).outcome = Outcome::Panicked;
+        }
+
+        save(&self.report, self.report_file.as_ref());
+    }
+}
+
+/// Quote a word for a POSIX shell, leaving it as it is when nothing in it needs quoting
+///
+/// # Arguments
+///
+/// * `word` - The command or argument to quote
+pub fn shell_quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// An app as a command line that can be pasted into a shell to run it again, e.g.
+/// `nvim --headless '+Lazy! sync' +qa`
+///
+/// # Arguments
+///
+/// * `app` - The app to write out
+pub fn command_line(app: &App) -> String {
+    std::iter::once(&app.command)
+        .chain(app.args.iter())
+        .map(|word| shell_quote(word))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// What replaces `sudo` in the commands when sudo would have to ask for a password without
+/// a terminal to ask on, `None` when there's a terminal, it's not a desktop session or
+/// sudo doesn't need a password
+///
+/// # Arguments
+///
+/// * `settings` - The `[sudo]` settings from the config
+fn escalation(settings: &config::Sudo) -> Option<Vec<String>> {
+    if io::stdin().is_terminal() || !detect::graphical_session() || detect::is_root() {
+        return None;
+    }
+
+    // a NOPASSWD rule or a password typed a minute ago doesn't need asking for
+    let needs_password = !scuttle::run_output(&App {
+        command: String::from("sudo"),
+        args: vec!["-n".to_string(), "true".to_string()],
+    })
+    .is_ok_and(|output| output.status.success());
+
+    if !needs_password {
+        return None;
+    }
+
+    let askpass = settings.askpass.clone().or_else(|| {
+        env::var_os("SUDO_ASKPASS")
+            .filter(|askpass| !askpass.is_empty())
+            .map(PathBuf::from)
+    });
+
+    match (settings.without_terminal, askpass) {
+        (config::Escalation::Sudo, _) => None,
+        (config::Escalation::Auto | config::Escalation::Askpass, Some(askpass)) => {
+            env::set_var("SUDO_ASKPASS", askpass);
+            Some(vec!["sudo".to_string(), "-A".to_string()])
+        }
+        (config::Escalation::Auto | config::Escalation::Pkexec, _) if has_binary("pkexec") => {
+            Some(vec!["pkexec".to_string()])
+        }
+        (config::Escalation::Askpass, None) => {
+            eprintln!("WARNING: [sudo] without_terminal is askpass but there's no askpass helper, set [sudo] askpass or SUDO_ASKPASS");
+            None
+        }
+        _ => {
+            eprintln!("WARNING: sudo has no terminal to ask for the password on and pkexec isn't installed");
+            None
+        }
+    }
+}
+
+/// The name a step goes by in the config: its command without the directory, not counting
+/// `sudo` and its options, `env` or environment variables
+///
+/// # Arguments
+///
+/// * `app` - The app to name
+fn step_name(app: &App) -> &str {
+    let mut words = std::iter::once(&app.command)
+        .chain(app.args.iter())
+        .map(String::as_str);
+    let mut sudo = false;
+    let mut name = app.command.as_str();
+
+    while let Some(word) = words.next() {
+        if word == "sudo" {
+            sudo = true;
+        } else if sudo && word.starts_with('-') {
+            if SUDO_VALUE_OPTIONS.contains(&word) {
+                words.next();
+            }
+        } else if word != "env" && !word.contains('=') {
+            name = word;
+            break;
+        }
+    }
+
+    name.rsplit(['/', '\\']).next().unwrap_or_default()
+}
+
+/// The user a step runs as and the step itself when it's been made to run as the user
+/// who started up2date with sudo, `sudo -u <user> -H <command>`
+///
+/// # Arguments
+///
+/// * `app` - The app about to run, as it runs for who it runs as
+fn dropped(app: &App) -> Option<(&str, App)> {
+    match app.args.as_slice() {
+        [u, user, h, command, args @ ..] if app.command == "sudo" && u == "-u" && h == "-H" => {
+            Some((
+                user.as_str(),
+                App {
+                    command: command.clone(),
+                    args: args.to_vec(),
+                },
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// The app with its shell script made to fail when any command in a pipeline does, not
+/// only the last one, the app itself when it isn't a shell script with a pipe
+///
+/// bash also says which commands of the pipeline failed, with a `parsers::PIPE_STATUS`
+/// line on stderr that's kept out of what's shown. sh only gets pipefail where it has
+/// it, dash doesn't.
+///
+/// # Arguments
+///
+/// * `app` - The app about to be spawned
+fn pipefail(app: &App) -> App {
+    let mut words: Vec<String> = std::iter::once(&app.command)
+        .chain(app.args.iter())
+        .cloned()
+        .collect();
+    // the script follows the shell and its `-c`, which can be grouped like `-lc`
+    let script = words.windows(3).position(|window| {
+        let shell = window[0].rsplit(['/', '\\']).next().unwrap_or_default();
+
+        SHELLS.contains(&shell)
+            && window[1].starts_with('-')
+            && !window[1].starts_with("--")
+            && window[1].ends_with('c')
+            && window[2].replace("||", "").contains('|')
+    });
+
+    if let Some(position) = script {
+        let shell = words[position]
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default();
+        let setup = match shell {
+            // the trap also fires for a single command failing, which says nothing the
+            // exit code doesn't, so only a pipeline with a failed command is printed
+            "bash" => format!(
+                "set -o pipefail\n\
+                 trap 'up2date_status=(\"${{PIPESTATUS[@]}}\"); \
+                 [[ ${{#up2date_status[@]}} -gt 1 && ${{up2date_status[*]}} =~ [1-9] ]] && \
+                 printf \"%s %s\\n\" \"{}\" \"${{up2date_status[*]}}\" >&2' ERR",
+                parsers::PIPE_STATUS
+            ),
+            "zsh" => String::from("set -o pipefail"),
+            _ => String::from("(set -o pipefail) 2>/dev/null && set -o pipefail"),
+        };
+        let script = &mut words[position + 2];
+
+        *script = format!("{}\n{}", setup, script);
+    }
+
+    App {
+        command: words.remove(0),
+        args: words,
+    }
+}
+
+/// What a package manager said to read in its output, nothing for everything else
+///
+/// # Arguments
+///
+/// * `app` - The app that ran
+/// * `output` - Everything it printed
+fn notes(app: &App, output: &str) -> Vec<Note> {
+    let parse = match step_name(app) {
+        "brew" => parsers::parse_brew_caveats,
+        "pacman" | "yay" | "paru" | "powerpill" => parsers::parse_pacman_messages,
+        "apt-get" | "apt" | "apt-fast" => parsers::parse_apt_news,
+        _ => return Vec::new(),
+    };
+
+    parse(output)
+}
+
+/// Check whether a step should run, asking the user first if the step needs it
+///
+/// # Arguments
+///
+/// * `step` - The step that is about to run
+/// * `redactor` - What hides the credentials in the command that's shown
+fn accepted(step: &Step, redactor: &Redactor) -> bool {
+    let command = redactor.redact(&command_line(&step.app));
+
+    !step.ask || confirm(&tr("prompt-run", &[("command", &command)]))
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// The file in the state directory that remembers when each step last succeeded, one
+/// `<step> <seconds since the Unix epoch>` per line
+fn last_ran_path() -> Option<PathBuf> {
+    Some(config::state_dir()?.join("last-ran"))
+}
+
+/// When each step last succeeded, nothing when it hasn't been remembered yet
+fn read_last_ran() -> HashMap<String, u64> {
+    let contents = match last_ran_path().and_then(|path| fs::read_to_string(path).ok()) {
+        Some(contents) => contents,
+        None => return HashMap::new(),
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (step, seconds) = line.split_once(' ')?;
+
+            Some((step.to_string(), seconds.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Remember when each step last succeeded
+///
+/// # Arguments
+///
+/// * `last_ran` - When each step last succeeded, by step name
+fn write_last_ran(last_ran: &HashMap<String, u64>) {
+    let path = match last_ran_path() {
+        Some(path) => path,
+        None => return,
+    };
+    let mut steps: Vec<(&String, &u64)> = last_ran.iter().collect();
+
+    steps.sort();
+
+    let contents: String = steps
+        .iter()
+        .map(|(step, seconds)| format!("{} {}\n", step, seconds))
+        .collect();
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    if let Err(error) = fs::write(&path, contents) {
+        eprintln!("WARNING: could not write {}: {}", path.display(), error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(command: &str, args: &[&str]) -> App {
+        App {
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    /// `cargo install-update -a` as it runs once it's dropped to alice
+    fn dropped_cargo() -> App {
+        app(
+            "sudo",
+            &["-u", "alice", "-H", "cargo", "install-update", "-a"],
+        )
+    }
+
+    #[test]
+    fn step_name_skips_sudo_env_and_variables() {
+        assert_eq!(step_name(&app("/usr/bin/apt-get", &["upgrade"])), "apt-get");
+        assert_eq!(step_name(&app("sudo", &["apt-get", "upgrade"])), "apt-get");
+        assert_eq!(
+            step_name(&app(
+                "sudo",
+                &["env", "DEBIAN_FRONTEND=noninteractive", "apt-get"]
+            )),
+            "apt-get"
+        );
+        assert_eq!(step_name(&dropped_cargo()), "cargo");
+        assert_eq!(
+            step_name(&app("sudo", &["-E", "-g", "wheel", "--", "pacman", "-Syu"])),
+            "pacman"
+        );
+    }
+
+    #[test]
+    fn dropped_finds_the_user_and_the_step() {
+        let cargo = dropped_cargo();
+        let (user, inner) = dropped(&cargo).unwrap();
+
+        assert_eq!(user, "alice");
+        assert_eq!(inner.command, "cargo");
+        assert_eq!(inner.args, vec!["install-update", "-a"]);
+        assert!(dropped(&app("sudo", &["apt-get", "upgrade"])).is_none());
+        assert!(dropped(&app("cargo", &["install-update", "-a"])).is_none());
+    }
+
+    #[test]
+    fn dropped_step_keeps_its_policy() {
+        let mut config = Config::default();
+
+        config.steps.insert(
+            String::from("cargo"),
+            config::StepPolicy {
+                required: Some(true),
+                sandbox: Some(Sandbox::Bwrap),
+                ..config::StepPolicy::default()
+            },
+        );
+
+        let mut runner = Runner::planning(&config, &[]);
+
+        runner.secrets = Some(vec![secrets::Secret {
+            env: String::from("CARGO_REGISTRY_TOKEN"),
+            value: String::from("token"),
+            steps: vec![String::from("cargo")],
+        }]);
+
+        assert!(runner.required(&dropped_cargo(), false));
+        assert_eq!(
+            runner.env(&dropped_cargo()),
+            vec![("CARGO_REGISTRY_TOKEN", "token")]
+        );
+
+        // sandboxed as alice, not left alone as if it ran as root
+        let sandboxed = runner.sandboxed(&dropped_cargo());
+
+        assert_eq!(sandboxed.command, "sudo");
+        assert_eq!(sandboxed.args[..4], ["-u", "alice", "-H", "bwrap"]);
+        assert_eq!(
+            sandboxed.args[sandboxed.args.len() - 4..],
+            ["--", "cargo", "install-update", "-a"]
+        );
+        assert!(lock(&runner.report).warnings.is_empty());
+    }
+}
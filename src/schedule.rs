@@ -0,0 +1,286 @@
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::thread;
+use std::time::Duration;
+
+use scuttle::App;
+
+use crate::boot;
+use crate::config::Schedule;
+use crate::i18n::tr;
+
+/// The days as `date +%u` numbers them, Monday being 1
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// When runs may update, e.g. `Mon-Fri 02:00-05:00`
+#[derive(Debug, PartialEq, Eq)]
+struct Window {
+    /// Whether the window is open on each day, Monday first
+    days: [bool; 7],
+    /// The minute of the day the window opens
+    start: u32,
+    /// The minute of the day it closes, before `start` when it goes past midnight into
+    /// the next day
+    end: u32,
+}
+
+/// Whether up2date was started by a timer or cron: `--scheduled` was given, which the
+/// cron entry has to pass, or it runs in a systemd unit, which sets `INVOCATION_ID`
+///
+/// Not having a terminal says nothing, `--stdin`, ssh and configuration management
+/// don't have one either.
+///
+/// # Arguments
+///
+/// * `flag` - Whether `--scheduled` was given
+pub fn scheduled(flag: bool) -> bool {
+    flag || env::var_os("INVOCATION_ID").is_some_and(|id| !id.is_empty())
+}
+
+/// Turn `HH:MM` into the minute of the day
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Turn a day like `mon` into its index, Monday being 0
+fn parse_day(day: &str) -> Option<usize> {
+    let day = day.to_lowercase();
+
+    DAYS.iter().position(|known| day.starts_with(known))
+}
+
+/// Parse a window from the config: an optional list of days or day ranges followed by
+/// the times it opens and closes, e.g. `Mon-Fri 02:00-05:00`, `Sat,Sun 22:00-06:00` or
+/// `03:00-04:00` for every day
+///
+/// # Arguments
+///
+/// * `window` - The window as it's written in `[schedule] windows`
+fn parse_window(window: &str) -> Result<Window, String> {
+    let invalid = || format!("invalid [schedule] window: {}", window);
+    let (days, times) = match window.trim().rsplit_once(' ') {
+        Some((days, times)) => (Some(days.trim()), times),
+        None => (None, window.trim()),
+    };
+    let (start, end) = times.split_once('-').ok_or_else(invalid)?;
+    let mut open = [days.is_none(); 7];
+
+    for part in days
+        .unwrap_or("")
+        .split(',')
+        .filter(|part| !part.is_empty())
+    {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let first = parse_day(first.trim()).ok_or_else(invalid)?;
+        let last = parse_day(last.trim()).ok_or_else(invalid)?;
+
+        // Sat-Mon wraps around the end of the week
+        let mut day = first;
+
+        loop {
+            open[day] = true;
+
+            if day == last {
+                break;
+            }
+
+            day = (day + 1) % 7;
+        }
+    }
+
+    Ok(Window {
+        days: open,
+        start: parse_time(start).ok_or_else(invalid)?,
+        end: parse_time(end).ok_or_else(invalid)?,
+    })
+}
+
+/// The local day of the week, Monday being 0, and the minute of the day
+///
+/// The standard library doesn't know the local timezone, `date` does.
+fn now() -> Option<(usize, u32)> {
+    let app = if cfg!(windows) {
+        App {
+            command: String::from("powershell"),
+            args: vec![
+                "-NoProfile".to_string(),
+                "-Command".to_string(),
+                "Get-Date -UFormat '%u %H:%M'".to_string(),
+            ],
+        }
+    } else {
+        App {
+            command: String::from("date"),
+            args: vec!["+%u %H:%M".to_string()],
+        }
+    };
+    let output = scuttle::run_output(&app).ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let (day, time) = output.trim().split_once(' ')?;
+    let day: usize = day.parse().ok()?;
+
+    Some(((day + 6) % 7, parse_time(time)?))
+}
+
+/// Whether now is outside every window `[schedule]` allows, never when it doesn't set
+/// any
+///
+/// # Arguments
+///
+/// * `schedule` - The `[schedule]` settings from the config
+pub fn outside_windows(schedule: &Schedule) -> Result<bool, String> {
+    if schedule.windows.is_empty() {
+        return Ok(false);
+    }
+
+    let windows = schedule
+        .windows
+        .iter()
+        .map(|window| parse_window(window))
+        .collect::<Result<Vec<Window>, String>>()?;
+    let (day, minute) = now().ok_or_else(|| String::from("could not find the local time"))?;
+
+    Ok(!windows.iter().any(|window| window.is_open(day, minute)))
+}
+
+impl Window {
+    /// Whether the window is open at a time of the week
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - The day, Monday being 0
+    /// * `minute` - The minute of the day
+    fn is_open(&self, day: usize, minute: u32) -> bool {
+        // after midnight a window is still open from the day before
+        let yesterday = (day + 6) % 7;
+
+        if self.start <= self.end {
+            self.days[day] && self.start <= minute && minute < self.end
+        } else {
+            (self.days[day] && minute >= self.start) || (self.days[yesterday] && minute < self.end)
+        }
+    }
+}
+
+/// A random number of seconds up to `max`
+///
+/// Each `RandomState` is seeded randomly, which is random enough to spread machines out.
+fn random_up_to(max: u64) -> u64 {
+    match max {
+        0 => 0,
+        max => RandomState::new().build_hasher().finish() % (max + 1),
+    }
+}
+
+/// Wait until the machine has been up for `[schedule] boot_delay` and then a random part
+/// of `jitter`, so a room full of machines doesn't hit the same mirror at once
+///
+/// # Arguments
+///
+/// * `schedule` - The `[schedule]` settings from the config
+pub fn wait(schedule: &Schedule) {
+    let boot = match boot::uptime() {
+        Some(uptime) => schedule.boot_delay.saturating_sub(uptime),
+        None => 0,
+    };
+    let seconds = boot + random_up_to(schedule.jitter);
+
+    if seconds > 0 {
+        println!("{}", tr("schedule-waiting", &[("seconds", &seconds)]));
+        thread::sleep(Duration::from_secs(seconds));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_days_and_times() {
+        assert_eq!(
+            parse_window("Mon-Fri 02:00-05:00"),
+            Ok(Window {
+                days: [true, true, true, true, true, false, false],
+                start: 120,
+                end: 300,
+            })
+        );
+        assert_eq!(
+            parse_window("03:00-04:00").map(|window| window.days),
+            Ok([true; 7])
+        );
+        assert_eq!(
+            parse_window("Sat,Sun 22:00-06:00").map(|window| window.days),
+            Ok([false, false, false, false, false, true, true])
+        );
+    }
+
+    #[test]
+    fn window_day_range_wraps_around_the_week() {
+        assert_eq!(
+            parse_window("Sat-Mon 01:00-02:00").map(|window| window.days),
+            Ok([true, false, false, false, false, true, true])
+        );
+    }
+
+    #[test]
+    fn window_rejects_bad_input() {
+        for window in [
+            "",
+            "02:00",
+            "Mon-Fri",
+            "Mon-Fri 2-5",
+            "Funday 02:00-05:00",
+            "Mon 24:00-05:00",
+            "Mon 02:60-05:00",
+            "Mon 02:00-5",
+        ] {
+            assert!(parse_window(window).is_err(), "{:?} was accepted", window);
+        }
+    }
+
+    #[test]
+    fn window_is_open_between_its_times() {
+        let window = parse_window("Mon-Fri 02:00-05:00").unwrap();
+
+        assert!(window.is_open(0, 120));
+        assert!(window.is_open(4, 299));
+        assert!(!window.is_open(0, 300));
+        assert!(!window.is_open(0, 119));
+        assert!(!window.is_open(5, 180));
+    }
+
+    #[test]
+    fn window_past_midnight_belongs_to_the_day_it_opens() {
+        let window = parse_window("Sat,Sun 22:00-06:00").unwrap();
+
+        // Saturday night and the early hours of Sunday and Monday
+        assert!(window.is_open(5, 23 * 60));
+        assert!(window.is_open(6, 60));
+        assert!(window.is_open(0, 5 * 60 + 59));
+        // Friday night didn't open it, and Monday night doesn't either
+        assert!(!window.is_open(5, 60));
+        assert!(!window.is_open(0, 23 * 60));
+        assert!(!window.is_open(1, 60));
+    }
+
+    #[test]
+    fn no_windows_is_never_outside() {
+        assert_eq!(outside_windows(&Schedule::default()), Ok(false));
+    }
+
+    #[test]
+    fn a_bad_window_is_an_error() {
+        let schedule = Schedule {
+            windows: vec![String::from("whenever")],
+            ..Schedule::default()
+        };
+
+        assert!(outside_windows(&schedule).is_err());
+    }
+}
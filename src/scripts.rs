@@ -0,0 +1,286 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config;
+#[cfg(feature = "lua")]
+use crate::config::Group;
+#[cfg(feature = "lua")]
+use crate::runner::Step;
+
+/// A Lua script from the scripts directory whose `detect` found something to update
+#[cfg(feature = "lua")]
+pub struct Script {
+    pub path: PathBuf,
+    /// The group its steps are run in
+    pub group: Group,
+}
+
+/// The `.lua` files in `scripts/` next to the config file, in name order
+fn files() -> Vec<PathBuf> {
+    let dir = match config::path().as_deref().and_then(|path| path.parent()) {
+        Some(dir) => dir.join("scripts"),
+        None => return Vec::new(),
+    };
+    let mut files: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "lua"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    files.sort();
+    files
+}
+
+/// What the scripts are told and allowed through the `up2date` table
+#[cfg(feature = "lua")]
+pub struct Context<'a> {
+    /// The Linux distribution id, `None` on other platforms
+    pub release: Option<&'a str>,
+    /// Whether the run is only being planned, `output` runs nothing then
+    pub planning: bool,
+    /// The settings for the scripts from the config
+    pub settings: &'a config::Scripts,
+}
+
+/// Load every script and keep the ones whose `detect` says they have something to do,
+/// without calling `detect` for the scripts in a group that's turned off
+///
+/// A script returns a table with a `detect` and a `plan` function and, optionally, the
+/// group its steps belong to, `system` when it's left out:
+///
+/// ```lua
+/// return {
+///   group = "languages",
+///   detect = function(up2date) return up2date.has_binary("opam") end,
+///   plan = function(up2date)
+///     return { { command = "opam", args = { "upgrade", "-y" } } }
+///   end,
+/// }
+/// ```
+///
+/// Scripts only get Lua's string, table, math and utf8 libraries and the `up2date`
+/// table, so the only way to look at the machine is through what that table offers.
+/// Scripts that fail are warned about and left out.
+///
+/// # Arguments
+///
+/// * `context` - What the scripts are told and allowed
+/// * `group` - Whether a group is turned on
+#[cfg(feature = "lua")]
+pub fn discover(context: &Context, mut group: impl FnMut(Group) -> bool) -> Vec<Script> {
+    files()
+        .into_iter()
+        .filter_map(|path| match lua::detect(&path, context, &mut group) {
+            Ok(Some(group)) => Some(Script { path, group }),
+            Ok(None) => None,
+            Err(error) => {
+                eprintln!("WARNING: {} detect failed: {}", path.display(), error);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Warn that the scripts can't be run when up2date was built without Lua, if there are any
+#[cfg(not(feature = "lua"))]
+pub fn warn_unsupported() {
+    if let Some(path) = files().first() {
+        eprintln!(
+            "WARNING: up2date was built without the lua feature, not running {} or any other script",
+            path.display()
+        );
+    }
+}
+
+/// Ask a script for the steps it would like run, from what its `plan` returns: a list of
+/// tables with the `command`, `args` and `required` an app on `--stdin` has
+///
+/// # Arguments
+///
+/// * `script` - The script to ask
+/// * `context` - What the script is told and allowed
+#[cfg(feature = "lua")]
+pub fn steps(script: &Script, context: &Context) -> Vec<Step> {
+    match lua::plan(&script.path, context) {
+        Ok(steps) => steps,
+        Err(error) => {
+            eprintln!("WARNING: {} plan failed: {}", script.path.display(), error);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(feature = "lua")]
+mod lua {
+    use std::fs;
+    use std::path::Path;
+
+    use mlua::{Function, Lua, LuaOptions, StdLib, Table};
+    use scuttle::App;
+
+    use super::Context;
+    use crate::config::Group;
+    use crate::detect;
+    use crate::runner::Step;
+    use crate::template;
+
+    /// The `up2date` table every function in a script is called with: `os`, `release`,
+    /// `planning`, `has_binary(name)` and `output(command, args)`, which runs one of the
+    /// commands `[scripts] output` lists quietly and returns its stdout and whether it
+    /// succeeded. Any other command is an error, and nothing is run while planning.
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - The Lua the script is loaded in
+    /// * `context` - What the script is told and allowed
+    fn api<'lua>(lua: &'lua Lua, context: &Context) -> mlua::Result<Table<'lua>> {
+        let api = lua.create_table()?;
+        let allowed: Vec<String> = context
+            .settings
+            .output
+            .iter()
+            .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .collect();
+        let planning = context.planning;
+
+        api.set("os", detect::os())?;
+        api.set("release", context.release)?;
+        api.set("planning", planning)?;
+        api.set(
+            "has_binary",
+            lua.create_function(|_, name: String| Ok(detect::has_binary(&name)))?,
+        )?;
+        api.set(
+            "output",
+            lua.create_function(move |_, (command, args): (String, Option<Vec<String>>)| {
+                let app = App {
+                    command,
+                    args: args.unwrap_or_default(),
+                };
+                let line = std::iter::once(&app.command)
+                    .chain(app.args.iter())
+                    .map(String::as_str)
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+
+                if !allowed.contains(&line) {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "`{}` isn't in [scripts] output",
+                        line
+                    )));
+                }
+
+                if planning {
+                    return Ok((String::new(), false));
+                }
+
+                Ok(match scuttle::run_output(&app) {
+                    Ok(output) => (
+                        String::from_utf8_lossy(&output.stdout).into_owned(),
+                        output.status.success(),
+                    ),
+                    Err(_) => (String::new(), false),
+                })
+            })?,
+        )?;
+
+        Ok(api)
+    }
+
+    /// Load a script in a Lua that can't reach the filesystem, the network or other
+    /// processes besides the commands `[scripts] output` lists, and call something on
+    /// what it returned
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The script
+    /// * `context` - What the script is told and allowed
+    /// * `call` - Gets the script's table and the `up2date` table
+    fn load<T>(
+        path: &Path,
+        context: &Context,
+        call: impl for<'lua> FnOnce(Table<'lua>, Table<'lua>) -> mlua::Result<T>,
+    ) -> Result<T, String> {
+        let source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let lua = Lua::new_with(
+            StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8,
+            LuaOptions::default(),
+        )
+        .map_err(|error| error.to_string())?;
+        let script: Table = lua
+            .load(source.as_str())
+            .set_name(path.display().to_string())
+            .eval()
+            .map_err(|error| error.to_string())?;
+        let api = api(&lua, context).map_err(|error| error.to_string())?;
+
+        call(script, api).map_err(|error| error.to_string())
+    }
+
+    /// Call a script's `detect`, returning its group when it found something to update,
+    /// `None` without calling it when its group is turned off
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The script
+    /// * `context` - What the script is told and allowed
+    /// * `enabled` - Whether a group is turned on
+    pub fn detect(
+        path: &Path,
+        context: &Context,
+        enabled: &mut impl FnMut(Group) -> bool,
+    ) -> Result<Option<Group>, String> {
+        load(path, context, |script, api| {
+            let group = match script.get::<_, Option<String>>("group")? {
+                Some(name) => Group::parse(&name)
+                    .ok_or_else(|| mlua::Error::RuntimeError(format!("unknown group: {}", name)))?,
+                None => Group::System,
+            };
+
+            if !enabled(group) {
+                return Ok(None);
+            }
+
+            let detect: Function = script.get("detect")?;
+
+            Ok(detect.call::<_, bool>(api)?.then_some(group))
+        })
+    }
+
+    /// Call a script's `plan` and turn what it returned into steps
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The script
+    /// * `context` - What the script is told and allowed
+    pub fn plan(path: &Path, context: &Context) -> Result<Vec<Step>, String> {
+        let variables = template::variables();
+
+        load(path, context, |script, api| {
+            let plan: Function = script.get("plan")?;
+            let apps: Vec<Table> = plan.call(api)?;
+
+            apps.into_iter()
+                .map(|app| {
+                    Ok(Step {
+                        app: App {
+                            command: app.get("command")?,
+                            args: app
+                                .get::<_, Option<Vec<String>>>("args")?
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|arg| template::expand(arg, &variables))
+                                .collect(),
+                        },
+                        // like the apps on --stdin nothing a script plans asks first
+                        ask: false,
+                        required: app.get::<_, Option<bool>>("required")?.unwrap_or(false),
+                    })
+                })
+                .collect()
+        })
+    }
+}
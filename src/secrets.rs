@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use scuttle::App;
+
+use crate::config;
+use crate::detect;
+
+/// A secret that was found, ready to be handed to the steps that need it
+#[derive(Clone)]
+pub struct Secret {
+    /// The environment variable the steps see it in
+    pub env: String,
+    pub value: String,
+    /// The steps that get it, every step when empty
+    pub steps: Vec<String>,
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Run a command and return what it printed with the trailing newline removed, `None`
+/// when it fails
+fn output(command_line: &[&str]) -> Option<String> {
+    let app = App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    };
+    let output = scuttle::run_output(&app).ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches('\n')
+            .to_string(),
+    )
+}
+
+/// Look a secret up in the login keyring, libsecret on Linux and the keychain on macOS
+///
+/// Store it first with `secret-tool store --label up2date service up2date key <name>` or
+/// `security add-generic-password -s up2date -a <name> -w`.
+///
+/// # Arguments
+///
+/// * `name` - The key the secret was stored under
+fn keyring(name: &str) -> Option<String> {
+    if detect::os() == "macos" {
+        output(&[
+            "security",
+            "find-generic-password",
+            "-s",
+            "up2date",
+            "-a",
+            name,
+            "-w",
+        ])
+    } else {
+        output(&["secret-tool", "lookup", "service", "up2date", "key", name])
+    }
+}
+
+/// Decrypt an age encrypted TOML file of `name = "value"` pairs
+///
+/// # Arguments
+///
+/// * `file` - The encrypted file
+/// * `identity` - The age identity that decrypts it
+fn age(file: &Path, identity: &Path) -> Option<toml::Table> {
+    let file = expand(file);
+    let identity = expand(identity);
+    let decrypted = output(&[
+        "age",
+        "--decrypt",
+        "--identity",
+        &identity.to_string_lossy(),
+        &file.to_string_lossy(),
+    ])?;
+
+    toml::from_str(&decrypted).ok()
+}
+
+/// Find the value of every configured secret
+///
+/// A secret that can't be found is left out with a warning naming it, values are never
+/// printed.
+///
+/// # Arguments
+///
+/// * `secrets` - The secrets from the config file, by name
+pub fn resolve(secrets: &HashMap<String, config::Secret>) -> Vec<Secret> {
+    // each age file is only decrypted once however many secrets it holds
+    let mut decrypted: HashMap<PathBuf, Option<toml::Table>> = HashMap::new();
+    let mut resolved: Vec<Secret> = Vec::new();
+
+    for (name, secret) in secrets.iter() {
+        let value = if let Some(variable) = secret.from_env.as_deref() {
+            env::var(variable).ok()
+        } else if let Some(key) = secret.keyring.as_deref() {
+            keyring(key)
+        } else if let (Some(file), Some(identity)) =
+            (secret.age_file.as_ref(), secret.age_identity.as_ref())
+        {
+            decrypted
+                .entry(file.clone())
+                .or_insert_with(|| age(file, identity))
+                .as_ref()
+                .and_then(|table| table.get(name))
+                .and_then(|value| value.as_str())
+                .map(String::from)
+        } else {
+            env::var(&secret.env).ok()
+        };
+
+        match value {
+            Some(value) => resolved.push(Secret {
+                env: secret.env.clone(),
+                value,
+                steps: secret.steps.clone(),
+            }),
+            None => eprintln!(
+                "WARNING: could not find the secret {}, steps that need it may fail",
+                name
+            ),
+        }
+    }
+
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(env: &str) -> config::Secret {
+        config::Secret {
+            env: env.to_string(),
+            ..config::Secret::default()
+        }
+    }
+
+    #[test]
+    fn expand_replaces_only_a_leading_tilde() {
+        let home = PathBuf::from(env::var_os("HOME").unwrap());
+
+        assert_eq!(
+            expand(Path::new("~/.config/key.txt")),
+            home.join(".config/key.txt")
+        );
+        assert_eq!(
+            expand(Path::new("/etc/~/key.txt")),
+            Path::new("/etc/~/key.txt")
+        );
+        assert_eq!(expand(Path::new("key~.txt")), Path::new("key~.txt"));
+    }
+
+    #[test]
+    fn resolve_reads_from_env_or_the_secrets_own_variable() {
+        env::set_var("UP2DATE_TEST_FROM", "from-elsewhere");
+        env::set_var("UP2DATE_TEST_OWN", "its-own");
+
+        let secrets = HashMap::from([
+            (
+                String::from("elsewhere"),
+                config::Secret {
+                    from_env: Some(String::from("UP2DATE_TEST_FROM")),
+                    steps: vec![String::from("gh")],
+                    ..secret("GH_TOKEN")
+                },
+            ),
+            (String::from("own"), secret("UP2DATE_TEST_OWN")),
+        ]);
+        let mut resolved = resolve(&secrets);
+
+        resolved.sort_by(|a, b| a.env.cmp(&b.env));
+
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[0].env, "GH_TOKEN");
+        assert_eq!(resolved[0].value, "from-elsewhere");
+        assert_eq!(resolved[0].steps, vec![String::from("gh")]);
+        assert_eq!(resolved[1].env, "UP2DATE_TEST_OWN");
+        assert_eq!(resolved[1].value, "its-own");
+    }
+
+    #[test]
+    fn resolve_leaves_out_what_it_cant_find() {
+        let secrets = HashMap::from([
+            (
+                String::from("unset"),
+                config::Secret {
+                    from_env: Some(String::from("UP2DATE_TEST_NEVER_SET")),
+                    ..secret("TOKEN")
+                },
+            ),
+            (
+                String::from("undecryptable"),
+                config::Secret {
+                    age_file: Some(PathBuf::from("/nonexistent/up2date/secrets.age")),
+                    age_identity: Some(PathBuf::from("/nonexistent/up2date/key.txt")),
+                    ..secret("API_KEY")
+                },
+            ),
+        ]);
+
+        assert!(resolve(&secrets).is_empty());
+    }
+}
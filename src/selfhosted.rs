@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use scuttle::App;
+
+use crate::config::Selfhosted;
+use crate::detect::has_binary;
+use crate::parsers;
+
+/// Where the Omnibus GitLab package says which version it installed
+const GITLAB_MANIFEST: &str = "/opt/gitlab/version-manifest.txt";
+
+/// Where Nextcloud usually lives when `[selfhosted] nextcloud` doesn't say
+const NEXTCLOUD: &str = "/var/www/nextcloud";
+
+/// Build an app from a command line
+fn app(command_line: &[&str]) -> App {
+    App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    }
+}
+
+/// Pi-hole's own updater, `None` when Pi-hole isn't installed
+pub fn pihole() -> Option<App> {
+    has_binary("pihole").then(|| app(&["sudo", "pihole", "-up"]))
+}
+
+/// The version of GitLab the system package manager keeps up to date, `None` when
+/// the Omnibus package isn't installed
+///
+/// The first line of the manifest is the package and its version, e.g.
+/// `gitlab-ce 16.11.1`.
+pub fn gitlab() -> Option<String> {
+    if !has_binary("gitlab-ctl") {
+        return None;
+    }
+
+    let manifest = fs::read_to_string(GITLAB_MANIFEST).unwrap_or_default();
+
+    Some(
+        manifest
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("")
+            .to_string(),
+    )
+}
+
+/// `occ` with the arguments given, as the web server's user or through the snap,
+/// `None` when Nextcloud can't be found
+///
+/// # Arguments
+///
+/// * `settings` - The `[selfhosted]` settings from the config
+/// * `args` - What to run, e.g. `status`
+pub fn occ(settings: &Selfhosted, args: &[&str]) -> Option<App> {
+    if has_binary("nextcloud.occ") {
+        return Some(app(&[&["sudo", "nextcloud.occ"], args].concat()));
+    }
+
+    let dir: PathBuf = settings
+        .nextcloud
+        .clone()
+        .unwrap_or_else(|| Path::new(NEXTCLOUD).to_path_buf());
+    let occ = dir.join("occ");
+
+    if !occ.is_file() || !has_binary("php") {
+        return None;
+    }
+
+    let occ = occ.display().to_string();
+
+    Some(app(&[
+        &["sudo", "-u", &settings.nextcloud_user, "php", &occ],
+        args,
+    ]
+    .concat()))
+}
+
+/// Whether Nextcloud's files are newer than its database, so it waits for
+/// `occ upgrade` in maintenance mode
+///
+/// # Arguments
+///
+/// * `settings` - The `[selfhosted]` settings from the config
+pub fn nextcloud_needs_upgrade(settings: &Selfhosted) -> bool {
+    let status = match occ(settings, &["status", "--output=json"]) {
+        Some(status) => status,
+        None => return false,
+    };
+
+    match scuttle::run_output(&status) {
+        Ok(output) => parsers::parse_occ_needs_upgrade(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => false,
+    }
+}
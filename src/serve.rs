@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::history;
+use crate::preview;
+use crate::secrets;
+
+/// The most headers read from a request before giving up on it
+const MAX_HEADERS: usize = 100;
+
+/// The most recent run, as `GET /status` shows it
+#[derive(Serialize)]
+struct LastRun {
+    started: Option<u64>,
+    outcome: Option<String>,
+    commands: usize,
+    failed: usize,
+    packages_changed: usize,
+}
+
+/// The answer to `GET /status`
+#[derive(Serialize)]
+struct Status {
+    /// Whether a run started through `POST /run` is still going
+    running: bool,
+    last_run: Option<LastRun>,
+}
+
+/// A request line and the headers that matter
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+}
+
+/// Read a request's line and headers, the body is never needed
+fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    reader.read_line(&mut line)?;
+
+    let mut words = line.split_whitespace();
+    let method = words.next().unwrap_or("").to_string();
+    // the query string isn't used by anything
+    let path = words
+        .next()
+        .unwrap_or("")
+        .split('?')
+        .next()
+        .unwrap_or("")
+        .to_string();
+    let mut authorization: Option<String> = None;
+
+    for _ in 0..MAX_HEADERS {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    Ok(Request {
+        method,
+        path,
+        authorization,
+    })
+}
+
+/// Write a JSON response and close the connection
+///
+/// # Arguments
+///
+/// * `stream` - The connection to answer on
+/// * `status` - The status code and reason, e.g. `200 OK`
+/// * `body` - The JSON to send
+fn respond(mut stream: &TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Compare a token without stopping at the first difference, so how long it takes
+/// doesn't give away how much of a guess was right
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Summarise the last run in the history for `GET /status`
+fn last_run() -> io::Result<Option<LastRun>> {
+    let runs = history::read()?;
+    let run = match runs.last() {
+        Some(run) => run,
+        None => return Ok(None),
+    };
+    let steps = run["steps"].as_array().map_or(&[][..], Vec::as_slice);
+
+    Ok(Some(LastRun {
+        started: run["started"].as_u64(),
+        outcome: run["outcome"].as_str().map(String::from),
+        commands: steps.len(),
+        failed: steps
+            .iter()
+            .filter(|step| step["success"].as_bool() == Some(false))
+            .count(),
+        packages_changed: steps
+            .iter()
+            .filter_map(|step| step["changes"].as_array())
+            .map(Vec::len)
+            .sum(),
+    }))
+}
+
+/// Answer one request
+///
+/// # Arguments
+///
+/// * `stream` - The connection the request came in on
+/// * `token` - The token `POST /run` needs, the endpoint is off without one
+/// * `running` - The run started by the last `POST /run`, if there's been one
+fn handle(stream: &TcpStream, token: Option<&str>, running: &mut Option<Child>) -> io::Result<()> {
+    let request = read_request(stream)?;
+    let busy = match running.as_mut() {
+        Some(child) => child.try_wait()?.is_none(),
+        None => false,
+    };
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => {
+            let status = Status {
+                running: busy,
+                last_run: last_run()?,
+            };
+
+            respond(stream, "200 OK", &serde_json::to_string(&status)?)
+        }
+        ("GET", "/pending") => {
+            let pending = preview::pending_path()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .and_then(|contents| serde_json::from_str::<Value>(&contents).ok());
+
+            match pending {
+                Some(pending) => respond(stream, "200 OK", &pending.to_string()),
+                None => respond(stream, "200 OK", "{\"pending\":null}"),
+            }
+        }
+        ("POST", "/run") => {
+            let token = match token {
+                Some(token) => token,
+                None => {
+                    return respond(
+                        stream,
+                        "403 Forbidden",
+                        "{\"error\":\"no token is configured\"}",
+                    )
+                }
+            };
+            let given = request
+                .authorization
+                .as_deref()
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .unwrap_or("");
+
+            if !same_token(given, token) {
+                return respond(
+                    stream,
+                    "401 Unauthorized",
+                    "{\"error\":\"missing or wrong token\"}",
+                );
+            }
+
+            if busy {
+                return respond(
+                    stream,
+                    "409 Conflict",
+                    "{\"error\":\"a run is already going\"}",
+                );
+            }
+
+            // nothing can be answered, so anything that would ask is refused
+            *running = Some(
+                Command::new(env::current_exe()?)
+                    .arg("run")
+                    .stdin(Stdio::null())
+                    .spawn()?,
+            );
+
+            respond(stream, "202 Accepted", "{\"started\":true}")
+        }
+        (_, "/status") | (_, "/pending") | (_, "/run") => respond(
+            stream,
+            "405 Method Not Allowed",
+            "{\"error\":\"method not allowed\"}",
+        ),
+        _ => respond(stream, "404 Not Found", "{\"error\":\"not found\"}"),
+    }
+}
+
+/// Answer HTTP requests for the last run's status and the pending updates, and start a
+/// run when asked to with the token, until up2date is stopped
+///
+/// # Arguments
+///
+/// * `config` - The settings, `[serve]` has the address and the token
+pub fn serve(config: &Config) -> io::Result<()> {
+    let token = secrets::resolve(&HashMap::from([(
+        String::from("serve"),
+        config.serve.token.clone(),
+    )]))
+    .pop()
+    .map(|secret| secret.value);
+
+    if token.is_none() {
+        eprintln!("WARNING: no token found for [serve.token], POST /run is turned off");
+    }
+
+    let listener = TcpListener::bind(&config.serve.address)?;
+    let mut running: Option<Child> = None;
+
+    println!("Listening on http://{}", config.serve.address);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("WARNING: could not accept a connection: {}", error);
+                continue;
+            }
+        };
+
+        // a client that never finishes its request would hold up every other one
+        let answered = stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .and_then(|_| handle(&stream, token.as_deref(), &mut running));
+
+        if let Err(error) = answered {
+            eprintln!("WARNING: could not answer a request: {}", error);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use scuttle::App;
+
+use crate::config::{self, SnapshotTool};
+use crate::parsers;
+
+/// The description every snapshot taken by up2date is given so they can be told apart
+/// from the ones taken by hand or on a schedule
+pub const DESCRIPTION: &str = "up2date pre-update";
+
+/// A snapshot up2date took before updating
+#[derive(Debug)]
+pub struct Snapshot {
+    /// What the tool calls the snapshot: a snapper number, a timeshift name or a zfs
+    /// `dataset@name`
+    pub id: String,
+    /// When it was taken, as the tool printed it
+    pub date: String,
+}
+
+/// Build an App from a command line run with sudo
+fn sudo(command_line: &[&str]) -> App {
+    App {
+        command: String::from("sudo"),
+        args: command_line.iter().map(|arg| arg.to_string()).collect(),
+    }
+}
+
+/// The app that takes a snapshot before updating, `None` when snapshots are turned off
+///
+/// # Arguments
+///
+/// * `snapshot` - The snapshot settings from the config file
+pub fn create_app(snapshot: &config::Snapshot) -> Option<App> {
+    match (snapshot.tool?, snapshot.dataset.as_deref()) {
+        (SnapshotTool::Snapper, _) => Some(sudo(&[
+            "snapper",
+            "create",
+            "--description",
+            DESCRIPTION,
+            "--cleanup-algorithm",
+            "number",
+        ])),
+        (SnapshotTool::Timeshift, _) => {
+            Some(sudo(&["timeshift", "--create", "--comments", DESCRIPTION]))
+        }
+        (SnapshotTool::Zfs, Some(dataset)) => {
+            let seconds = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+
+            Some(sudo(&[
+                "zfs",
+                "snapshot",
+                &format!("{}@up2date-{}", dataset, seconds),
+            ]))
+        }
+        (SnapshotTool::Zfs, None) => panic!("ERROR: snapshots with zfs need a dataset"),
+    }
+}
+
+/// List the snapshots up2date took, oldest first
+///
+/// # Arguments
+///
+/// * `snapshot` - The snapshot settings from the config file
+pub fn list(snapshot: &config::Snapshot) -> Vec<Snapshot> {
+    let (app, parse): (App, fn(&str) -> Vec<Snapshot>) = match snapshot.tool {
+        Some(SnapshotTool::Snapper) => (
+            sudo(&["snapper", "list", "--columns", "number,date,description"]),
+            parsers::parse_snapper,
+        ),
+        Some(SnapshotTool::Timeshift) => (sudo(&["timeshift", "--list"]), parsers::parse_timeshift),
+        Some(SnapshotTool::Zfs) => {
+            let dataset = snapshot.dataset.as_deref().unwrap_or("");
+
+            (
+                App {
+                    command: String::from("zfs"),
+                    args: [
+                        "list",
+                        "-H",
+                        "-t",
+                        "snapshot",
+                        "-s",
+                        "creation",
+                        "-o",
+                        "name,creation",
+                        "-d",
+                        "1",
+                        dataset,
+                    ]
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect(),
+                },
+                parsers::parse_zfs,
+            )
+        }
+        None => return Vec::new(),
+    };
+
+    match scuttle::run_output(&app) {
+        Ok(output) => parse(&String::from_utf8_lossy(&output.stdout)),
+        Err(error) => panic!("panic:{}", error),
+    }
+}
+
+/// The app that restores a snapshot
+///
+/// snapper and timeshift both need a reboot afterwards, zfs rolls the dataset back in
+/// place and destroys any snapshots taken after it.
+///
+/// # Arguments
+///
+/// * `tool` - The tool that took the snapshot
+/// * `snapshot` - The snapshot to restore
+pub fn restore_app(tool: SnapshotTool, snapshot: &Snapshot) -> App {
+    match tool {
+        SnapshotTool::Snapper => sudo(&["snapper", "rollback", &snapshot.id]),
+        SnapshotTool::Timeshift => sudo(&[
+            "timeshift",
+            "--restore",
+            "--snapshot",
+            &snapshot.id,
+            "--yes",
+        ]),
+        SnapshotTool::Zfs => sudo(&["zfs", "rollback", "-r", &snapshot.id]),
+    }
+}
@@ -0,0 +1,193 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cli::StatusFormat;
+use crate::history;
+use crate::i18n::tr;
+use crate::preview;
+
+/// What the scheduled checks and the last run left behind, nothing is looked up again
+#[derive(Debug, Default)]
+struct Status {
+    /// How many updates the last check found and how many of them are security updates
+    pending: Option<(u64, u64)>,
+    /// How many of them each manager has
+    managers: Vec<(String, u64)>,
+    /// When the last run started and how many of its steps failed
+    last_run: Option<(u64, usize)>,
+    /// Whether the last run found a reboot pending
+    reboot: bool,
+}
+
+/// What a waybar custom module with `"return-type": "json"` reads
+#[derive(Serialize)]
+struct Waybar {
+    /// The number of pending updates, empty before the first check
+    text: String,
+    /// Each manager's pending updates and the rest of the status, one per line
+    tooltip: String,
+    /// `updated`, `pending` or `security` for styling, with `reboot` when one is needed
+    class: Vec<&'static str>,
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format a duration as short as it gets for a prompt, e.g. `5m`, `3h` or `2d`
+///
+/// # Arguments
+///
+/// * `seconds` - The duration to format
+fn short_duration(seconds: u64) -> String {
+    match seconds {
+        0..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+/// Read what `pending.json` and the history hold
+fn read() -> Status {
+    let checked = preview::pending_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok());
+    let pending = checked.as_ref().and_then(|checked| {
+        Some((
+            checked["pending"].as_u64()?,
+            checked["security"].as_u64().unwrap_or(0),
+        ))
+    });
+    // only checks since the managers were counted have them
+    let managers = checked
+        .as_ref()
+        .and_then(|checked| checked["managers"].as_object())
+        .map(|managers| {
+            managers
+                .iter()
+                .filter_map(|(manager, count)| Some((manager.clone(), count.as_u64()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let run = history::last().ok().flatten();
+
+    Status {
+        pending,
+        managers,
+        last_run: run.as_ref().and_then(|run| {
+            let failed = run["steps"].as_array().map_or(0, |steps| {
+                steps
+                    .iter()
+                    .filter(|step| step["success"].as_bool() == Some(false))
+                    .count()
+            });
+
+            Some((run["started"].as_u64()?, failed))
+        }),
+        reboot: run.is_some_and(|run| run["reboot_pending_since"].as_u64().is_some()),
+    }
+}
+
+/// The parts of the status, in the order they're shown
+///
+/// # Arguments
+///
+/// * `status` - What was read
+fn parts(status: &Status) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+
+    match status.pending {
+        Some((0, _)) => parts.push(tr("status-up-to-date", &[])),
+        Some((pending, security)) => {
+            let mut part = match pending {
+                1 => tr("status-pending-one", &[]),
+                _ => tr("status-pending", &[("count", &pending)]),
+            };
+
+            if security > 0 {
+                part.push_str(&format!(
+                    " ({})",
+                    tr("status-security", &[("count", &security)])
+                ));
+            }
+
+            parts.push(part);
+        }
+        None => {}
+    }
+
+    if let Some((started, failed)) = status.last_run {
+        let ago = short_duration(now().saturating_sub(started));
+
+        parts.push(match failed {
+            0 => tr("status-last-run", &[("ago", &ago)]),
+            _ => tr(
+                "status-last-run-failed",
+                &[("ago", &ago), ("failed", &failed)],
+            ),
+        });
+    }
+
+    if status.reboot {
+        parts.push(tr("status-reboot", &[]));
+    }
+
+    parts
+}
+
+/// Print the status from what's cached, quick enough to run from a shell prompt
+///
+/// # Arguments
+///
+/// * `format` - How to print it
+pub fn print(format: StatusFormat) {
+    let status = read();
+    let parts = parts(&status);
+
+    match format {
+        StatusFormat::Lines => {
+            for part in parts.iter() {
+                println!("{}", part);
+            }
+        }
+        StatusFormat::Short => println!("{}", parts.join(", ")),
+        StatusFormat::Waybar => {
+            let mut class: Vec<&'static str> = vec![match status.pending {
+                Some((_, security)) if security > 0 => "security",
+                Some((pending, _)) if pending > 0 => "pending",
+                _ => "updated",
+            }];
+
+            if status.reboot {
+                class.push("reboot");
+            }
+
+            let waybar = Waybar {
+                text: status
+                    .pending
+                    .map(|(pending, _)| pending.to_string())
+                    .unwrap_or_default(),
+                tooltip: status
+                    .managers
+                    .iter()
+                    .map(|(manager, count)| format!("{}: {}", manager, count))
+                    .chain(parts)
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+                class,
+            };
+
+            match serde_json::to_string(&waybar) {
+                Ok(json) => println!("{}", json),
+                Err(error) => panic!("ERROR: {}", error),
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::detect;
+
+/// What the commands are called in the snippet
+const ALIAS: &str = "UP2DATE";
+
+/// A word of a command as sudoers matches it, with the characters it reads as syntax or
+/// as a wildcard escaped, `"` too so an arg can't be read as the `""` of no args, `None`
+/// when it has whitespace in it since sudoers can't tell that apart from two words
+///
+/// # Arguments
+///
+/// * `word` - The command's path or one of its args
+fn escape(word: &str) -> Option<String> {
+    if word.chars().any(char::is_whitespace) {
+        return None;
+    }
+
+    Some(
+        word.chars()
+            .flat_map(|char| match char {
+                ',' | ':' | '=' | '\\' | '"' | '*' | '?' | '[' | ']' | '!' | '#' => {
+                    vec!['\\', char]
+                }
+                _ => vec![char],
+            })
+            .collect(),
+    )
+}
+
+/// A command as a sudoers `Cmnd`, with the full path sudoers needs and `""` when it has no
+/// args so it isn't allowed with any args at all
+///
+/// # Arguments
+///
+/// * `command` - The command and its args, without `sudo`
+fn cmnd(command: &[String]) -> Result<String, String> {
+    let path =
+        detect::binary_path(&command[0]).ok_or_else(|| format!("{} isn't on PATH", command[0]))?;
+    let mut words = vec![path.display().to_string()];
+
+    words.extend(command[1..].iter().cloned());
+
+    let mut escaped = words
+        .iter()
+        .map(|word| escape(word))
+        .collect::<Option<Vec<String>>>()
+        .ok_or_else(|| format!("{} has an arg with a space in it", command[0]))?;
+
+    if escaped.len() == 1 {
+        escaped.push(String::from("\"\""));
+    }
+
+    Ok(escaped.join(" "))
+}
+
+/// A sudoers snippet that lets a user run exactly the commands a run needs as root without
+/// a password, instead of `NOPASSWD: ALL`
+///
+/// The args are the ones the plan had, so it has to be generated again when the plan
+/// changes, e.g. a step is turned on or the packages to remove are different. The
+/// commands that can't be matched exactly are left out with a warning, sudo asks for
+/// the password for those as it always has.
+///
+/// # Arguments
+///
+/// * `commands` - The commands the plan runs with sudo, without the `sudo`
+/// * `user` - Who runs up2date
+/// * `hostname` - The machine the plan is for
+pub fn generate(commands: &[Vec<String>], user: &str, hostname: &str) -> String {
+    let mut cmnds: Vec<String> = Vec::new();
+
+    for command in commands.iter().filter(|command| !command.is_empty()) {
+        match cmnd(command) {
+            Ok(cmnd) if !cmnds.contains(&cmnd) => cmnds.push(cmnd),
+            Ok(_) => {}
+            Err(error) => eprintln!("WARNING: left out of the sudoers snippet, {}", error),
+        }
+    }
+
+    let mut snippet = format!(
+        "# What up2date runs with sudo on {}, from up2date sudoers generate\n\
+         # Check it with visudo -cf <file> and install it with\n\
+         # sudo install -m 0440 <file> /etc/sudoers.d/up2date\n\
+         # Generate it again when the steps or their args change\n",
+        hostname
+    );
+
+    if cmnds.is_empty() {
+        snippet.push_str("# Nothing in the plan runs with sudo\n");
+        return snippet;
+    }
+
+    snippet.push_str(&format!("Cmnd_Alias {} = \\\n", ALIAS));
+    snippet.push_str(
+        &cmnds
+            .iter()
+            .map(|cmnd| format!("    {}", cmnd))
+            .collect::<Vec<String>>()
+            .join(", \\\n"),
+    );
+    snippet.push_str(&format!("\n\n{} ALL=(root) NOPASSWD: {}\n", user, ALIAS));
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn escape_leaves_plain_words() {
+        assert_eq!(
+            escape("/usr/bin/apt-get"),
+            Some(String::from("/usr/bin/apt-get"))
+        );
+        assert_eq!(
+            escape("--with-new-pkgs"),
+            Some(String::from("--with-new-pkgs"))
+        );
+    }
+
+    #[test]
+    fn escape_escapes_syntax_and_wildcards() {
+        assert_eq!(
+            escape("--option=a,b:c"),
+            Some(String::from("--option\\=a\\,b\\:c"))
+        );
+        assert_eq!(escape("*.deb"), Some(String::from("\\*.deb")));
+        assert_eq!(escape("[!a]#?"), Some(String::from("\\[\\!a\\]\\#\\?")));
+        assert_eq!(escape("a\\b"), Some(String::from("a\\\\b")));
+    }
+
+    #[test]
+    fn escape_escapes_quotes() {
+        assert_eq!(escape("\"\""), Some(String::from("\\\"\\\"")));
+    }
+
+    #[test]
+    fn escape_rejects_whitespace() {
+        assert_eq!(escape("two words"), None);
+        assert_eq!(escape("tab\there"), None);
+    }
+
+    #[test]
+    fn cmnd_without_args_allows_no_args() {
+        let sh = detect::binary_path("sh").unwrap();
+
+        assert_eq!(
+            cmnd(&command(&["sh"])),
+            Ok(format!("{} \"\"", sh.display()))
+        );
+        assert_eq!(
+            cmnd(&command(&["sh", "-c", "true"])),
+            Ok(format!("{} -c true", sh.display()))
+        );
+    }
+
+    #[test]
+    fn cmnd_needs_the_command_on_path() {
+        assert!(cmnd(&command(&["no-such-command-up2date"])).is_err());
+    }
+
+    #[test]
+    fn generate_lists_each_command_once() {
+        let snippet = generate(
+            &[
+                command(&["sh", "-c", "true"]),
+                command(&["sh", "-c", "true"]),
+                command(&["sh"]),
+            ],
+            "alice",
+            "box",
+        );
+
+        assert_eq!(snippet.matches(" -c true").count(), 1);
+        assert_eq!(snippet.matches(" \"\"").count(), 1);
+        assert!(snippet.contains("Cmnd_Alias UP2DATE = \\\n"));
+        assert!(snippet.ends_with("\n\nalice ALL=(root) NOPASSWD: UP2DATE\n"));
+    }
+
+    #[test]
+    fn generate_leaves_out_args_with_whitespace() {
+        let snippet = generate(&[command(&["sh", "-c", "echo hi"])], "alice", "box");
+
+        assert!(!snippet.contains("echo"));
+        assert!(snippet.contains("# Nothing in the plan runs with sudo"));
+        assert!(!snippet.contains("NOPASSWD"));
+    }
+}
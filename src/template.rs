@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::detect;
+use crate::history;
+
+/// The AUR helpers `{detected.pacman_helper}` can stand for, the first one found winning
+const PACMAN_HELPERS: [&str; 2] = ["paru", "yay"];
+
+/// What the placeholders in the args of the apps on `--stdin`, from plugins and from
+/// scripts stand for
+///
+/// `{hostname}`, `{date}` (`2024-05-01`), `{os}` and `{release}` are always there,
+/// `{detected.pacman_helper}` only when paru or yay is installed.
+pub fn variables() -> HashMap<&'static str, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let mut variables = HashMap::from([
+        ("hostname", sys_info::hostname().unwrap_or_default()),
+        ("date", history::format_date(now)[..10].to_string()),
+        ("os", detect::os().to_string()),
+        ("release", detect::release().unwrap_or_default()),
+    ]);
+
+    if let Some(helper) = PACMAN_HELPERS
+        .iter()
+        .find(|helper| detect::has_binary(helper))
+    {
+        variables.insert("detected.pacman_helper", helper.to_string());
+    }
+
+    variables
+}
+
+/// Fill in the placeholders of an arg, leaving the braces that aren't one of them alone
+/// so e.g. `find -exec {} +` still works
+///
+/// # Arguments
+///
+/// * `arg` - The arg to fill in
+/// * `variables` - What each placeholder stands for
+pub fn expand(arg: &str, variables: &HashMap<&'static str, String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = arg;
+
+    while let Some(start) = rest.find('{') {
+        let (before, placeholder) = rest.split_at(start);
+        let value = placeholder
+            .find('}')
+            .and_then(|end| Some((variables.get(&placeholder[1..end])?, end)));
+
+        expanded.push_str(before);
+
+        match value {
+            Some((value, end)) => {
+                expanded.push_str(value);
+                rest = &placeholder[end + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> HashMap<&'static str, String> {
+        HashMap::from([
+            ("hostname", String::from("box")),
+            ("date", String::from("2024-05-01")),
+            ("detected.pacman_helper", String::from("paru")),
+        ])
+    }
+
+    #[test]
+    fn expand_fills_in_placeholders() {
+        assert_eq!(
+            expand("/backups/{hostname}-{date}.tar", &variables()),
+            "/backups/box-2024-05-01.tar"
+        );
+        assert_eq!(expand("{detected.pacman_helper}", &variables()), "paru");
+        assert_eq!(expand("{hostname}{hostname}", &variables()), "boxbox");
+    }
+
+    #[test]
+    fn expand_leaves_other_braces_alone() {
+        assert_eq!(expand("{}", &variables()), "{}");
+        assert_eq!(expand("{user}", &variables()), "{user}");
+        assert_eq!(expand("{{hostname}}", &variables()), "{box}");
+        assert_eq!(expand("a{b", &variables()), "a{b");
+        assert_eq!(expand("}{hostname", &variables()), "}{hostname");
+    }
+
+    #[test]
+    fn expand_keeps_args_without_placeholders() {
+        assert_eq!(expand("", &variables()), "");
+        assert_eq!(expand("--noconfirm", &variables()), "--noconfirm");
+        assert_eq!(expand("ünïcode {date}", &variables()), "ünïcode 2024-05-01");
+    }
+
+    #[test]
+    fn date_is_a_day() {
+        let date = &super::variables()["date"];
+
+        assert_eq!(date.len(), 10);
+        assert!(history::parse_date(date).is_some());
+    }
+}
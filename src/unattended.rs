@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use scuttle::App;
+
+use crate::detect::has_binary;
+use crate::parsers;
+use crate::report::Automatic;
+
+/// Where apt's daily timers leave a stamp each time they've done something
+const PERIODIC: &str = "/var/lib/apt/periodic";
+
+/// What unattended-upgrades logs each run to, only readable by root and `adm`
+const LOG: &str = "/var/log/unattended-upgrades/unattended-upgrades.log";
+
+/// How long ago the package lists can have been refreshed and still count as fresh
+const FRESH: u64 = 3600;
+
+/// When a file was last modified, in seconds since the Unix epoch
+///
+/// # Arguments
+///
+/// * `path` - The file to look at
+fn modified(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+/// Whether unattended-upgrades is installed and apt's timers are set to run it
+///
+/// `apt-config dump APT::Periodic::Unattended-Upgrade` prints
+/// `APT::Periodic::Unattended-Upgrade "1";` when it runs every day, `"0"` when it's off.
+fn enabled() -> bool {
+    if !has_binary("unattended-upgrade") || !has_binary("apt-config") {
+        return false;
+    }
+
+    let output = match scuttle::run_output(&App {
+        command: String::from("apt-config"),
+        args: vec![
+            "dump".to_string(),
+            "APT::Periodic::Unattended-Upgrade".to_string(),
+        ],
+    }) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(_) => return false,
+    };
+
+    output
+        .split('"')
+        .nth(1)
+        .and_then(|days| days.parse::<u32>().ok())
+        .is_some_and(|days| days > 0)
+}
+
+/// What unattended-upgrades has already done, `None` when it isn't turned on here
+///
+/// # Arguments
+///
+/// * `release` - The Linux distribution id
+pub fn detect(release: Option<&str>) -> Option<Automatic> {
+    if !matches!(release, Some("ubuntu") | Some("pop") | Some("debian")) || !enabled() {
+        return None;
+    }
+
+    Some(Automatic {
+        service: "unattended-upgrades",
+        last_run: modified(&Path::new(PERIODIC).join("unattended-upgrades-stamp")),
+        // without access to the log the summary only says when it ran
+        upgraded: fs::read_to_string(LOG)
+            .map(|log| parsers::parse_unattended_upgrades(&log))
+            .unwrap_or_default(),
+    })
+}
+
+/// Whether apt's daily timer refreshed the package lists recently enough that
+/// `apt-get update` has nothing to add
+pub fn lists_fresh() -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    match modified(&Path::new(PERIODIC).join("update-success-stamp")) {
+        Some(refreshed) => now.saturating_sub(refreshed) < FRESH,
+        None => false,
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use scuttle::App;
+
+use crate::config;
+use crate::detect::has_binary;
+use crate::parsers;
+use crate::runner::Runner;
+
+/// A package one of the Windows package managers can upgrade
+#[derive(Debug, PartialEq, Eq)]
+pub struct Outdated {
+    /// What the manager calls it, winget's id or the choco and scoop name
+    pub id: String,
+    pub version: String,
+    pub available: String,
+    /// Held back with `winget pin`, `choco pin` or `scoop hold`
+    pub pinned: bool,
+}
+
+/// Build an App from a command line
+fn app(command_line: &[&str]) -> App {
+    App {
+        command: command_line[0].to_string(),
+        args: command_line[1..]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+    }
+}
+
+/// Run a check command and return its stdout, empty when it can't be run
+fn check_output(command_line: &[&str]) -> String {
+    match scuttle::run_output(&app(command_line)) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(error) => {
+            eprintln!("WARNING: could not run {}: {}", command_line[0], error);
+            String::new()
+        }
+    }
+}
+
+/// The name a package is matched by across managers: choco and scoop names as they are,
+/// the last part of a winget id like `Git.Git`, all lowercase
+///
+/// # Arguments
+///
+/// * `manager` - The manager the package is from
+/// * `id` - What the manager calls it
+pub fn package_key(manager: &str, id: &str) -> String {
+    let name = if manager == "winget" {
+        id.rsplit('.').next().unwrap_or(id)
+    } else {
+        id
+    };
+
+    name.to_lowercase()
+}
+
+/// What the managers that are installed have to upgrade, in the configured order
+///
+/// # Arguments
+///
+/// * `managers` - The managers to use, the first one wins a package that's in several
+fn outdated(managers: &[String]) -> Vec<(&str, Vec<Outdated>)> {
+    managers
+        .iter()
+        .filter(|manager| has_binary(manager))
+        .filter_map(|manager| {
+            let found = match manager.as_str() {
+                "winget" => parsers::parse_winget(
+                    &check_output(&[
+                        "winget",
+                        "upgrade",
+                        "--include-pinned",
+                        "--accept-source-agreements",
+                    ]),
+                    &check_output(&["winget", "pin", "list"]),
+                ),
+                "choco" => parsers::parse_choco(&check_output(&["choco", "outdated", "-r"])),
+                "scoop" => parsers::parse_scoop(&check_output(&["scoop", "status"])),
+                _ => {
+                    eprintln!("WARNING: unknown Windows package manager {}", manager);
+                    return None;
+                }
+            };
+
+            Some((manager.as_str(), found))
+        })
+        .collect()
+}
+
+/// The command that upgrades one package
+fn upgrade_app(manager: &str, id: &str) -> App {
+    match manager {
+        "winget" => app(&[
+            "winget",
+            "upgrade",
+            "--id",
+            id,
+            "--exact",
+            "--silent",
+            "--accept-source-agreements",
+            "--accept-package-agreements",
+        ]),
+        "choco" => app(&["choco", "upgrade", id, "-y"]),
+        _ => app(&["scoop", "update", id]),
+    }
+}
+
+/// Upgrade everything winget, choco and scoop have to upgrade
+///
+/// A package that more than one manager installed is only upgraded by the one
+/// `[windows.owners]` names, or else the first of `managers`. Pinned and held packages
+/// are never upgraded, even by id, and are recorded so the summary lists them.
+///
+/// # Arguments
+///
+/// * `runner` - Runs the upgrades
+/// * `windows` - The Windows settings from the config
+/// * `refresh` - Whether to update winget's sources and scoop's buckets first
+pub fn upgrade(runner: &mut Runner, windows: &config::Windows, refresh: bool) {
+    if refresh {
+        if has_binary("winget") && windows.managers.iter().any(|manager| manager == "winget") {
+            runner.run_apps(&[app(&["winget", "source", "update"])]);
+        }
+
+        if has_binary("scoop") && windows.managers.iter().any(|manager| manager == "scoop") {
+            runner.run_apps(&[app(&["scoop", "update"])]);
+        }
+    }
+
+    let found = outdated(&windows.managers);
+    // which manager upgrades each package, by its key
+    let mut owners: HashMap<String, &str> = HashMap::new();
+
+    for (manager, packages) in found.iter() {
+        for package in packages.iter() {
+            let key = package_key(manager, &package.id);
+            // the configured owner only wins when it has the package to upgrade
+            let owner = match windows.owners.get(&key) {
+                Some(owner)
+                    if found.iter().any(|(other, packages)| {
+                        other == owner
+                            && packages
+                                .iter()
+                                .any(|package| package_key(other, &package.id) == key)
+                    }) =>
+                {
+                    owner.as_str()
+                }
+                _ => manager,
+            };
+
+            owners.entry(key).or_insert(owner);
+        }
+    }
+
+    let mut pinned: Vec<String> = Vec::new();
+
+    for (manager, packages) in found.iter() {
+        for package in packages.iter() {
+            let owner = owners[&package_key(manager, &package.id)];
+
+            if package.pinned {
+                pinned.push(format!(
+                    "{} ({} {} -> {})",
+                    package.id, manager, package.version, package.available
+                ));
+            } else if owner != *manager {
+                println!();
+                println!(
+                    "Leaving {} to {}, {} installed it too",
+                    package.id, owner, manager
+                );
+            } else {
+                runner.run_app(&upgrade_app(manager, &package.id));
+            }
+        }
+    }
+
+    runner.pinned(pinned);
+}
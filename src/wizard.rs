@@ -0,0 +1,107 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::confirm;
+use crate::detect::has_binary;
+
+/// The steps that only run once they're turned on, with the binaries that show the
+/// tool is installed
+const TOGGLES: &[(&str, &str, &[&str])] = &[
+    ("julia", "Update julia packages?", &["julia"]),
+    ("r", "Update R packages?", &["R"]),
+    ("haskell", "Update GHCup and stack?", &["ghcup", "stack"]),
+    (
+        "conda",
+        "Update the conda base environment?",
+        &["conda", "mamba"],
+    ),
+    (
+        "infra",
+        "Update helm repos and plugins and the latest terraform?",
+        &["helm", "tfenv", "tenv"],
+    ),
+    (
+        "audit",
+        "Check for known vulnerabilities after updating?",
+        &["cargo-audit", "pip-audit"],
+    ),
+];
+
+/// Ask which of the installed tools to update and write the answers to a new config file
+///
+/// Only tools that are installed are asked about, anything left out keeps its default.
+///
+/// # Arguments
+///
+/// * `path` - Where to write the config file
+pub fn run(path: &Path) -> io::Result<()> {
+    let mut config =
+        String::from("# Written by the up2date setup, see the README for every setting\n");
+    // the config section and the binary of each system manager
+    let managers: Vec<&str> = [("apt", "apt-get"), ("pacman", "pacman"), ("yay", "yay")]
+        .iter()
+        .filter(|(_, binary)| has_binary(binary))
+        .map(|(manager, _)| *manager)
+        .collect();
+
+    if !managers.is_empty()
+        && !confirm(&format!(
+            "Let {} upgrade without asking first?",
+            managers.join(", ")
+        ))
+    {
+        for manager in managers.iter() {
+            config.push_str(&format!("\n[{}]\nassume_yes = \"ask\"\n", manager));
+        }
+    }
+
+    for (name, question, binaries) in TOGGLES.iter() {
+        if binaries.iter().any(|binary| has_binary(binary)) && confirm(question) {
+            config.push_str(&format!("\n[{}]\nenabled = true\n", name));
+        }
+    }
+
+    let mut disabled: Vec<&str> = Vec::new();
+
+    if !confirm("Remove orphaned packages and old downloads after updating?") {
+        disabled.push("cleanup");
+    }
+
+    if has_binary("nvim") && !confirm("Update Neovim plugins?") {
+        disabled.push("editors");
+    }
+
+    if !disabled.is_empty() {
+        config.push_str("\n[groups]\n");
+
+        for group in disabled.iter() {
+            config.push_str(&format!("{} = false\n", group));
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, config)?;
+    println!("Wrote {}", path.display());
+
+    Ok(())
+}
+
+/// Write a config file that keeps every default, so the setup is only offered once
+///
+/// # Arguments
+///
+/// * `path` - Where to write the config file
+pub fn skip(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(
+        path,
+        "# Every setting is at its default, see the README for what can be changed\n",
+    )
+}
@@ -0,0 +1,426 @@
+// shared by the integration tests, not every test uses all of it
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What a shim prints and exits with when its arguments start with `args`
+pub struct Response {
+    pub args: &'static str,
+    pub stdout: &'static str,
+    pub code: i32,
+}
+
+/// How many runs have been set up so far, each gets a directory of its own since the
+/// tests run in parallel
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// A command that answers every invocation with nothing and success
+pub const NOTHING: &[Response] = &[];
+
+/// A pretend machine: the OS and distribution up2date sees and the commands on it
+pub struct Profile {
+    pub name: &'static str,
+    pub os: &'static str,
+    pub release: Option<&'static str>,
+    pub shims: Vec<(&'static str, &'static [Response])>,
+}
+
+/// What a run printed and the commands it ran, `sudo` and the command it ran both
+/// being recorded
+pub struct Run {
+    pub success: bool,
+    pub stdout: String,
+    pub invocations: Vec<String>,
+}
+
+impl Run {
+    /// Whether a command line was run, `sudo` included
+    pub fn ran(&self, command_line: &str) -> bool {
+        self.invocations
+            .iter()
+            .any(|invocation| invocation == command_line)
+    }
+}
+
+/// Quote a string for sh
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Write a shim that records how it was run and answers with the first response whose
+/// arguments match, only sh builtins are used since `PATH` holds nothing but shims
+///
+/// # Arguments
+///
+/// * `dir` - Where the shims go
+/// * `command` - The command the shim stands in for
+/// * `responses` - The canned answers
+/// * `log` - Where every invocation is appended
+fn write_shim(dir: &Path, command: &str, responses: &[Response], log: &Path) {
+    let mut script = format!(
+        "#!/bin/sh\nprintf '%s\\n' \"{} $*\" >> {}\n",
+        command,
+        quote(&log.display().to_string())
+    );
+
+    if command == "sudo" {
+        script.push_str("exec \"$@\"\n");
+    } else {
+        script.push_str("case \"$*\" in\n");
+
+        for response in responses.iter() {
+            script.push_str(&format!(
+                "  {}*) printf '%s' {}; exit {} ;;\n",
+                quote(response.args),
+                quote(response.stdout),
+                response.code
+            ));
+        }
+
+        script.push_str("esac\nexit 0\n");
+    }
+
+    let path = dir.join(command);
+
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Run up2date against a profile's shims, in a home directory of its own
+///
+/// # Arguments
+///
+/// * `profile` - The machine to pretend to be
+/// * `args` - The command line arguments
+/// * `answers` - What's typed at every prompt
+pub fn run(profile: &Profile, args: &[&str], answers: &str) -> Run {
+    let root: PathBuf = env::temp_dir().join(format!(
+        "up2date-shims-{}-{}-{}",
+        profile.name,
+        process::id(),
+        RUNS.fetch_add(1, Ordering::SeqCst)
+    ));
+    let bin = root.join("bin");
+    let home = root.join("home");
+    let log = root.join("invocations.log");
+
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(&home).unwrap();
+
+    for (command, responses) in profile.shims.iter() {
+        write_shim(&bin, command, responses, &log);
+    }
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_up2date"));
+
+    command
+        .args(args)
+        .env_clear()
+        .env("HOME", &home)
+        .env("UP2DATE_SHIMS", &bin)
+        .env("UP2DATE_SHIM_OS", profile.os)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+
+    if let Some(release) = profile.release {
+        command.env("UP2DATE_SHIM_RELEASE", release);
+    }
+
+    let mut child = command.spawn().unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(answers.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    let invocations = fs::read_to_string(&log)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .collect();
+
+    fs::remove_dir_all(&root).unwrap();
+
+    Run {
+        success: output.status.success(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        invocations,
+    }
+}
+
+/// The rust and editor steps every profile runs
+fn everywhere(
+    mut shims: Vec<(&'static str, &'static [Response])>,
+) -> Vec<(&'static str, &'static [Response])> {
+    shims.extend([
+        (
+            "rustup",
+            &[
+                Response {
+                    args: "check",
+                    stdout: include_str!("../fixtures/rustup-check.txt"),
+                    code: 0,
+                },
+                Response {
+                    args: "toolchain list",
+                    stdout: "stable-x86_64-unknown-linux-gnu (default)\n",
+                    code: 0,
+                },
+            ][..],
+        ),
+        (
+            "cargo",
+            &[Response {
+                args: "install --list",
+                stdout: include_str!("../fixtures/cargo-install-list.txt"),
+                code: 0,
+            }][..],
+        ),
+        ("nvim", NOTHING),
+    ]);
+    shims
+}
+
+pub fn ubuntu() -> Profile {
+    Profile {
+        name: "ubuntu",
+        os: "linux",
+        release: Some("ubuntu"),
+        shims: everywhere(vec![
+            ("sudo", NOTHING),
+            (
+                "apt-get",
+                &[
+                    Response {
+                        args: "-s upgrade",
+                        stdout: include_str!("../fixtures/apt-get-s-upgrade.txt"),
+                        code: 0,
+                    },
+                    Response {
+                        args: "-qq --print-uris",
+                        stdout: include_str!("../fixtures/apt-get-print-uris.txt"),
+                        code: 0,
+                    },
+                    Response {
+                        args: "-s autoremove",
+                        stdout: "Remv libfoo0 [0.9-2]\n",
+                        code: 0,
+                    },
+                    Response {
+                        args: "-y upgrade",
+                        stdout: include_str!("../fixtures/apt-get-upgrade.txt"),
+                        code: 0,
+                    },
+                ],
+            ),
+        ]),
+    }
+}
+
+pub fn arch() -> Profile {
+    Profile {
+        name: "arch",
+        os: "linux",
+        release: Some("arch"),
+        shims: everywhere(vec![
+            ("sudo", NOTHING),
+            (
+                "pacman",
+                &[
+                    Response {
+                        args: "-Qu",
+                        stdout: include_str!("../fixtures/pacman-qu.txt"),
+                        code: 0,
+                    },
+                    Response {
+                        args: "-Qtdq",
+                        stdout: include_str!("../fixtures/pacman-qtdq.txt"),
+                        code: 0,
+                    },
+                    Response {
+                        args: "-Rns --print",
+                        stdout: "gnome-common 3.18.0-4\npython-mako 1.3.2-1\n",
+                        code: 0,
+                    },
+                    Response {
+                        args: "--noconfirm -Syu",
+                        stdout: include_str!("../fixtures/pacman-syu.txt"),
+                        code: 0,
+                    },
+                ],
+            ),
+            ("yay", NOTHING),
+        ]),
+    }
+}
+
+pub fn proxmox() -> Profile {
+    Profile {
+        name: "proxmox",
+        os: "linux",
+        release: Some("debian"),
+        shims: everywhere(vec![
+            ("sudo", NOTHING),
+            ("apt-get", NOTHING),
+            ("pveversion", NOTHING),
+            (
+                "dpkg-query",
+                &[Response {
+                    args: "-W",
+                    stdout: include_str!("../fixtures/dpkg-query-kernels.txt"),
+                    code: 1,
+                }],
+            ),
+            (
+                "uname",
+                &[Response {
+                    args: "-r",
+                    stdout: "6.5.13-1-pve\n",
+                    code: 0,
+                }],
+            ),
+        ]),
+    }
+}
+
+pub fn steamos() -> Profile {
+    Profile {
+        name: "steamos",
+        os: "linux",
+        release: Some("steamos"),
+        shims: everywhere(vec![
+            ("sudo", NOTHING),
+            ("pacman", NOTHING),
+            (
+                "steamos-update",
+                &[Response {
+                    args: "check",
+                    stdout: "Update available\n",
+                    code: 0,
+                }],
+            ),
+            (
+                "flatpak",
+                &[Response {
+                    args: "remote-ls --updates --user",
+                    stdout: include_str!("../fixtures/flatpak-remote-ls.txt"),
+                    code: 0,
+                }],
+            ),
+        ]),
+    }
+}
+
+pub fn fedora() -> Profile {
+    Profile {
+        name: "fedora",
+        os: "linux",
+        release: Some("fedora"),
+        shims: everywhere(vec![
+            ("sudo", NOTHING),
+            (
+                "dnf",
+                &[Response {
+                    args: "--assumeno upgrade",
+                    stdout: include_str!("../fixtures/dnf4-upgrade.txt"),
+                    code: 1,
+                }],
+            ),
+            (
+                "rpm",
+                &[Response {
+                    args: "-qa",
+                    stdout: include_str!("../fixtures/rpm-qa.txt"),
+                    code: 0,
+                }],
+            ),
+        ]),
+    }
+}
+
+pub fn macos() -> Profile {
+    Profile {
+        name: "macos",
+        os: "macos",
+        release: None,
+        shims: everywhere(vec![
+            (
+                "brew",
+                &[
+                    Response {
+                        args: "outdated --json=v2",
+                        stdout: include_str!("../fixtures/brew-outdated.json"),
+                        code: 0,
+                    },
+                    Response {
+                        args: "outdated --formula --quiet",
+                        stdout: "node\n",
+                        code: 0,
+                    },
+                    Response {
+                        args: "services list",
+                        stdout: "Name          Status  User File\nnode          none\n",
+                        code: 0,
+                    },
+                    Response {
+                        args: "cleanup --dry-run",
+                        stdout: "Would remove: /Users/me/Library/Caches/Homebrew/node--20.1.0 (12.3MB)\n",
+                        code: 0,
+                    },
+                ],
+            ),
+            (
+                "sysctl",
+                &[Response {
+                    args: "-n kern.boottime",
+                    stdout: "{ sec = 1704794401, usec = 0 } Tue Jan  9 10:00:01 2024\n",
+                    code: 0,
+                }],
+            ),
+        ]),
+    }
+}
+
+pub fn windows() -> Profile {
+    Profile {
+        name: "windows",
+        os: "windows",
+        release: None,
+        shims: everywhere(vec![
+            (
+                "winget",
+                &[
+                    Response {
+                        args: "upgrade --include-pinned",
+                        stdout: include_str!("../fixtures/winget-upgrade.txt"),
+                        code: 0,
+                    },
+                    Response {
+                        args: "pin list",
+                        stdout: include_str!("../fixtures/winget-pin-list.txt"),
+                        code: 0,
+                    },
+                ],
+            ),
+            (
+                "choco",
+                &[Response {
+                    args: "outdated -r",
+                    stdout: include_str!("../fixtures/choco-outdated.txt"),
+                    code: 0,
+                }],
+            ),
+        ]),
+    }
+}
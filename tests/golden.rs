@@ -0,0 +1,89 @@
+// the shims are shell scripts
+#![cfg(unix)]
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use common::{arch, fedora, macos, run, ubuntu, windows, Profile};
+
+/// Compare what `up2date plan` prints for a profile with its snapshot in `tests/golden`,
+/// or write the snapshot when `UP2DATE_BLESS` is set
+///
+/// # Arguments
+///
+/// * `name` - The snapshot's name
+/// * `profile` - The machine to plan for
+/// * `args` - The options given to `plan`
+fn check(name: &str, profile: Profile, args: &[&str]) {
+    let run = run(&profile, &[&["plan"], args].concat(), "");
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.txt", name));
+
+    assert!(run.success, "planning for {} failed", name);
+    // planning never changes anything
+    assert!(run
+        .invocations
+        .iter()
+        .all(|invocation| !invocation.starts_with("sudo ")));
+
+    if env::var_os("UP2DATE_BLESS").is_some() {
+        fs::write(&path, &run.stdout).unwrap();
+        return;
+    }
+
+    assert_eq!(
+        run.stdout,
+        fs::read_to_string(&path).unwrap_or_default(),
+        "the plan for {} changed, run the tests with UP2DATE_BLESS=1 if that's on purpose",
+        name
+    );
+}
+
+#[test]
+fn ubuntu_plan() {
+    check("ubuntu", ubuntu(), &[]);
+}
+
+#[test]
+fn ubuntu_offline_ok_plan() {
+    check("ubuntu-offline-ok", ubuntu(), &["--offline-ok"]);
+}
+
+#[test]
+fn ubuntu_offline_plan() {
+    check("ubuntu-offline", ubuntu(), &["--offline"]);
+}
+
+#[test]
+fn arch_plan() {
+    check("arch", arch(), &[]);
+}
+
+#[test]
+fn arch_offline_ok_plan() {
+    check("arch-offline-ok", arch(), &["--offline-ok"]);
+}
+
+#[test]
+fn macos_plan() {
+    check("macos", macos(), &[]);
+}
+
+#[test]
+fn windows_plan() {
+    check("windows", windows(), &[]);
+}
+
+#[test]
+fn fedora_has_no_system_steps() {
+    // only `check` knows dnf so far, a run stops before doing anything
+    let run = run(&fedora(), &["plan"], "");
+
+    assert!(!run.success);
+    assert!(run.invocations.is_empty());
+}
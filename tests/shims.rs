@@ -0,0 +1,126 @@
+// the shims are shell scripts
+#![cfg(unix)]
+
+mod common;
+
+use common::{arch, macos, proxmox, run, steamos, ubuntu, windows};
+
+#[test]
+fn ubuntu_previews_upgrades_and_cleans_up() {
+    let run = run(&ubuntu(), &["--preview"], "y\ny\n");
+
+    assert!(run.success);
+    assert!(run.stdout.contains("libssl3"));
+    assert!(run.ran("sudo apt-get update"));
+    assert!(run.ran("sudo apt-get -y upgrade --allow-downgrades --with-new-pkgs"));
+    assert!(run.ran("sudo apt-get -y autoremove"));
+    assert!(run.ran("cargo install ripgrep"));
+    assert!(!run.ran("cargo install tm"));
+    assert!(run.stdout.contains("Summary"));
+}
+
+#[test]
+fn ubuntu_keeps_what_isnt_confirmed() {
+    let run = run(&ubuntu(), &["--only-group", "cleanup"], "n\n");
+
+    assert!(run.success);
+    assert!(run.ran("apt-get -s autoremove"));
+    assert!(!run.ran("sudo apt-get -y autoremove"));
+}
+
+#[test]
+fn ubuntu_offline_ok_skips_the_refresh() {
+    let run = run(&ubuntu(), &["--offline-ok", "--only-group", "system"], "");
+
+    assert!(run.success);
+    assert!(!run.ran("sudo apt-get update"));
+    assert!(run.ran("sudo apt-get -y upgrade --allow-downgrades --with-new-pkgs"));
+}
+
+#[test]
+fn ubuntu_changed_only_hides_what_had_nothing_to_do() {
+    let run = run(&ubuntu(), &["--changed-only", "--only-group", "system"], "");
+
+    assert!(run.success);
+    assert!(run.ran("sudo apt-get update"));
+    assert!(!run.stdout.contains("$ sudo apt-get update"));
+    assert!(run
+        .stdout
+        .contains("$ sudo apt-get -y upgrade --allow-downgrades --with-new-pkgs"));
+    assert!(run.stdout.contains("Summary"));
+}
+
+#[test]
+fn proxmox_full_upgrades_and_purges_old_kernels() {
+    let run = run(
+        &proxmox(),
+        &["--only-group", "system", "--only-group", "cleanup"],
+        "y\n",
+    );
+
+    assert!(run.success);
+    assert!(run.ran("sudo apt-get -y dist-upgrade --allow-downgrades"));
+    assert!(run.ran(
+        "sudo apt-get -y purge linux-image-6.1.0-18-amd64 linux-image-6.1.0-17-amd64 \
+         linux-image-6.1.0-15-amd64 pve-kernel-5.15.131-2-pve"
+    ));
+}
+
+#[test]
+fn steamos_updates_the_image_and_flatpaks_but_not_pacman() {
+    let run = run(&steamos(), &["--only-group", "system"], "");
+
+    assert!(run.success);
+    assert!(run.ran("steamos-update check"));
+    assert!(run.ran("steamos-update"));
+    assert!(!run.ran("sudo pacman --noconfirm -Syu"));
+    assert!(run.ran(
+        "flatpak update -y --user app/org.mozilla.firefox/x86_64/stable \
+         runtime/org.freedesktop.Platform.GL.default/x86_64/23.08 \
+         runtime/org.gnome.Platform/x86_64/45 app/org.gnome.Calculator/x86_64/stable"
+    ));
+    assert!(run
+        .stdout
+        .contains("flatpak (user): 2 apps and 2 runtimes updated"));
+}
+
+#[test]
+fn arch_upgrades_and_removes_orphans() {
+    let run = run(&arch(), &["--preview"], "y\ny\n");
+
+    assert!(run.success);
+    assert!(run.stdout.contains("openssl"));
+    assert!(run.ran("sudo pacman --noconfirm -S archlinux-keyring"));
+    assert!(run.ran("sudo pacman --noconfirm -Syu"));
+    assert!(run.ran("yay --noconfirm -Syu"));
+    assert!(run.ran("sudo pacman --noconfirm -Rns gnome-common python-mako"));
+    assert!(run.stdout.contains("Summary"));
+}
+
+#[test]
+fn macos_upgrades_brew_and_cleans_up() {
+    let run = run(&macos(), &["--preview"], "y\ny\n");
+
+    assert!(run.success);
+    assert!(run.stdout.contains("node"));
+    assert!(run.ran("brew update"));
+    assert!(run.ran("brew upgrade"));
+    assert!(run.ran("brew cleanup"));
+    assert!(run.stdout.contains("Summary"));
+}
+
+#[test]
+fn windows_upgrades_each_package_once() {
+    let run = run(&windows(), &["--only-group", "system"], "");
+
+    assert!(run.success);
+    assert!(run.ran(
+        "winget upgrade --id 7zip.7zip --exact --silent --accept-source-agreements --accept-package-agreements"
+    ));
+    assert!(!run.ran("choco upgrade 7zip -y"));
+    assert!(!run
+        .invocations
+        .iter()
+        .any(|line| line.contains("Git.Git --exact")));
+    assert!(run.stdout.contains("held back by pins"));
+}